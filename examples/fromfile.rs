@@ -3,8 +3,6 @@ use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 
-use fasta;
-
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<_> = env::args().collect();
     if args.len() < 2 {
@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+use fasta::cancel::CancellationToken;
+use fasta::dedup::{dedup, DedupBy, Keep};
+
+use crate::dry_run::Outputs;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum By {
+    /// Identical IDs
+    Id,
+    /// Identical sequences
+    Seq,
+    /// Identical sequences on either strand
+    CanonicalSeq,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum KeepArg {
+    /// The record seen first
+    First,
+    /// The longest record, ties keeping the earlier one
+    Longest,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// What makes records duplicates
+    #[arg(long, value_enum, default_value_t = By::Seq)]
+    by: By,
+
+    /// Which of a set of duplicates to keep, in the position of the first
+    #[arg(long, value_enum, default_value_t = KeepArg::First)]
+    keep: KeepArg,
+
+    /// Write a TSV of each kept ID and the IDs collapsed into it
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Report the files that would be written without creating them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let by = match args.by {
+        By::Id => DedupBy::Id,
+        By::Seq => DedupBy::Sequence,
+        By::CanonicalSeq => DedupBy::CanonicalSequence,
+    };
+    let keep = match args.keep {
+        KeepArg::First => Keep::First,
+        KeepArg::Longest => Keep::Longest,
+    };
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let deduped = dedup(records, by, keep)?;
+
+    let outputs = Outputs::new(args.dry_run);
+    let mut out = outputs.create_output(&args.output)?;
+    for rec in &deduped.records {
+        rec.write(&mut out)?;
+    }
+    out.flush()?;
+    if let Some(path) = &args.report {
+        let mut report = outputs.create(&path.to_string_lossy())?;
+        deduped.write_report(&mut report)?;
+        report.flush()?;
+    }
+    outputs.report(&mut io::stdout().lock())?;
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Support for `--dry-run`: subcommands create their output files
+//! through `Outputs`, which either creates them or only tallies what
+//! would have been written to each, so the plan can be reported
+//! without touching disk.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+use flate2::write::GzEncoder;
+
+/// What would be written to one output file
+struct Planned {
+    path: String,
+    records: u64,
+    bytes: u64,
+}
+
+/// Counts the bytes and FASTA records written to it
+pub struct Tally {
+    planned: Rc<RefCell<Vec<Planned>>>,
+    index: usize,
+    line_start: bool,
+}
+
+impl Write for Tally {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut planned = self.planned.borrow_mut();
+        let entry = &mut planned[self.index];
+        entry.bytes += buf.len() as u64;
+        for &b in buf {
+            if self.line_start && b == b'>' {
+                entry.records += 1;
+            }
+            self.line_start = b == b'\n';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+type Gzip = GzEncoder<BufWriter<File>>;
+
+/// An output file, real or tallied
+pub enum OutputFile {
+    File(BufWriter<File>),
+    /// A file or stdout, from `crate::create_output`
+    Stream(BufWriter<Box<dyn Write>>),
+    /// A gzip stream, shared with `Outputs`, which finishes it once the
+    /// file is dropped
+    Gzip(Rc<RefCell<Gzip>>),
+    Tally(Tally),
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::File(w) => w.write(buf),
+            OutputFile::Stream(w) => w.write(buf),
+            OutputFile::Gzip(w) => w.borrow_mut().write(buf),
+            OutputFile::Tally(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::File(w) => w.flush(),
+            OutputFile::Stream(w) => w.flush(),
+            OutputFile::Gzip(w) => w.borrow_mut().flush(),
+            OutputFile::Tally(w) => w.flush(),
+        }
+    }
+}
+
+/// Creates the output files of a subcommand
+pub struct Outputs {
+    dry_run: bool,
+    planned: Rc<RefCell<Vec<Planned>>>,
+    /// Gzip streams not yet finished
+    gzip: RefCell<Vec<Rc<RefCell<Gzip>>>>,
+}
+
+impl Outputs {
+    pub fn new(dry_run: bool) -> Outputs {
+        Outputs {
+            dry_run,
+            planned: Rc::new(RefCell::new(Vec::new())),
+            gzip: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Create `path`, or start tallying it when dry-running
+    pub fn create(&self, path: &str) -> io::Result<OutputFile> {
+        if !self.dry_run {
+            return File::create(path).map(|f| OutputFile::File(BufWriter::new(f)));
+        }
+        Ok(self.tally(path))
+    }
+
+    /// Create the output `path`, or write to stdout for `-`, as
+    /// `crate::create_output` does; a dry run tallies either
+    pub fn create_output(&self, path: &str) -> io::Result<OutputFile> {
+        if !self.dry_run {
+            return crate::create_output(path).map(OutputFile::Stream);
+        }
+        Ok(self.tally(path))
+    }
+
+    /// Create `path` compressed with gzip. Dry runs tally the bytes
+    /// before compression. The stream is finished once the returned
+    /// file has been dropped, by the next `create_gzip` or by `finish`.
+    pub fn create_gzip(&self, path: &str) -> io::Result<OutputFile> {
+        if !self.dry_run {
+            self.finish_closed()?;
+            let file = BufWriter::new(File::create(path)?);
+            let gz = Rc::new(RefCell::new(GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )));
+            self.gzip.borrow_mut().push(Rc::clone(&gz));
+            return Ok(OutputFile::Gzip(gz));
+        }
+        Ok(self.tally(path))
+    }
+
+    /// Finish the gzip streams whose files have been dropped
+    fn finish_closed(&self) -> io::Result<()> {
+        let open = std::mem::take(&mut *self.gzip.borrow_mut());
+        for gz in open {
+            match Rc::try_unwrap(gz) {
+                Ok(gz) => {
+                    gz.into_inner().finish()?.flush()?;
+                }
+                Err(gz) => self.gzip.borrow_mut().push(gz),
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish every gzip stream. Call once all output files have been
+    /// dropped; any still held are reported as an error.
+    pub fn finish(&self) -> io::Result<()> {
+        self.finish_closed()?;
+        if !self.gzip.borrow().is_empty() {
+            return Err(io::Error::other("gzip output still open"));
+        }
+        Ok(())
+    }
+
+    fn tally(&self, path: &str) -> OutputFile {
+        let mut planned = self.planned.borrow_mut();
+        planned.push(Planned {
+            path: path.to_owned(),
+            records: 0,
+            bytes: 0,
+        });
+        OutputFile::Tally(Tally {
+            planned: Rc::clone(&self.planned),
+            index: planned.len() - 1,
+            line_start: true,
+        })
+    }
+
+    /// When dry-running, write the tallied files as a `file\trecords\tbytes`
+    /// table; otherwise do nothing
+    pub fn report<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if !self.dry_run {
+            return Ok(());
+        }
+        writeln!(w, "file\trecords\tbytes")?;
+        for p in self.planned.borrow().iter() {
+            writeln!(w, "{}\t{}\t{}", p.path, p.records, p.bytes)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use fasta::bgzf::{gzi_path, is_bgzf, GziIndex};
+use fasta::cancel::CancellationToken;
+use fasta::compress::{self, Compression};
+use fasta::index::{fai_path, FastaIndex, IndexedFasta};
+use fasta::Record;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Sequence line width of extracted regions, 0 for no wrapping
+    #[arg(long, default_value_t = 60)]
+    width: usize,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// FASTA file, plain or compressed with bgzip
+    input: PathBuf,
+
+    /// Regions to print: name, name:start or name:start-end, 1-based and
+    /// inclusive
+    regions: Vec<String>,
+}
+
+/// Whether `path` is bgzipped, rejecting other compression, which can't
+/// be read at random
+pub(crate) fn is_bgzipped(path: &Path) -> Result<bool, Box<dyn Error>> {
+    let mut header = Vec::new();
+    File::open(path)?.take(18).read_to_end(&mut header)?;
+    match Compression::detect(&header) {
+        Compression::None => Ok(false),
+        Compression::Gzip if is_bgzf(&header) => Ok(true),
+        other => Err(format!(
+            "{} is {}-compressed; recompress it with bgzip to index it",
+            path.display(),
+            other
+        )
+        .into()),
+    }
+}
+
+/// Write `<path>.fai`, and `<path>.gzi` for bgzipped input, unless they
+/// already exist
+fn write_indexes(
+    path: &Path,
+    bgzipped: bool,
+    token: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let fai = fai_path(path);
+    if !fai.exists() {
+        let input = compress::decompress(BufReader::new(File::open(path)?))?;
+        let index = FastaIndex::build_with_cancel(input, token)?;
+        let mut out = BufWriter::new(File::create(&fai)?);
+        index.write(&mut out)?;
+        out.flush()?;
+    }
+    let gzi = gzi_path(path);
+    if bgzipped && !gzi.exists() {
+        let index = GziIndex::build(BufReader::new(File::open(path)?))?;
+        let mut out = BufWriter::new(File::create(&gzi)?);
+        index.write(&mut out)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn print_regions<R: io::Read + io::Seek, W: Write>(
+    fasta: &mut IndexedFasta<R>,
+    regions: &[String],
+    width: usize,
+    out: &mut W,
+) -> Result<(), Box<dyn Error>> {
+    for spec in regions {
+        let region = fasta.index().region(spec)?;
+        let rec = Record {
+            id: spec.clone(),
+            description: String::new(),
+            sequence: fasta.fetch_region(&region.name, region.start, region.end)?,
+        };
+        rec.write_with_width(out, width)?;
+    }
+    Ok(())
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let bgzipped = is_bgzipped(&args.input)?;
+    write_indexes(&args.input, bgzipped, token)?;
+    if args.regions.is_empty() {
+        return Ok(());
+    }
+    let mut out = crate::create_output(&args.output)?;
+    if bgzipped {
+        let mut fasta = IndexedFasta::open_bgzf(&args.input)?;
+        print_regions(&mut fasta, &args.regions, args.width, &mut out)?;
+    } else {
+        let mut fasta = IndexedFasta::open(&args.input)?;
+        print_regions(&mut fasta, &args.regions, args.width, &mut out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::CancellationToken;
+use fasta::stats::Stats;
+
+use crate::unset_or;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Keep records at least this long
+    #[arg(long, value_name = "N")]
+    min_len: Option<u64>,
+
+    /// Keep records at most this long
+    #[arg(long, value_name = "N")]
+    max_len: Option<u64>,
+
+    /// Keep records with at least this GC fraction, between 0 and 1
+    #[arg(long, value_name = "FRACTION")]
+    min_gc: Option<f64>,
+
+    /// Keep records with at most this GC fraction, between 0 and 1
+    #[arg(long, value_name = "FRACTION")]
+    max_gc: Option<f64>,
+
+    /// Keep records with at most this fraction of N bases
+    #[arg(long, value_name = "FRACTION")]
+    max_n_fraction: Option<f64>,
+
+    /// Keep the records that fail the thresholds instead
+    #[arg(long)]
+    invert: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+impl Args {
+    /// Whether a record with statistics `s` meets every threshold
+    fn passes(&self, s: &Stats) -> bool {
+        let len = s.total_length;
+        unset_or(self.min_len, |min| len >= min)
+            && unset_or(self.max_len, |max| len <= max)
+            && unset_or(self.min_gc, |min| s.gc_content() >= min)
+            && unset_or(self.max_gc, |max| s.gc_content() <= max)
+            && unset_or(self.max_n_fraction, |max| s.n_fraction() <= max)
+    }
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let fractions = [
+        ("--min-gc", args.min_gc),
+        ("--max-gc", args.max_gc),
+        ("--max-n-fraction", args.max_n_fraction),
+    ];
+    for (flag, value) in fractions {
+        if value.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+            return Err(format!("{} must be between 0 and 1", flag).into());
+        }
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+
+    let (mut kept, mut dropped) = (0u64, 0u64);
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        };
+        let mut stats = Stats::new();
+        stats.add(&rec);
+        if args.passes(&stats) != args.invert {
+            rec.write(&mut out)?;
+            kept += 1;
+        } else {
+            dropped += 1;
+        }
+    }
+    out.flush()?;
+    eprintln!("fasta filter: kept {}, dropped {}", kept, dropped);
+    Ok(())
+}
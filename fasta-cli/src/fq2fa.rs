@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::{self, CancellationToken};
+use fasta::fastq::FastqReader;
+use fasta::qc::mean_quality;
+use fasta::report::NumberFormat;
+use flate2::write::GzEncoder;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Append mean_q=Q, the read's mean Phred+33 quality, to descriptions
+    #[arg(long)]
+    mean_quality: bool,
+
+    /// Sequence line width, 0 for no wrapping
+    #[arg(long, default_value_t = 0)]
+    width: usize,
+
+    /// Output file, or - for stdout; compressed with gzip if it ends in
+    /// .gz
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTQ files or glob patterns, plain or compressed, read in
+    /// order; - or none for stdin
+    inputs: Vec<String>,
+}
+
+fn convert<W: Write>(
+    args: &Args,
+    out: &mut W,
+    token: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let fmt = NumberFormat {
+        decimals: 2,
+        ..NumberFormat::default()
+    };
+    for path in crate::expand_inputs(&args.inputs)? {
+        for rec in FastqReader::new(crate::open_raw(&path)?) {
+            if token.is_cancelled() {
+                return Err(cancel::cancelled().into());
+            }
+            let rec = rec?;
+            let quality = args.mean_quality.then(|| mean_quality(&rec));
+            let mut rec = rec.into_fasta();
+            if let Some(q) = quality {
+                if !rec.description.is_empty() {
+                    rec.description.push(' ');
+                }
+                rec.description.push_str("mean_q=");
+                rec.description.push_str(&fmt.format(q));
+            }
+            rec.write_with_width(out, args.width)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let mut out = crate::create_output(&args.output)?;
+    if !args.output.ends_with(".gz") {
+        let result = convert(&args, &mut out, token);
+        out.flush()?;
+        return result;
+    }
+    let mut gz = GzEncoder::new(out, flate2::Compression::default());
+    let result = convert(&args, &mut gz, token);
+    gz.finish()?.flush()?;
+    result
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+use fasta::cancel::{self, CancellationToken};
+use fasta::fastq::FastqReader;
+use fasta::tabular::{write_fasta_tab, write_fastq_tab};
+use fasta::FastaReaderBuilder;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA or FASTQ files or glob patterns, read in order; - or
+    /// none for stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let mut out = crate::create_output(&args.output)?;
+    for path in crate::expand_inputs(&args.inputs)? {
+        let mut input = crate::open_raw(&path)?;
+        let start = input.fill_buf()?;
+        let fastq = start.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'@');
+        if fastq {
+            for rec in FastqReader::new(input) {
+                if token.is_cancelled() {
+                    out.flush()?;
+                    return Err(cancel::cancelled().into());
+                }
+                write_fastq_tab(&mut out, &rec?)?;
+            }
+        } else {
+            let records = FastaReaderBuilder::new()
+                .cancellation(token.clone())
+                .from_reader(input);
+            for rec in records {
+                write_fasta_tab(&mut out, &rec?)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::CancellationToken;
+use fasta::Record;
+use regex::Regex;
+
+use crate::unset_or;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Select records whose ID matches this regex
+    #[arg(long, value_name = "REGEX")]
+    id_regex: Option<Regex>,
+
+    /// Select records whose description matches this regex
+    #[arg(long, value_name = "REGEX")]
+    desc_regex: Option<Regex>,
+
+    /// Select records containing this IUPAC motif on either strand
+    #[arg(long, value_name = "PATTERN")]
+    seq: Option<String>,
+
+    /// Select the records that don't match instead
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+
+    /// Print only the number of selected records
+    #[arg(short, long)]
+    count: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+impl Args {
+    /// Whether `rec` meets every given criterion
+    fn matches(&self, rec: &Record) -> bool {
+        unset_or(self.id_regex.as_ref(), |re| re.is_match(&rec.id))
+            && unset_or(self.desc_regex.as_ref(), |re| re.is_match(&rec.description))
+            && unset_or(self.seq.as_ref(), |pattern| {
+                rec.find_motif(pattern).is_ok_and(|found| !found.is_empty())
+            })
+    }
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    if args.id_regex.is_none() && args.desc_regex.is_none() && args.seq.is_none() {
+        return Err("give --id-regex, --desc-regex or --seq".into());
+    }
+    if let Some(pattern) = &args.seq {
+        // Reject a bad pattern up front rather than matching nothing
+        Record::new().find_motif(pattern)?;
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+
+    let mut selected = 0u64;
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        };
+        if args.matches(&rec) == args.invert_match {
+            continue;
+        }
+        selected += 1;
+        if !args.count {
+            rec.write(&mut out)?;
+        }
+    }
+    if args.count {
+        writeln!(out, "{}", selected)?;
+    }
+    out.flush()?;
+    Ok(())
+}
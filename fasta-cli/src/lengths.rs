@@ -0,0 +1,40 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::CancellationToken;
+use fasta::report::NumberFormat;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Add a GC content column; sequences are then read in full
+    #[arg(long)]
+    gc: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+    if args.gc {
+        let fmt = NumberFormat::default();
+        for rec in records {
+            let rec = rec?;
+            let gc = fmt.format(rec.gc_content());
+            writeln!(out, "{}\t{}\t{}", rec.id, rec.sequence.len(), gc)?;
+        }
+    } else {
+        for rec in records.lengths() {
+            let (rec, len) = rec?;
+            writeln!(out, "{}\t{}", rec.id, len)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
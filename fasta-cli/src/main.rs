@@ -0,0 +1,240 @@
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Parser, Subcommand};
+use fasta::cancel::{self, CancellationToken};
+use fasta::chain::Chain;
+use fasta::compress;
+use fasta::{FastaBuffer, FastaReaderBuilder};
+
+mod dedupe;
+mod dry_run;
+mod faidx;
+mod filter;
+mod fq2fa;
+mod fx2tab;
+mod grep;
+mod lengths;
+mod mask;
+mod merge;
+mod rc;
+mod reformat;
+mod rename;
+mod sample;
+mod sort;
+mod split;
+mod stats;
+mod subseq;
+mod tab2fx;
+mod translate;
+mod validate;
+
+/// Command line tools for working with FASTA files
+#[derive(Parser)]
+#[command(name = "fasta", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Remove records with duplicate IDs or sequences
+    Dedupe(dedupe::Args),
+    /// Index a FASTA file and print regions of it, like samtools faidx
+    Faidx(faidx::Args),
+    /// Keep records within length, GC and N content thresholds
+    Filter(filter::Args),
+    /// Convert FASTQ reads to FASTA
+    Fq2fa(fq2fa::Args),
+    /// Convert FASTA or FASTQ records to tab-delimited lines
+    Fx2tab(fx2tab::Args),
+    /// Select records by ID, description or sequence motif
+    Grep(grep::Args),
+    /// Print the ID and length of each record
+    Lengths(lengths::Args),
+    /// Hard- or soft-mask the intervals of a BED file
+    Mask(mask::Args),
+    /// Combine files into one, resolving duplicate IDs
+    Merge(merge::Args),
+    /// Reverse complement records
+    Rc(rc::Args),
+    /// Rewrap lines, change case, strip descriptions or line endings
+    Reformat(reformat::Args),
+    /// Rename records with a prefix, regex replacements or a mapping file
+    Rename(rename::Args),
+    /// Select a subset of records
+    Sample(sample::Args),
+    /// Sort records by length or ID, spilling to disk for large inputs
+    Sort(sort::Args),
+    /// Split records into several files
+    Split(split::Args),
+    /// Report length, N50, GC and N content per file or per record
+    Stats(stats::Args),
+    /// Extract regions given on the command line or in a BED file
+    Subseq(subseq::Args),
+    /// Convert tab-delimited lines back to FASTA or FASTQ records
+    Tab2fx(tab2fx::Args),
+    /// Translate nucleotide records to protein
+    Translate(translate::Args),
+    /// Check files for structural, alphabet and formatting problems
+    Validate(validate::Args),
+}
+
+type Source = io::Result<FastaBuffer<Box<dyn BufRead + Send>>>;
+
+/// Records read from all of a subcommand's inputs in turn
+pub(crate) type Inputs = Chain<Box<dyn Iterator<Item = Source>>, Box<dyn BufRead + Send>>;
+
+/// Expand input arguments into paths, with no arguments meaning `-` for
+/// stdin. Arguments containing `*`, `?` or `[` are glob patterns,
+/// expanded in sorted order here rather than by the shell so that huge
+/// directories don't hit argument length limits.
+pub(crate) fn expand_inputs(args: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if args.is_empty() {
+        return Ok(vec![PathBuf::from("-")]);
+    }
+    let mut paths = Vec::new();
+    for arg in args {
+        if !arg.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+        let before = paths.len();
+        for path in glob::glob(arg)? {
+            paths.push(path?);
+        }
+        if paths.len() == before {
+            return Err(format!("no files match {}", arg).into());
+        }
+    }
+    Ok(paths)
+}
+
+/// Open one input for reading, decompressing it if needed and stopping
+/// once `token` is cancelled. `-` reads stdin.
+pub(crate) fn open_path(path: &Path, token: &CancellationToken) -> Source {
+    let builder = FastaReaderBuilder::new().cancellation(token.clone());
+    if path.as_os_str() == "-" {
+        let stdin = compress::decompress(BufReader::new(io::stdin()))?;
+        return Ok(builder.from_reader(stdin));
+    }
+    builder
+        .open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Open one input as decompressed bytes, for subcommands that read
+/// lines or other formats themselves. `-` reads stdin.
+pub(crate) fn open_raw(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let input = if path.as_os_str() == "-" {
+        compress::decompress(BufReader::new(io::stdin()))
+    } else {
+        File::open(path).and_then(|f| compress::decompress(BufReader::new(f)))
+    };
+    input.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Open the inputs for reading one after another, as `open_path` does
+pub(crate) fn open_inputs(
+    args: &[String],
+    token: &CancellationToken,
+) -> Result<Inputs, Box<dyn Error>> {
+    let token = token.clone();
+    let paths = expand_inputs(args)?.into_iter();
+    let sources = paths.map(move |path| open_path(&path, &token));
+    Ok(Chain::new(Box::new(sources)))
+}
+
+/// Create the output file `path`, or write to stdout for `-`
+pub(crate) fn create_output(path: &str) -> io::Result<BufWriter<Box<dyn Write>>> {
+    let out: Box<dyn Write> = if path == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(path)?)
+    };
+    Ok(BufWriter::new(out))
+}
+
+/// Whether an optional criterion is met, as one left unset always is
+pub(crate) fn unset_or<T>(criterion: Option<T>, meets: impl FnOnce(T) -> bool) -> bool {
+    match criterion {
+        Some(c) => meets(c),
+        None => true,
+    }
+}
+
+/// Whether an error returned by a subcommand is due to Ctrl-C
+fn was_cancelled(e: &(dyn Error + 'static)) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(cancel::is_cancelled)
+}
+
+/// Whether an error returned by a subcommand is a write to a closed
+/// pipe, as when the output is piped into `head`
+fn broken_pipe(e: &(dyn Error + 'static)) -> bool {
+    let mut error = Some(e);
+    while let Some(e) = error {
+        let kind = e.downcast_ref::<io::Error>().map(io::Error::kind);
+        if kind == Some(io::ErrorKind::BrokenPipe) {
+            return true;
+        }
+        error = e.source();
+    }
+    false
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    // Ctrl-C asks the running subcommand to stop; it flushes what it has
+    // written so far and we exit with the conventional status 130.
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    if let Err(e) = ctrlc::set_handler(move || handler_token.cancel()) {
+        eprintln!("fasta: could not install Ctrl-C handler: {}", e);
+    }
+
+    let result: Result<(), Box<dyn Error>> = match cli.command {
+        Command::Dedupe(args) => dedupe::run(args, &token),
+        Command::Faidx(args) => faidx::run(args, &token),
+        Command::Filter(args) => filter::run(args, &token),
+        Command::Fq2fa(args) => fq2fa::run(args, &token),
+        Command::Fx2tab(args) => fx2tab::run(args, &token),
+        Command::Grep(args) => grep::run(args, &token),
+        Command::Lengths(args) => lengths::run(args, &token),
+        Command::Mask(args) => mask::run(args, &token),
+        Command::Merge(args) => merge::run(args, &token),
+        Command::Rc(args) => rc::run(args, &token),
+        Command::Reformat(args) => reformat::run(args, &token),
+        Command::Rename(args) => rename::run(args, &token),
+        Command::Sample(args) => sample::run(args, &token),
+        Command::Sort(args) => sort::run(args, &token),
+        Command::Split(args) => split::run(args, &token),
+        Command::Stats(args) => stats::run(args, &token),
+        Command::Subseq(args) => subseq::run(args, &token),
+        Command::Tab2fx(args) => tab2fx::run(args, &token),
+        Command::Translate(args) => translate::run(args, &token),
+        Command::Validate(args) => validate::run(args, &token),
+    };
+    if let Err(e) = result {
+        if was_cancelled(e.as_ref()) {
+            eprintln!("fasta: interrupted");
+            process::exit(130);
+        }
+        // Whoever closed the pipe has all the output they wanted
+        if broken_pipe(e.as_ref()) {
+            process::exit(0);
+        }
+        if let Some(failed) = e.downcast_ref::<validate::Failed>() {
+            eprintln!("fasta: {}", failed);
+            process::exit(failed.status());
+        }
+        eprintln!("fasta: {}", e);
+        process::exit(1);
+    }
+}
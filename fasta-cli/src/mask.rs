@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use clap::ArgGroup;
+use fasta::bed::read_bed;
+use fasta::cancel::CancellationToken;
+use fasta::dust::DustOptions;
+use fasta::mask::MaskMode;
+
+use crate::dry_run::Outputs;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Mode {
+    /// Replace masked bases with N
+    Hard,
+    /// Lower-case masked bases
+    Soft,
+}
+
+#[derive(clap::Args)]
+#[group(skip)]
+#[command(group(ArgGroup::new("source").required(true).multiple(true).args(["bed", "dust"])))]
+pub struct Args {
+    /// BED file of intervals to mask
+    #[arg(long, value_name = "FILE")]
+    bed: Option<PathBuf>,
+
+    /// Mask low-complexity regions found by DUST scoring
+    #[arg(long)]
+    dust: bool,
+
+    /// Bases per DUST window
+    #[arg(long, value_name = "N", default_value_t = 64, requires = "dust")]
+    dust_window: usize,
+
+    /// DUST score above which a window is low-complexity
+    #[arg(long, value_name = "SCORE", default_value_t = 20.0, requires = "dust")]
+    dust_threshold: f64,
+
+    /// How masked bases are written
+    #[arg(long, value_enum, default_value_t = Mode::Hard)]
+    mode: Mode,
+
+    /// Report the files that would be written without creating them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let mode = match args.mode {
+        Mode::Hard => MaskMode::Hard,
+        Mode::Soft => MaskMode::Soft,
+    };
+    if args.dust_window < 4 {
+        return Err("--dust-window must be at least 4".into());
+    }
+    let dust = DustOptions {
+        window: args.dust_window,
+        threshold: args.dust_threshold,
+    };
+    let mut intervals: HashMap<String, Vec<Range<usize>>> = HashMap::new();
+    if let Some(bed) = &args.bed {
+        for rec in read_bed(BufReader::new(File::open(bed)?))? {
+            let range = rec.start as usize..rec.end as usize;
+            intervals.entry(rec.chrom).or_default().push(range);
+        }
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let outputs = Outputs::new(args.dry_run);
+    let mut out = outputs.create_output(&args.output)?;
+    let mut seen = HashSet::new();
+
+    for rec in records {
+        let mut rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        };
+        // Every record with a given ID gets that ID's intervals
+        let mut ranges = intervals.get(&rec.id).cloned().unwrap_or_default();
+        if !ranges.is_empty() {
+            seen.insert(rec.id.clone());
+        }
+        if args.dust {
+            ranges.extend(rec.low_complexity(&dust));
+        }
+        // mask rejects non-ASCII sequences, which pass through untouched
+        // when there is nothing to mask
+        if !ranges.is_empty() {
+            if let Err(e) = rec.mask(&ranges, mode) {
+                out.flush()?;
+                return Err(e.into());
+            }
+        }
+        rec.write(&mut out)?;
+    }
+    out.flush()?;
+    if let Some(chrom) = intervals.keys().filter(|c| !seen.contains(*c)).min() {
+        return Err(format!("{} has intervals but no sequence", chrom).into());
+    }
+    outputs.report(&mut io::stdout().lock())?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::io::Write;
+use std::iter;
+use std::path::Path;
+
+use fasta::cancel::CancellationToken;
+use fasta::{DuplicatePolicy, Fasta};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OnDup {
+    /// Fail on the first repeated ID
+    Error,
+    /// Keep the first record with an ID and drop later ones
+    Skip,
+    /// Keep every record, renaming repeats to ID_1, ID_2, ...
+    Rename,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// What to do with a record whose ID was already seen
+    #[arg(long, value_enum, default_value_t = OnDup::Error)]
+    on_dup: OnDup,
+
+    /// Prefix IDs with the name of their file, up to its first dot, and
+    /// an underscore
+    #[arg(long)]
+    prefix_filename: bool,
+
+    /// Sequence line width, 0 for no wrapping
+    #[arg(long, default_value_t = 60)]
+    width: usize,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, merged in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+/// The name `--prefix-filename` gives records of `path`
+fn file_prefix(path: &Path) -> String {
+    if path.as_os_str() == "-" {
+        return "stdin".to_owned();
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.split('.').next().unwrap_or_default().to_owned()
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let policy = match args.on_dup {
+        OnDup::Error => DuplicatePolicy::Error,
+        OnDup::Skip => DuplicatePolicy::KeepFirst,
+        OnDup::Rename => DuplicatePolicy::RenameWithSuffix,
+    };
+    // Files are opened one at a time as the merge reaches them
+    let sources = crate::expand_inputs(&args.inputs)?.into_iter().map(|path| {
+        let records = match crate::open_path(&path, token) {
+            Ok(records) => records,
+            Err(e) => return Box::new(iter::once(Err(e))) as Box<dyn Iterator<Item = _>>,
+        };
+        let prefix = args.prefix_filename.then(|| file_prefix(&path));
+        Box::new(records.map(move |rec| {
+            let mut rec = rec?;
+            if let Some(prefix) = &prefix {
+                rec.id = format!("{}_{}", prefix, rec.id);
+            }
+            Ok(rec)
+        }))
+    });
+    let merged = Fasta::merge(sources, policy)?;
+
+    let mut out = crate::create_output(&args.output)?;
+    for rec in merged.records() {
+        rec.write_with_width(&mut out, args.width)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use fasta::cancel::CancellationToken;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Reverse complement only records with these IDs, passing the rest
+    /// through unchanged
+    #[arg(long, num_args = 1.., value_name = "ID")]
+    only: Vec<String>,
+
+    /// Reverse complement only records whose IDs are listed, one per line,
+    /// in this file
+    #[arg(long, value_name = "FILE")]
+    only_file: Option<PathBuf>,
+
+    /// Append a suffix to reverse complemented IDs: _rc for a bare
+    /// --suffix, otherwise given as --suffix=SUFFIX
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "_rc",
+        value_name = "SUFFIX"
+    )]
+    suffix: Option<String>,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let selected = !args.only.is_empty() || args.only_file.is_some();
+    let mut only: HashSet<String> = args.only.into_iter().collect();
+    if let Some(path) = &args.only_file {
+        let ids = fs::read_to_string(path)?;
+        only.extend(
+            ids.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(String::from),
+        );
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        };
+        if selected && !only.contains(&rec.id) {
+            rec.write(&mut out)?;
+            continue;
+        }
+        let mut rc = rec.reverse_complement();
+        if let Some(suffix) = &args.suffix {
+            rc.id.push_str(suffix);
+        }
+        rc.write(&mut out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
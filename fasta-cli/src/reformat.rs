@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::CancellationToken;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Wrap sequences at N characters
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 60,
+        conflicts_with = "no_wrap"
+    )]
+    wrap: usize,
+
+    /// Write each sequence on one line
+    #[arg(long)]
+    no_wrap: bool,
+
+    /// Convert sequences to upper case
+    #[arg(long, conflicts_with = "lower")]
+    upper: bool,
+
+    /// Convert sequences to lower case
+    #[arg(long)]
+    lower: bool,
+
+    /// Drop descriptions, keeping only IDs
+    #[arg(long)]
+    strip_desc: bool,
+
+    /// End lines with CRLF
+    #[arg(long)]
+    windows_newlines: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let width = if args.no_wrap { 0 } else { args.wrap };
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+
+    for rec in records {
+        let mut rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        };
+        if args.upper {
+            rec.sequence.make_ascii_uppercase();
+        } else if args.lower {
+            rec.sequence.make_ascii_lowercase();
+        }
+        if args.strip_desc {
+            rec.description.clear();
+        }
+        let text = rec.as_string_with_width(width);
+        if args.windows_newlines {
+            out.write_all(text.replace('\n', "\r\n").as_bytes())?;
+        } else {
+            out.write_all(text.as_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+
+use fasta::cancel::CancellationToken;
+use fasta::errors::FastaError;
+use fasta::rename::{read_map, Renamer};
+use fasta::Record;
+use regex::Regex;
+
+use crate::dry_run::Outputs;
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Prepend a prefix to every ID, after any other renaming
+    #[arg(long)]
+    prefix: Option<String>,
+
+    /// Rewrite IDs matching REGEX with TEMPLATE, which may use $1 for
+    /// capture groups; split at the last =. May be repeated
+    #[arg(long, value_name = "REGEX=TEMPLATE")]
+    replace: Vec<String>,
+
+    /// Rename IDs listed in a two-column old/new table before anything
+    /// else; other IDs are left alone
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+
+    /// Print the planned renames as an old/new table, and the file that
+    /// would be written, without writing it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let mut renamer = Renamer::new();
+    if let Some(path) = &args.map {
+        renamer = renamer.map(read_map(BufReader::new(File::open(path)?))?);
+    }
+    for spec in &args.replace {
+        let (re, template) = spec
+            .rsplit_once('=')
+            .ok_or_else(|| format!("--replace expects REGEX=TEMPLATE, got {}", spec))?;
+        renamer = renamer.replace(Regex::new(re)?, template);
+    }
+    if let Some(prefix) = &args.prefix {
+        renamer = renamer.prefix(prefix);
+    }
+    if args.map.is_none() && args.replace.is_empty() && args.prefix.is_none() {
+        return Err("give --prefix, --replace or --map".into());
+    }
+
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut records = records.collect::<Result<Vec<Record>, _>>()?;
+    let plan = renamer
+        .plan(records.iter().map(|r| r.id.as_str()))
+        .map_err(|e| match e {
+            FastaError::DuplicateId(id) => format!("renaming would give two records the ID {}", id),
+            e => e.to_string(),
+        })?;
+    let outputs = Outputs::new(args.dry_run);
+    let mut out = outputs.create_output(&args.output)?;
+    let mut stdout = io::stdout().lock();
+    if args.dry_run {
+        writeln!(stdout, "old\tnew")?;
+    }
+    for (rec, (old, new)) in records.iter_mut().zip(plan) {
+        if args.dry_run {
+            writeln!(stdout, "{}\t{}", old, new)?;
+        }
+        rec.id = new;
+    }
+    for rec in &records {
+        rec.write(&mut out)?;
+    }
+    out.flush()?;
+    if args.dry_run {
+        writeln!(stdout)?;
+    }
+    outputs.report(&mut stdout)?;
+    Ok(())
+}
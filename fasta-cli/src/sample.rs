@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::io::Write;
+
+use clap::ArgGroup;
+use fasta::cancel::CancellationToken;
+use fasta::sample::{sample_fraction, sample_n};
+use fasta::Record;
+
+#[derive(clap::Args)]
+#[group(skip)]
+#[command(group(ArgGroup::new("mode").required(true).args(["every", "n", "fraction"])))]
+pub struct Args {
+    /// Keep every N-th record, starting with the first
+    #[arg(long, value_name = "N")]
+    every: Option<usize>,
+
+    /// Keep a uniform random sample of N records, in input order
+    #[arg(short, value_name = "N")]
+    n: Option<usize>,
+
+    /// Keep each record with this probability, between 0 and 1
+    #[arg(long)]
+    fraction: Option<f64>,
+
+    /// Random seed for -n and --fraction; the same seed and input always
+    /// give the same sample
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let inputs = crate::open_inputs(&args.inputs, token)?;
+    let records: Box<dyn Iterator<Item = std::io::Result<Record>>> =
+        match (args.every, args.n, args.fraction) {
+            (Some(0), _, _) => return Err("--every must be at least 1".into()),
+            (Some(every), _, _) => Box::new(inputs.every_nth(every)),
+            (_, Some(n), _) => Box::new(sample_n(inputs, n, args.seed)?.into_iter().map(Ok)),
+            (_, _, Some(p)) if !(0.0..=1.0).contains(&p) => {
+                return Err("--fraction must be between 0 and 1".into())
+            }
+            (_, _, Some(p)) => Box::new(sample_fraction(inputs, p, args.seed)),
+            _ => unreachable!("clap requires one sampling mode"),
+        };
+    let mut out = crate::create_output(&args.output)?;
+
+    for rec in records {
+        match rec {
+            Ok(rec) => rec.write(&mut out)?,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
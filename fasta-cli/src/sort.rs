@@ -0,0 +1,79 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+use fasta::cancel::{self, CancellationToken};
+use fasta::sort::{sort_records, SortKey, SortOptions};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum By {
+    /// Longest sequence first
+    Length,
+    /// Natural ID order, chr2 before chr10
+    Id,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Sort order
+    #[arg(long, value_enum, default_value_t = By::Id)]
+    by: By,
+
+    /// Megabytes of records held in memory before a sorted run is written
+    /// to a temporary file
+    #[arg(long, default_value_t = 1024, value_name = "MB")]
+    memory: usize,
+
+    /// Directory for temporary run files, the system temporary directory
+    /// unless given
+    #[arg(long, value_name = "DIR")]
+    temp_dir: Option<PathBuf>,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    if args.memory == 0 {
+        return Err("--memory must be at least 1".into());
+    }
+    let run_bytes = args
+        .memory
+        .checked_mul(1 << 20)
+        .ok_or_else(|| format!("--memory {} is too large", args.memory))?;
+    let mut opts = SortOptions {
+        key: match args.by {
+            By::Length => SortKey::Length,
+            By::Id => SortKey::Id,
+        },
+        run_bytes,
+        ..SortOptions::default()
+    };
+    if let Some(dir) = args.temp_dir {
+        opts.temp_dir = dir;
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let sorted = sort_records(records, &opts)?;
+    let mut out = crate::create_output(&args.output)?;
+
+    for rec in sorted {
+        if token.is_cancelled() {
+            out.flush()?;
+            return Err(cancel::cancelled().into());
+        }
+        match rec {
+            Ok(rec) => rec.write(&mut out)?,
+            Err(e) => {
+                out.flush()?;
+                return Err(e.into());
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io;
+
+use clap::ArgGroup;
+use fasta::cancel::CancellationToken;
+use fasta::split::{
+    check_template, fill_template, split_by_count, split_by_length, split_by_size,
+    split_into_parts, split_per_record,
+};
+
+use crate::dry_run::Outputs;
+
+#[derive(clap::Args)]
+#[group(skip)]
+#[command(group(
+    ArgGroup::new("mode")
+        .required(true)
+        .args(["records", "by_part", "by_size", "by_length", "by_id"])
+))]
+pub struct Args {
+    /// Records per output file
+    #[arg(long, value_name = "N")]
+    records: Option<usize>,
+
+    /// Deal records out to N files in turn
+    #[arg(long, value_name = "N")]
+    by_part: Option<usize>,
+
+    /// Bytes of FASTA per output file, with an optional K, M or G suffix
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    by_size: Option<u64>,
+
+    /// Bases of sequence per output file, such as 5000000bp or 5Mb
+    #[arg(long, value_name = "LENGTH", value_parser = parse_length)]
+    by_length: Option<u64>,
+
+    /// Write each record to its own file
+    #[arg(long)]
+    by_id: bool,
+
+    /// Output file name; {} is replaced by the chunk number and {id} by
+    /// the first record's ID
+    #[arg(short, long, default_value = "chunk_{}.fa")]
+    output: String,
+
+    /// Compress output files with gzip, adding .gz to names without it
+    #[arg(long)]
+    gzip: bool,
+
+    /// Report the files that would be written without creating them
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+/// Parse a count with an optional K, M or G suffix, in powers of
+/// `base`, after removing any of the `units` suffixes
+fn parse_amount(s: &str, base: u64, units: &[&str]) -> Result<u64, String> {
+    let lower = s.to_ascii_lowercase();
+    let mut digits = lower.as_str();
+    if let Some(unit) = units.iter().find(|u| digits.ends_with(*u)) {
+        digits = &digits[..digits.len() - unit.len()];
+    }
+    let (digits, scale) = match digits.as_bytes().last() {
+        Some(b'k') => (&digits[..digits.len() - 1], base),
+        Some(b'm') => (&digits[..digits.len() - 1], base.pow(2)),
+        Some(b'g') => (&digits[..digits.len() - 1], base.pow(3)),
+        _ => (digits, 1),
+    };
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid amount {}", s))?;
+    match n.checked_mul(scale) {
+        Some(0) | None => Err(format!("{} must be a positive amount", s)),
+        Some(n) => Ok(n),
+    }
+}
+
+fn parse_size(s: &str) -> Result<u64, String> {
+    parse_amount(s, 1024, &["b"])
+}
+
+fn parse_length(s: &str) -> Result<u64, String> {
+    parse_amount(s, 1000, &["bp", "b"])
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    if args.records == Some(0) || args.by_part == Some(0) {
+        return Err("the number of records or parts must be at least 1".into());
+    }
+    // Only a single part can share one file name
+    if args.by_part != Some(1) {
+        check_template(&args.output)?;
+    }
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let outputs = Outputs::new(args.dry_run);
+    let template = args.output;
+    let gzip = args.gzip;
+    // {id} repeats with the IDs, and a chunk must not overwrite another
+    let mut written = HashSet::new();
+    let mut create = |chunk: usize, first: &fasta::Record| {
+        let mut path = fill_template(&template, chunk, first);
+        if !written.insert(path.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} would be written more than once", path),
+            ));
+        }
+        if !gzip {
+            return outputs.create(&path);
+        }
+        if !path.ends_with(".gz") {
+            path.push_str(".gz");
+        }
+        outputs.create_gzip(&path)
+    };
+    if let Some(n) = args.records {
+        split_by_count(records, n, &mut create)?;
+    } else if let Some(n) = args.by_part {
+        split_into_parts(records, n, &mut create)?;
+    } else if let Some(bytes) = args.by_size {
+        split_by_size(records, bytes, &mut create)?;
+    } else if let Some(bases) = args.by_length {
+        split_by_length(records, bases, &mut create)?;
+    } else {
+        split_per_record(records, &mut create)?;
+    }
+    outputs.finish()?;
+    outputs.report(&mut io::stdout().lock())?;
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::error::Error;
+use std::io;
+use std::io::Write;
+
+use fasta::cancel::CancellationToken;
+use fasta::report::{NumberFormat, SCHEMA_VERSION};
+use fasta::stats::Stats;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    Tsv,
+    Json,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Tsv)]
+    format: Format,
+
+    /// Also report each record; in TSV, rows are records instead of files
+    #[arg(long)]
+    per_record: bool,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, each reported separately; - or
+    /// none for stdin
+    inputs: Vec<String>,
+}
+
+/// Metrics of one record
+struct RecordStats {
+    id: String,
+    stats: Stats,
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn write_json_file<W: Write>(
+    w: &mut W,
+    file: &str,
+    stats: &Stats,
+    records: Option<&[RecordStats]>,
+    fmt: &NumberFormat,
+) -> io::Result<()> {
+    write!(
+        w,
+        "{{\"file\":{},\"records\":{},\"total_length\":{},\"min_length\":{},\
+         \"max_length\":{},\"mean_length\":{},\"n50\":{},\"gc_content\":{},\
+         \"n_bases\":{},\"n_fraction\":{}",
+        json_string(file),
+        stats.records,
+        stats.total_length,
+        stats.min_length,
+        stats.max_length,
+        fmt.format(stats.mean_length()),
+        stats.n50(),
+        fmt.format(stats.gc_content()),
+        stats.n_bases,
+        fmt.format(stats.n_fraction()),
+    )?;
+    if let Some(records) = records {
+        write!(w, ",\"sequences\":[")?;
+        for (i, rec) in records.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(
+                w,
+                "{{\"id\":{},\"length\":{},\"gc_content\":{},\"n_bases\":{}}}",
+                json_string(&rec.id),
+                rec.stats.total_length,
+                fmt.format(rec.stats.gc_content()),
+                rec.stats.n_bases,
+            )?;
+        }
+        write!(w, "]")?;
+    }
+    write!(w, "}}")
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let fmt = NumberFormat::default();
+    let mut out = crate::create_output(&args.output)?;
+    match (args.format, args.per_record) {
+        (Format::Tsv, false) => writeln!(
+            out,
+            "#{}\nfile\trecords\ttotal_length\tmin_length\tmax_length\tmean_length\t\
+             n50\tgc_content\tn_bases\tn_fraction",
+            SCHEMA_VERSION
+        )?,
+        (Format::Tsv, true) => writeln!(
+            out,
+            "#{}\nfile\tid\tlength\tgc_content\tn_bases",
+            SCHEMA_VERSION
+        )?,
+        (Format::Json, _) => write!(
+            out,
+            "{{\"schema\":{},\"files\":[",
+            json_string(SCHEMA_VERSION)
+        )?,
+    }
+
+    for (i, path) in crate::expand_inputs(&args.inputs)?.iter().enumerate() {
+        let file = path.display().to_string();
+        let mut stats = Stats::new();
+        let mut records = Vec::new();
+        for rec in crate::open_path(path, token)? {
+            let rec = rec?;
+            stats.add(&rec);
+            if args.per_record {
+                let mut rec_stats = Stats::new();
+                rec_stats.add(&rec);
+                records.push(RecordStats {
+                    id: rec.id,
+                    stats: rec_stats,
+                });
+            }
+        }
+        match (args.format, args.per_record) {
+            (Format::Tsv, false) => writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                file,
+                stats.records,
+                stats.total_length,
+                stats.min_length,
+                stats.max_length,
+                fmt.format(stats.mean_length()),
+                stats.n50(),
+                fmt.format(stats.gc_content()),
+                stats.n_bases,
+                fmt.format(stats.n_fraction()),
+            )?,
+            (Format::Tsv, true) => {
+                for rec in &records {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}\t{}\t{}",
+                        file,
+                        rec.id,
+                        rec.stats.total_length,
+                        fmt.format(rec.stats.gc_content()),
+                        rec.stats.n_bases,
+                    )?;
+                }
+            }
+            (Format::Json, per_record) => {
+                if i > 0 {
+                    write!(out, ",")?;
+                }
+                let records = if per_record { Some(&records[..]) } else { None };
+                write_json_file(&mut out, &file, &stats, records, &fmt)?;
+            }
+        }
+    }
+    if args.format == Format::Json {
+        writeln!(out, "]}}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
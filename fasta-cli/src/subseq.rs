@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::PathBuf;
+
+use fasta::bed::read_bed;
+use fasta::cancel::{self, CancellationToken};
+use fasta::index::{IndexedFasta, Region};
+use fasta::motif::Strand;
+use fasta::seq::reverse_complement;
+use fasta::Record;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum NameFrom {
+    /// name:start-end, 1-based, with (-) for reverse complemented regions
+    Region,
+    /// The BED name column, falling back to the region
+    Name,
+    /// The name of the sequence the region lies on
+    Sequence,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// BED file of regions to extract
+    #[arg(long, value_name = "FILE")]
+    bed: Option<PathBuf>,
+
+    /// Regions to extract: name, name:start or name:start-end, 1-based
+    /// and inclusive
+    #[arg(long, num_args = 1.., value_name = "REGION")]
+    regions: Vec<String>,
+
+    /// Reverse complement regions on the minus strand of the BED file
+    #[arg(long)]
+    strand_aware: bool,
+
+    /// How output records are named
+    #[arg(long, value_enum, default_value_t = NameFrom::Region)]
+    name_from: NameFrom,
+
+    /// Sequence line width, 0 for no wrapping
+    #[arg(long, default_value_t = 60)]
+    width: usize,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// FASTA file, plain or compressed with bgzip, indexed in memory if
+    /// it has no .fai
+    input: PathBuf,
+}
+
+/// A region to extract, with its BED name and strand
+struct Wanted {
+    region: Region,
+    name: Option<String>,
+    reverse: bool,
+}
+
+fn extract<R: Read + Seek>(
+    fasta: &mut IndexedFasta<R>,
+    args: &Args,
+    token: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let mut wanted = Vec::new();
+    for spec in &args.regions {
+        wanted.push(Wanted {
+            region: fasta.index().region(spec)?,
+            name: None,
+            reverse: false,
+        });
+    }
+    if let Some(bed) = &args.bed {
+        for rec in read_bed(BufReader::new(File::open(bed)?))? {
+            wanted.push(Wanted {
+                region: Region {
+                    name: rec.chrom,
+                    start: rec.start,
+                    end: rec.end,
+                },
+                name: rec.name,
+                reverse: args.strand_aware && rec.strand == Some(Strand::Reverse),
+            });
+        }
+    }
+
+    let mut out = crate::create_output(&args.output)?;
+    for w in wanted {
+        if token.is_cancelled() {
+            out.flush()?;
+            return Err(cancel::cancelled().into());
+        }
+        let region = &w.region;
+        let mut sequence = fasta.fetch_region(&region.name, region.start, region.end)?;
+        if w.reverse {
+            sequence = reverse_complement(&sequence);
+        }
+        let spelled = || {
+            let strand = if w.reverse { "(-)" } else { "" };
+            format!(
+                "{}:{}-{}{}",
+                region.name,
+                region.start + 1,
+                region.end,
+                strand
+            )
+        };
+        let id = match args.name_from {
+            NameFrom::Region => spelled(),
+            NameFrom::Name => w.name.clone().unwrap_or_else(spelled),
+            NameFrom::Sequence => region.name.clone(),
+        };
+        let rec = Record {
+            id,
+            description: String::new(),
+            sequence,
+        };
+        rec.write_with_width(&mut out, args.width)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    if args.bed.is_none() && args.regions.is_empty() {
+        return Err("give regions with --bed or --regions".into());
+    }
+    if crate::faidx::is_bgzipped(&args.input)? {
+        extract(&mut IndexedFasta::open_bgzf(&args.input)?, &args, token)
+    } else {
+        extract(&mut IndexedFasta::open(&args.input)?, &args, token)
+    }
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::io::Write;
+
+use fasta::cancel::{self, CancellationToken};
+use fasta::tabular::{TabReader, TabRecord};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// FASTA sequence line width, 0 for no wrapping
+    #[arg(long, default_value_t = 60)]
+    width: usize,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input files of tab-delimited records or glob patterns, read in
+    /// order; - or none for stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let mut out = crate::create_output(&args.output)?;
+    for path in crate::expand_inputs(&args.inputs)? {
+        for rec in TabReader::new(crate::open_raw(&path)?) {
+            if token.is_cancelled() {
+                out.flush()?;
+                return Err(cancel::cancelled().into());
+            }
+            match rec {
+                Ok(TabRecord::Fasta(rec)) => rec.write_with_width(&mut out, args.width)?,
+                Ok(TabRecord::Fastq(rec)) => rec.write(&mut out)?,
+                Err(e) => {
+                    out.flush()?;
+                    return Err(format!("{}: {}", path.display(), e).into());
+                }
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
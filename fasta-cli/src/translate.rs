@@ -0,0 +1,70 @@
+use std::error::Error;
+
+use fasta::cancel::CancellationToken;
+use fasta::translate::{translate_stream, GeneticCode, TranslateOptions};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// NCBI translation table number: 1-6, 9-16 or 21-33
+    #[arg(long, default_value_t = 1)]
+    table: u8,
+
+    /// Reading frame: 1, 2, 3 or -1, -2, -3 for the reverse strand
+    #[arg(long, default_value_t = 1, allow_hyphen_values = true)]
+    frame: i8,
+
+    /// Translate all six frames, suffixing IDs with _f{frame} unless
+    /// --suffix is given
+    #[arg(long, conflicts_with = "frame")]
+    all_frames: bool,
+
+    /// End each protein before its first stop codon
+    #[arg(long)]
+    trim_at_stop: bool,
+
+    /// Suffix appended to output IDs; {frame} is replaced by the frame
+    #[arg(long)]
+    suffix: Option<String>,
+
+    /// Protein line width, 0 for no wrapping
+    #[arg(long, default_value_t = 80)]
+    width: usize,
+
+    /// Output file, or - for stdout
+    #[arg(short, long, default_value = "-")]
+    output: String,
+
+    /// Input FASTA files or glob patterns, read in order; - or none for
+    /// stdin
+    inputs: Vec<String>,
+}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let code = GeneticCode::from_id(args.table).ok_or_else(|| {
+        let ids: Vec<String> = GeneticCode::all()
+            .iter()
+            .map(|c| c.id.to_string())
+            .collect();
+        format!(
+            "unknown translation table {}; expected one of {}",
+            args.table,
+            ids.join(", ")
+        )
+    })?;
+    let (frames, default_suffix) = if args.all_frames {
+        (vec![1, 2, 3, -1, -2, -3], "_f{frame}")
+    } else {
+        (vec![args.frame], "")
+    };
+    let opts = TranslateOptions {
+        code,
+        frames,
+        id_suffix: args.suffix.unwrap_or_else(|| default_suffix.to_owned()),
+        line_width: args.width,
+        trim_at_stop: args.trim_at_stop,
+    };
+    let records = crate::open_inputs(&args.inputs, token)?;
+    let mut out = crate::create_output(&args.output)?;
+    translate_stream(records, &mut out, &opts)?;
+    Ok(())
+}
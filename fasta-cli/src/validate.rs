@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+
+use fasta::cancel::{self, CancellationToken};
+use fasta::validate::{validate_with_cancel, ValidateOptions};
+use fasta::Alphabet;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum AlphabetArg {
+    Any,
+    Dna,
+    Rna,
+    Protein,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Characters allowed in sequences
+    #[arg(long, value_enum, default_value_t = AlphabetArg::Any)]
+    alphabet: AlphabetArg,
+
+    /// Fail on warnings as well as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Issues printed per file; the rest are only counted
+    #[arg(long, default_value_t = 100, value_name = "N")]
+    max_issues: usize,
+
+    /// Input FASTA files or glob patterns, each checked separately; - or
+    /// none for stdin
+    inputs: Vec<String>,
+}
+
+/// Why validation did not pass, determining the exit status
+#[derive(Debug)]
+pub(crate) struct Failed {
+    /// Files with errors, or warnings under --strict
+    invalid: usize,
+    /// Inputs that could not be read
+    unreadable: usize,
+}
+
+impl Failed {
+    /// 2 if any input was unreadable, otherwise 1 for invalid files
+    pub(crate) fn status(&self) -> i32 {
+        if self.unreadable > 0 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+fn files(n: usize) -> String {
+    if n == 1 {
+        "1 file".to_owned()
+    } else {
+        format!("{} files", n)
+    }
+}
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.invalid, self.unreadable) {
+            (invalid, 0) => write!(f, "{} failed validation", files(invalid)),
+            (0, unreadable) => write!(f, "{} could not be read", files(unreadable)),
+            (invalid, unreadable) => write!(
+                f,
+                "{} failed validation and {} could not be read",
+                files(invalid),
+                files(unreadable)
+            ),
+        }
+    }
+}
+
+impl Error for Failed {}
+
+pub fn run(args: Args, token: &CancellationToken) -> Result<(), Box<dyn Error>> {
+    let opts = ValidateOptions {
+        alphabet: match args.alphabet {
+            AlphabetArg::Any => Alphabet::Any,
+            AlphabetArg::Dna => Alphabet::Dna,
+            AlphabetArg::Rna => Alphabet::Rna,
+            AlphabetArg::Protein => Alphabet::Protein,
+        },
+        max_issues: args.max_issues,
+    };
+    let paths = crate::expand_inputs(&args.inputs)?;
+    let mut out = io::stdout().lock();
+    let mut invalid = 0;
+    let mut unreadable = 0;
+    for path in &paths {
+        let file = path.display();
+        let report = match crate::open_raw(path)
+            .and_then(|input| validate_with_cancel(input, &opts, token))
+        {
+            Ok(report) => report,
+            Err(e) if cancel::is_cancelled(&e) => return Err(e.into()),
+            Err(e) => {
+                // Reported in order with the other files, which are
+                // still checked
+                out.flush()?;
+                eprintln!("fasta: {}: {}", file, e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        for issue in &report.issues {
+            writeln!(out, "{}: {}", file, issue)?;
+        }
+        let shown = report.issues.len() as u64;
+        if report.errors + report.warnings > shown {
+            let hidden = report.errors + report.warnings - shown;
+            writeln!(out, "{}: {} more issues not shown", file, hidden)?;
+        }
+        let valid = report.is_valid(args.strict);
+        writeln!(
+            out,
+            "{}: {} records, {} errors, {} warnings: {}",
+            file,
+            report.records,
+            report.errors,
+            report.warnings,
+            if valid { "ok" } else { "FAILED" }
+        )?;
+        if !valid {
+            invalid += 1;
+        }
+    }
+    out.flush()?;
+    if invalid > 0 || unreadable > 0 {
+        return Err(Box::new(Failed {
+            invalid,
+            unreadable,
+        }));
+    }
+    Ok(())
+}
@@ -0,0 +1,618 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+fn write_input(name: &str, contents: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn fasta(args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn dedupe_report() {
+    let input = write_input("dedupe.fa", ">a\nACGTT\n>b\nGG\n>c\nAACGT\n>d\nACGTT\n");
+    let report = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dedupe.tsv");
+    let out = fasta(&[
+        "dedupe",
+        "--by",
+        "canonical-seq",
+        "--report",
+        report.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(out, ">a\nACGTT\n>b\nGG\n");
+    assert_eq!(
+        fs::read_to_string(&report).unwrap(),
+        "schema\tfasta-report/1\na\tc,d\n"
+    );
+
+    let planned = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("dedupe_dry.tsv");
+    let out = fasta(&[
+        "dedupe",
+        "--report",
+        planned.to_str().unwrap(),
+        "--dry-run",
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        out,
+        format!(
+            "file\trecords\tbytes\n-\t3\t24\n{}\t0\t26\n",
+            planned.display()
+        )
+    );
+    assert!(!planned.exists());
+}
+
+#[test]
+fn fq2fa_gzip() {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(b"@r1 lane1\nACGT\n+\nIIII\n@r2\nGG\n+\n!+\n")
+        .unwrap();
+    let input = write_input("fq2fa.fq.gz", "");
+    fs::write(&input, gz.finish().unwrap()).unwrap();
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fq2fa.fa.gz");
+    fasta(&[
+        "fq2fa",
+        "--mean-quality",
+        "-o",
+        output.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(fs::File::open(output).unwrap())
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, ">r1 lane1 mean_q=40.00\nACGT\n>r2 mean_q=5.00\nGG\n");
+}
+
+#[test]
+fn tabular_round_trip() {
+    let fa = write_input("fx2tab.fa", ">a x\nACGT\nAC\n>b\nGG\n");
+    let fq = write_input("fx2tab.fq", "@r1\nAC\n+\nII\n");
+    let tab = fasta(&["fx2tab", fa.to_str().unwrap(), fq.to_str().unwrap()]);
+    assert_eq!(tab, "a\tx\tACGTAC\nb\t\tGG\nr1\t\tAC\tII\n");
+    let tab = write_input("tab2fx.tsv", &tab);
+    let out = fasta(&["tab2fx", "--width", "4", tab.to_str().unwrap()]);
+    assert_eq!(out, ">a x\nACGT\nAC\n>b\nGG\n@r1\nAC\n+\nII\n");
+}
+
+#[test]
+fn grep_motif_and_ids() {
+    let input = write_input(
+        "grep.fa",
+        ">p1 circular\nAAGAATTCAA\n>p2\nACGTACGT\n>q1 circular\nTTGGATCC\n",
+    );
+    let input = input.to_str().unwrap();
+    let out = fasta(&["grep", "--seq", "GGATYC", input]);
+    assert_eq!(out, ">q1 circular\nTTGGATCC\n");
+    let out = fasta(&[
+        "grep",
+        "-c",
+        "--desc-regex",
+        "circ",
+        "--id-regex",
+        "^p",
+        input,
+    ]);
+    assert_eq!(out, "1\n");
+    let out = fasta(&["grep", "-v", "-c", "--id-regex", "^p", input]);
+    assert_eq!(out, "1\n");
+}
+
+#[test]
+fn lengths_tsv() {
+    let input = write_input("lengths.fa", ">a x\nACGT\nAC\n>b\nGGAT\n");
+    let input = input.to_str().unwrap();
+    assert_eq!(fasta(&["lengths", input]), "a\t6\nb\t4\n");
+    assert_eq!(
+        fasta(&["lengths", "--gc", input]),
+        "a\t6\t0.5000\nb\t4\t0.5000\n"
+    );
+}
+
+#[test]
+fn mask_bed() {
+    let input = write_input("mask.fa", ">chr1\nACGTACGT\n>chr2\nACGT\n");
+    let input = input.to_str().unwrap();
+    let bed = write_input("mask.bed", "chr1\t1\t3\nchr1\t6\t8\nchr2\t0\t1\n");
+    let bed = bed.to_str().unwrap();
+    let out = fasta(&["mask", "--bed", bed, "--mode", "soft", input]);
+    assert_eq!(out, ">chr1\nAcgTACgt\n>chr2\naCGT\n");
+    let out = fasta(&["mask", "--bed", bed, input]);
+    assert_eq!(out, ">chr1\nANNTACNN\n>chr2\nNCGT\n");
+    let out = fasta(&["mask", "--bed", bed, "--dry-run", input]);
+    assert_eq!(out, "file\trecords\tbytes\n-\t2\t26\n");
+
+    let dups = write_input("mask_dups.fa", ">chr2\nACGT\n>chr2\nTTTT\n");
+    let dups_bed = write_input("mask_dups.bed", "chr2\t0\t1\n");
+    let dups_bed = dups_bed.to_str().unwrap();
+    let out = fasta(&["mask", "--bed", dups_bed, dups.to_str().unwrap()]);
+    assert_eq!(out, ">chr2\nNCGT\n>chr2\nNTTT\n");
+
+    let bad = write_input("mask_bad.bed", "chr2\t2\t9\n");
+    let status = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["mask", "--bed", bad.to_str().unwrap(), input])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+}
+
+#[test]
+fn mask_dust() {
+    let flank = "GATCCTAGGCTAAGTCCGATATCGCGTAAGCTTGCAACGTGATCAGTCAGGTACCATGGCTA";
+    let input = format!(">r\n{}{}{}\n", flank, "A".repeat(100), flank);
+    let input = write_input("dust.fa", &input);
+    let out = fasta(&["mask", "--dust", "--mode", "soft", input.to_str().unwrap()]);
+    let masked: String = out.lines().skip(1).collect();
+    let n = masked.bytes().filter(u8::is_ascii_lowercase).count();
+    assert!((100..164).contains(&n), "{}", out);
+    assert!(masked.contains(&"a".repeat(100)), "{}", out);
+
+    let status = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["mask", "--bed", "x.bed", "--dust-window", "32"])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+    assert!(String::from_utf8_lossy(&status.stderr).contains("--dust"));
+}
+
+#[test]
+fn mask_passes_unnamed_records() {
+    let input = write_input("mask_utf8.fa", ">a\nACGT\n>b\nACéGT\n");
+    let bed = write_input("mask_utf8.bed", "a\t0\t2\n");
+    let out = fasta(&[
+        "mask",
+        "--bed",
+        bed.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(out, ">a\nNNGT\n>b\nACéGT\n");
+}
+
+#[test]
+fn merge_duplicates() {
+    let a = write_input("merge_a.fa", ">r1\nACGT\n>r2\nAA\n");
+    let b = write_input("merge_b.fa.gz", "");
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(b">r1\nGG\n").unwrap();
+    fs::write(&b, gz.finish().unwrap()).unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    let out = fasta(&["merge", "--on-dup", "rename", "--width", "2", a, b]);
+    assert_eq!(out, ">r1\nAC\nGT\n>r2\nAA\n>r1_1\nGG\n");
+    let out = fasta(&["merge", "--prefix-filename", a, b]);
+    assert_eq!(out, ">merge_a_r1\nACGT\n>merge_a_r2\nAA\n>merge_b_r1\nGG\n");
+    let status = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["merge", a, b])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+}
+
+#[test]
+fn reformat_case_and_wrapping() {
+    let input = write_input("reformat.fa", ">r1 desc\r\nacg\r\ntac\r\n");
+    let input = input.to_str().unwrap();
+    let out = fasta(&["reformat", "--wrap", "4", "--upper", "--strip-desc", input]);
+    assert_eq!(out, ">r1\nACGT\nAC\n");
+    let out = fasta(&["reformat", "--no-wrap", "--windows-newlines", input]);
+    assert_eq!(out, ">r1 desc\r\nacgtac\r\n");
+}
+
+#[test]
+fn rename_replace_and_prefix() {
+    let input = write_input("rename.fa", ">contig_1 x\nAC\n>contig_2\nGT\n");
+    let input = input.to_str().unwrap();
+    let args = [
+        "rename",
+        "--replace",
+        "^contig_(\\d+)$=ctg$1",
+        "--prefix",
+        "s1_",
+    ];
+    let out = fasta(&[&args[..], &[input]].concat());
+    assert_eq!(out, ">s1_ctg1 x\nAC\n>s1_ctg2\nGT\n");
+    let out = fasta(&[&args[..], &["--dry-run", input]].concat());
+    assert_eq!(
+        out,
+        "old\tnew\ncontig_1\ts1_ctg1\ncontig_2\ts1_ctg2\n\nfile\trecords\tbytes\n-\t2\t26\n"
+    );
+
+    let status = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["rename", "--replace", "_\\d=", input])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+}
+
+#[test]
+fn sample_every() {
+    let data: String = (0..5).map(|i| format!(">r{}\nACGT\n", i)).collect();
+    let input = write_input("sample_every.fa", &data);
+    let out = fasta(&["sample", "--every", "2", input.to_str().unwrap()]);
+    assert_eq!(out, ">r0\nACGT\n>r2\nACGT\n>r4\nACGT\n");
+}
+
+#[test]
+fn closed_stdout_is_not_an_error() {
+    let data: String = (0..100_000).map(|i| format!(">r{}\nACGT\n", i)).collect();
+    let input = write_input("sample_pipe.fa", &data);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["sample", "--every", "1", input.to_str().unwrap()])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut first = [0; 4];
+    stdout.read_exact(&mut first).unwrap();
+    assert_eq!(&first, b">r0\n");
+    drop(stdout);
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    assert!(
+        out.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn filter_thresholds() {
+    let input = write_input(
+        "filter.fa",
+        ">short\nGC\n>at\nATATAT\n>gc\nGCGCAT\n>ns\nGCNNNN\n",
+    );
+    let input = input.to_str().unwrap();
+    let out = fasta(&["filter", "--min-len", "3", "--min-gc", "0.5", input]);
+    assert_eq!(out, ">gc\nGCGCAT\n>ns\nGCNNNN\n");
+    let out = fasta(&["filter", "--max-n-fraction", "0.5", "--invert", input]);
+    assert_eq!(out, ">ns\nGCNNNN\n");
+}
+
+#[test]
+fn sort_by_length() {
+    let input = write_input("sort.fa", ">a\nAC\n>b\nACGT\n>c\nACG\n");
+    let out = fasta(&["sort", "--by", "length", input.to_str().unwrap()]);
+    assert_eq!(out, ">b\nACGT\n>c\nACG\n>a\nAC\n");
+
+    let huge = (usize::MAX >> 10).to_string();
+    let out = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["sort", "--memory", &huge, input.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("is too large"));
+}
+
+#[test]
+fn sample_seeded() {
+    let data: String = (0..50).map(|i| format!(">r{}\nACGT\n", i)).collect();
+    let input = write_input("sample_seeded.fa", &data);
+    let input = input.to_str().unwrap();
+    let first = fasta(&["sample", "-n", "5", "--seed", "42", input]);
+    assert_eq!(first.matches('>').count(), 5);
+    assert_eq!(first, fasta(&["sample", "-n", "5", "--seed", "42", input]));
+    let some = fasta(&["sample", "--fraction", "0.5", "--seed", "7", input]);
+    assert_eq!(
+        some,
+        fasta(&["sample", "--fraction", "0.5", "--seed", "7", input])
+    );
+    assert!(fasta(&["sample", "--fraction", "0", input]).is_empty());
+}
+
+#[test]
+fn translate_frames() {
+    let input = write_input("translate.fa", ">g1\nATGGCCAAATTT\n");
+    let out = fasta(&[
+        "translate",
+        "--frame",
+        "-1",
+        "--suffix",
+        "_f{frame}",
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(out, ">g1_f-1\nKFGH\n");
+}
+
+#[test]
+fn translate_all_frames() {
+    let input = write_input("translate_all.fa", ">g1\nATGTAAATG\n");
+    let out = fasta(&[
+        "translate",
+        "--all-frames",
+        "--trim-at-stop",
+        input.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        out,
+        ">g1_f1\nM\n>g1_f2\nCK\n>g1_f3\nVN\n>g1_f-1\nHLH\n>g1_f-2\nIY\n>g1_f-3\nFT\n"
+    );
+}
+
+#[test]
+fn split_dry_run() {
+    let data: String = (0..5).map(|i| format!(">r{}\nACGT\n", i)).collect();
+    let input = write_input("split_dry_run.fa", &data);
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let template = dir.join("dry_{}.fa");
+    let template = template.to_str().unwrap();
+    let out = fasta(&[
+        "split",
+        "--records",
+        "2",
+        "--output",
+        template,
+        "--dry-run",
+        input.to_str().unwrap(),
+    ]);
+    let first = template.replace("{}", "0");
+    assert_eq!(out.lines().nth(1).unwrap(), format!("{}\t2\t18", first));
+    assert_eq!(out.lines().count(), 4);
+    assert!(!PathBuf::from(first).exists());
+}
+
+#[test]
+fn split_modes() {
+    let data = ">a\nAAAA\n>b\nCC\n>c\nGGGGGG\n";
+    let input = write_input("split_modes.fa", data);
+    let input = input.to_str().unwrap();
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let template = dir.join("by_part_{}.fa");
+    let out = fasta(&[
+        "split",
+        "--by-part",
+        "2",
+        "-o",
+        template.to_str().unwrap(),
+        "--dry-run",
+        input,
+    ]);
+    let counts: Vec<&str> = out
+        .lines()
+        .skip(1)
+        .map(|l| l.split('\t').nth(1).unwrap())
+        .collect();
+    assert_eq!(counts, ["2", "1"]);
+
+    let template = dir.join("by_length_{}.fa");
+    fasta(&[
+        "split",
+        "--by-length",
+        "6bp",
+        "--gzip",
+        "-o",
+        template.to_str().unwrap(),
+        input,
+    ]);
+    for (i, expected) in [">a\nAAAA\n>b\nCC\n", ">c\nGGGGGG\n"].iter().enumerate() {
+        let path = dir.join(format!("by_length_{}.fa.gz", i));
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, *expected);
+    }
+}
+
+#[test]
+fn split_rejects_repeated_names() {
+    let input = write_input("split_repeat.fa", ">a\nAC\n>b\nGT\n>a\nTT\n");
+    let input = input.to_str().unwrap();
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let fails = |mode: &[&str], name: &str| {
+        let out = Command::new(env!("CARGO_BIN_EXE_fasta"))
+            .arg("split")
+            .args(mode)
+            .args(["-o", dir.join(name).to_str().unwrap(), input])
+            .output()
+            .unwrap();
+        !out.status.success()
+    };
+    assert!(fails(&["--records", "1"], "split_repeat_out.fa"));
+    assert!(!dir.join("split_repeat_out.fa").exists());
+    assert!(fails(&["--by-id"], "split_repeat_{id}.fa"));
+    assert!(!fails(&["--by-part", "1"], "split_repeat_one.fa"));
+    assert_eq!(
+        fs::read_to_string(dir.join("split_repeat_one.fa")).unwrap(),
+        ">a\nAC\n>b\nGT\n>a\nTT\n"
+    );
+}
+
+#[test]
+fn glob_inputs() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("glob_inputs");
+    fs::create_dir_all(&dir).unwrap();
+    for (name, id) in [("b.fa", "r2"), ("a.fa", "r1"), ("c.txt", "skip")] {
+        fs::write(dir.join(name), format!(">{}\nACGT\n", id)).unwrap();
+    }
+    let extra = write_input("glob_extra.fa", ">r3\nGG\n");
+    let pattern = dir.join("*.fa");
+    let out = fasta(&[
+        "sample",
+        "--every",
+        "1",
+        pattern.to_str().unwrap(),
+        extra.to_str().unwrap(),
+    ]);
+    assert_eq!(out, ">r1\nACGT\n>r2\nACGT\n>r3\nGG\n");
+}
+
+#[test]
+fn stdin_and_stdout() {
+    use std::process::Stdio;
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(b">r0\nAC\n>r1\nGT\n>r2\nTT\n").unwrap();
+    let gz = gz.finish().unwrap();
+    for args in [
+        &["sample", "--every", "2"][..],
+        &["sample", "--every", "2", "-"],
+    ] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_fasta"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(&gz).unwrap();
+        let out = child.wait_with_output().unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout, b">r0\nAC\n>r2\nTT\n");
+    }
+
+    let input = write_input("stdout_output.fa", ">r0\nAC\n");
+    let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("stdout_output.out.fa");
+    let out = fasta(&[
+        "sample",
+        "--every",
+        "1",
+        "-o",
+        output.to_str().unwrap(),
+        input.to_str().unwrap(),
+    ]);
+    assert!(out.is_empty());
+    assert_eq!(fs::read_to_string(output).unwrap(), ">r0\nAC\n");
+}
+
+#[test]
+fn stats_formats() {
+    let a = write_input("stats_a.fa", ">a\nACGTNN\n>b\nGGCC\n");
+    let b = write_input("stats_b.fa", ">c\nA\n");
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+    let tsv = fasta(&["stats", a, b]);
+    let lines: Vec<&str> = tsv.lines().collect();
+    assert_eq!(lines[0], "#fasta-report/1");
+    assert_eq!(
+        lines[2],
+        format!("{}\t2\t10\t4\t6\t5.0000\t6\t0.7500\t2\t0.2000", a)
+    );
+    assert_eq!(lines.len(), 4);
+
+    let per_record = fasta(&["stats", "--per-record", a]);
+    assert_eq!(
+        per_record.lines().nth(3).unwrap(),
+        format!("{}\tb\t4\t1.0000\t0", a)
+    );
+
+    let json = fasta(&["stats", "--format", "json", "--per-record", b]);
+    assert_eq!(
+        json.trim_end(),
+        format!(
+            "{{\"schema\":\"fasta-report/1\",\"files\":[{{\"file\":\"{}\",\"records\":1,\
+             \"total_length\":1,\"min_length\":1,\"max_length\":1,\"mean_length\":1.0000,\
+             \"n50\":1,\"gc_content\":0.0000,\"n_bases\":0,\"n_fraction\":0.0000,\
+             \"sequences\":[{{\"id\":\"c\",\"length\":1,\"gc_content\":0.0000,\"n_bases\":0}}]}}]}}",
+            b
+        )
+    );
+}
+
+#[test]
+fn faidx_regions() {
+    let input = write_input(
+        "faidx.fa",
+        ">chr1 first\nACGTA\nCGTAC\nGT\n>chr2\nTTTT\nGG\n",
+    );
+    let fai = PathBuf::from(format!("{}.fai", input.display()));
+    let _ = fs::remove_file(&fai);
+    let path = input.to_str().unwrap();
+    assert_eq!(fasta(&["faidx", path]), "");
+    assert_eq!(
+        fs::read_to_string(&fai).unwrap(),
+        "chr1\t12\t12\t5\t6\nchr2\t6\t33\t4\t5\n"
+    );
+    let out = fasta(&["faidx", "--width", "4", path, "chr1:4-11", "chr2"]);
+    assert_eq!(out, ">chr1:4-11\nTACG\nTACG\n>chr2\nTTTT\nGG\n");
+
+    let mut w = fasta::bgzf::BgzfWriter::new(Vec::new());
+    w.write_all(b">chr1\nACGTACGT\n").unwrap();
+    let (bgzf, _) = w.finish().unwrap();
+    let gz = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("faidx.fa.gz");
+    fs::write(&gz, bgzf).unwrap();
+    let _ = fs::remove_file(format!("{}.fai", gz.display()));
+    let _ = fs::remove_file(format!("{}.gzi", gz.display()));
+    let out = fasta(&["faidx", gz.to_str().unwrap(), "chr1:3-5"]);
+    assert_eq!(out, ">chr1:3-5\nGTA\n");
+    assert!(PathBuf::from(format!("{}.gzi", gz.display())).exists());
+}
+
+#[test]
+fn subseq_regions_and_bed() {
+    let input = write_input("subseq.fa", ">chr1\nAACCGGTT\n>chr2\nACGT\n");
+    let bed = write_input("subseq.bed", "chr1\t0\t3\tfirst\t0\t-\nchr2\t1\t3\n");
+    let (input, bed) = (input.to_str().unwrap(), bed.to_str().unwrap());
+    let out = fasta(&["subseq", "--regions", "chr1:3-6", "chr2", "--", input]);
+    assert_eq!(out, ">chr1:3-6\nCCGG\n>chr2:1-4\nACGT\n");
+
+    let out = fasta(&["subseq", "--bed", bed, "--strand-aware", input]);
+    assert_eq!(out, ">chr1:1-3(-)\nGTT\n>chr2:2-3\nCG\n");
+    let out = fasta(&["subseq", "--bed", bed, "--name-from", "name", input]);
+    assert_eq!(out, ">first\nAAC\n>chr2:2-3\nCG\n");
+    let out = fasta(&["subseq", "--bed", bed, "--name-from", "sequence", input]);
+    assert_eq!(out, ">chr1\nAAC\n>chr2\nCG\n");
+}
+
+#[test]
+fn rc_selected() {
+    let input = write_input("rc.fa", ">a x\nAACG\n>b\nTTGA\n");
+    let path = input.to_str().unwrap();
+    assert_eq!(fasta(&["rc", path]), ">a x\nCGTT\n>b\nTCAA\n");
+    let ids = write_input("rc_ids.txt", "b\n");
+    let out = fasta(&["rc", "--only-file", ids.to_str().unwrap(), "--suffix", path]);
+    assert_eq!(out, ">a x\nAACG\n>b_rc\nTCAA\n");
+    let out = fasta(&["rc", "--only", "a", "--suffix=.rev", path]);
+    assert_eq!(out, ">a.rev x\nCGTT\n>b\nTTGA\n");
+}
+
+#[test]
+fn validate_exit_status() {
+    let good = write_input("validate_good.fa", ">a\nACGT\n");
+    let warn = write_input("validate_warn.fa", ">a\nACGT\n>b\n");
+    let bad = write_input("validate_bad.fa", ">a\nACGT\n>a\nAC\n");
+    let status = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_fasta"))
+            .arg("validate")
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let (good, warn, bad) = (
+        good.to_str().unwrap(),
+        warn.to_str().unwrap(),
+        bad.to_str().unwrap(),
+    );
+    assert_eq!(status(&[good, warn]), Some(0));
+    assert_eq!(status(&["--strict", good, warn]), Some(1));
+    assert_eq!(status(&[bad]), Some(1));
+    assert_eq!(status(&["validate_missing.fa"]), Some(2));
+    assert_eq!(status(&["validate_missing.fa", bad]), Some(2));
+
+    let out = Command::new(env!("CARGO_BIN_EXE_fasta"))
+        .args(["validate", "validate_missing.fa", good])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.ends_with("1 records, 0 errors, 0 warnings: ok\n"));
+
+    let out = fasta(&["validate", warn]);
+    assert!(out.contains("line 3: warning: record b has an empty sequence"));
+    assert!(out.ends_with("2 records, 0 errors, 1 warnings: ok\n"));
+}
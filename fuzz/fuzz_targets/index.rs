@@ -0,0 +1,25 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use fasta::index::{FastaIndex, IndexedFasta};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Index the input and fetch every indexed sequence back out
+    if let Ok(index) = FastaIndex::build(data) {
+        let names: Vec<String> = index.entries().iter().map(|e| e.name.clone()).collect();
+        let mut fasta = IndexedFasta::new(Cursor::new(data), index);
+        for name in names {
+            let _ = fasta.fetch(&name);
+        }
+    }
+    // An arbitrary .fai must either be rejected or be safe to use
+    if let Ok(index) = FastaIndex::read(data) {
+        let entries = index.entries().to_vec();
+        let mut fasta = IndexedFasta::new(Cursor::new(data), index);
+        for e in entries.iter().take(16) {
+            let _ = fasta.fetch_region(&e.name, 0, e.length.min(1 << 16));
+        }
+    }
+});
@@ -0,0 +1,30 @@
+#![no_main]
+
+use fasta::{Case, FastaReaderBuilder};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte selects the parser options, the rest is the input
+    let (flags, input) = match data.split_first() {
+        Some((f, rest)) => (*f, rest),
+        None => return,
+    };
+    let builder = FastaReaderBuilder::new()
+        .trim_whitespace(flags & 1 != 0)
+        .comments(flags & 2 != 0)
+        .allow_blank_lines(flags & 4 != 0)
+        .allow_leading_junk(flags & 8 != 0)
+        .case(match (flags >> 4) % 3 {
+            0 => Case::Preserve,
+            1 => Case::Upper,
+            _ => Case::Lower,
+        });
+
+    for rec in builder.from_reader(input).take(1000).flatten() {
+        let _ = rec.as_string();
+        let _ = format!("{}", rec);
+        let _ = rec.etag();
+        let _ = rec.gc_content();
+        let _ = rec.reverse_complement();
+    }
+});
@@ -0,0 +1,41 @@
+/* C interface to the fasta crate, built with `cargo rustc --release
+ * --lib --features ffi --crate-type cdylib` as libfasta.so / libfasta.dylib / fasta.dll.
+ *
+ * Readers and records are opaque and owned by the caller. Strings
+ * returned for a record stay valid until the record is freed.
+ */
+#ifndef FASTA_H
+#define FASTA_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FastaReader fasta_reader;
+typedef struct FastaRecord fasta_record;
+
+/* Open a FASTA file; NULL if it cannot be opened. */
+fasta_reader *fasta_open(const char *path);
+
+/* The next record, or NULL at the end of the file or on an error. */
+fasta_record *fasta_next_record(fasta_reader *reader);
+
+/* The error from the last fasta_next_record call, or NULL. */
+const char *fasta_error(const fasta_reader *reader);
+
+const char *fasta_record_id(const fasta_record *record);
+const char *fasta_record_description(const fasta_record *record);
+const char *fasta_record_seq(const fasta_record *record);
+size_t fasta_record_len(const fasta_record *record);
+
+/* Free a record or a reader; NULL is ignored. */
+void fasta_record_free(fasta_record *record);
+void fasta_free(fasta_reader *reader);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FASTA_H */
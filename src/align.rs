@@ -0,0 +1,288 @@
+//! Pairwise global (Needleman-Wunsch) and local (Smith-Waterman)
+//! alignment with affine gap penalties
+//!
+//! Both functions use O(nm) time and memory, which suits the small
+//! comparisons this module is meant for. Bases are compared ignoring
+//! case.
+
+use crate::Record;
+
+/// Alignment scores. A gap of length `n` scores `gap_open + n * gap_extend`,
+/// so penalties are given as negative numbers; set `gap_open` to 0 for
+/// linear gap costs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scoring {
+    pub match_score: i32,
+    pub mismatch: i32,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+}
+
+impl Default for Scoring {
+    /// Match 2, mismatch -1, gap open -2, gap extend -1
+    fn default() -> Scoring {
+        Scoring {
+            match_score: 2,
+            mismatch: -1,
+            gap_open: -2,
+            gap_extend: -1,
+        }
+    }
+}
+
+/// The result of aligning record `a` (the query) to record `b`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairwiseAlignment {
+    pub score: i64,
+    /// The aligned part of `a`, with `-` for gaps
+    pub aligned_a: String,
+    /// The aligned part of `b`, with `-` for gaps
+    pub aligned_b: String,
+    /// 0-based, half-open range of `a` covered by the alignment
+    pub a_start: usize,
+    pub a_end: usize,
+    /// 0-based, half-open range of `b` covered by the alignment
+    pub b_start: usize,
+    pub b_end: usize,
+    /// CIGAR string of `a` against `b`: `M` for aligned bases, `I` for
+    /// bases only in `a` and `D` for bases only in `b`
+    pub cigar: String,
+}
+
+impl PairwiseAlignment {
+    /// Fraction of alignment columns with identical bases
+    pub fn identity(&self) -> f64 {
+        let columns = self.aligned_a.len();
+        if columns == 0 {
+            return 0.0;
+        }
+        let same = self
+            .aligned_a
+            .bytes()
+            .zip(self.aligned_b.bytes())
+            .filter(|(x, y)| x != &b'-' && x.eq_ignore_ascii_case(y))
+            .count();
+        same as f64 / columns as f64
+    }
+}
+
+/// Align the whole of `a` to the whole of `b`
+pub fn global(a: &Record, b: &Record, scoring: &Scoring) -> PairwiseAlignment {
+    align(a.sequence.as_bytes(), b.sequence.as_bytes(), scoring, false)
+}
+
+/// Find the best-scoring alignment between any part of `a` and any part
+/// of `b`. The alignment is empty, with score 0, if no pair of bases
+/// scores positively.
+pub fn local(a: &Record, b: &Record, scoring: &Scoring) -> PairwiseAlignment {
+    align(a.sequence.as_bytes(), b.sequence.as_bytes(), scoring, true)
+}
+
+const NEG: i64 = i64::MIN / 4;
+
+// States of the dynamic programme, also used as traceback pointers
+const MATCH: u8 = 0;
+const INS: u8 = 1;
+const DEL: u8 = 2;
+const START: u8 = 3;
+
+fn align(a: &[u8], b: &[u8], s: &Scoring, local: bool) -> PairwiseAlignment {
+    let (n, m) = (a.len(), b.len());
+    let w = m + 1;
+    let open = i64::from(s.gap_open) + i64::from(s.gap_extend);
+    let extend = i64::from(s.gap_extend);
+
+    // Best score of an alignment of a[..i] and b[..j] ending in each state
+    let mut sm = vec![NEG; (n + 1) * w];
+    let mut si = vec![NEG; (n + 1) * w];
+    let mut sd = vec![NEG; (n + 1) * w];
+    let mut pm = vec![START; (n + 1) * w];
+    let mut pi = vec![MATCH; (n + 1) * w];
+    let mut pd = vec![MATCH; (n + 1) * w];
+
+    sm[0] = 0;
+    if !local {
+        for i in 1..=n {
+            si[i * w] = open + extend * (i as i64 - 1);
+            pi[i * w] = if i == 1 { MATCH } else { INS };
+        }
+        for j in 1..=m {
+            sd[j] = open + extend * (j as i64 - 1);
+            pd[j] = if j == 1 { MATCH } else { DEL };
+        }
+    }
+
+    let best_of = |m: i64, i: i64, d: i64| -> (i64, u8) {
+        if m >= i && m >= d {
+            (m, MATCH)
+        } else if i >= d {
+            (i, INS)
+        } else {
+            (d, DEL)
+        }
+    };
+
+    let mut best = (0, 0, 0);
+    for i in 1..=n {
+        for j in 1..=m {
+            let c = i * w + j;
+            let diag = c - w - 1;
+            let pair = if a[i - 1].eq_ignore_ascii_case(&b[j - 1]) {
+                s.match_score
+            } else {
+                s.mismatch
+            };
+            let (prev, from) = best_of(sm[diag], si[diag], sd[diag]);
+            let (prev, from) = if local && prev <= 0 {
+                (0, START)
+            } else {
+                (prev, from)
+            };
+            sm[c] = prev + i64::from(pair);
+            pm[c] = from;
+
+            let up = c - w;
+            let (v, from) = best_of(sm[up] + open, si[up] + extend, sd[up] + open);
+            si[c] = v;
+            pi[c] = from;
+
+            let left = c - 1;
+            let (v, from) = best_of(sm[left] + open, si[left] + open, sd[left] + extend);
+            sd[c] = v;
+            pd[c] = from;
+
+            if local && sm[c] > best.0 {
+                best = (sm[c], i, j);
+            }
+        }
+    }
+
+    let (score, mut i, mut j, mut state) = if local {
+        (best.0, best.1, best.2, MATCH)
+    } else {
+        let c = n * w + m;
+        let (score, state) = best_of(sm[c], si[c], sd[c]);
+        (score, n, m, state)
+    };
+    let (a_end, b_end) = (i, j);
+
+    // An empty local alignment ends at (0, 0), so nothing is traced
+    let mut ops = Vec::new();
+    while i > 0 || j > 0 {
+        let c = i * w + j;
+        match state {
+            MATCH => {
+                state = pm[c];
+                ops.push(b'M');
+                i -= 1;
+                j -= 1;
+            }
+            INS => {
+                state = pi[c];
+                ops.push(b'I');
+                i -= 1;
+            }
+            _ => {
+                state = pd[c];
+                ops.push(b'D');
+                j -= 1;
+            }
+        }
+        if state == START {
+            break;
+        }
+    }
+    ops.reverse();
+
+    let (mut aligned_a, mut aligned_b) = (String::new(), String::new());
+    let (mut x, mut y) = (i, j);
+    for &op in &ops {
+        match op {
+            b'M' => {
+                aligned_a.push(a[x] as char);
+                aligned_b.push(b[y] as char);
+                x += 1;
+                y += 1;
+            }
+            b'I' => {
+                aligned_a.push(a[x] as char);
+                aligned_b.push('-');
+                x += 1;
+            }
+            _ => {
+                aligned_a.push('-');
+                aligned_b.push(b[y] as char);
+                y += 1;
+            }
+        }
+    }
+    PairwiseAlignment {
+        score,
+        aligned_a,
+        aligned_b,
+        a_start: i,
+        a_end,
+        b_start: j,
+        b_end,
+        cigar: cigar(&ops),
+    }
+}
+
+fn cigar(ops: &[u8]) -> String {
+    let mut out = String::new();
+    let mut iter = ops.iter().peekable();
+    while let Some(&op) = iter.next() {
+        let mut len = 1;
+        while iter.peek() == Some(&&op) {
+            iter.next();
+            len += 1;
+        }
+        out.push_str(&len.to_string());
+        out.push(op as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(seq: &str) -> Record {
+        Record {
+            sequence: seq.to_owned(),
+            ..Record::new()
+        }
+    }
+
+    #[test]
+    fn global_with_gap() {
+        let aln = global(&rec("ACGTTACG"), &rec("ACGACG"), &Scoring::default());
+        assert_eq!(aln.aligned_a, "ACGTTACG");
+        assert_eq!(aln.aligned_b, "ACG--ACG");
+        assert_eq!(aln.cigar, "3M2I3M");
+        // 6 matches, one gap of length 2
+        assert_eq!(aln.score, 12 - 4);
+        assert_eq!(
+            (aln.a_start, aln.a_end, aln.b_start, aln.b_end),
+            (0, 8, 0, 6)
+        );
+    }
+
+    #[test]
+    fn local_finds_core() {
+        let aln = local(
+            &rec("TTTTGATTACATTTT"),
+            &rec("CCGATTACACC"),
+            &Scoring::default(),
+        );
+        assert_eq!(aln.aligned_a, "GATTACA");
+        assert_eq!(aln.cigar, "7M");
+        assert_eq!((aln.a_start, aln.b_start), (4, 2));
+        assert_eq!(aln.score, 14);
+        assert_eq!(aln.identity(), 1.0);
+        assert_eq!(
+            local(&rec("AAAA"), &rec("CCCC"), &Scoring::default()).cigar,
+            ""
+        );
+    }
+}
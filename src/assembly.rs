@@ -0,0 +1,429 @@
+//! A mutable assembly workspace with undo history and AGP export
+//!
+//! `Assembly` wraps a `Fasta` whose record IDs are unique and tracks,
+//! for every sequence, which parts of the original input it is built
+//! from. Curation operations can be undone one at a time, and the
+//! result can be written as FASTA together with an AGP file describing
+//! it in terms of the original sequences.
+
+use std::io;
+use std::io::Write;
+
+use crate::collection::Fasta;
+use crate::errors::FastaError;
+use crate::Record;
+
+/// One part of an assembled sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    /// Bases `start..end` (0-based, half-open) of the original sequence
+    /// `source`, reverse complemented if `reverse` is set
+    Component {
+        source: String,
+        start: usize,
+        end: usize,
+        reverse: bool,
+    },
+    /// A run of `N` of the given length
+    Gap(usize),
+}
+
+impl Piece {
+    pub fn len(&self) -> usize {
+        match self {
+            Piece::Component { start, end, .. } => end - start,
+            Piece::Gap(len) => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The part of this piece covering `a..b`, relative to its start
+    fn slice(&self, a: usize, b: usize) -> Piece {
+        match self {
+            Piece::Component {
+                source,
+                start,
+                end,
+                reverse,
+            } => {
+                let (start, end) = if *reverse {
+                    (end - b, end - a)
+                } else {
+                    (start + a, start + b)
+                };
+                Piece::Component {
+                    source: source.clone(),
+                    start,
+                    end,
+                    reverse: *reverse,
+                }
+            }
+            Piece::Gap(_) => Piece::Gap(b - a),
+        }
+    }
+}
+
+type Entry = (Record, Vec<Piece>);
+
+/// Records replaced by one step of an operation
+#[derive(Debug)]
+struct Edit {
+    position: usize,
+    before: Vec<Entry>,
+    after_len: usize,
+}
+
+/// An editable set of sequences with undo history
+#[derive(Debug)]
+pub struct Assembly {
+    fasta: Fasta,
+    layouts: Vec<Vec<Piece>>,
+    history: Vec<Vec<Edit>>,
+}
+
+impl Assembly {
+    /// Start a workspace from `fasta`, whose record IDs must be unique
+    pub fn new(fasta: Fasta) -> Result<Assembly, FastaError> {
+        for (i, rec) in fasta.iter().enumerate() {
+            if fasta.position(&rec.id) != Some(i) {
+                return Err(FastaError::DuplicateId(rec.id.clone()));
+            }
+        }
+        let layouts = fasta
+            .iter()
+            .map(|rec| {
+                let len = rec.sequence.len();
+                let source = Piece::Component {
+                    source: rec.id.clone(),
+                    start: 0,
+                    end: len,
+                    reverse: false,
+                };
+                if len == 0 {
+                    vec![]
+                } else {
+                    vec![source]
+                }
+            })
+            .collect();
+        Ok(Assembly {
+            fasta,
+            layouts,
+            history: Vec::new(),
+        })
+    }
+
+    /// The current sequences
+    pub fn fasta(&self) -> &Fasta {
+        &self.fasta
+    }
+
+    /// Consume the workspace, returning the current sequences
+    pub fn into_fasta(self) -> Fasta {
+        self.fasta
+    }
+
+    /// The pieces making up the sequence `id`
+    pub fn layout(&self, id: &str) -> Option<&[Piece]> {
+        self.fasta.position(id).map(|i| self.layouts[i].as_slice())
+    }
+
+    /// Number of operations that can be undone
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Rename a sequence
+    pub fn rename(&mut self, id: &str, new_id: &str) -> Result<(), FastaError> {
+        let i = self.find(id)?;
+        if id != new_id {
+            self.ensure_free(new_id)?;
+        }
+        let (mut rec, layout) = self.entry(i);
+        rec.id = new_id.to_owned();
+        let edit = self.replace(i, 1, vec![(rec, layout)]);
+        self.history.push(vec![edit]);
+        Ok(())
+    }
+
+    /// Reverse complement a sequence in place
+    pub fn reverse_complement(&mut self, id: &str) -> Result<(), FastaError> {
+        let i = self.find(id)?;
+        let rec = self.fasta[i].reverse_complement();
+        let layout = self.layouts[i]
+            .iter()
+            .rev()
+            .map(|p| match p {
+                Piece::Component {
+                    source,
+                    start,
+                    end,
+                    reverse,
+                } => Piece::Component {
+                    source: source.clone(),
+                    start: *start,
+                    end: *end,
+                    reverse: !reverse,
+                },
+                Piece::Gap(len) => Piece::Gap(*len),
+            })
+            .collect();
+        let edit = self.replace(i, 1, vec![(rec, layout)]);
+        self.history.push(vec![edit]);
+        Ok(())
+    }
+
+    /// Split a sequence before position `at`. The first part keeps the
+    /// ID and description; the second becomes `right_id` and is placed
+    /// directly after it.
+    pub fn split(&mut self, id: &str, at: usize, right_id: &str) -> Result<(), FastaError> {
+        let i = self.find(id)?;
+        self.ensure_free(right_id)?;
+        let (rec, layout) = self.entry(i);
+        let len = rec.sequence.len();
+        if at == 0 || at >= len || !rec.sequence.is_char_boundary(at) {
+            return Err(out_of_bounds(&rec, at, at));
+        }
+        let left = Record {
+            id: rec.id.clone(),
+            description: rec.description.clone(),
+            sequence: rec.sequence[..at].to_owned(),
+        };
+        let right = Record {
+            id: right_id.to_owned(),
+            description: String::new(),
+            sequence: rec.sequence[at..].to_owned(),
+        };
+        let entries = vec![
+            (left, slice_layout(&layout, 0, at)),
+            (right, slice_layout(&layout, at, len)),
+        ];
+        let edit = self.replace(i, 1, entries);
+        self.history.push(vec![edit]);
+        Ok(())
+    }
+
+    /// Append `second` to `first`, separated by `gap` Ns. The joined
+    /// sequence keeps the ID, description and position of `first`.
+    pub fn join(&mut self, first: &str, second: &str, gap: usize) -> Result<(), FastaError> {
+        let i = self.find(first)?;
+        let j = self.find(second)?;
+        if i == j {
+            return Err(FastaError::DuplicateId(second.to_owned()));
+        }
+        let (mut rec, mut layout) = self.entry(i);
+        let (other, other_layout) = self.entry(j);
+        // iter::repeat_n needs Rust 1.82
+        #[allow(clippy::manual_repeat_n)]
+        rec.sequence.extend(std::iter::repeat('N').take(gap));
+        rec.sequence.push_str(&other.sequence);
+        if gap > 0 {
+            layout.push(Piece::Gap(gap));
+        }
+        layout.extend(other_layout);
+
+        let removed = self.replace(j, 1, vec![]);
+        let i = if j < i { i - 1 } else { i };
+        let joined = self.replace(i, 1, vec![(rec, layout)]);
+        self.history.push(vec![removed, joined]);
+        Ok(())
+    }
+
+    /// Remove bases `start..end` (0-based, half-open) from a sequence
+    pub fn excise(&mut self, id: &str, start: usize, end: usize) -> Result<(), FastaError> {
+        let i = self.find(id)?;
+        let (mut rec, layout) = self.entry(i);
+        let len = rec.sequence.len();
+        if start > end
+            || end > len
+            || !rec.sequence.is_char_boundary(start)
+            || !rec.sequence.is_char_boundary(end)
+        {
+            return Err(out_of_bounds(&rec, start, end));
+        }
+        rec.sequence.replace_range(start..end, "");
+        let mut new_layout = slice_layout(&layout, 0, start);
+        new_layout.extend(slice_layout(&layout, end, len));
+        let edit = self.replace(i, 1, vec![(rec, new_layout)]);
+        self.history.push(vec![edit]);
+        Ok(())
+    }
+
+    /// Revert the most recent operation. Returns false if there is
+    /// nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let edits = match self.history.pop() {
+            Some(edits) => edits,
+            None => return false,
+        };
+        for edit in edits.into_iter().rev() {
+            self.replace(edit.position, edit.after_len, edit.before);
+        }
+        true
+    }
+
+    /// Write the current sequences as FASTA
+    pub fn write_fasta<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for rec in self.fasta.iter() {
+            rec.write(&mut *w)?;
+        }
+        Ok(())
+    }
+
+    /// Write an AGP 2.1 file describing the current sequences in terms of
+    /// the original ones. Gaps are written as `scaffold` gaps with
+    /// unspecified linkage evidence.
+    pub fn write_agp<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "##agp-version\t2.1")?;
+        for (rec, layout) in self.fasta.iter().zip(&self.layouts) {
+            let mut pos = 0;
+            for (part, piece) in layout.iter().enumerate() {
+                let (beg, end) = (pos + 1, pos + piece.len());
+                pos = end;
+                write!(w, "{}\t{}\t{}\t{}\t", rec.id, beg, end, part + 1)?;
+                match piece {
+                    Piece::Component {
+                        source,
+                        start,
+                        end,
+                        reverse,
+                    } => writeln!(
+                        w,
+                        "W\t{}\t{}\t{}\t{}",
+                        source,
+                        start + 1,
+                        end,
+                        if *reverse { '-' } else { '+' }
+                    )?,
+                    Piece::Gap(len) => writeln!(w, "N\t{}\tscaffold\tyes\tunspecified", len)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find(&self, id: &str) -> Result<usize, FastaError> {
+        self.fasta
+            .position(id)
+            .ok_or_else(|| FastaError::UnknownId(id.to_owned()))
+    }
+
+    fn ensure_free(&self, id: &str) -> Result<(), FastaError> {
+        if self.fasta.contains(id) {
+            Err(FastaError::DuplicateId(id.to_owned()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn entry(&self, i: usize) -> Entry {
+        let rec = &self.fasta[i];
+        let copy = Record {
+            id: rec.id.clone(),
+            description: rec.description.clone(),
+            sequence: rec.sequence.clone(),
+        };
+        (copy, self.layouts[i].clone())
+    }
+
+    /// Replace `len` entries at `position`, returning the edit that undoes it
+    fn replace(&mut self, position: usize, len: usize, entries: Vec<Entry>) -> Edit {
+        let after_len = entries.len();
+        let (records, layouts): (Vec<Record>, Vec<Vec<Piece>>) = entries.into_iter().unzip();
+        let old_records = self.fasta.splice(position..position + len, records);
+        let old_layouts = self.layouts.splice(position..position + len, layouts);
+        Edit {
+            position,
+            before: old_records.into_iter().zip(old_layouts).collect(),
+            after_len,
+        }
+    }
+}
+
+fn out_of_bounds(rec: &Record, start: usize, end: usize) -> FastaError {
+    FastaError::OutOfBounds {
+        id: rec.id.clone(),
+        start,
+        end,
+        len: rec.sequence.len(),
+    }
+}
+
+/// The pieces covering `a..b` of the sequence built from `layout`
+fn slice_layout(layout: &[Piece], a: usize, b: usize) -> Vec<Piece> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    for piece in layout {
+        let (start, end) = (pos, pos + piece.len());
+        pos = end;
+        let (lo, hi) = (start.max(a), end.min(b));
+        if lo < hi {
+            result.push(piece.slice(lo - start, hi - start));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(id: &str, seq: &str) -> Record {
+        Record {
+            id: id.to_owned(),
+            description: String::new(),
+            sequence: seq.to_owned(),
+        }
+    }
+
+    fn assembly() -> Assembly {
+        Assembly::new(Fasta::from(vec![rec("a", "AACCGGTT"), rec("b", "TTTT")])).unwrap()
+    }
+
+    #[test]
+    fn curate_and_export() {
+        let mut asm = assembly();
+        asm.reverse_complement("b").unwrap();
+        asm.join("a", "b", 3).unwrap();
+        asm.excise("a", 1, 3).unwrap();
+        asm.split("a", 4, "a2").unwrap();
+        asm.rename("a", "chr1").unwrap();
+        let seqs: Vec<(&str, &str)> = asm
+            .fasta()
+            .iter()
+            .map(|r| (r.id.as_str(), r.sequence.as_str()))
+            .collect();
+        assert_eq!(seqs, [("chr1", "ACGG"), ("a2", "TTNNNAAAA")]);
+
+        let mut agp = Vec::new();
+        asm.write_agp(&mut agp).unwrap();
+        assert_eq!(
+            String::from_utf8(agp).unwrap(),
+            "##agp-version\t2.1\n\
+             chr1\t1\t1\t1\tW\ta\t1\t1\t+\n\
+             chr1\t2\t4\t2\tW\ta\t4\t6\t+\n\
+             a2\t1\t2\t1\tW\ta\t7\t8\t+\n\
+             a2\t3\t5\t2\tN\t3\tscaffold\tyes\tunspecified\n\
+             a2\t6\t9\t3\tW\tb\t1\t4\t-\n"
+        );
+    }
+
+    #[test]
+    fn undo_restores_state() {
+        let mut asm = assembly();
+        asm.join("b", "a", 0).unwrap();
+        asm.split("b", 2, "a").unwrap();
+        assert!(asm.rename("b", "a").is_err());
+        assert_eq!(asm.history_len(), 2);
+        assert!(asm.undo());
+        assert!(asm.undo());
+        assert!(!asm.undo());
+        let ids: Vec<&str> = asm.fasta().iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(asm.fasta()["a"].sequence, "AACCGGTT");
+        assert_eq!(asm.layout("b").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+//! Structured `key=value` attributes in record descriptions
+//!
+//! Descriptions such as `gene=ABC1 locus_tag=XYZ [organism=E. coli]`
+//! carry metadata as whitespace-separated `key=value` pairs (values may
+//! be double quoted) and as bracketed NCBI source modifiers, whose
+//! values may contain spaces. `Record::attributes` parses them, keeping
+//! any other text, and `Record::set_attributes` writes them back.
+
+use crate::Record;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Text(String),
+    Pair {
+        key: String,
+        value: String,
+        bracketed: bool,
+    },
+}
+
+/// The parsed description of a record, in its original order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    tokens: Vec<Token>,
+}
+
+impl Attributes {
+    /// Parse a description
+    pub fn parse(description: &str) -> Attributes {
+        let mut tokens = Vec::new();
+        let mut rest = description.trim_start();
+        while !rest.is_empty() {
+            let (token, len) = if let Some(inner) = rest.strip_prefix('[') {
+                match inner.find(']') {
+                    Some(end) => (bracketed(&inner[..end]), end + 2),
+                    None => word(rest),
+                }
+            } else {
+                word(rest)
+            };
+            tokens.push(token);
+            rest = rest[len..].trim_start();
+        }
+        Attributes { tokens }
+    }
+
+    /// The value of the first attribute named `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Iterate over `(key, value)` pairs in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tokens.iter().filter_map(|t| match t {
+            Token::Pair { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Token::Text(_) => None,
+        })
+    }
+
+    /// Number of attributes
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set the value of `key`, replacing the first existing value in
+    /// place or appending a new `key=value` pair
+    pub fn insert(&mut self, key: &str, value: &str) {
+        for t in self.tokens.iter_mut() {
+            if let Token::Pair {
+                key: k, value: v, ..
+            } = t
+            {
+                if k == key {
+                    *v = value.to_owned();
+                    return;
+                }
+            }
+        }
+        self.tokens.push(Token::Pair {
+            key: key.to_owned(),
+            value: value.to_owned(),
+            bracketed: false,
+        });
+    }
+
+    /// Remove every attribute named `key`, returning the first value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let first = self.get(key).map(str::to_owned);
+        self.tokens
+            .retain(|t| !matches!(t, Token::Pair { key: k, .. } if k == key));
+        first
+    }
+
+    /// The words of the description that are not attributes
+    pub fn text(&self) -> String {
+        let words: Vec<&str> = self
+            .tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Text(s) => Some(s.as_str()),
+                Token::Pair { .. } => None,
+            })
+            .collect();
+        words.join(" ")
+    }
+
+    /// Serialize back to a description, separating tokens by single
+    /// spaces. Plain values containing whitespace or quotes, or that are
+    /// empty, are double quoted, with inner double quotes written as
+    /// single quotes.
+    pub fn to_description(&self) -> String {
+        let parts: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => s.clone(),
+                Token::Pair {
+                    key,
+                    value,
+                    bracketed: true,
+                } => format!("[{}={}]", key, value),
+                Token::Pair { key, value, .. } => {
+                    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"') {
+                        format!("{}=\"{}\"", key, value.replace('"', "'"))
+                    } else {
+                        format!("{}={}", key, value)
+                    }
+                }
+            })
+            .collect();
+        parts.join(" ")
+    }
+}
+
+fn bracketed(inner: &str) -> Token {
+    match inner.split_once('=') {
+        Some((k, v)) if !k.trim().is_empty() => Token::Pair {
+            key: k.trim().to_owned(),
+            value: v.trim().to_owned(),
+            bracketed: true,
+        },
+        _ => Token::Text(format!("[{}]", inner)),
+    }
+}
+
+/// The token at the start of `s` and its length in bytes
+fn word(s: &str) -> (Token, usize) {
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    match s[..end].split_once('=') {
+        Some((k, v)) if !k.is_empty() => {
+            if let Some(quoted) = s[k.len() + 1..].strip_prefix('"') {
+                if let Some(close) = quoted.find('"') {
+                    let value = quoted[..close].to_owned();
+                    let len = k.len() + 1 + close + 2;
+                    return (
+                        Token::Pair {
+                            key: k.to_owned(),
+                            value,
+                            bracketed: false,
+                        },
+                        len,
+                    );
+                }
+            }
+            (
+                Token::Pair {
+                    key: k.to_owned(),
+                    value: v.to_owned(),
+                    bracketed: false,
+                },
+                end,
+            )
+        }
+        _ => (Token::Text(s[..end].to_owned()), end),
+    }
+}
+
+impl Record {
+    /// Parse the description into attributes
+    pub fn attributes(&self) -> Attributes {
+        Attributes::parse(&self.description)
+    }
+
+    /// Replace the description with serialized `attributes`
+    pub fn set_attributes(&mut self, attributes: &Attributes) {
+        self.description = attributes.to_description();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_serialize() {
+        let rec = Record {
+            description:
+                "putative kinase gene=ABC1  note=\"two words\" [organism=E. coli] [strain=K-12]"
+                    .to_owned(),
+            ..Record::new()
+        };
+        let mut attrs = rec.attributes();
+        assert_eq!(attrs.len(), 4);
+        assert_eq!(attrs.get("gene"), Some("ABC1"));
+        assert_eq!(attrs.get("note"), Some("two words"));
+        assert_eq!(attrs.get("organism"), Some("E. coli"));
+        assert_eq!(attrs.text(), "putative kinase");
+
+        attrs.insert("gene", "ABC2");
+        attrs.insert("locus_tag", "XYZ");
+        assert_eq!(attrs.remove("strain").as_deref(), Some("K-12"));
+        assert_eq!(
+            attrs.to_description(),
+            "putative kinase gene=ABC2 note=\"two words\" [organism=E. coli] locus_tag=XYZ"
+        );
+        assert_eq!(Attributes::parse(&attrs.to_description()), attrs);
+    }
+}
@@ -0,0 +1,86 @@
+//! BED interval files
+//!
+//! `read_bed` parses the first six columns of BED lines: the sequence
+//! name, 0-based half-open coordinates, and the optional name and strand.
+//! Further columns are ignored.
+
+use std::io::BufRead;
+
+use crate::errors::FastaError;
+use crate::motif::Strand;
+
+/// One interval of a BED file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedRecord {
+    pub chrom: String,
+    /// 0-based start
+    pub start: u64,
+    /// Exclusive end
+    pub end: u64,
+    pub name: Option<String>,
+    /// None when the strand column is missing or `.`
+    pub strand: Option<Strand>,
+}
+
+fn parse_line(line: &str, n: usize) -> Result<BedRecord, FastaError> {
+    let err = |message: &str| FastaError::Format {
+        line: n,
+        message: message.to_owned(),
+    };
+    let f: Vec<&str> = line.split('\t').collect();
+    if f.len() < 3 {
+        return Err(err("expected at least 3 tab-separated columns"));
+    }
+    let start: u64 = f[1].parse().map_err(|_| err("invalid start"))?;
+    let end: u64 = f[2].parse().map_err(|_| err("invalid end"))?;
+    if end < start {
+        return Err(err("end must not be before start"));
+    }
+    let strand = match f.get(5).copied() {
+        Some("+") => Some(Strand::Forward),
+        Some("-") => Some(Strand::Reverse),
+        Some(".") | None => None,
+        Some(_) => return Err(err("invalid strand")),
+    };
+    Ok(BedRecord {
+        chrom: f[0].to_owned(),
+        start,
+        end,
+        name: f.get(3).filter(|s| !s.is_empty()).map(|s| (*s).to_owned()),
+        strand,
+    })
+}
+
+/// Read the intervals of a BED file, skipping blank, comment, `track`
+/// and `browser` lines
+pub fn read_bed<R: BufRead>(r: R) -> Result<Vec<BedRecord>, FastaError> {
+    let mut records = Vec::new();
+    for (n, line) in r.lines().enumerate() {
+        let line = line?;
+        let skip = ["#", "track", "browser"];
+        if line.trim().is_empty() || skip.iter().any(|s| line.starts_with(s)) {
+            continue;
+        }
+        records.push(parse_line(line.trim_end_matches('\r'), n + 1)?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bed() {
+        let input = "track name=x\n# note\nchr1\t0\t10\nchr2\t5\t8\tgene1\t0\t-\textra\n";
+        let records = read_bed(input.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, None);
+        assert_eq!(records[1].name.as_deref(), Some("gene1"));
+        assert_eq!(records[1].strand, Some(Strand::Reverse));
+        assert_eq!((records[1].start, records[1].end), (5, 8));
+
+        let bad = read_bed("chr1\t10\t5\n".as_bytes()).unwrap_err();
+        assert!(matches!(bad, FastaError::Format { line: 1, .. }));
+    }
+}
@@ -0,0 +1,403 @@
+//! BGZF, the blocked gzip of `bgzip` and samtools
+//!
+//! A BGZF file is a series of gzip members holding at most 64 KiB each,
+//! so it can be decompressed from the start of any block. `BgzfWriter`
+//! writes one, returning the virtual offset of each record, and keeps a
+//! `.gzi` index of where blocks start. `BgzfReader` uses that index to
+//! seek by uncompressed offset, so `IndexedFasta` can read a bgzipped
+//! file with an ordinary `.fai` index (see `IndexedFasta::open_bgzf`).
+//!
+//! A virtual offset is the compressed offset of a block shifted left 16
+//! bits, plus an offset within the block's uncompressed data.
+
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+
+use crate::Record;
+
+// Uncompressed bytes per block, as in htslib, leaving room for deflate
+// overhead within the 64 KiB block limit
+const BLOCK_SIZE: usize = 0xff00;
+const HEADER_LEN: usize = 18;
+const FOOTER_LEN: usize = 8;
+
+// The empty block ending every BGZF file
+const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A `.gzi` index: the compressed and uncompressed offsets at which
+/// blocks start, after the first block at 0, 0
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GziIndex {
+    entries: Vec<(u64, u64)>,
+}
+
+impl GziIndex {
+    /// Pairs of compressed and uncompressed offsets, in file order
+    pub fn entries(&self) -> &[(u64, u64)] {
+        &self.entries
+    }
+
+    /// Read an index in the little-endian format of `bgzip -i`
+    pub fn read<R: Read>(mut r: R) -> io::Result<GziIndex> {
+        let mut word = [0u8; 8];
+        let mut next = |r: &mut R| -> io::Result<u64> {
+            r.read_exact(&mut word)?;
+            Ok(u64::from_le_bytes(word))
+        };
+        let n = next(&mut r)?;
+        let mut entries = Vec::new();
+        for _ in 0..n {
+            entries.push((next(&mut r)?, next(&mut r)?));
+        }
+        Ok(GziIndex { entries })
+    }
+
+    /// Write the index in the format of `bgzip -i`
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (compressed, uncompressed) in &self.entries {
+            w.write_all(&compressed.to_le_bytes())?;
+            w.write_all(&uncompressed.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Build an index by reading the block headers and sizes of a BGZF
+    /// file, without decompressing it
+    pub fn build<R: Read + Seek>(mut r: R) -> io::Result<GziIndex> {
+        let mut entries = Vec::new();
+        let (mut compressed, mut uncompressed) = (0u64, 0u64);
+        let mut header = [0u8; HEADER_LEN];
+        while read_header(&mut r, &mut header)? {
+            let bsize = block_size(&header)?;
+            r.seek(SeekFrom::Current((bsize - HEADER_LEN - 4) as i64))?;
+            let mut isize = [0u8; 4];
+            r.read_exact(&mut isize)?;
+            compressed += bsize as u64;
+            let len = u64::from(u32::from_le_bytes(isize));
+            // Empty blocks such as the end of file marker need no entry
+            if len > 0 {
+                uncompressed += len;
+                entries.push((compressed, uncompressed));
+            }
+        }
+        Ok(GziIndex { entries })
+    }
+}
+
+/// The conventional index path for a BGZF file, `<path>.gzi`
+pub fn gzi_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut p = path.as_ref().as_os_str().to_owned();
+    p.push(".gzi");
+    PathBuf::from(p)
+}
+
+/// Whether `bytes` start with a BGZF block header, to tell BGZF from
+/// other gzip
+pub fn is_bgzf(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LEN
+        && bytes[..4] == [0x1f, 0x8b, 0x08, 0x04]
+        && bytes[10..14] == [6, 0, b'B', b'C']
+}
+
+// Read a block header, returning false at a clean end of input
+fn read_header<R: Read>(r: &mut R, header: &mut [u8; HEADER_LEN]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match r.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+// Total size of the block starting with `header`
+fn block_size(header: &[u8; HEADER_LEN]) -> io::Result<usize> {
+    if !is_bgzf(header) {
+        return Err(invalid_data("not a BGZF block"));
+    }
+    let bsize = usize::from(u16::from_le_bytes([header[16], header[17]])) + 1;
+    if bsize < HEADER_LEN + FOOTER_LEN {
+        return Err(invalid_data("BGZF block is too short"));
+    }
+    Ok(bsize)
+}
+
+/// Writes BGZF. Call `finish` once everything is written to add the end
+/// of file marker.
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    level: flate2::Compression,
+    block: Vec<u8>,
+    // Offsets at which the block being filled starts
+    compressed: u64,
+    uncompressed: u64,
+    gzi: GziIndex,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    /// A writer compressing at zlib's default level
+    pub fn new(inner: W) -> BgzfWriter<W> {
+        BgzfWriter::with_level(inner, flate2::Compression::default())
+    }
+
+    /// A writer compressing at `level`
+    pub fn with_level(inner: W, level: flate2::Compression) -> BgzfWriter<W> {
+        BgzfWriter {
+            inner,
+            level,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            compressed: 0,
+            uncompressed: 0,
+            gzi: GziIndex::default(),
+        }
+    }
+
+    /// The virtual offset of the next byte written
+    pub fn virtual_offset(&self) -> u64 {
+        (self.compressed << 16) | self.block.len() as u64
+    }
+
+    /// Write a record, returning the virtual offset of its header
+    pub fn write_record(&mut self, rec: &Record) -> io::Result<u64> {
+        let offset = self.virtual_offset();
+        rec.write(self)?;
+        Ok(offset)
+    }
+
+    /// The index of the blocks written so far
+    pub fn gzi(&self) -> &GziIndex {
+        &self.gzi
+    }
+
+    /// Write any buffered data and the end of file marker, returning the
+    /// underlying writer and the complete index
+    pub fn finish(mut self) -> io::Result<(W, GziIndex)> {
+        self.write_block()?;
+        self.inner.write_all(&EOF_BLOCK)?;
+        self.inner.flush()?;
+        Ok((self.inner, self.gzi))
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::with_capacity(BLOCK_SIZE), self.level);
+        encoder.write_all(&self.block)?;
+        let cdata = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+
+        let bsize = HEADER_LEN + cdata.len() + FOOTER_LEN;
+        let mut header = [
+            0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0, 0, 0,
+        ];
+        header[16..].copy_from_slice(&((bsize - 1) as u16).to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&cdata)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.block.len() as u32).to_le_bytes())?;
+
+        self.compressed += bsize as u64;
+        self.uncompressed += self.block.len() as u64;
+        self.gzi.entries.push((self.compressed, self.uncompressed));
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..n]);
+        if self.block.len() == BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    /// Ends the current block early and flushes the underlying writer
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.flush()
+    }
+}
+
+/// Reads BGZF, seeking by uncompressed offset with a `.gzi` index
+pub struct BgzfReader<R: Read + Seek> {
+    inner: R,
+    // Block starts, including the first at 0, 0
+    starts: Vec<(u64, u64)>,
+    block: Vec<u8>,
+    pos: usize,
+    // Uncompressed offset of the start of `block`
+    block_start: u64,
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// A reader over BGZF input `inner`, described by `gzi`
+    pub fn new(inner: R, gzi: GziIndex) -> BgzfReader<R> {
+        let mut starts = vec![(0, 0)];
+        starts.extend(gzi.entries);
+        BgzfReader {
+            inner,
+            starts,
+            block: Vec::new(),
+            pos: 0,
+            block_start: 0,
+        }
+    }
+
+    // Replace `block` with the next block, returning false at the end of
+    // the input
+    fn next_block(&mut self) -> io::Result<bool> {
+        self.block_start += self.block.len() as u64;
+        self.block.clear();
+        self.pos = 0;
+        let mut header = [0u8; HEADER_LEN];
+        if !read_header(&mut self.inner, &mut header)? {
+            return Ok(false);
+        }
+        let mut rest = vec![0u8; block_size(&header)? - HEADER_LEN];
+        self.inner.read_exact(&mut rest)?;
+        let (cdata, footer) = rest.split_at(rest.len() - FOOTER_LEN);
+        DeflateDecoder::new(cdata).read_to_end(&mut self.block)?;
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if footer[..4] != crc.sum().to_le_bytes()
+            || footer[4..] != (self.block.len() as u32).to_le_bytes()
+        {
+            return Err(invalid_data("BGZF block failed its CRC or size check"));
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Skip empty blocks such as the end of file marker
+        while self.pos == self.block.len() {
+            if !self.next_block()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for BgzfReader<R> {
+    /// Seek to an uncompressed offset; seeking from the end is not
+    /// supported
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(target) => target,
+            SeekFrom::Current(delta) => (self.block_start + self.pos as u64)
+                .checked_add_signed(delta)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "cannot seek from the end of BGZF input",
+                ))
+            }
+        };
+        let i = self.starts.partition_point(|&(_, u)| u <= target) - 1;
+        let (compressed, uncompressed) = self.starts[i];
+        self.inner.seek(SeekFrom::Start(compressed))?;
+        self.block.clear();
+        self.block_start = uncompressed;
+        while self.block_start + (self.block.len() as u64) <= target {
+            if !self.next_block()? {
+                break;
+            }
+        }
+        self.pos = (target - self.block_start).min(self.block.len() as u64) as usize;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{FastaIndex, IndexedFasta};
+    use std::io::Cursor;
+
+    #[test]
+    fn write_index_and_seek() {
+        let records: Vec<Record> = (0..40)
+            .map(|i| Record {
+                id: format!("seq{}", i),
+                description: String::new(),
+                sequence: "ACGTTGCA".repeat(400 + i),
+            })
+            .collect();
+        let mut w = BgzfWriter::new(Vec::new());
+        let offsets: Vec<u64> = records
+            .iter()
+            .map(|rec| w.write_record(rec).unwrap())
+            .collect();
+        let (bgzf, gzi) = w.finish().unwrap();
+        assert!(is_bgzf(&bgzf));
+        assert!(!is_bgzf(&bgzf[..10]));
+        assert!(gzi.entries().len() > 2);
+        assert_eq!(GziIndex::build(Cursor::new(&bgzf)).unwrap(), gzi);
+        let mut saved = Vec::new();
+        gzi.write(&mut saved).unwrap();
+        assert_eq!(GziIndex::read(&saved[..]).unwrap(), gzi);
+
+        let mut plain = String::new();
+        flate2::read::MultiGzDecoder::new(&bgzf[..])
+            .read_to_string(&mut plain)
+            .unwrap();
+        let expected: String = records.iter().map(Record::as_string).collect();
+        assert_eq!(plain, expected);
+
+        // Each virtual offset points at its record's header
+        let starts: Vec<u64> = std::iter::once(0)
+            .chain(gzi.entries().iter().map(|&(c, _)| c))
+            .collect();
+        let mut at = 0;
+        for (rec, voffset) in records.iter().zip(offsets) {
+            let block = starts.iter().position(|&c| c == voffset >> 16).unwrap();
+            let uncompressed = std::iter::once(0)
+                .chain(gzi.entries().iter().map(|&(_, u)| u))
+                .nth(block)
+                .unwrap();
+            assert_eq!(uncompressed + (voffset & 0xffff), at);
+            at += rec.as_string().len() as u64;
+        }
+
+        let index = FastaIndex::build(plain.as_bytes()).unwrap();
+        let mut indexed = IndexedFasta::new(BgzfReader::new(Cursor::new(bgzf), gzi), index);
+        for name in ["seq39", "seq7", "seq20"] {
+            let rec = indexed.fetch(name).unwrap();
+            let i: usize = name[3..].parse().unwrap();
+            assert_eq!(rec.sequence, records[i].sequence);
+        }
+        assert_eq!(
+            indexed.fetch_region("seq13", 3195, 3205).unwrap(),
+            &records[13].sequence[3195..3205]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Cooperative cancellation of long-running operations
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::errors::FastaError;
+
+/// A cloneable flag used to ask long-running operations to stop.
+///
+/// Readers and builders given a token check it as they go; once it is
+/// cancelled they stop at the next record or line and report an error
+/// recognized by `is_cancelled`. Cancelling is typically done from a
+/// signal handler or another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Returns a new token that has not been cancelled
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request cancellation of every operation holding a clone of this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The error reported by an operation that stopped because it was cancelled
+pub fn cancelled() -> io::Error {
+    io::Error::other(FastaError::Cancelled)
+}
+
+/// Whether an io::Error was produced by a cancelled operation
+pub fn is_cancelled(e: &io::Error) -> bool {
+    matches!(
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<FastaError>()),
+        Some(FastaError::Cancelled)
+    )
+}
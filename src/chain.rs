@@ -0,0 +1,231 @@
+//! Reading several inputs as one stream of records
+//!
+//! `Chain` yields the records of each of a series of `FastaBuffer`s in
+//! turn, opening the next only once the previous is exhausted, so a set
+//! of per-chromosome files reads like a single file without holding them
+//! all open. `FastaBuffer::from_paths` chains files, decompressing each
+//! as `fasta::open` does; `chain` chains buffers over any readers.
+
+use std::io;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use crate::{FastaBuffer, FastaReaderBuilder, Record};
+
+/// Iterator over the records of several sources, created by `chain`,
+/// `FastaBuffer::from_paths` or `FastaReaderBuilder::open_paths`
+pub struct Chain<I, B: BufRead> {
+    sources: I,
+    current: Option<FastaBuffer<B>>,
+    // Number of sources taken from `sources`
+    taken: usize,
+}
+
+impl<I, B> Chain<I, B>
+where
+    I: Iterator<Item = io::Result<FastaBuffer<B>>>,
+    B: BufRead,
+{
+    /// Chain the buffers produced by `sources`. A source that fails to
+    /// open yields its error and the chain moves on to the next.
+    pub fn new(sources: I) -> Chain<I, B> {
+        Chain {
+            sources,
+            current: None,
+            taken: 0,
+        }
+    }
+
+    /// Index of the source the last record or error came from
+    pub fn source_index(&self) -> usize {
+        self.taken.saturating_sub(1)
+    }
+
+    /// Keep every n-th record across all sources, starting with the
+    /// first, as `FastaBuffer::every_nth` does. Panics if `n` is 0.
+    pub fn every_nth(self, n: usize) -> EveryNth<I, B> {
+        assert!(n > 0, "every_nth requires n > 0");
+        EveryNth {
+            inner: self,
+            n,
+            first: true,
+        }
+    }
+
+    /// Yield records without their sequences, paired with the sequence
+    /// lengths, as `FastaBuffer::lengths` does
+    pub fn lengths(self) -> Lengths<I, B> {
+        Lengths { inner: self }
+    }
+
+    fn read_record(&mut self, keep: bool) -> Option<io::Result<Record>> {
+        loop {
+            if let Some(rec) = self.current.as_mut().and_then(|b| b.read_record(keep)) {
+                return Some(rec);
+            }
+            self.current = None;
+            self.taken += 1;
+            match self.sources.next()? {
+                Ok(buffer) => self.current = Some(buffer),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<I, B> Iterator for Chain<I, B>
+where
+    I: Iterator<Item = io::Result<FastaBuffer<B>>>,
+    B: BufRead,
+{
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record(true)
+    }
+}
+
+/// Iterator returned by `Chain::every_nth`
+pub struct EveryNth<I, B: BufRead> {
+    inner: Chain<I, B>,
+    n: usize,
+    first: bool,
+}
+
+impl<I, B> Iterator for EveryNth<I, B>
+where
+    I: Iterator<Item = io::Result<FastaBuffer<B>>>,
+    B: BufRead,
+{
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.first {
+            for _ in 1..self.n {
+                match self.inner.read_record(false)? {
+                    Ok(_) => (),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        self.first = false;
+        self.inner.read_record(true)
+    }
+}
+
+/// Iterator returned by `Chain::lengths`
+pub struct Lengths<I, B: BufRead> {
+    inner: Chain<I, B>,
+}
+
+impl<I, B> Iterator for Lengths<I, B>
+where
+    I: Iterator<Item = io::Result<FastaBuffer<B>>>,
+    B: BufRead,
+{
+    type Item = io::Result<(Record, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rec = self.inner.read_record(false)?;
+        let len = self.inner.current.as_ref().map_or(0, |b| b.seq_len);
+        Some(rec.map(|rec| (rec, len)))
+    }
+}
+
+/// Chain already opened buffers
+pub fn chain<I, B>(buffers: I) -> Chain<impl Iterator<Item = io::Result<FastaBuffer<B>>>, B>
+where
+    I: IntoIterator<Item = FastaBuffer<B>>,
+    B: BufRead,
+{
+    Chain::new(buffers.into_iter().map(Ok))
+}
+
+/// Files opened in turn by a `Chain` from `FastaBuffer::from_paths`
+pub struct Paths {
+    paths: std::vec::IntoIter<PathBuf>,
+    opts: FastaReaderBuilder,
+}
+
+impl Iterator for Paths {
+    type Item = io::Result<FastaBuffer<Box<dyn BufRead + Send>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = self.paths.next()?;
+        Some(
+            self.opts
+                .open(&path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+        )
+    }
+}
+
+impl FastaReaderBuilder {
+    /// Read the files at `paths` in order as one stream of records.
+    /// Each file is opened when the one before it is exhausted.
+    pub fn open_paths(&self, paths: &[PathBuf]) -> Chain<Paths, Box<dyn BufRead + Send>> {
+        Chain::new(Paths {
+            paths: Vec::from(paths).into_iter(),
+            opts: self.clone(),
+        })
+    }
+}
+
+impl FastaBuffer<Box<dyn BufRead + Send>> {
+    /// Read the files at `paths` in order with the default options,
+    /// decompressing each as `fasta::open` does
+    pub fn from_paths(paths: &[PathBuf]) -> Chain<Paths, Box<dyn BufRead + Send>> {
+        FastaReaderBuilder::new().open_paths(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn reads_files_in_order() {
+        let dir = std::env::temp_dir().join(format!("fasta-chain-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("chr1.fa");
+        std::fs::write(&plain, ">chr1\nACGT\n>chr1b\nA\n").unwrap();
+        let gz = dir.join("chr2.fa.gz");
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        enc.write_all(b">chr2\nGG\n").unwrap();
+        std::fs::write(&gz, enc.finish().unwrap()).unwrap();
+
+        let paths = [plain, dir.join("missing.fa"), gz];
+        let mut records = FastaBuffer::from_paths(&paths);
+        let mut ids = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(rec) = records.next() {
+            match rec {
+                Ok(rec) => ids.push((rec.id, records.source_index())),
+                Err(e) => errors.push((e.to_string(), records.source_index())),
+            }
+        }
+        assert_eq!(
+            ids,
+            [("chr1".into(), 0), ("chr1b".into(), 0), ("chr2".into(), 2)]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.contains("missing.fa"));
+        assert_eq!(errors[0].1, 1);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let buffers = vec![
+            FastaBuffer::from(&b">a\nA\n"[..]),
+            FastaBuffer::from(&b">b\nC\n"[..]),
+        ];
+        let ids: Vec<String> = chain(buffers).map(|r| r.unwrap().id).collect();
+        assert_eq!(ids, ["a", "b"]);
+
+        let buffers = vec![
+            FastaBuffer::from(&b">a\nA\n>b\nA\n"[..]),
+            FastaBuffer::from(&b">c\nC\n>d\nC\n>e\nC\n"[..]),
+        ];
+        let ids: Vec<String> = chain(buffers).every_nth(2).map(|r| r.unwrap().id).collect();
+        assert_eq!(ids, ["a", "c", "e"]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Records delivered in pieces, for sequences too large to hold
+//!
+//! `Chunked`, created by `FastaBuffer::chunked`, yields a `Piece::Header`
+//! for each record followed by its sequence as `Piece::Sequence` chunks
+//! of a fixed size, each carrying its coordinate in the record. Only one
+//! chunk and one input line are held at a time, so memory is bounded by
+//! the chunk size and the longest line rather than the record length: a
+//! record on a single line is still read whole, unless a
+//! `guard::Limits::max_record_bytes` caps the line length.
+
+use std::io;
+use std::io::BufRead;
+
+use crate::cancel;
+use crate::parser::{Parser, Step};
+use crate::{guard, validation_error, FastaBuffer, Record};
+
+/// Part of a record, yielded by `Chunked`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece {
+    /// The start of a record, with an empty sequence
+    Header(Record),
+    /// The next stretch of the current record's sequence
+    Sequence(SequenceChunk),
+}
+
+/// A stretch of sequence at a position in its record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceChunk {
+    /// 0-based offset of the first base in the record's sequence
+    pub start: usize,
+    /// `chunk_size` bases, or fewer at the end of the record
+    pub sequence: String,
+}
+
+impl SequenceChunk {
+    /// Offset just past the last base
+    pub fn end(&self) -> usize {
+        self.start + self.sequence.len()
+    }
+}
+
+/// An iterator over the headers and sequence chunks of a FastaBuffer's
+/// records
+pub struct Chunked<B: BufRead> {
+    buffer: FastaBuffer<B>,
+    parser: Parser,
+    chunk_size: usize,
+    // The current record's ID, kept for errors after the parser drops
+    // the record
+    id: String,
+    // Sequence of the current record not yet handed out, and the offset
+    // of its first base
+    pending: String,
+    start: usize,
+    // The current record has ended, so `pending` is its last chunk
+    ended: bool,
+}
+
+impl<B: BufRead> Chunked<B> {
+    pub(crate) fn new(buffer: FastaBuffer<B>, chunk_size: usize) -> Chunked<B> {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        let parser = Parser::new(buffer.opts.parse);
+        Chunked {
+            buffer,
+            parser,
+            chunk_size,
+            id: String::new(),
+            pending: String::new(),
+            start: 0,
+            ended: false,
+        }
+    }
+
+    /// Hand out the first `chunk_size` pending bases, or all of them
+    fn take_chunk(&mut self) -> SequenceChunk {
+        let rest = if self.pending.len() > self.chunk_size {
+            let mut at = self.chunk_size;
+            while !self.pending.is_char_boundary(at) {
+                at += 1;
+            }
+            self.pending.split_off(at)
+        } else {
+            String::new()
+        };
+        let chunk = SequenceChunk {
+            start: self.start,
+            sequence: std::mem::replace(&mut self.pending, rest),
+        };
+        self.start = chunk.end();
+        chunk
+    }
+}
+
+impl<B: BufRead> Iterator for Chunked<B> {
+    type Item = io::Result<Piece>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let full = self.pending.len() >= self.chunk_size;
+            if full || (self.ended && !self.pending.is_empty()) {
+                return Some(Ok(Piece::Sequence(self.take_chunk())));
+            }
+            if self.buffer.finished {
+                return None;
+            }
+            self.ended = false;
+            if let Some(token) = &self.buffer.opts.cancel {
+                if token.is_cancelled() {
+                    self.buffer.finished = true;
+                    return Some(Err(cancel::cancelled()));
+                }
+            }
+            let line = match self.buffer.lines.next_line() {
+                Some(Ok(line)) => line,
+                Some(Err(mut e)) => {
+                    guard::set_record_id(&mut e, &self.id);
+                    if guard::is_limit_error(&e) {
+                        self.buffer.finished = true;
+                    }
+                    return Some(Err(e));
+                }
+                None => {
+                    self.parser.take_sequence(&mut self.pending);
+                    self.parser.finish();
+                    self.buffer.finished = true;
+                    self.ended = true;
+                    continue;
+                }
+            };
+            match self.parser.push_line(line.text, true) {
+                Ok(Step::Done(_)) => {
+                    // The header starts the next record
+                    self.buffer.lines.unread();
+                    self.ended = true;
+                }
+                Ok(Step::Header) => {
+                    self.start = 0;
+                    let mut rec = Record::new();
+                    if let Some(header) = self.parser.record() {
+                        rec.id.clone_from(&header.id);
+                        rec.description.clone_from(&header.description);
+                    }
+                    self.id.clone_from(&rec.id);
+                    return Some(Ok(Piece::Header(rec)));
+                }
+                Ok(Step::Sequence) => self.parser.take_sequence(&mut self.pending),
+                Ok(Step::Skipped) | Ok(Step::Junk) => (),
+                Err(e) => {
+                    self.pending.clear();
+                    let err = validation_error(
+                        &self.id,
+                        line.number,
+                        line.offset,
+                        line.text,
+                        e,
+                        self.buffer.opts.context_width,
+                    );
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_with_coordinates() {
+        let input = ">a one\nACGTA\nCG\n>b\n>c\nACGT\n";
+        let pieces: Vec<Piece> = FastaBuffer::from(input.as_bytes())
+            .chunked(3)
+            .map(Result::unwrap)
+            .collect();
+        let got: Vec<String> = pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Header(rec) => format!(">{} {}", rec.id, rec.description),
+                Piece::Sequence(chunk) => format!("{}:{}", chunk.start, chunk.sequence),
+            })
+            .collect();
+        assert_eq!(
+            got,
+            [">a one", "0:ACG", "3:TAC", "6:G", ">b ", ">c ", "0:ACG", "3:T"]
+        );
+
+        let strict = crate::FastaReaderBuilder::new().alphabet(crate::parser::Alphabet::Dna);
+        let mut pieces = strict.from_reader(">x\nACGT\nAXG\n".as_bytes()).chunked(4);
+        assert!(matches!(pieces.next(), Some(Ok(Piece::Header(_)))));
+        assert!(matches!(pieces.next(), Some(Ok(Piece::Sequence(_)))));
+        let err = pieces.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("line 3, column 2"), "{}", err);
+        assert!(err.to_string().contains("in record x"), "{}", err);
+    }
+}
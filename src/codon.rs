@@ -0,0 +1,276 @@
+//! Codon-aware operations on coding sequences
+
+use std::io;
+use std::io::Write;
+
+use crate::errors::FastaError;
+use crate::report::{NumberFormat, SCHEMA_VERSION};
+use crate::translate::GeneticCode;
+use crate::Record;
+
+fn is_gap(c: char) -> bool {
+    c == '-' || c == '.'
+}
+
+/// Thread a CDS through an aligned protein row, producing a codon alignment row.
+///
+/// Each residue of `protein_row` consumes the next codon of `cds` and each
+/// gap (`-` or `.`) becomes `---`, so the result is exactly three times
+/// as wide as the protein alignment. Gaps already present in the CDS are
+/// ignored. A single trailing codon in the CDS that has no residue in
+/// the protein is dropped if it is a stop codon in the standard code;
+/// any other length difference is an error.
+pub fn insert_gaps_in_codons(cds: &Record, protein_row: &Record) -> Result<Record, FastaError> {
+    insert_gaps_in_codons_with_code(cds, protein_row, &GeneticCode::standard())
+}
+
+/// As `insert_gaps_in_codons`, recognising a trailing stop codon by
+/// `code` rather than the standard code
+pub fn insert_gaps_in_codons_with_code(
+    cds: &Record,
+    protein_row: &Record,
+    code: &GeneticCode,
+) -> Result<Record, FastaError> {
+    let nucs: Vec<char> = cds.sequence.chars().filter(|&c| !is_gap(c)).collect();
+    let residues = protein_row.sequence.chars().filter(|&c| !is_gap(c)).count();
+
+    let trailing_stop = nucs.len() == 3 * (residues + 1) && {
+        let codon: String = nucs[3 * residues..].iter().collect();
+        code.translate_codon(codon.as_bytes()) == b'*'
+    };
+    if nucs.len() != 3 * residues && !trailing_stop {
+        return Err(FastaError::LengthMismatch {
+            id: cds.id.clone(),
+            expected: 3 * residues,
+            found: nucs.len(),
+        });
+    }
+
+    let mut sequence = String::with_capacity(3 * protein_row.sequence.len());
+    let mut codons = nucs.chunks(3);
+    for c in protein_row.sequence.chars() {
+        if is_gap(c) {
+            sequence.push_str("---");
+        } else if let Some(codon) = codons.next() {
+            sequence.extend(codon);
+        }
+    }
+
+    Ok(Record {
+        id: cds.id.clone(),
+        description: cds.description.clone(),
+        sequence,
+    })
+}
+
+const BASES: &[u8; 4] = b"TCAG";
+
+/// Index of a codon in NCBI order (TCAG at each position), or None if a
+/// base is not one of ACGTU
+fn codon_index(codon: &[u8]) -> Option<usize> {
+    codon.iter().try_fold(0, |index, b| {
+        let base = match b.to_ascii_uppercase() {
+            b'T' | b'U' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => return None,
+        };
+        Some(index * 4 + base)
+    })
+}
+
+/// Counts of the 64 codons of one or more coding sequences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodonUsage {
+    counts: [u64; 64],
+}
+
+impl Default for CodonUsage {
+    /// No codons counted
+    fn default() -> CodonUsage {
+        CodonUsage { counts: [0; 64] }
+    }
+}
+
+impl CodonUsage {
+    /// Count the codons of `cds`, read in frame from its first base.
+    ///
+    /// Gaps (`-` or `.`) are removed first, so aligned CDSs can be
+    /// counted. Case is ignored and U is read as T; codons with any other
+    /// base are skipped, as is a trailing partial codon.
+    pub fn add_sequence(&mut self, cds: &str) {
+        let bases: Vec<u8> = cds.bytes().filter(|&b| !is_gap(b as char)).collect();
+        for codon in bases.chunks_exact(3) {
+            if let Some(i) = codon_index(codon) {
+                self.counts[i] += 1;
+            }
+        }
+    }
+
+    /// Add the counts of `other` to these
+    pub fn merge(&mut self, other: &CodonUsage) {
+        for (a, b) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *a += b;
+        }
+    }
+
+    /// The count of `codon`, 0 if it is not three of ACGTU
+    pub fn count(&self, codon: &str) -> u64 {
+        match codon.len() {
+            3 => codon_index(codon.as_bytes()).map_or(0, |i| self.counts[i]),
+            _ => 0,
+        }
+    }
+
+    /// Total number of codons counted
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The fraction of all codons counted that are `codon`, 0 if none
+    /// were counted
+    pub fn frequency(&self, codon: &str) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.count(codon) as f64 / total as f64,
+        }
+    }
+
+    /// `(codon, count)` for the 64 codons in NCBI order: TTT, TTC, TTA,
+    /// TTG, TCT, ... GGG
+    pub fn counts(&self) -> impl Iterator<Item = (String, u64)> + '_ {
+        self.counts.iter().enumerate().map(|(i, &n)| {
+            let codon = [i >> 4, (i >> 2) & 3, i & 3]
+                .iter()
+                .map(|&b| BASES[b] as char)
+                .collect();
+            (codon, n)
+        })
+    }
+
+    /// Write the 64-row codon usage table as TSV, after the schema line,
+    /// under a header: codon, the amino acid it encodes under `code`,
+    /// count, frequency per thousand codons, and fraction of the amino
+    /// acid's codons. Fractions of an amino acid with no codons counted
+    /// are written as 0.
+    pub fn write_table<W: Write>(
+        &self,
+        w: &mut W,
+        code: &GeneticCode,
+        fmt: &NumberFormat,
+    ) -> io::Result<()> {
+        let total = self.total();
+        let amino_acids: Vec<u8> = self
+            .counts()
+            .map(|(codon, _)| code.translate_codon(codon.as_bytes()))
+            .collect();
+        let mut synonymous = [0; 256];
+        for (&aa, &n) in amino_acids.iter().zip(self.counts.iter()) {
+            synonymous[aa as usize] += n;
+        }
+        let ratio = |n: u64, d: u64| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+        writeln!(w, "schema\t{}", SCHEMA_VERSION)?;
+        writeln!(w, "codon\tamino_acid\tcount\tper_thousand\tfraction")?;
+        for ((codon, n), aa) in self.counts().zip(amino_acids) {
+            writeln!(
+                w,
+                "{}\t{}\t{}\t{}\t{}",
+                codon,
+                aa as char,
+                n,
+                fmt.format(1000.0 * ratio(n, total)),
+                fmt.format(ratio(n, synonymous[aa as usize]))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Record {
+    /// The codon usage of the record's sequence as a CDS, see
+    /// `CodonUsage::add_sequence`
+    pub fn codon_usage(&self) -> CodonUsage {
+        let mut usage = CodonUsage::default();
+        usage.add_sequence(&self.sequence);
+        usage
+    }
+}
+
+/// The codon usage of each record of a stream of CDSs, by ID in input
+/// order, and of all of them together
+pub fn codon_usage<I>(records: I) -> io::Result<(Vec<(String, CodonUsage)>, CodonUsage)>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let mut each = Vec::new();
+    let mut all = CodonUsage::default();
+    for rec in records {
+        let rec = rec?;
+        let usage = rec.codon_usage();
+        all.merge(&usage);
+        each.push((rec.id, usage));
+    }
+    Ok((each, all))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(id: &str, seq: &str) -> Record {
+        Record {
+            id: id.to_owned(),
+            description: String::new(),
+            sequence: seq.to_owned(),
+        }
+    }
+
+    #[test]
+    fn expands_gaps() {
+        let cds = rec("g1", "ATGAAATTTTAA");
+        let prot = rec("g1", "M-K-F");
+        let out = insert_gaps_in_codons(&cds, &prot).unwrap();
+        assert_eq!(out.sequence, "ATG---AAA---TTT");
+
+        let bad = rec("g1", "ATGAA");
+        assert!(insert_gaps_in_codons(&bad, &prot).is_err());
+        let sense = rec("g1", "ATGAAATTTTGG");
+        assert!(insert_gaps_in_codons(&sense, &prot).is_err());
+        // TGA is tryptophan in the vertebrate mitochondrial code
+        let mito = GeneticCode::from_id(2).unwrap();
+        let opal = rec("g1", "ATGAAATTTTGA");
+        assert!(insert_gaps_in_codons(&opal, &prot).is_ok());
+        assert!(insert_gaps_in_codons_with_code(&opal, &prot, &mito).is_err());
+    }
+
+    #[test]
+    fn usage() {
+        let input = ">g1\nATGAAAAAGTAA\n>g2\nATG-AAA.NNNaug\n";
+        let (each, all) = codon_usage(crate::FastaBuffer::from(input.as_bytes())).unwrap();
+        assert_eq!(each.len(), 2);
+        assert_eq!(each[0].0, "g1");
+        assert_eq!(each[0].1.total(), 4);
+        assert_eq!(each[1].1.count("ATG"), 2);
+        assert_eq!(each[1].1.total(), 3);
+        assert_eq!(all.count("AAA"), 2);
+        assert_eq!(all.count("aug"), 3);
+        assert_eq!(all.frequency("ATG"), 3.0 / 7.0);
+
+        let codons: Vec<(String, u64)> = all.counts().collect();
+        assert_eq!(codons.len(), 64);
+        assert_eq!(codons[0], ("TTT".to_owned(), 0));
+        assert_eq!(codons[63].0, "GGG");
+
+        let mut out = Vec::new();
+        let fmt = NumberFormat::new(2, crate::report::Rounding::HalfEven);
+        all.write_table(&mut out, &GeneticCode::standard(), &fmt)
+            .unwrap();
+        let table = String::from_utf8(out).unwrap();
+        assert_eq!(table.lines().count(), 66);
+        assert!(table.starts_with("schema\tfasta-report/1\ncodon\t"));
+        assert!(table.contains("\nAAA\tK\t2\t285.71\t0.67\n"), "{}", table);
+        assert!(table.contains("\nTAA\t*\t1\t142.86\t1.00\n"), "{}", table);
+        assert!(table.contains("\nTTT\tF\t0\t0.00\t0.00\n"), "{}", table);
+    }
+}
@@ -0,0 +1,526 @@
+//! An in-memory collection of FASTA records
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+use std::vec;
+
+use crate::errors::FastaError;
+use crate::Record;
+
+/// How `Fasta::merge` resolves records whose ID is already present
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail with `FastaError::DuplicateId`
+    Error,
+    /// Keep the record seen first and discard later ones
+    KeepFirst,
+    /// Keep every record, renaming later ones to `<id>_1`, `<id>_2`, ...
+    RenameWithSuffix,
+    /// Keep the longest sequence, in the position of the first occurrence.
+    /// Ties keep the earlier record.
+    PreferLongest,
+}
+
+/// How `Fasta::reorder_like` treats records missing from the reference order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extras {
+    /// Fail with `FastaError::UnknownId`
+    Error,
+    /// Place them after the ordered records, in their original order
+    Append,
+}
+
+/// Read a sequence order from a `.fai` index, a genome file, or a plain
+/// list of IDs: the first whitespace-delimited field of every non-blank,
+/// non-`#` line
+pub fn read_order<R: BufRead>(r: R) -> io::Result<Vec<String>> {
+    let mut order = Vec::new();
+    for line in r.lines() {
+        let line = line?;
+        match line.split_whitespace().next() {
+            Some(id) if !id.starts_with('#') => order.push(id.to_owned()),
+            _ => (),
+        }
+    }
+    Ok(order)
+}
+
+/// Compare strings treating runs of ASCII digits as numbers, so that
+/// `chr2` sorts before `chr10`. Equal numbers with different zero padding
+/// (`chr01`, `chr1`) are ordered by the padding so the order is total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let si = i;
+            let sj = j;
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            let na = trim_zeros(&a[si..i]);
+            let nb = trim_zeros(&b[sj..j]);
+            let ord = na
+                .len()
+                .cmp(&nb.len())
+                .then_with(|| na.cmp(nb))
+                .then_with(|| (i - si).cmp(&(j - sj)));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        } else {
+            if a[i] != b[j] {
+                return a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits
+        .iter()
+        .position(|&d| d != b'0')
+        .unwrap_or(digits.len());
+    &digits[start..]
+}
+
+#[derive(Debug, Default)]
+/// fasta::Fasta holds records in input order along with an ID lookup table.
+///
+/// When several records share an ID, lookups by ID return the first one.
+///
+/// Ordering is deterministic: records stay in insertion order unless
+/// explicitly reordered, every sort is stable (equal records keep their
+/// relative order), and no operation depends on hash iteration order. The
+/// same input therefore always produces the same output order, on every
+/// platform.
+pub struct Fasta {
+    records: Vec<Record>,
+    index: HashMap<String, usize>,
+}
+
+impl Fasta {
+    /// Returns a new, empty fasta::Fasta
+    pub fn new() -> Fasta {
+        Fasta {
+            ..Default::default()
+        }
+    }
+
+    /// Number of records in the collection
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the collection holds no records
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Append a record to the end of the collection
+    pub fn push(&mut self, rec: Record) {
+        self.index
+            .entry(rec.id.clone())
+            .or_insert(self.records.len());
+        self.records.push(rec);
+    }
+
+    /// Look up a record by ID
+    pub fn get(&self, id: &str) -> Option<&Record> {
+        self.index.get(id).map(|&i| &self.records[i])
+    }
+
+    /// Whether a record with the given ID is present
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Iterate over the records in order
+    pub fn iter(&self) -> slice::Iter<'_, Record> {
+        self.records.iter()
+    }
+
+    /// The records as a slice, in order
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// Consume the collection, returning the underlying records
+    pub fn into_records(self) -> Vec<Record> {
+        self.records
+    }
+
+    /// Merge several record sources into one collection with unique IDs.
+    ///
+    /// Sources are consumed in order and records keep their input order;
+    /// `policy` decides what happens when an ID has already been seen.
+    pub fn merge<S, I>(sources: S, policy: DuplicatePolicy) -> Result<Fasta, FastaError>
+    where
+        S: IntoIterator<Item = I>,
+        I: IntoIterator<Item = Result<Record, io::Error>>,
+    {
+        let mut fasta = Fasta::new();
+        for source in sources {
+            for rec in source {
+                let mut rec = rec?;
+                let existing = match fasta.index.get(&rec.id) {
+                    None => {
+                        fasta.push(rec);
+                        continue;
+                    }
+                    Some(&i) => i,
+                };
+                match policy {
+                    DuplicatePolicy::Error => return Err(FastaError::DuplicateId(rec.id)),
+                    DuplicatePolicy::KeepFirst => (),
+                    DuplicatePolicy::RenameWithSuffix => {
+                        let base = rec.id.clone();
+                        let mut n = 1;
+                        while fasta.contains(&rec.id) {
+                            rec.id = format!("{}_{}", base, n);
+                            n += 1;
+                        }
+                        fasta.push(rec);
+                    }
+                    DuplicatePolicy::PreferLongest => {
+                        if rec.sequence.len() > fasta.records[existing].sequence.len() {
+                            fasta.records[existing] = rec;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(fasta)
+    }
+
+    /// Arrange records to follow `order`.
+    ///
+    /// IDs listed in `order` but absent from the collection are ignored;
+    /// records whose ID is not listed are handled according to `extras`.
+    /// Records sharing an ID keep their relative order.
+    pub fn reorder_like<S: AsRef<str>>(
+        &mut self,
+        order: &[S],
+        extras: Extras,
+    ) -> Result<(), FastaError> {
+        let rank: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_ref(), i))
+            .collect();
+        if extras == Extras::Error {
+            if let Some(rec) = self
+                .records
+                .iter()
+                .find(|r| !rank.contains_key(r.id.as_str()))
+            {
+                return Err(FastaError::UnknownId(rec.id.clone()));
+            }
+        }
+        self.records
+            .sort_by_key(|r| rank.get(r.id.as_str()).copied().unwrap_or(usize::MAX));
+        self.reindex();
+        Ok(())
+    }
+
+    /// Sort records by sequence length, longest first. Records of equal
+    /// length keep their relative order.
+    pub fn sort_by_length(&mut self) {
+        self.sort_by(|a, b| b.sequence.len().cmp(&a.sequence.len()));
+    }
+
+    /// Sort records by ID using natural ordering (`chr2` before `chr10`)
+    pub fn sort_by_id(&mut self) {
+        self.sort_by(|a, b| natural_cmp(&a.id, &b.id));
+    }
+
+    /// Sort records by GC content, lowest first. Ties keep their order.
+    pub fn sort_by_gc(&mut self) {
+        // Each sequence is scanned once rather than at every comparison
+        let mut keyed: Vec<(f64, Record)> = self
+            .records
+            .drain(..)
+            .map(|rec| (rec.gc_content(), rec))
+            .collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.records = keyed.into_iter().map(|(_, rec)| rec).collect();
+        self.reindex();
+    }
+
+    /// Sort records with a custom comparison. The sort is stable; this
+    /// is the same as `sort_stable_by`.
+    pub fn sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&Record, &Record) -> Ordering,
+    {
+        self.sort_stable_by(compare);
+    }
+
+    /// Sort records with a custom comparison, guaranteeing that records
+    /// comparing equal keep their relative order
+    pub fn sort_stable_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&Record, &Record) -> Ordering,
+    {
+        self.records.sort_by(compare);
+        self.reindex();
+    }
+
+    /// Sort records by a key, guaranteeing that records with equal keys
+    /// keep their relative order
+    pub fn sort_stable_by_key<K, F>(&mut self, key: F)
+    where
+        F: FnMut(&Record) -> K,
+        K: Ord,
+    {
+        self.records.sort_by_key(key);
+        self.reindex();
+    }
+
+    /// Whether records are in the order produced by `sort_by_id`
+    pub fn is_sorted_by_id(&self) -> bool {
+        self.is_sorted_by(|a, b| natural_cmp(&a.id, &b.id))
+    }
+
+    /// Whether no record compares greater than the record following it
+    pub fn is_sorted_by<F>(&self, mut compare: F) -> bool
+    where
+        F: FnMut(&Record, &Record) -> Ordering,
+    {
+        self.records
+            .windows(2)
+            .all(|w| compare(&w[0], &w[1]) != Ordering::Greater)
+    }
+
+    /// Position of the first record with the given ID
+    pub(crate) fn position(&self, id: &str) -> Option<usize> {
+        self.index.get(id).copied()
+    }
+
+    /// Replace the records in `range` with `with`, returning the removed ones
+    pub(crate) fn splice<R>(&mut self, range: R, with: Vec<Record>) -> Vec<Record>
+    where
+        R: std::ops::RangeBounds<usize>,
+    {
+        let removed = self.records.splice(range, with).collect();
+        self.reindex();
+        removed
+    }
+
+    /// Rebuild the ID lookup table after the record order changed
+    pub(crate) fn reindex(&mut self) {
+        self.index.clear();
+        for (i, rec) in self.records.iter().enumerate() {
+            self.index.entry(rec.id.clone()).or_insert(i);
+        }
+    }
+}
+
+impl From<Vec<Record>> for Fasta {
+    fn from(records: Vec<Record>) -> Fasta {
+        let mut fasta = Fasta {
+            records,
+            index: HashMap::new(),
+        };
+        fasta.reindex();
+        fasta
+    }
+}
+
+impl FromIterator<Record> for Fasta {
+    fn from_iter<I: IntoIterator<Item = Record>>(iter: I) -> Fasta {
+        let mut fasta = Fasta::new();
+        fasta.extend(iter);
+        fasta
+    }
+}
+
+impl Extend<Record> for Fasta {
+    fn extend<I: IntoIterator<Item = Record>>(&mut self, iter: I) {
+        for rec in iter {
+            self.push(rec);
+        }
+    }
+}
+
+impl IntoIterator for Fasta {
+    type Item = Record;
+    type IntoIter = vec::IntoIter<Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Fasta {
+    type Item = &'a Record;
+    type IntoIter = slice::Iter<'a, Record>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.iter()
+    }
+}
+
+/// Index by record ID, panicking if the ID is not present
+impl Index<&str> for Fasta {
+    type Output = Record;
+
+    fn index(&self, id: &str) -> &Record {
+        self.get(id)
+            .unwrap_or_else(|| panic!("no record with id {}", id))
+    }
+}
+
+/// Index by position in the collection
+impl Index<usize> for Fasta {
+    type Output = Record;
+
+    fn index(&self, i: usize) -> &Record {
+        &self.records[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(id: &str, seq: &str) -> Record {
+        Record {
+            id: id.to_owned(),
+            description: String::new(),
+            sequence: seq.to_owned(),
+        }
+    }
+
+    #[test]
+    fn collect_index_extend() {
+        let mut fasta: Fasta = vec![rec("chr1", "ACGT"), rec("chr2", "GG")]
+            .into_iter()
+            .collect();
+        fasta.extend(vec![rec("chr3", "T"), rec("chr1", "CCCC")]);
+
+        assert_eq!(fasta.len(), 4);
+        assert_eq!(fasta["chr1"].sequence, "ACGT");
+        assert_eq!(fasta[3].sequence, "CCCC");
+        let ids: Vec<&str> = (&fasta).into_iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["chr1", "chr2", "chr3", "chr1"]);
+    }
+
+    #[test]
+    fn merge_policies() {
+        let sources = || {
+            vec![
+                vec![Ok(rec("chr1", "AC")), Ok(rec("chr2", "G"))],
+                vec![Ok(rec("chr1", "ACGT")), Ok(rec("p1", "TT"))],
+            ]
+        };
+        assert!(Fasta::merge(sources(), DuplicatePolicy::Error).is_err());
+
+        let first = Fasta::merge(sources(), DuplicatePolicy::KeepFirst).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first["chr1"].sequence, "AC");
+
+        let renamed = Fasta::merge(sources(), DuplicatePolicy::RenameWithSuffix).unwrap();
+        assert_eq!(renamed["chr1_1"].sequence, "ACGT");
+        assert_eq!(renamed[2].id, "chr1_1");
+
+        let longest = Fasta::merge(sources(), DuplicatePolicy::PreferLongest).unwrap();
+        assert_eq!(longest[0].sequence, "ACGT");
+        assert_eq!(longest.len(), 3);
+    }
+
+    #[test]
+    fn natural_ordering() {
+        let mut ids = vec![
+            "chr10",
+            "chr2",
+            "chrX",
+            "chr1",
+            "chr02",
+            "scaffold_100",
+            "scaffold_9",
+        ];
+        ids.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            ids,
+            vec![
+                "chr1",
+                "chr2",
+                "chr02",
+                "chr10",
+                "chrX",
+                "scaffold_9",
+                "scaffold_100"
+            ]
+        );
+    }
+
+    #[test]
+    fn sorting() {
+        let mut fasta: Fasta = vec![rec("c10", "AT"), rec("c2", "GGGC"), rec("c1", "GCAT")]
+            .into_iter()
+            .collect();
+        fasta.sort_by_length();
+        let ids: Vec<&str> = fasta.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c2", "c1", "c10"]);
+
+        fasta.sort_by_id();
+        let ids: Vec<&str> = fasta.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c1", "c2", "c10"]);
+        assert_eq!(fasta["c10"].sequence, "AT");
+
+        assert!(fasta.is_sorted_by_id());
+
+        fasta.sort_by_gc();
+        let ids: Vec<&str> = fasta.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c10", "c1", "c2"]);
+        assert_eq!(fasta["c2"].sequence, "GGGC");
+        assert!(!fasta.is_sorted_by_id());
+    }
+
+    #[test]
+    fn stable_sort_keeps_ties() {
+        let mut fasta: Fasta = vec![
+            rec("b", "AA"),
+            rec("a", "CC"),
+            rec("c", "G"),
+            rec("d", "TT"),
+        ]
+        .into_iter()
+        .collect();
+        fasta.sort_stable_by_key(|r| r.sequence.len());
+        let ids: Vec<&str> = fasta.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn reorder_from_fai() {
+        let fai = "chr2\t4\t6\t4\t5\nchr10\t2\t17\t2\t3\nchr1\t3\t25\t3\t4\n";
+        let order = read_order(fai.as_bytes()).unwrap();
+        let mut fasta: Fasta = vec![
+            rec("chr1", "A"),
+            rec("plasmid", "T"),
+            rec("chr10", "C"),
+            rec("chr2", "G"),
+        ]
+        .into_iter()
+        .collect();
+        assert!(fasta.reorder_like(&order, Extras::Error).is_err());
+
+        fasta.reorder_like(&order, Extras::Append).unwrap();
+        let ids: Vec<&str> = fasta.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["chr2", "chr10", "chr1", "plasmid"]);
+        assert_eq!(fasta["chr1"].sequence, "A");
+    }
+}
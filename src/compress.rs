@@ -0,0 +1,195 @@
+//! Transparent decompression of input
+//!
+//! `decompress` sniffs the first bytes of a reader and wraps it in the
+//! matching decoder. gzip (including BGZF) is always supported; zstd,
+//! bzip2 and xz need the features of the same names. Concatenated
+//! streams, as written by `cat a.gz b.gz` or parallel compressors, are
+//! read to the end.
+//!
+//! For output, `zstd_writer` wraps a writer in a zstd encoder.
+
+use std::fmt;
+use std::io;
+#[cfg(feature = "zstd")]
+use std::io::Write;
+use std::io::{BufRead, BufReader};
+
+/// A compression format recognized by its magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// The format whose magic bytes begin `bytes`
+    pub fn detect(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Compression::None => "uncompressed",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        })
+    }
+}
+
+/// A reader over the decompressed contents of `r`, which is returned
+/// unchanged if it is not compressed
+pub fn decompress<R: BufRead + Send + 'static>(mut r: R) -> io::Result<Box<dyn BufRead + Send>> {
+    let compression = loop {
+        match r.fill_buf() {
+            Ok(buf) => break Compression::detect(buf),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    Ok(match compression {
+        Compression::None => Box::new(r),
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(r))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(r)?)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(r))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(
+            r,
+        ))),
+        #[allow(unreachable_patterns)]
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "input is {}-compressed; enable the `{}` feature of the fasta crate to read it",
+                    other, other
+                ),
+            ))
+        }
+    })
+}
+
+#[cfg(feature = "zstd")]
+/// Settings of a `zstd_writer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZstdOptions {
+    /// Compression level, from 1 (fastest) to 22; 0 means zstd's default
+    pub level: i32,
+    /// Worker threads compressing in the background; 0 compresses on
+    /// the calling thread
+    pub threads: u32,
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdOptions {
+    /// zstd's default level, single-threaded
+    fn default() -> ZstdOptions {
+        ZstdOptions {
+            level: 0,
+            threads: 0,
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+/// A zstd encoder writing to `w`. Call `finish` on it once all records
+/// are written to complete the stream and get `w` back.
+pub fn zstd_writer<W: Write>(
+    w: W,
+    opts: &ZstdOptions,
+) -> io::Result<zstd::stream::write::Encoder<'static, W>> {
+    let mut encoder = zstd::stream::write::Encoder::new(w, opts.level)?;
+    if opts.threads > 0 {
+        encoder.multithread(opts.threads)?;
+    }
+    Ok(encoder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn detects_and_decompresses() {
+        assert_eq!(Compression::detect(b">a\nAC\n"), Compression::None);
+        assert_eq!(Compression::detect(b"BZh91AY"), Compression::Bzip2);
+        assert_eq!(Compression::detect(b""), Compression::None);
+
+        let mut gz = Vec::new();
+        for part in [">a\nAC\n", ">b\nGT\n"] {
+            let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            enc.write_all(part.as_bytes()).unwrap();
+            gz.extend(enc.finish().unwrap());
+        }
+        let mut text = String::new();
+        decompress(io::Cursor::new(gz))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, ">a\nAC\n>b\nGT\n");
+    }
+
+    #[cfg(all(feature = "zstd", feature = "bzip2", feature = "xz"))]
+    #[test]
+    fn optional_formats() {
+        let input: &[u8] = b">a\nACGT\n";
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bz.write_all(input).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+        xz.write_all(input).unwrap();
+        for (compressed, expected) in [
+            (zstd::encode_all(input, 1).unwrap(), Compression::Zstd),
+            (bz.finish().unwrap(), Compression::Bzip2),
+            (xz.finish().unwrap(), Compression::Xz),
+        ] {
+            assert_eq!(Compression::detect(&compressed), expected);
+            let mut out = Vec::new();
+            decompress(io::Cursor::new(compressed))
+                .unwrap()
+                .read_to_end(&mut out)
+                .unwrap();
+            assert_eq!(out, input);
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_output() {
+        let rec = crate::Record {
+            id: "a".to_owned(),
+            description: String::new(),
+            sequence: "ACGT".repeat(10_000),
+        };
+        let opts = ZstdOptions {
+            level: 19,
+            threads: 2,
+        };
+        let mut w = zstd_writer(Vec::new(), &opts).unwrap();
+        rec.write(&mut w).unwrap();
+        let compressed = w.finish().unwrap();
+        assert!(compressed.len() < 1_000);
+
+        let records = crate::FastaBuffer::from(decompress(io::Cursor::new(compressed)).unwrap());
+        let read: Vec<crate::Record> = records.collect::<Result<_, _>>().unwrap();
+        assert_eq!(read[0].sequence, rec.sequence);
+    }
+}
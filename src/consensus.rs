@@ -0,0 +1,259 @@
+//! Apply VCF variants to a reference to build a consensus sequence
+//!
+//! This covers the `bcftools consensus` workflow: SNPs, MNPs and indels
+//! from a VCF stream are applied to each reference sequence, optionally
+//! only those a given sample carries. Variants are applied in position
+//! order; a variant overlapping one already applied is skipped, as are
+//! symbolic alleles such as `<DEL>` and `*`.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::collection::Fasta;
+use crate::errors::FastaError;
+use crate::Record;
+
+/// Which genotypes of the selected sample cause a variant to be applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GenotypeFilter {
+    /// Apply the first non-reference allele the sample carries
+    #[default]
+    AnyAlt,
+    /// Apply only variants for which the sample is homozygous for an
+    /// alternate allele
+    HomozygousAlt,
+}
+
+/// Options for `apply_variants`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VcfOptions {
+    /// Sample whose genotypes decide which variants apply. Without one,
+    /// the first ALT allele of every variant is applied.
+    pub sample: Option<String>,
+    pub genotypes: GenotypeFilter,
+    /// Apply only variants whose FILTER is `PASS` or `.`
+    pub pass_only: bool,
+}
+
+/// A consensus and how many variants went into it
+#[derive(Debug)]
+pub struct Consensus {
+    pub fasta: Fasta,
+    pub applied: usize,
+    /// Variants not applied because of the filters, the genotype, a
+    /// symbolic allele or an overlap with an earlier variant
+    pub skipped: usize,
+}
+
+#[derive(Debug)]
+struct Variant {
+    line: usize,
+    // 0-based start of the reference allele
+    start: usize,
+    reference: String,
+    alt: String,
+}
+
+fn format_error(line: usize, message: &str) -> FastaError {
+    FastaError::Format {
+        line,
+        message: message.to_owned(),
+    }
+}
+
+/// The ALT allele index chosen by a sample's GT field, if any
+fn chosen_allele(gt: &str, filter: GenotypeFilter) -> Option<usize> {
+    let alleles: Vec<Option<usize>> = gt.split(['/', '|']).map(|a| a.parse().ok()).collect();
+    match filter {
+        GenotypeFilter::AnyAlt => alleles.iter().flatten().copied().find(|&a| a > 0),
+        GenotypeFilter::HomozygousAlt => match alleles.first() {
+            Some(&Some(a)) if a > 0 && alleles.iter().all(|&x| x == Some(a)) => Some(a),
+            _ => None,
+        },
+    }
+}
+
+/// Apply the variants in `vcf` to `reference`, returning a new sequence
+/// for every reference record in the same order. Fails if a line is
+/// malformed, a variant names an unknown sequence or lies past its end,
+/// its REF allele does not match the reference, or the selected sample
+/// is not in the VCF.
+pub fn apply_variants<R: BufRead>(
+    reference: &Fasta,
+    vcf: R,
+    opts: &VcfOptions,
+) -> Result<Consensus, FastaError> {
+    let mut by_chrom: HashMap<String, Vec<Variant>> = HashMap::new();
+    let mut sample_column = None;
+    let mut skipped = 0;
+
+    for (n, line) in vcf.lines().enumerate() {
+        let line = line?;
+        let n = n + 1;
+        if line.starts_with("##") || line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if let Some(header) = line.strip_prefix('#') {
+            if let Some(sample) = &opts.sample {
+                let column = header.split('\t').position(|s| s == sample);
+                sample_column = Some(column.ok_or_else(|| FastaError::UnknownId(sample.clone()))?);
+            }
+            continue;
+        }
+        if fields.len() < 8 {
+            return Err(format_error(n, "expected at least 8 tab-separated fields"));
+        }
+        let pos: usize = fields[1]
+            .parse()
+            .ok()
+            .filter(|&p| p > 0)
+            .ok_or_else(|| format_error(n, "invalid POS"))?;
+        if !reference.contains(fields[0]) {
+            return Err(FastaError::UnknownId(fields[0].to_owned()));
+        }
+        if opts.pass_only && fields[6] != "PASS" && fields[6] != "." {
+            skipped += 1;
+            continue;
+        }
+        let reference_allele = fields[3];
+        if reference_allele.is_empty() || !reference_allele.bytes().all(|b| b.is_ascii_alphabetic())
+        {
+            return Err(format_error(n, "invalid REF allele"));
+        }
+        let alts: Vec<&str> = fields[4].split(',').collect();
+        let allele = match (&opts.sample, sample_column) {
+            (None, _) => Some(1),
+            (Some(_), None) => return Err(format_error(n, "variant before #CHROM header")),
+            (Some(_), Some(column)) => {
+                let gt_index = fields
+                    .get(8)
+                    .and_then(|f| f.split(':').position(|k| k == "GT"))
+                    .ok_or_else(|| format_error(n, "no GT field"))?;
+                let gt = fields
+                    .get(column)
+                    .and_then(|s| s.split(':').nth(gt_index))
+                    .ok_or_else(|| format_error(n, "missing sample genotype"))?;
+                chosen_allele(gt, opts.genotypes)
+            }
+        };
+        let alt = match allele.and_then(|a| alts.get(a - 1)) {
+            Some(alt) if alt.bytes().all(|b| b.is_ascii_alphabetic()) => alt,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        by_chrom
+            .entry(fields[0].to_owned())
+            .or_default()
+            .push(Variant {
+                line: n,
+                start: pos - 1,
+                reference: reference_allele.to_owned(),
+                alt: (*alt).to_owned(),
+            });
+    }
+
+    let mut applied = 0;
+    let mut records = Vec::with_capacity(reference.len());
+    for rec in reference.iter() {
+        let mut variants = by_chrom.remove(&rec.id).unwrap_or_default();
+        variants.sort_by_key(|v| (v.start, v.line));
+        let seq = &rec.sequence;
+        let mut out = String::with_capacity(seq.len());
+        let mut cursor = 0;
+        for v in variants {
+            if v.start < cursor {
+                skipped += 1;
+                continue;
+            }
+            if v.start > seq.len() {
+                return Err(format_error(v.line, "POS past the end of the sequence"));
+            }
+            let end = v.start + v.reference.len();
+            let found = seq.get(v.start..end.min(seq.len())).unwrap_or("");
+            if !found.eq_ignore_ascii_case(&v.reference) {
+                return Err(FastaError::ReferenceMismatch {
+                    id: rec.id.clone(),
+                    position: v.start + 1,
+                    expected: v.reference,
+                    found: found.to_owned(),
+                });
+            }
+            out.push_str(&seq[cursor..v.start]);
+            out.push_str(&v.alt);
+            cursor = end;
+            applied += 1;
+        }
+        out.push_str(&seq[cursor..]);
+        records.push(Record {
+            id: rec.id.clone(),
+            description: rec.description.clone(),
+            sequence: out,
+        });
+    }
+    Ok(Consensus {
+        fasta: Fasta::from(records),
+        applied,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    const VCF: &str = "##fileformat=VCFv4.2\n\
+        #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1\ts2\n\
+        chr1\t2\t.\tC\tT\t.\tPASS\t.\tGT\t0/1\t0/0\n\
+        chr1\t4\t.\tTA\tT\t.\tPASS\t.\tGT\t1/1\t1/1\n\
+        chr1\t5\t.\tA\tG\t.\tPASS\t.\tGT\t1/1\t0/0\n\
+        chr1\t7\t.\tG\tGCC,GT\t.\tlowq\t.\tGT\t2|2\t0|1\n";
+
+    fn reference() -> Fasta {
+        let input = ">chr1 test\nACGTAAGT\n>chr2\nTTTT\n";
+        Fasta::from(
+            FastaBuffer::from(input.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn per_sample() {
+        let all = apply_variants(&reference(), VCF.as_bytes(), &VcfOptions::default()).unwrap();
+        // The SNP at 5 overlaps the deletion at 4-5
+        assert_eq!(all.fasta["chr1"].sequence, "ATGTAGCCT");
+        assert_eq!((all.applied, all.skipped), (3, 1));
+
+        let opts = VcfOptions {
+            sample: Some("s1".to_owned()),
+            genotypes: GenotypeFilter::HomozygousAlt,
+            pass_only: true,
+        };
+        let s1 = apply_variants(&reference(), VCF.as_bytes(), &opts).unwrap();
+        assert_eq!(s1.fasta["chr1"].sequence, "ACGTAGT");
+        assert_eq!(s1.fasta["chr2"].sequence, "TTTT");
+    }
+
+    #[test]
+    fn reference_checked() {
+        let vcf = "chr1\t1\t.\tG\tT\t.\t.\t.\n";
+        match apply_variants(&reference(), vcf.as_bytes(), &VcfOptions::default()) {
+            Err(FastaError::ReferenceMismatch { position, .. }) => assert_eq!(position, 1),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        for vcf in [
+            "chr1\t20\t.\t\tT\t.\t.\t.\n",
+            "chr1\t2\t.\tC*\tT\t.\t.\t.\n",
+        ] {
+            match apply_variants(&reference(), vcf.as_bytes(), &VcfOptions::default()) {
+                Err(FastaError::Format { line, .. }) => assert_eq!(line, 1),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+}
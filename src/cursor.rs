@@ -0,0 +1,272 @@
+//! Random access over all records as one concatenated coordinate space
+//!
+//! `GenomeCursor` numbers the bases of every record consecutively, in
+//! record order, so that whole-genome scanners can address positions
+//! and windows without caring where one sequence ends and the next
+//! begins. Global positions are 0-based.
+
+use std::borrow::Cow;
+
+use crate::Record;
+
+/// A global position resolved to a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// Index of the record
+    pub record: usize,
+    /// 0-based position within the record
+    pub offset: usize,
+}
+
+/// The part of a window that falls in one record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Index of the record
+    pub record: usize,
+    /// 0-based, half-open range within the record
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A stretch of the concatenated sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window<'a> {
+    /// Global start position
+    pub start: usize,
+    /// The bases of the window. Borrowed when it lies within one record.
+    pub sequence: Cow<'a, str>,
+    pub spans: Vec<Span>,
+}
+
+/// A cursor over the concatenation of a set of records
+#[derive(Debug, Clone)]
+pub struct GenomeCursor<'a> {
+    records: &'a [Record],
+    // offsets[i] is the global start of record i; the last entry is the
+    // total length
+    offsets: Vec<usize>,
+    pos: usize,
+}
+
+impl<'a> GenomeCursor<'a> {
+    /// Create a cursor positioned at the start of the first record
+    pub fn new(records: &'a [Record]) -> GenomeCursor<'a> {
+        let mut offsets = Vec::with_capacity(records.len() + 1);
+        let mut total = 0;
+        offsets.push(0);
+        for rec in records {
+            total += rec.sequence.len();
+            offsets.push(total);
+        }
+        GenomeCursor {
+            records,
+            offsets,
+            pos: 0,
+        }
+    }
+
+    /// Total number of bases in all records
+    pub fn total_len(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    /// Global start position of every record, in record order
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets[..self.records.len()]
+    }
+
+    /// The current global position
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Resolve a global position, or None if it is past the last base
+    pub fn locate(&self, pos: usize) -> Option<Location> {
+        if pos >= self.total_len() {
+            return None;
+        }
+        // Skip empty records by taking the last record starting at or
+        // before pos
+        let record = self.offsets.partition_point(|&o| o <= pos) - 1;
+        Some(Location {
+            record,
+            offset: pos - self.offsets[record],
+        })
+    }
+
+    /// The global position of `offset` within record `record`
+    pub fn global(&self, record: usize, offset: usize) -> Option<usize> {
+        let rec = self.records.get(record)?;
+        if offset < rec.sequence.len() {
+            Some(self.offsets[record] + offset)
+        } else {
+            None
+        }
+    }
+
+    /// Move to a global position, returning where it falls. The cursor
+    /// does not move if `pos` is past the last base.
+    pub fn seek(&mut self, pos: usize) -> Option<Location> {
+        let loc = self.locate(pos)?;
+        self.pos = pos;
+        Some(loc)
+    }
+
+    /// The `len` bases starting at global position `start`, truncated at
+    /// the end of the last record
+    pub fn window(&self, start: usize, len: usize) -> Window<'a> {
+        let end = start.saturating_add(len).min(self.total_len());
+        let mut spans = Vec::new();
+        if let Some(first) = self.locate(start) {
+            for i in first.record..self.records.len() {
+                if self.offsets[i] >= end {
+                    break;
+                }
+                let a = start.max(self.offsets[i]) - self.offsets[i];
+                let b = end.min(self.offsets[i + 1]) - self.offsets[i];
+                if a < b {
+                    spans.push(Span {
+                        record: i,
+                        start: a,
+                        end: b,
+                    });
+                }
+            }
+        }
+        let sequence = match spans.as_slice() {
+            [] => Cow::Borrowed(""),
+            [s] => Cow::Borrowed(&self.records[s.record].sequence[s.start..s.end]),
+            _ => Cow::Owned(
+                spans
+                    .iter()
+                    .map(|s| &self.records[s.record].sequence[s.start..s.end])
+                    .collect(),
+            ),
+        };
+        Window {
+            start,
+            sequence,
+            spans,
+        }
+    }
+
+    /// Read `len` bases from the current position and advance past them
+    pub fn read(&mut self, len: usize) -> Window<'a> {
+        let window = self.window(self.pos, len);
+        self.pos = (self.pos + window.sequence.len()).min(self.total_len());
+        window
+    }
+
+    /// Iterate over full windows of `len` bases every `step` bases,
+    /// starting at the current position. Panics if `len` or `step` is 0.
+    pub fn windows(&self, len: usize, step: usize) -> Windows<'_, 'a> {
+        assert!(
+            len > 0 && step > 0,
+            "window length and step must be non-zero"
+        );
+        Windows {
+            cursor: self,
+            start: self.pos,
+            len,
+            step,
+        }
+    }
+}
+
+/// Iterator over fixed-size windows of a `GenomeCursor`
+#[derive(Debug, Clone)]
+pub struct Windows<'c, 'a> {
+    cursor: &'c GenomeCursor<'a>,
+    start: usize,
+    len: usize,
+    step: usize,
+}
+
+impl<'c, 'a> Iterator for Windows<'c, 'a> {
+    type Item = Window<'a>;
+
+    fn next(&mut self) -> Option<Window<'a>> {
+        match self.start.checked_add(self.len) {
+            Some(end) if end <= self.cursor.total_len() => (),
+            _ => return None,
+        }
+        let window = self.cursor.window(self.start, self.len);
+        // Saturating, so a huge step ends the iteration at the next call
+        self.start = self.start.saturating_add(self.step);
+        Some(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(seq: &str) -> Record {
+        Record {
+            sequence: seq.to_owned(),
+            ..Record::new()
+        }
+    }
+
+    #[test]
+    fn seek_and_windows() {
+        let records = vec![rec("ACGT"), rec(""), rec("GG"), rec("TTTA")];
+        let mut cursor = GenomeCursor::new(&records);
+        assert_eq!(cursor.total_len(), 10);
+        assert_eq!(cursor.offsets(), [0, 4, 4, 6]);
+        assert_eq!(
+            cursor.seek(4),
+            Some(Location {
+                record: 2,
+                offset: 0
+            })
+        );
+        assert_eq!(cursor.seek(10), None);
+        assert_eq!(cursor.position(), 4);
+        assert_eq!(cursor.global(3, 1), Some(7));
+
+        let windows: Vec<String> = cursor
+            .windows(4, 3)
+            .map(|w| w.sequence.into_owned())
+            .collect();
+        assert_eq!(windows, ["GGTT"]);
+
+        let mut huge = cursor.windows(1, usize::MAX);
+        assert_eq!(huge.next().unwrap().sequence, "G");
+        assert!(huge.next().is_none());
+        assert!(huge.next().is_none());
+        assert!(cursor.windows(usize::MAX, 1).next().is_none());
+    }
+
+    #[test]
+    fn read_across_records() {
+        let records = vec![rec("ACGT"), rec("GG"), rec("TTTA")];
+        let mut cursor = GenomeCursor::new(&records);
+        cursor.seek(3);
+        let w = cursor.read(4);
+        assert_eq!(w.sequence, "TGGT");
+        assert_eq!(
+            w.spans,
+            [
+                Span {
+                    record: 0,
+                    start: 3,
+                    end: 4
+                },
+                Span {
+                    record: 1,
+                    start: 0,
+                    end: 2
+                },
+                Span {
+                    record: 2,
+                    start: 0,
+                    end: 1
+                },
+            ]
+        );
+        assert_eq!(cursor.position(), 7);
+        assert!(matches!(cursor.read(2).sequence, Cow::Borrowed("TT")));
+        assert_eq!(cursor.read(5).sequence, "A");
+    }
+}
@@ -0,0 +1,190 @@
+//! Removing records with duplicate IDs or sequences
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::hash::fnv1a;
+use crate::report::SCHEMA_VERSION;
+use crate::seq::reverse_complement;
+use crate::Record;
+
+/// A representative record and the IDs of the records collapsed into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collapsed {
+    pub representative: String,
+    pub duplicates: Vec<String>,
+}
+
+/// The result of `dedup` and `dedup_sequences`
+#[derive(Debug, Default)]
+pub struct Dedup {
+    /// The kept record of each set of duplicates, in input order
+    pub records: Vec<Record>,
+    /// One entry per representative that had duplicates, in input order
+    pub report: Vec<Collapsed>,
+}
+
+impl Dedup {
+    /// Write the report as TSV after the schema line: representative ID,
+    /// then a comma-separated list of the IDs collapsed into it
+    pub fn write_report<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "schema\t{}", SCHEMA_VERSION)?;
+        for c in &self.report {
+            writeln!(w, "{}\t{}", c.representative, c.duplicates.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// What makes two records duplicates in `dedup`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupBy {
+    /// Identical IDs
+    Id,
+    /// Identical sequences, compared exactly and case-sensitively
+    Sequence,
+    /// Identical sequences, or one the reverse complement of the other
+    CanonicalSequence,
+}
+
+/// Which record of a set of duplicates `dedup` keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    /// The record seen first
+    First,
+    /// The longest sequence, ties keeping the earlier record
+    Longest,
+}
+
+/// The duplicates collapsed into one kept record
+struct Group {
+    /// IDs of every record in the group, in input order
+    members: Vec<String>,
+    /// Position of the kept record in `members`
+    kept: usize,
+}
+
+/// Remove duplicate records, keeping one of each set of duplicates in
+/// the position of the first.
+///
+/// The report lists, for each kept record that had duplicates, the IDs
+/// of the records dropped in its favour, in input order.
+pub fn dedup<I>(records: I, by: DedupBy, keep: Keep) -> io::Result<Dedup>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let mut dedup = Dedup::default();
+    // Hash of the key -> indices of kept records with that hash
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut groups: Vec<Group> = Vec::new();
+
+    for rec in records {
+        let rec = rec?;
+        let rc = if by == DedupBy::CanonicalSequence {
+            Some(reverse_complement(&rec.sequence))
+        } else {
+            None
+        };
+        let key = match &rc {
+            _ if by == DedupBy::Id => rec.id.as_str(),
+            Some(rc) if rc.as_str() < rec.sequence.as_str() => rc.as_str(),
+            _ => rec.sequence.as_str(),
+        };
+        let candidates = seen.entry(fnv1a(key.as_bytes())).or_default();
+        let matched = candidates.iter().copied().find(|&i| {
+            let kept = &dedup.records[i];
+            match by {
+                DedupBy::Id => kept.id == rec.id,
+                _ => kept.sequence == rec.sequence || rc.as_deref() == Some(&kept.sequence),
+            }
+        });
+
+        match matched {
+            Some(i) => {
+                let group = &mut groups[i];
+                group.members.push(rec.id.clone());
+                if keep == Keep::Longest && rec.sequence.len() > dedup.records[i].sequence.len() {
+                    group.kept = group.members.len() - 1;
+                    dedup.records[i] = rec;
+                }
+            }
+            None => {
+                candidates.push(dedup.records.len());
+                groups.push(Group {
+                    members: vec![rec.id.clone()],
+                    kept: 0,
+                });
+                dedup.records.push(rec);
+            }
+        }
+    }
+
+    for mut group in groups.into_iter().filter(|g| g.members.len() > 1) {
+        let representative = group.members.remove(group.kept);
+        dedup.report.push(Collapsed {
+            representative,
+            duplicates: group.members,
+        });
+    }
+    Ok(dedup)
+}
+
+/// Remove records whose sequence is identical to an earlier record.
+///
+/// Comparison is exact and case-sensitive. With `canonical` set, a
+/// sequence also matches the reverse complement of an earlier one, so
+/// either strand of the same molecule collapses to the first seen.
+pub fn dedup_sequences<I>(records: I, canonical: bool) -> io::Result<Dedup>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let by = if canonical {
+        DedupBy::CanonicalSequence
+    } else {
+        DedupBy::Sequence
+    };
+    dedup(records, by, Keep::First)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    const INPUT: &str = ">a\nACGTT\n>b\nGGGG\n>c\nAACGT\n>d\nACGTT\n>e\nCCCC\n>f\nGGGG\n";
+
+    #[test]
+    fn exact_and_canonical() {
+        let exact = dedup_sequences(FastaBuffer::from(INPUT.as_bytes()), false).unwrap();
+        let ids: Vec<&str> = exact.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c", "e"]);
+        let mut out = Vec::new();
+        exact.write_report(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "schema\tfasta-report/1\na\td\nb\tf\n"
+        );
+
+        let canon = dedup_sequences(FastaBuffer::from(INPUT.as_bytes()), true).unwrap();
+        let ids: Vec<&str> = canon.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(canon.report[0].duplicates, vec!["c", "d"]);
+        assert_eq!(canon.report[1].duplicates, vec!["e", "f"]);
+    }
+
+    #[test]
+    fn by_id_keep_longest() {
+        let input = ">a\nAC\n>b\nA\n>a\nACGT\n>a\nACG\n";
+        let d = dedup(
+            FastaBuffer::from(input.as_bytes()),
+            DedupBy::Id,
+            Keep::Longest,
+        )
+        .unwrap();
+        let kept: Vec<&str> = d.records.iter().map(|r| r.sequence.as_str()).collect();
+        assert_eq!(kept, vec!["ACGT", "A"]);
+        assert_eq!(d.report.len(), 1);
+        assert_eq!(d.report[0].duplicates, vec!["a", "a"]);
+    }
+}
@@ -0,0 +1,66 @@
+//! SAM sequence dictionaries (`.dict` files and `@SQ` header lines)
+
+use std::io;
+use std::io::Write;
+
+use crate::Record;
+
+/// The sequence bytes that contribute to `M5` and `LN`: uppercased, with
+/// gaps and anything outside printable ASCII removed
+pub(crate) fn normalized_sequence(seq: &str) -> Vec<u8> {
+    seq.bytes()
+        .filter(|&b| (33..=126).contains(&b) && b != b'-' && b != b'.')
+        .map(|b| b.to_ascii_uppercase())
+        .collect()
+}
+
+/// An `@SQ` header line (without a trailing newline) for a record.
+/// `uri`, if given, is written as the `UR` field.
+pub fn sq_line(rec: &Record, uri: Option<&str>) -> String {
+    let len = normalized_sequence(&rec.sequence).len();
+    let mut line = format!("@SQ\tSN:{}\tLN:{}\tM5:{}", rec.id, len, rec.md5());
+    if let Some(uri) = uri {
+        line.push_str("\tUR:");
+        line.push_str(uri);
+    }
+    line
+}
+
+/// Write a sequence dictionary for the given records: an `@HD` line
+/// followed by one `@SQ` line per record, as produced by
+/// `samtools dict` or Picard `CreateSequenceDictionary`
+pub fn write_dict<I, W>(records: I, w: &mut W, uri: Option<&str>) -> io::Result<()>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+{
+    writeln!(w, "@HD\tVN:1.6\tSO:unsorted")?;
+    for rec in records {
+        writeln!(w, "{}", sq_line(&rec?, uri))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn dict_lines() {
+        let input = ">chr1 test\nacgt\nAC-GT\n>chrM\n\n";
+        let mut out = Vec::new();
+        write_dict(
+            FastaBuffer::from(input.as_bytes()),
+            &mut out,
+            Some("file:/ref.fa"),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "@HD\tVN:1.6\tSO:unsorted\n\
+             @SQ\tSN:chr1\tLN:8\tM5:cc0af3a4fedb18378b4b57b98068e69f\tUR:file:/ref.fa\n\
+             @SQ\tSN:chrM\tLN:0\tM5:d41d8cd98f00b204e9800998ecf8427e\tUR:file:/ref.fa\n"
+        );
+    }
+}
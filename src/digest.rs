@@ -0,0 +1,250 @@
+//! Restriction enzyme digests
+//!
+//! Recognition sites may contain IUPAC ambiguity codes. A subject base
+//! matches a site position only if every base it stands for is allowed
+//! there, so an `N` in the sequence never produces a cut. Sites are
+//! searched on both strands and cut positions are reported on the top
+//! strand; overhangs are not modelled.
+
+use crate::errors::FastaError;
+use crate::seq::{base_mask, reverse_complement};
+use crate::Record;
+
+/// A restriction enzyme: a recognition site and the top-strand cut
+/// offset from the start of the site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enzyme {
+    pub name: String,
+    pub site: String,
+    pub cut: usize,
+}
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("AluI", "AG^CT"),
+    ("ApaI", "GGGCC^C"),
+    ("AvaI", "C^YCGRG"),
+    ("BamHI", "G^GATCC"),
+    ("BglII", "A^GATCT"),
+    ("DpnII", "^GATC"),
+    ("EcoRI", "G^AATTC"),
+    ("EcoRV", "GAT^ATC"),
+    ("HaeIII", "GG^CC"),
+    ("HincII", "GTY^RAC"),
+    ("HindIII", "A^AGCTT"),
+    ("KpnI", "GGTAC^C"),
+    ("MspI", "C^CGG"),
+    ("NcoI", "C^CATGG"),
+    ("NdeI", "CA^TATG"),
+    ("NotI", "GC^GGCCGC"),
+    ("PstI", "CTGCA^G"),
+    ("SacI", "GAGCT^C"),
+    ("SalI", "G^TCGAC"),
+    ("SmaI", "CCC^GGG"),
+    ("SpeI", "A^CTAGT"),
+    ("XbaI", "T^CTAGA"),
+    ("XhoI", "C^TCGAG"),
+];
+
+impl Enzyme {
+    /// Define an enzyme from a site written with `^` at the cut, such as
+    /// `G^AATTC`
+    pub fn new(name: &str, site: &str) -> Result<Enzyme, FastaError> {
+        let invalid = || FastaError::InvalidPattern(site.to_owned());
+        let cut = site.find('^').ok_or_else(invalid)?;
+        let bases: String = site.chars().filter(|&c| c != '^').collect();
+        if bases.is_empty()
+            || bases.len() + 1 != site.len()
+            || bases.bytes().any(|b| base_mask(b) == 0)
+        {
+            return Err(invalid());
+        }
+        Ok(Enzyme {
+            name: name.to_owned(),
+            site: bases.to_ascii_uppercase(),
+            cut,
+        })
+    }
+
+    /// Look up an enzyme in the built-in table, ignoring case
+    pub fn builtin(name: &str) -> Option<Enzyme> {
+        BUILTIN
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(n, site)| Enzyme::new(n, site).unwrap())
+    }
+
+    /// All built-in enzymes, by name
+    pub fn all() -> Vec<Enzyme> {
+        BUILTIN
+            .iter()
+            .map(|(n, site)| Enzyme::new(n, site).unwrap())
+            .collect()
+    }
+}
+
+/// A cut made by an enzyme
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cut {
+    pub enzyme: String,
+    /// Top-strand position of the cut: bases before it go to the left
+    /// fragment
+    pub position: usize,
+    /// Whether the site was found on the reverse strand
+    pub reverse: bool,
+}
+
+fn matches_at(seq: &[u8], pos: usize, site: &[u8]) -> bool {
+    site.iter().enumerate().all(|(i, &p)| {
+        let s = base_mask(seq[pos + i]);
+        s != 0 && s & !base_mask(p) == 0
+    })
+}
+
+/// Find every cut the enzymes make in `seq`, ordered by position. On a
+/// circular molecule, sites spanning the origin are found too and cut
+/// positions are taken modulo the length.
+pub fn cut_sites(seq: &str, enzymes: &[Enzyme], circular: bool) -> Vec<Cut> {
+    let len = seq.len();
+    let mut cuts = Vec::new();
+    for enzyme in enzymes {
+        let site = enzyme.site.as_bytes();
+        let rc = reverse_complement(&enzyme.site);
+        let mut strands = vec![(site.to_vec(), false)];
+        if rc != enzyme.site {
+            strands.push((rc.into_bytes(), true));
+        }
+        if len < site.len() && !circular {
+            continue;
+        }
+        let mut subject = seq.as_bytes().to_vec();
+        if circular {
+            subject.extend(seq.as_bytes().iter().cycle().take(site.len() - 1));
+        }
+        let starts = if circular { len } else { len - site.len() + 1 };
+        for (pattern, reverse) in &strands {
+            for start in 0..starts {
+                if start + pattern.len() > subject.len() || !matches_at(&subject, start, pattern) {
+                    continue;
+                }
+                let offset = if *reverse {
+                    site.len() - enzyme.cut
+                } else {
+                    enzyme.cut
+                };
+                let mut position = start + offset;
+                if circular {
+                    position %= len;
+                } else if position == 0 || position >= len {
+                    continue;
+                }
+                cuts.push(Cut {
+                    enzyme: enzyme.name.clone(),
+                    position,
+                    reverse: *reverse,
+                });
+            }
+        }
+    }
+    cuts.sort_by(|a, b| a.position.cmp(&b.position).then(a.reverse.cmp(&b.reverse)));
+    cuts
+}
+
+/// Digest a record into fragments named `<id>_1`, `<id>_2`, ... with a
+/// `start-end` description (1-based, inclusive). A fragment of a
+/// circular molecule that spans the origin has `end < start`. Several
+/// enzymes cutting at the same position produce one break.
+pub fn digest(rec: &Record, enzymes: &[Enzyme], circular: bool) -> Vec<Record> {
+    let len = rec.sequence.len();
+    let mut breaks: Vec<usize> = cut_sites(&rec.sequence, enzymes, circular)
+        .into_iter()
+        .map(|c| c.position)
+        .collect();
+    breaks.dedup();
+    let fragment = |n: usize, start: usize, end: usize| {
+        let sequence = if start < end {
+            rec.sequence[start..end].to_owned()
+        } else {
+            format!("{}{}", &rec.sequence[start..], &rec.sequence[..end])
+        };
+        Record {
+            id: format!("{}_{}", rec.id, n),
+            description: format!("{}-{}", start + 1, if end == 0 { len } else { end }),
+            sequence,
+        }
+    };
+    let mut fragments = Vec::new();
+    if circular {
+        match breaks.len() {
+            0 => fragments.push(fragment(1, 0, len)),
+            n => {
+                // A single cut linearizes the molecule at the cut
+                for i in 0..n {
+                    fragments.push(fragment(i + 1, breaks[i], breaks[(i + 1) % n]));
+                }
+            }
+        }
+    } else {
+        let mut start = 0;
+        for (i, &end) in breaks.iter().chain(std::iter::once(&len)).enumerate() {
+            fragments.push(fragment(i + 1, start, end));
+            start = end;
+        }
+    }
+    fragments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(seq: &str) -> Record {
+        Record {
+            id: "p".to_owned(),
+            description: String::new(),
+            sequence: seq.to_owned(),
+        }
+    }
+
+    #[test]
+    fn linear_digest() {
+        let enzymes = [
+            Enzyme::builtin("ecori").unwrap(),
+            Enzyme::builtin("PstI").unwrap(),
+        ];
+        let r = rec("AAGAATTCAACTGCAGTTGAANTTC");
+        let cuts = cut_sites(&r.sequence, &enzymes, false);
+        let positions: Vec<usize> = cuts.iter().map(|c| c.position).collect();
+        assert_eq!(positions, [3, 15]);
+        let frags: Vec<(String, String)> = digest(&r, &enzymes, false)
+            .into_iter()
+            .map(|f| (f.description, f.sequence))
+            .collect();
+        assert_eq!(
+            frags,
+            [
+                ("1-3".to_owned(), "AAG".to_owned()),
+                ("4-15".to_owned(), "AATTCAACTGCA".to_owned()),
+                ("16-25".to_owned(), "GTTGAANTTC".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_and_circular() {
+        // A non-palindromic site found on the reverse strand
+        let enzyme = Enzyme::new("X", "GA^YC").unwrap();
+        let cuts = cut_sites("TTGGTCTT", &[enzyme], false);
+        assert_eq!(cuts.len(), 1);
+        assert_eq!((cuts[0].position, cuts[0].reverse), (4, true));
+        assert!(Enzyme::new("bad", "GAZC").is_err());
+
+        // EcoRI site spanning the origin of a circular molecule
+        let r = rec("ATTCCCCCGA");
+        let ecori = [Enzyme::builtin("EcoRI").unwrap()];
+        let frags = digest(&r, &ecori, true);
+        assert_eq!(frags.len(), 1);
+        assert_eq!(frags[0].sequence, "AATTCCCCCG");
+        assert_eq!(frags[0].description, "10-9");
+        assert!(digest(&r, &ecori, false).len() == 1);
+    }
+}
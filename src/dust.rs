@@ -0,0 +1,173 @@
+//! Low-complexity regions by DUST score
+//!
+//! The DUST score of a window counts how often each of the 64 nucleotide
+//! triplets occurs in it, `c_t`, and sums `c_t * (c_t - 1) / 2` over the
+//! triplets, divided by one less than the number of triplets in the
+//! window. Repeats of short motifs reuse few triplets and score high;
+//! random sequence scores near 0. Windows scoring above a threshold are
+//! reported as low-complexity and can be masked before motif searches or
+//! primer design.
+
+use std::ops::Range;
+
+use crate::errors::FastaError;
+use crate::mask::{merge_intervals, MaskMode};
+use crate::Record;
+
+/// Options for `Record::low_complexity`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DustOptions {
+    /// Bases per window
+    pub window: usize,
+    /// Windows scoring above this are low-complexity
+    pub threshold: f64,
+}
+
+impl Default for DustOptions {
+    /// Windows of 64 bases and a threshold of 20, as in dustmasker
+    fn default() -> DustOptions {
+        DustOptions {
+            window: 64,
+            threshold: 20.0,
+        }
+    }
+}
+
+/// The triplet code of each position of `seq`, or None where the
+/// triplet starting there has a base other than ACGT
+fn triplets(seq: &[u8]) -> Vec<Option<u8>> {
+    let code = |b: u8| match b.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    };
+    seq.windows(3)
+        .map(|t| Some(code(t[0])? << 4 | code(t[1])? << 2 | code(t[2])?))
+        .collect()
+}
+
+/// Running triplet counts of a window
+struct Counts {
+    counts: [usize; 64],
+    // Sum of c * (c - 1) / 2 over the counts
+    pairs: usize,
+}
+
+impl Counts {
+    fn add(&mut self, t: Option<u8>) {
+        if let Some(t) = t {
+            self.pairs += self.counts[t as usize];
+            self.counts[t as usize] += 1;
+        }
+    }
+
+    fn remove(&mut self, t: Option<u8>) {
+        if let Some(t) = t {
+            self.counts[t as usize] -= 1;
+            self.pairs -= self.counts[t as usize];
+        }
+    }
+}
+
+/// The DUST score of `seq` as one window; 0 for fewer than four bases
+pub fn dust_score(seq: &[u8]) -> f64 {
+    let mut counts = Counts {
+        counts: [0; 64],
+        pairs: 0,
+    };
+    let triplets = triplets(seq);
+    for &t in &triplets {
+        counts.add(t);
+    }
+    if triplets.len() < 2 {
+        0.0
+    } else {
+        counts.pairs as f64 / (triplets.len() - 1) as f64
+    }
+}
+
+impl Record {
+    /// The low-complexity regions of the sequence: the union of the
+    /// windows scoring above `opts.threshold`, as sorted, non-overlapping
+    /// 0-based, half-open intervals. A sequence shorter than the window
+    /// is scored as one window. Panics if the window is under 4 bases.
+    pub fn low_complexity(&self, opts: &DustOptions) -> Vec<Range<usize>> {
+        assert!(opts.window >= 4, "DUST window must be at least 4 bases");
+        let seq = self.sequence.as_bytes();
+        let window = opts.window.min(seq.len());
+        if window < 4 {
+            return Vec::new();
+        }
+        let triplets = triplets(seq);
+        // Triplets per window
+        let per = window - 2;
+        let mut counts = Counts {
+            counts: [0; 64],
+            pairs: 0,
+        };
+        let mut windows = Vec::new();
+        for (i, &t) in triplets.iter().enumerate() {
+            counts.add(t);
+            if i >= per {
+                counts.remove(triplets[i - per]);
+            }
+            if i + 1 >= per && counts.pairs as f64 / (per - 1) as f64 > opts.threshold {
+                let start = i + 1 - per;
+                windows.push(start..start + window);
+            }
+        }
+        merge_intervals(&windows)
+    }
+
+    /// Mask the low-complexity regions found by `low_complexity`,
+    /// returning them. Fails, leaving the record unchanged, if the
+    /// sequence is not ASCII.
+    pub fn dust_mask(
+        &mut self,
+        opts: &DustOptions,
+        mode: MaskMode,
+    ) -> Result<Vec<Range<usize>>, FastaError> {
+        let regions = self.low_complexity(opts);
+        self.mask(&regions, mode)?;
+        Ok(regions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_repeats() {
+        assert_eq!(dust_score(b"AAAAAA"), 2.0);
+        assert_eq!(dust_score(b"ACGTTGCA"), 0.0);
+
+        let random = "GATCCTAGGCTAAGTCCGATATCGCGTAAGCTTGCAACGTGATCAGTCAGGTACCATGGCTA";
+        let mut rec = Record {
+            sequence: format!("{}{}{}", random, "CA".repeat(40), random),
+            ..Record::new()
+        };
+        let opts = DustOptions {
+            window: 32,
+            threshold: 5.0,
+        };
+        let regions = rec.dust_mask(&opts, MaskMode::Soft).unwrap();
+        assert_eq!(regions.len(), 1);
+        let r = &regions[0];
+        assert!(
+            r.start <= random.len() && r.end >= random.len() + 80,
+            "{:?}",
+            r
+        );
+        assert!(
+            r.start + 40 > random.len() && r.end < random.len() + 120,
+            "{:?}",
+            r
+        );
+        assert!(rec.sequence[random.len()..random.len() + 80]
+            .bytes()
+            .all(|b| b.is_ascii_lowercase()));
+    }
+}
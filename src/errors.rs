@@ -1,22 +1,269 @@
 use std::error;
 use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
 
 // Define our error types. These may be customized for our error handling cases.
 // Now we will be able to write our own errors, defer to an underlying error
-// implementation, or do something in between.
+// implementation, or do something in between. IO errors are held in an
+// Arc, as io::Error is not Clone.
 #[derive(Debug, Clone)]
-pub struct FastaError;
+pub enum FastaError {
+    // Generic parse failure, underlying cause isn't tracked.
+    Parse,
+    // Reading or writing the underlying data failed.
+    Io(Arc<io::Error>),
+    // A user supplied pattern or specification could not be interpreted.
+    InvalidPattern(String),
+    // A record's sequence is too short for the requested operation.
+    SequenceTooShort {
+        id: String,
+        len: usize,
+        needed: usize,
+    },
+    // Two sequences that must correspond have inconsistent lengths.
+    LengthMismatch {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+    // The same record ID occurred more than once where IDs must be unique.
+    DuplicateId(String),
+    // A record ID was not found where it was required.
+    UnknownId(String),
+    // The operation was stopped through a CancellationToken.
+    Cancelled,
+    // A sequence contains a character outside the expected alphabet.
+    InvalidSequence {
+        id: String,
+        position: usize,
+        found: char,
+    },
+    // A 0-based, half-open interval does not fit within a sequence.
+    OutOfBounds {
+        id: String,
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    // A record exceeded the size budget of a GuardedReader.
+    RecordTooLarge {
+        id: String,
+        limit: usize,
+    },
+    // An input line exceeded the size budget of a GuardedReader. The line
+    // is numbered from 1 and its offset in the input from 0; the ID is
+    // empty before the first record.
+    LineTooLong {
+        id: String,
+        line: u64,
+        offset: u64,
+        limit: usize,
+    },
+    // A record took longer to parse than the budget of a GuardedReader.
+    RecordTimeout {
+        id: String,
+        limit: Duration,
+    },
+    // A line of a non-FASTA input (VCF, GFF3, ...) could not be parsed.
+    // Lines are numbered from 1.
+    Format {
+        line: usize,
+        message: String,
+    },
+    // A variant's reference allele disagrees with the reference sequence.
+    // Positions are 1-based.
+    ReferenceMismatch {
+        id: String,
+        position: usize,
+        expected: String,
+        found: String,
+    },
+    // Paired reads do not match up: the mates have different names, or
+    // one file ran out of reads before the other (mate is None).
+    UnpairedRead {
+        id: String,
+        mate: Option<String>,
+    },
+    // A FASTQ quality character is outside the range of its encoding.
+    InvalidQuality {
+        id: String,
+        position: usize,
+        found: char,
+    },
+    // Input failed structure or alphabet validation while parsing. Lines
+    // and byte columns are numbered from 1 and the offset of the byte in
+    // the input from 0; the ID is empty before the first record and the
+    // context is empty unless requested.
+    Validation {
+        id: String,
+        line: u64,
+        column: usize,
+        offset: u64,
+        byte: u8,
+        message: String,
+        context: String,
+    },
+    // Reading the input failed while parsing the line numbered `line`
+    // (from 1), which starts at byte `offset` (from 0).
+    Read {
+        line: u64,
+        offset: u64,
+        error: Arc<io::Error>,
+    },
+}
 
 impl fmt::Display for FastaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error parsing FASTA records")
+        match self {
+            FastaError::Parse => write!(f, "Error parsing FASTA records"),
+            FastaError::Io(e) => write!(f, "IO error: {}", e),
+            FastaError::InvalidPattern(p) => write!(f, "Invalid pattern: {}", p),
+            FastaError::SequenceTooShort { id, len, needed } => write!(
+                f,
+                "Sequence {} has length {} but at least {} is required",
+                id, len, needed
+            ),
+            FastaError::LengthMismatch {
+                id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Sequence {} has length {} but {} was expected",
+                id, found, expected
+            ),
+            FastaError::DuplicateId(id) => write!(f, "Duplicate record ID: {}", id),
+            FastaError::UnknownId(id) => write!(f, "Unknown record ID: {}", id),
+            FastaError::Cancelled => write!(f, "Operation cancelled"),
+            FastaError::InvalidSequence {
+                id,
+                position,
+                found,
+            } => write!(
+                f,
+                "Sequence {} has unexpected character {:?} at position {}",
+                id, found, position
+            ),
+            FastaError::OutOfBounds {
+                id,
+                start,
+                end,
+                len,
+            } => write!(
+                f,
+                "Interval {}..{} is outside sequence {} of length {}",
+                start, end, id, len
+            ),
+            FastaError::RecordTooLarge { id, limit } if id.is_empty() => {
+                write!(f, "Record exceeds the limit of {} bytes", limit)
+            }
+            FastaError::RecordTooLarge { id, limit } => {
+                write!(f, "Record {} exceeds the limit of {} bytes", id, limit)
+            }
+            FastaError::LineTooLong {
+                id,
+                line,
+                offset,
+                limit,
+            } => {
+                write!(
+                    f,
+                    "Line {} (offset {}) exceeds the limit of {} bytes",
+                    line, offset, limit
+                )?;
+                if !id.is_empty() {
+                    write!(f, " in record {}", id)?;
+                }
+                Ok(())
+            }
+            FastaError::RecordTimeout { id, limit } => write!(
+                f,
+                "Record {} took longer than {} ms to parse",
+                id,
+                limit.as_millis()
+            ),
+            FastaError::Format { line, message } => {
+                write!(f, "Malformed input at line {}: {}", line, message)
+            }
+            FastaError::ReferenceMismatch {
+                id,
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Reference allele {} at {}:{} does not match the sequence ({})",
+                expected, id, position, found
+            ),
+            FastaError::UnpairedRead {
+                id,
+                mate: Some(mate),
+            } => write!(
+                f,
+                "Read {} is paired with differently named read {}",
+                id, mate
+            ),
+            FastaError::UnpairedRead { id, mate: None } => {
+                write!(f, "Read {} has no mate", id)
+            }
+            FastaError::InvalidQuality {
+                id,
+                position,
+                found,
+            } => write!(
+                f,
+                "Read {} has invalid quality character {:?} at position {}",
+                id, found, position
+            ),
+            FastaError::Validation {
+                id,
+                line,
+                column,
+                byte,
+                message,
+                context,
+                ..
+            } => {
+                write!(
+                    f,
+                    "{} at line {}, column {} (byte 0x{:02x})",
+                    message, line, column, byte
+                )?;
+                if !id.is_empty() {
+                    write!(f, " in record {}", id)?;
+                }
+                if !context.is_empty() {
+                    write!(f, ": {:?}", context)?;
+                }
+                Ok(())
+            }
+            FastaError::Read {
+                line,
+                offset,
+                error,
+            } => write!(
+                f,
+                "IO error while parsing Fasta records at line {} (offset {}): {}",
+                line, offset, error
+            ),
+        }
     }
 }
 
 // This is important for other errors to wrap this one.
 impl error::Error for FastaError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        // Generic error, underlying cause isn't tracked.
-        None
+        match self {
+            FastaError::Io(e) | FastaError::Read { error: e, .. } => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FastaError {
+    fn from(e: io::Error) -> FastaError {
+        FastaError::Io(Arc::new(e))
     }
 }
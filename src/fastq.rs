@@ -0,0 +1,306 @@
+//! FASTQ records and reader
+//!
+//! Records are four lines: `@` header, sequence, `+` (optionally
+//! repeating the header) and quality, one character per base. Sequence
+//! and quality lines must not wrap. Qualities are kept as the raw
+//! characters; `FastqRecord::quality_scores` decodes them as Phred scores.
+
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Lines, Write};
+
+use crate::errors::FastaError;
+use crate::Record;
+
+/// How Phred scores are encoded as quality characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhredEncoding {
+    /// Score + 33: Sanger and Illumina 1.8+
+    #[default]
+    Phred33,
+    /// Score + 64: Illumina 1.3 to 1.7
+    Phred64,
+}
+
+impl PhredEncoding {
+    /// The character offset of score 0
+    pub fn offset(self) -> u8 {
+        match self {
+            PhredEncoding::Phred33 => 33,
+            PhredEncoding::Phred64 => 64,
+        }
+    }
+}
+
+/// Guess the encoding of a sample of reads from their quality characters.
+///
+/// Any character below `@` (64) means Phred+33 and any above `K` (75),
+/// beyond the scores Phred+33 files use in practice, means Phred+64.
+/// Returns None if the sample is empty or all characters lie in between.
+pub fn detect_encoding<'a, I>(records: I) -> Option<PhredEncoding>
+where
+    I: IntoIterator<Item = &'a FastqRecord>,
+{
+    let mut high = false;
+    for rec in records {
+        for q in rec.quality.bytes() {
+            if q < 64 {
+                return Some(PhredEncoding::Phred33);
+            }
+            high |= q > b'K';
+        }
+    }
+    if high {
+        Some(PhredEncoding::Phred64)
+    } else {
+        None
+    }
+}
+
+/// A single FASTQ record
+#[derive(Debug, Default)]
+pub struct FastqRecord {
+    pub id: String,
+    pub description: String,
+    pub sequence: String,
+    /// Raw quality characters, as long as the sequence
+    pub quality: String,
+}
+
+impl FastqRecord {
+    /// Returns an empty FastqRecord
+    pub fn new() -> FastqRecord {
+        FastqRecord {
+            ..Default::default()
+        }
+    }
+
+    /// Generate the four-line FASTQ representation
+    pub fn as_string(&self) -> String {
+        if self.description.is_empty() {
+            format!("@{}\n{}\n+\n{}\n", self.id, self.sequence, self.quality)
+        } else {
+            format!(
+                "@{} {}\n{}\n+\n{}\n",
+                self.id, self.description, self.sequence, self.quality
+            )
+        }
+    }
+
+    /// Write the record to an object implementing Write
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(self.as_string().as_bytes())
+    }
+
+    /// Drop the qualities, keeping ID, description and sequence
+    pub fn into_fasta(self) -> Record {
+        Record {
+            id: self.id,
+            description: self.description,
+            sequence: self.sequence,
+        }
+    }
+
+    /// Phred scores of the bases. Characters below the encoding's offset
+    /// decode as 0.
+    pub fn quality_scores(&self, encoding: PhredEncoding) -> Vec<u8> {
+        let offset = encoding.offset();
+        self.quality
+            .bytes()
+            .map(|q| q.saturating_sub(offset))
+            .collect()
+    }
+
+    /// Re-encode the qualities from one encoding to another.
+    ///
+    /// Fails, leaving the record unchanged, on a character below the
+    /// offset of `from` or a score that `to` cannot represent.
+    pub fn convert_encoding(
+        &mut self,
+        from: PhredEncoding,
+        to: PhredEncoding,
+    ) -> Result<(), FastaError> {
+        let mut quality = String::with_capacity(self.quality.len());
+        for (position, q) in self.quality.bytes().enumerate() {
+            let converted = q
+                .checked_sub(from.offset())
+                .map(|score| u32::from(score) + u32::from(to.offset()))
+                .filter(|&c| c <= u32::from(b'~'));
+            match converted {
+                Some(c) => quality.push(c as u8 as char),
+                None => {
+                    return Err(FastaError::InvalidQuality {
+                        id: self.id.clone(),
+                        position,
+                        found: q as char,
+                    })
+                }
+            }
+        }
+        self.quality = quality;
+        Ok(())
+    }
+
+    /// Keep only bases `start..end`, with their qualities
+    pub(crate) fn truncate_to(&mut self, start: usize, end: usize) {
+        self.sequence.truncate(end);
+        self.quality.truncate(end);
+        self.sequence.drain(..start);
+        self.quality.drain(..start);
+    }
+}
+
+impl fmt::Display for FastqRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "@{} {}\n{}...",
+            self.id,
+            self.description,
+            self.sequence.get(0..40).unwrap_or(&self.sequence)
+        )
+    }
+}
+
+fn format_error(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::Format {
+            line,
+            message: message.to_owned(),
+        },
+    )
+}
+
+/// An iterator over the records of a FASTQ file
+pub struct FastqReader<B: BufRead> {
+    lines: Lines<B>,
+    line: usize,
+}
+
+impl<B: BufRead> FastqReader<B> {
+    pub fn new(b: B) -> FastqReader<B> {
+        FastqReader {
+            lines: b.lines(),
+            line: 0,
+        }
+    }
+
+    fn next_line(&mut self) -> Option<io::Result<String>> {
+        let line = self.lines.next()?;
+        self.line += 1;
+        Some(line.map(|mut line| {
+            let len = line.trim_end().len();
+            line.truncate(len);
+            line
+        }))
+    }
+
+    fn read_record(&mut self, header: String) -> io::Result<FastqRecord> {
+        let header = header
+            .strip_prefix('@')
+            .ok_or_else(|| format_error(self.line, "expected '@' header"))?;
+        let mut parts = header.splitn(2, char::is_whitespace);
+        let mut rec = FastqRecord {
+            id: parts.next().unwrap_or("").to_owned(),
+            description: parts.next().unwrap_or("").trim().to_owned(),
+            ..FastqRecord::new()
+        };
+        let id = rec.id.clone();
+        let mut field = |name: &str| match self.next_line() {
+            Some(line) => line,
+            None => Err(format_error(
+                self.line,
+                &format!("record {} ends before its {} line", id, name),
+            )),
+        };
+        rec.sequence = field("sequence")?;
+        let sep = field("separator")?;
+        rec.quality = field("quality")?;
+        if !sep.starts_with('+') {
+            return Err(format_error(self.line - 1, "expected '+' separator"));
+        }
+        if rec.quality.len() != rec.sequence.len() {
+            return Err(format_error(
+                self.line,
+                &format!(
+                    "record {} has {} bases but {} qualities",
+                    rec.id,
+                    rec.sequence.len(),
+                    rec.quality.len()
+                ),
+            ));
+        }
+        Ok(rec)
+    }
+}
+
+impl<B: BufRead> Iterator for FastqReader<B> {
+    type Item = Result<FastqRecord, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let header = loop {
+            match self.next_line()? {
+                Ok(line) if line.is_empty() => continue,
+                Ok(line) => break line,
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        Some(self.read_record(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_records() {
+        let input = "@r1 lane=1\nACGT\n+r1\nIIII\r\n@r2\nAC\n+\n#I\n\n";
+        let records: Vec<FastqRecord> = FastqReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].description, "lane=1");
+        assert_eq!(records[0].quality, "IIII");
+        assert_eq!(records[1].as_string(), "@r2\nAC\n+\n#I\n");
+
+        let short = FastqReader::new("@r\nACGT\n+\nII\n".as_bytes()).next();
+        assert!(short.unwrap().is_err());
+        let truncated = FastqReader::new("@r\nACGT\n".as_bytes()).next();
+        assert!(truncated.unwrap().is_err());
+    }
+
+    #[test]
+    fn encodings() {
+        let r = FastqRecord {
+            id: "r".to_owned(),
+            sequence: "ACG".to_owned(),
+            quality: "#5I".to_owned(),
+            ..FastqRecord::new()
+        };
+        assert_eq!(r.quality_scores(PhredEncoding::Phred33), [2, 20, 40]);
+        assert_eq!(
+            detect_encoding(std::iter::once(&r)),
+            Some(PhredEncoding::Phred33)
+        );
+
+        let mut r = FastqRecord {
+            quality: "BTh".to_owned(),
+            ..r
+        };
+        assert_eq!(
+            detect_encoding(std::iter::once(&r)),
+            Some(PhredEncoding::Phred64)
+        );
+        r.convert_encoding(PhredEncoding::Phred64, PhredEncoding::Phred33)
+            .unwrap();
+        assert_eq!(r.quality, "#5I");
+        r.quality = "~".to_owned();
+        assert!(r
+            .convert_encoding(PhredEncoding::Phred33, PhredEncoding::Phred64)
+            .is_err());
+        assert_eq!(r.quality, "~");
+        assert_eq!(detect_encoding(&[]), None);
+    }
+}
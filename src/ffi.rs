@@ -0,0 +1,202 @@
+//! C interface
+//!
+//! Built with the `ffi` feature; `include/fasta.h` declares these
+//! functions. Readers and records are opaque pointers owned by the
+//! caller, who releases them with `fasta_free` and `fasta_record_free`.
+//! Strings returned for a record stay valid until the record is freed.
+
+use std::ffi::{CStr, CString, NulError};
+use std::fs::File;
+use std::io::BufReader;
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::{FastaBuffer, Record};
+
+/// An open FASTA file
+pub struct FastaReader {
+    records: FastaBuffer<BufReader<File>>,
+    error: Option<CString>,
+}
+
+/// A record with NUL-terminated copies of its fields
+pub struct FastaRecord {
+    id: CString,
+    description: CString,
+    sequence: CString,
+    len: usize,
+}
+
+impl FastaRecord {
+    fn new(rec: Record) -> Result<FastaRecord, NulError> {
+        Ok(FastaRecord {
+            len: rec.sequence.len(),
+            id: CString::new(rec.id)?,
+            description: CString::new(rec.description)?,
+            sequence: CString::new(rec.sequence)?,
+        })
+    }
+}
+
+/// Open a FASTA file, returning NULL if it cannot be opened.
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_open(path: *const c_char) -> *mut FastaReader {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+    match File::open(path) {
+        Ok(file) => Box::into_raw(Box::new(FastaReader {
+            records: FastaBuffer::from(BufReader::new(file)),
+            error: None,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Read the next record. Returns NULL at the end of the file or on an
+/// error, which `fasta_error` then describes.
+///
+/// # Safety
+///
+/// `reader` must come from `fasta_open` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_next_record(reader: *mut FastaReader) -> *mut FastaRecord {
+    let reader = match reader.as_mut() {
+        Some(reader) => reader,
+        None => return ptr::null_mut(),
+    };
+    reader.error = None;
+    let rec = match reader.records.next() {
+        Some(Ok(rec)) => rec,
+        Some(Err(e)) => {
+            reader.error = CString::new(e.to_string()).ok();
+            return ptr::null_mut();
+        }
+        None => return ptr::null_mut(),
+    };
+    match FastaRecord::new(rec) {
+        Ok(rec) => Box::into_raw(Box::new(rec)),
+        Err(_) => {
+            reader.error = CString::new("record contains a NUL byte").ok();
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The error from the last `fasta_next_record` call, or NULL if it
+/// succeeded or reached the end of the file.
+///
+/// # Safety
+///
+/// `reader` must come from `fasta_open` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_error(reader: *const FastaReader) -> *const c_char {
+    match reader.as_ref().and_then(|r| r.error.as_ref()) {
+        Some(e) => e.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// The record's ID.
+///
+/// # Safety
+///
+/// `record` must come from `fasta_next_record` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_record_id(record: *const FastaRecord) -> *const c_char {
+    (*record).id.as_ptr()
+}
+
+/// The record's description, empty if it has none.
+///
+/// # Safety
+///
+/// `record` must come from `fasta_next_record` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_record_description(record: *const FastaRecord) -> *const c_char {
+    (*record).description.as_ptr()
+}
+
+/// The record's sequence.
+///
+/// # Safety
+///
+/// `record` must come from `fasta_next_record` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_record_seq(record: *const FastaRecord) -> *const c_char {
+    (*record).sequence.as_ptr()
+}
+
+/// The length of the record's sequence in bytes.
+///
+/// # Safety
+///
+/// `record` must come from `fasta_next_record` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_record_len(record: *const FastaRecord) -> usize {
+    (*record).len
+}
+
+/// Free a record. NULL is ignored.
+///
+/// # Safety
+///
+/// `record` must come from `fasta_next_record` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_record_free(record: *mut FastaRecord) {
+    if !record.is_null() {
+        drop(Box::from_raw(record));
+    }
+}
+
+/// Close a reader. NULL is ignored.
+///
+/// # Safety
+///
+/// `reader` must come from `fasta_open` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fasta_free(reader: *mut FastaReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_through_c_interface() {
+        let path = std::env::temp_dir().join(format!("fasta-ffi-{}.fa", std::process::id()));
+        std::fs::write(&path, ">a one\nACGT\n>b\nGG\n").unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let reader = fasta_open(c_path.as_ptr());
+            assert!(!reader.is_null());
+            let rec = fasta_next_record(reader);
+            assert_eq!(CStr::from_ptr(fasta_record_id(rec)).to_str(), Ok("a"));
+            assert_eq!(
+                CStr::from_ptr(fasta_record_description(rec)).to_str(),
+                Ok("one")
+            );
+            assert_eq!(CStr::from_ptr(fasta_record_seq(rec)).to_str(), Ok("ACGT"));
+            assert_eq!(fasta_record_len(rec), 4);
+            fasta_record_free(rec);
+            fasta_record_free(fasta_next_record(reader));
+            assert!(fasta_next_record(reader).is_null());
+            assert!(fasta_error(reader).is_null());
+            fasta_free(reader);
+        }
+        std::fs::remove_file(path).unwrap();
+        let missing = CString::new("/nonexistent/x.fa").unwrap();
+        assert!(unsafe { fasta_open(missing.as_ptr()) }.is_null());
+    }
+}
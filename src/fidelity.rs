@@ -0,0 +1,398 @@
+//! Round-trip fidelity
+//!
+//! `Faithful`, created by `FastaReaderBuilder::from_reader_faithful`,
+//! yields each record with a `Layout` of how it was written: the header
+//! line as spelled, the length of every sequence line, whitespace trimmed
+//! around sequence, comment and blank lines, line endings, and whether the
+//! input ended with a newline. `Layout::write` writes a record back in its
+//! layout, so unedited input with `\n` or `\r\n` line endings is
+//! reproduced byte for byte. Lines before the first header belong to the
+//! first record's layout.
+
+use std::io;
+use std::io::{BufRead, Write};
+
+use crate::parser::{Parser, Step};
+use crate::{guard, utf8_error, validation_error, FastaReaderBuilder, Record};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Ending {
+    #[default]
+    Lf,
+    CrLf,
+    // The last line of input with no line ending
+    Missing,
+}
+
+impl Ending {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Ending::Lf => b"\n",
+            Ending::CrLf => b"\r\n",
+            Ending::Missing => b"",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    // `count` consecutive sequence lines of `len` bytes
+    Sequence {
+        len: usize,
+        count: usize,
+        ending: Ending,
+    },
+    // A sequence line of `len` bytes with whitespace trimmed around it
+    Padded {
+        prefix: String,
+        len: usize,
+        suffix: String,
+        ending: Ending,
+    },
+    // A comment, blank line or leading junk
+    Other {
+        text: String,
+        ending: Ending,
+    },
+}
+
+/// How a record was laid out in its input
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    preamble: Vec<Line>,
+    header: String,
+    header_ending: Ending,
+    lines: Vec<Line>,
+}
+
+impl Layout {
+    /// The header line as read, including the `>`
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Length of the first sequence line, or 0 if there is none
+    pub fn line_width(&self) -> usize {
+        self.lines
+            .iter()
+            .find_map(|line| match line {
+                Line::Sequence { len, .. } | Line::Padded { len, .. } => Some(*len),
+                Line::Other { .. } => None,
+            })
+            .unwrap_or(0)
+    }
+
+    /// Whether the record's last line ends with a line ending
+    pub fn final_newline(&self) -> bool {
+        self.final_ending() != Ending::Missing
+    }
+
+    fn final_ending(&self) -> Ending {
+        match self.lines.last() {
+            Some(Line::Sequence { ending, .. })
+            | Some(Line::Padded { ending, .. })
+            | Some(Line::Other { ending, .. }) => *ending,
+            None => self.header_ending,
+        }
+    }
+
+    fn push(&mut self, line: Line) {
+        if let (
+            Some(Line::Sequence { len, count, ending }),
+            Line::Sequence {
+                len: new_len,
+                ending: new_ending,
+                ..
+            },
+        ) = (self.lines.last_mut(), &line)
+        {
+            if len == new_len && ending == new_ending {
+                *count += 1;
+                return;
+            }
+        }
+        self.lines.push(line);
+    }
+
+    /// Write `rec` in this layout. A header or sequence edited since
+    /// reading is written the standard way, with sequence beyond the
+    /// original length wrapped at `line_width`.
+    pub fn write(&self, rec: &Record, w: &mut impl Write) -> io::Result<()> {
+        let mut out = Out {
+            w,
+            pending: &[],
+            default_ending: self.header_ending.as_bytes(),
+        };
+        for line in &self.preamble {
+            if let Line::Other { text, ending } = line {
+                out.line(&[text.as_bytes()], *ending)?;
+            }
+        }
+        let mut original = Record::new();
+        original.set_header(self.header.trim());
+        if original.id == rec.id && original.description == rec.description {
+            out.line(&[self.header.as_bytes()], self.header_ending)?;
+        } else if rec.description.is_empty() {
+            out.line(&[b">", rec.id.as_bytes()], self.header_ending)?;
+        } else {
+            let header = [b">", rec.id.as_bytes(), b" ", rec.description.as_bytes()];
+            out.line(&header, self.header_ending)?;
+        }
+
+        let mut seq = rec.sequence.as_bytes();
+        for line in &self.lines {
+            match line {
+                Line::Sequence { len, count, ending } => {
+                    for _ in 0..*count {
+                        if seq.is_empty() {
+                            break;
+                        }
+                        let (chunk, rest) = seq.split_at((*len).min(seq.len()));
+                        out.line(&[chunk], *ending)?;
+                        seq = rest;
+                    }
+                }
+                Line::Padded {
+                    prefix,
+                    len,
+                    suffix,
+                    ending,
+                } => {
+                    if !seq.is_empty() {
+                        let (chunk, rest) = seq.split_at((*len).min(seq.len()));
+                        out.line(&[prefix.as_bytes(), chunk, suffix.as_bytes()], *ending)?;
+                        seq = rest;
+                    }
+                }
+                Line::Other { text, ending } => out.line(&[text.as_bytes()], *ending)?,
+            }
+        }
+        if !seq.is_empty() {
+            let width = match self.line_width() {
+                0 => seq.len(),
+                width => width,
+            };
+            if out.pending.is_empty() {
+                out.pending = out.default_ending;
+            }
+            for chunk in seq.chunks(width) {
+                let ending = out.default_ending;
+                out.line_with(&[chunk], ending)?;
+            }
+            out.pending = self.final_ending().as_bytes();
+        }
+        out.w.write_all(out.pending)
+    }
+}
+
+// Writes lines, holding back each line ending so that sequence added
+// after the original last line goes before a missing final newline
+struct Out<'a, W: Write> {
+    w: &'a mut W,
+    pending: &'static [u8],
+    default_ending: &'static [u8],
+}
+
+impl<'a, W: Write> Out<'a, W> {
+    fn line(&mut self, parts: &[&[u8]], ending: Ending) -> io::Result<()> {
+        self.line_with(parts, ending.as_bytes())
+    }
+
+    fn line_with(&mut self, parts: &[&[u8]], ending: &'static [u8]) -> io::Result<()> {
+        self.w.write_all(self.pending)?;
+        for part in parts {
+            self.w.write_all(part)?;
+        }
+        self.pending = ending;
+        Ok(())
+    }
+}
+
+/// Iterator over records and their layouts, created by
+/// `FastaReaderBuilder::from_reader_faithful`
+pub struct Faithful<B: BufRead> {
+    inner: B,
+    parser: Parser,
+    line: Vec<u8>,
+    // The line in `line` ended the last record and must be pushed again
+    replay: bool,
+    line_number: u64,
+    // Offset of the line in `line`, and of the line after it
+    line_offset: u64,
+    next_offset: u64,
+    in_record: bool,
+    layout: Layout,
+    finished: bool,
+}
+
+impl<B: BufRead> Faithful<B> {
+    pub(crate) fn new(inner: B, opts: &FastaReaderBuilder) -> Faithful<B> {
+        Faithful {
+            inner,
+            parser: Parser::new(opts.parse),
+            line: Vec::new(),
+            replay: false,
+            line_number: 0,
+            line_offset: 0,
+            next_offset: 0,
+            in_record: false,
+            layout: Layout::default(),
+            finished: false,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<bool> {
+        if self.replay {
+            self.replay = false;
+            return Ok(true);
+        }
+        self.line.clear();
+        self.line_number += 1;
+        let n = self.inner.read_until(b'\n', &mut self.line)?;
+        self.line_offset = self.next_offset;
+        self.next_offset += n as u64;
+        Ok(n > 0)
+    }
+}
+
+impl<B: BufRead> Iterator for Faithful<B> {
+    type Item = Result<(Record, Layout), io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            match self.next_line() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.finished = true;
+                    let rec = self.parser.finish()?;
+                    return Some(Ok((rec, std::mem::take(&mut self.layout))));
+                }
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+            let (text, ending) = match self.line.strip_suffix(b"\r\n") {
+                Some(text) => (text, Ending::CrLf),
+                None => match self.line.strip_suffix(b"\n") {
+                    Some(text) => (text, Ending::Lf),
+                    None => (&self.line[..], Ending::Missing),
+                },
+            };
+            let text = match std::str::from_utf8(text) {
+                Ok(text) => text,
+                Err(e) => {
+                    self.finished = true;
+                    let mut err = utf8_error(self.line_number, self.line_offset, text, e);
+                    if self.in_record {
+                        let mut header = Record::new();
+                        header.set_header(self.layout.header.trim());
+                        guard::set_record_id(&mut err, &header.id);
+                    }
+                    return Some(Err(err));
+                }
+            };
+            let before = self.parser.seq_len();
+            match self.parser.push_line(text, true) {
+                Ok(Step::Done(rec)) => {
+                    self.replay = true;
+                    self.in_record = false;
+                    return Some(Ok((rec, std::mem::take(&mut self.layout))));
+                }
+                Ok(Step::Header) => {
+                    self.in_record = true;
+                    self.layout.header = text.to_owned();
+                    self.layout.header_ending = ending;
+                }
+                Ok(Step::Sequence) => {
+                    let len = self.parser.seq_len() - before;
+                    if text.len() == len {
+                        self.layout.push(Line::Sequence {
+                            len,
+                            count: 1,
+                            ending,
+                        });
+                    } else {
+                        let lead = text.len() - text.trim_start().len();
+                        self.layout.push(Line::Padded {
+                            prefix: text[..lead].to_owned(),
+                            len,
+                            suffix: text[lead + len..].to_owned(),
+                            ending,
+                        });
+                    }
+                }
+                Ok(Step::Skipped) | Ok(Step::Junk) => {
+                    let line = Line::Other {
+                        text: text.to_owned(),
+                        ending,
+                    };
+                    if self.in_record {
+                        self.layout.push(line);
+                    } else {
+                        self.layout.preamble.push(line);
+                    }
+                }
+                Err(e) => {
+                    let mut header = Record::new();
+                    header.set_header(self.layout.header.trim());
+                    self.in_record = false;
+                    self.layout = Layout::default();
+                    return Some(Err(validation_error(
+                        &header.id,
+                        self.line_number,
+                        self.line_offset,
+                        text,
+                        e,
+                        0,
+                    )));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &str) -> String {
+        let mut out = Vec::new();
+        for item in FastaReaderBuilder::new().from_reader_faithful(input.as_bytes()) {
+            let (rec, layout) = item.unwrap();
+            layout.write(&rec, &mut out).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn reproduces_input() {
+        for input in [
+            ">a  two  spaces\nACGTA\nCGTAC\nGT\n>b\nAC\nGTT\nA\n",
+            "junk\n;note\n>a\r\nACG\r\n\r\n  TT \r\n>b\nA",
+            ">a\n>b\nAC\n;trailing\n\n",
+        ] {
+            assert_eq!(round_trip(input), input);
+        }
+    }
+
+    #[test]
+    fn edited_records() {
+        let mut records =
+            FastaReaderBuilder::new().from_reader_faithful(">a x\nACG\nTA".as_bytes());
+        let (mut rec, layout) = records.next().unwrap().unwrap();
+        assert_eq!(layout.header(), ">a x");
+        assert_eq!(layout.line_width(), 3);
+        assert!(!layout.final_newline());
+
+        rec.id = "b".to_owned();
+        rec.description.clear();
+        rec.sequence.push_str("GGCCT");
+        let mut out = Vec::new();
+        layout.write(&rec, &mut out).unwrap();
+        assert_eq!(out, b">b\nACG\nTA\nGGC\nCT");
+    }
+}
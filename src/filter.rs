@@ -0,0 +1,280 @@
+//! Iterator adapters selecting records from a stream
+
+use std::collections::HashMap;
+use std::io;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use crate::Record;
+
+/// k-mer size used to prefilter candidates in `filter_by_identity`
+const IDENTITY_K: usize = 8;
+
+/// Each k-mer of `seq` with the number of times it occurs
+fn kmers(seq: &[u8], k: usize) -> HashMap<&[u8], usize> {
+    let mut counts = HashMap::new();
+    if seq.len() >= k {
+        for kmer in seq.windows(k) {
+            *counts.entry(kmer).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Edit distance between `a` and `b`, or None if it exceeds `max`.
+/// Only cells within `max` of the diagonal are computed.
+fn bounded_edit_distance(a: &[u8], b: &[u8], max: usize) -> Option<usize> {
+    let (la, lb) = (a.len(), b.len());
+    if la.max(lb) - la.min(lb) > max {
+        return None;
+    }
+    let inf = usize::MAX / 2;
+    let mut prev = vec![inf; lb + 1];
+    let mut cur = vec![inf; lb + 1];
+    for (j, cell) in prev.iter_mut().enumerate().take(lb.min(max) + 1) {
+        *cell = j;
+    }
+
+    for i in 1..=la {
+        let lo = i.saturating_sub(max);
+        let hi = lb.min(i + max);
+        for cell in &mut cur[lo.saturating_sub(1)..=lb.min(hi + 1)] {
+            *cell = inf;
+        }
+        if lo == 0 {
+            cur[0] = i;
+        }
+        let mut row_min = if lo == 0 { i } else { inf };
+        for j in lo.max(1)..=hi {
+            let sub = prev[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let v = sub.min(prev[j] + 1).min(cur[j - 1] + 1);
+            cur[j] = v;
+            row_min = row_min.min(v);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    Some(prev[lb]).filter(|&d| d <= max)
+}
+
+/// Percent identity of two sequences as a fraction in [0, 1].
+///
+/// Identity is `1 - d / max(len(a), len(b))` where `d` is the edit
+/// distance of an optimal global alignment. Comparison ignores case.
+pub fn identity(a: &str, b: &str) -> f64 {
+    let a = a.to_ascii_uppercase();
+    let b = b.to_ascii_uppercase();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let d = bounded_edit_distance(a.as_bytes(), b.as_bytes(), longest).unwrap_or(longest);
+    1.0 - d as f64 / longest as f64
+}
+
+/// Iterator returned by `filter_by_identity`
+pub struct IdentityFilter<I> {
+    records: I,
+    reference: Vec<u8>,
+    ref_kmers: HashMap<Vec<u8>, usize>,
+    min_identity: f64,
+}
+
+impl<I> IdentityFilter<I> {
+    fn passes(&self, rec: &Record) -> bool {
+        let query = rec.sequence.to_ascii_uppercase();
+        let query = query.as_bytes();
+        let longest = query.len().max(self.reference.len());
+        if longest == 0 {
+            return true;
+        }
+        let max_edits = ((1.0 - self.min_identity) * longest as f64 + 1e-9).floor() as usize;
+
+        // Each edit destroys at most k of the query's k-mers, so a query
+        // sharing fewer k-mers than this, counted with multiplicity,
+        // cannot reach min_identity.
+        let total = (query.len() + 1).saturating_sub(IDENTITY_K);
+        let needed = total.saturating_sub(IDENTITY_K * max_edits);
+        if needed > 0 {
+            let shared: usize = kmers(query, IDENTITY_K)
+                .into_iter()
+                .map(|(k, n)| n.min(self.ref_kmers.get(k).copied().unwrap_or(0)))
+                .sum();
+            if shared < needed {
+                return false;
+            }
+        }
+
+        bounded_edit_distance(query, &self.reference, max_edits).is_some()
+    }
+}
+
+impl<I> Iterator for IdentityFilter<I>
+where
+    I: Iterator<Item = Result<Record, io::Error>>,
+{
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.records.next()? {
+                Ok(rec) if !self.passes(&rec) => continue,
+                other => return Some(other),
+            }
+        }
+    }
+}
+
+/// Keep records whose identity to `reference` is at least `min_identity`.
+///
+/// Candidates are prefiltered by shared k-mers, using a bound that never
+/// rejects a record that would pass, and then confirmed with a banded
+/// global alignment. See `identity` for the definition used.
+pub fn filter_by_identity<I>(
+    records: I,
+    reference: &Record,
+    min_identity: f64,
+) -> IdentityFilter<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let reference = reference.sequence.to_ascii_uppercase().into_bytes();
+    let ref_kmers = kmers(&reference, IDENTITY_K)
+        .into_iter()
+        .map(|(k, n)| (k.to_vec(), n))
+        .collect();
+    IdentityFilter {
+        records: records.into_iter(),
+        reference,
+        ref_kmers,
+        min_identity,
+    }
+}
+
+/// Iterator returned by `filter_by_id` and `filter_by_description`
+#[cfg(feature = "regex")]
+pub struct RegexFilter<I> {
+    records: I,
+    re: Regex,
+    on_id: bool,
+}
+
+#[cfg(feature = "regex")]
+impl<I> Iterator for RegexFilter<I>
+where
+    I: Iterator<Item = Result<Record, io::Error>>,
+{
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.records.next()? {
+                Ok(rec) => {
+                    let field = if self.on_id {
+                        &rec.id
+                    } else {
+                        &rec.description
+                    };
+                    if self.re.is_match(field) {
+                        return Some(Ok(rec));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Keep records whose ID matches `re`
+#[cfg(feature = "regex")]
+pub fn filter_by_id<I>(records: I, re: Regex) -> RegexFilter<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    RegexFilter {
+        records: records.into_iter(),
+        re,
+        on_id: true,
+    }
+}
+
+/// Keep records whose description matches `re`
+#[cfg(feature = "regex")]
+pub fn filter_by_description<I>(records: I, re: Regex) -> RegexFilter<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    RegexFilter {
+        records: records.into_iter(),
+        re,
+        on_id: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn edit_distance_matches_full_dp() {
+        assert_eq!(bounded_edit_distance(b"kitten", b"sitting", 10), Some(3));
+        assert_eq!(bounded_edit_distance(b"kitten", b"sitting", 2), None);
+        assert_eq!(bounded_edit_distance(b"", b"ACG", 3), Some(3));
+        assert!((identity("ACGTACGTAC", "ACGTACCTAC") - 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn keeps_close_sequences() {
+        let mut reference = Record::new();
+        reference.sequence = "ACGTTGCATGCATGCAAGTCCGATCGATGCTAGCTAGGCTA".to_owned();
+        let input = format!(
+            ">same\n{}\n>one_snp\n{}\n>other\n{}\n",
+            reference.sequence,
+            reference.sequence.replacen("GCAA", "GGAA", 1),
+            "TTTTTTTTTTGGGGGGGGGGCCCCCCCCCCAAAAAAAAAAT"
+        );
+        let ids: Vec<String> =
+            filter_by_identity(FastaBuffer::from(input.as_bytes()), &reference, 0.95)
+                .map(|r| r.unwrap().id)
+                .collect();
+        assert_eq!(ids, vec!["same", "one_snp"]);
+    }
+
+    #[test]
+    fn keeps_low_complexity_self_match() {
+        let mut reference = Record::new();
+        reference.sequence = "A".repeat(20);
+        let input = format!(">same\n{}\n>longer\n{}\n", "A".repeat(20), "A".repeat(30));
+        let ids: Vec<String> =
+            filter_by_identity(FastaBuffer::from(input.as_bytes()), &reference, 0.95)
+                .map(|r| r.unwrap().id)
+                .collect();
+        assert_eq!(ids, vec!["same"]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_filters() {
+        let input =
+            ">NC_012920.1 Homo sapiens mitochondrion\nACGT\n>NC_000001.11 chromosome 1\nGG\n";
+        let mito: Vec<String> = filter_by_description(
+            FastaBuffer::from(input.as_bytes()),
+            Regex::new("mitochondrion").unwrap(),
+        )
+        .map(|r| r.unwrap().id)
+        .collect();
+        assert_eq!(mito, vec!["NC_012920.1"]);
+
+        let n = filter_by_id(
+            FastaBuffer::from(input.as_bytes()),
+            Regex::new("^NC_0000").unwrap(),
+        )
+        .count();
+        assert_eq!(n, 1);
+    }
+}
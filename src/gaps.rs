@@ -0,0 +1,204 @@
+//! Detection of N-gaps in scaffolded sequences
+
+use std::io;
+use std::io::Write;
+
+use crate::report::{NumberFormat, SCHEMA_VERSION};
+use crate::Record;
+
+/// Iterator over runs of `N`/`n` in a sequence, as half-open
+/// `(start, end)` positions. Created by `Record::gaps`.
+#[derive(Debug, Clone)]
+pub struct Gaps<'a> {
+    seq: &'a [u8],
+    pos: usize,
+    min_len: usize,
+}
+
+impl<'a> Iterator for Gaps<'a> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        while self.pos < self.seq.len() {
+            let start = match self.seq[self.pos..]
+                .iter()
+                .position(|b| b.eq_ignore_ascii_case(&b'N'))
+            {
+                Some(i) => self.pos + i,
+                None => {
+                    self.pos = self.seq.len();
+                    return None;
+                }
+            };
+            let end = self.seq[start..]
+                .iter()
+                .position(|b| !b.eq_ignore_ascii_case(&b'N'))
+                .map_or(self.seq.len(), |i| start + i);
+            self.pos = end;
+            if end - start >= self.min_len {
+                return Some((start, end));
+            }
+        }
+        None
+    }
+}
+
+impl Record {
+    /// Returns the runs of `N` or `n` at least `min_len` bases long. A
+    /// `min_len` of 0 is treated as 1.
+    pub fn gaps(&self, min_len: usize) -> Gaps<'_> {
+        Gaps {
+            seq: self.sequence.as_bytes(),
+            pos: 0,
+            min_len: min_len.max(1),
+        }
+    }
+}
+
+/// The largest gap seen by a `GapReport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargestGap {
+    pub id: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LargestGap {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Gap totals accumulated over a file or collection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GapReport {
+    /// Minimum run length counted as a gap
+    pub min_len: usize,
+    pub records: u64,
+    /// Records containing at least one gap
+    pub records_with_gaps: u64,
+    pub gaps: u64,
+    pub gap_bases: u64,
+    pub total_length: u64,
+    /// The first of the longest gaps, if any
+    pub largest: Option<LargestGap>,
+}
+
+impl GapReport {
+    /// Returns an empty report counting runs of at least `min_len` bases
+    pub fn new(min_len: usize) -> GapReport {
+        GapReport {
+            min_len: min_len.max(1),
+            ..GapReport::default()
+        }
+    }
+
+    /// Scan a record stream for gaps
+    pub fn from_records<I>(records: I, min_len: usize) -> io::Result<GapReport>
+    where
+        I: IntoIterator<Item = Result<Record, io::Error>>,
+    {
+        let mut report = GapReport::new(min_len);
+        for rec in records {
+            report.add(&rec?);
+        }
+        Ok(report)
+    }
+
+    /// Add a record's gaps to the totals
+    pub fn add(&mut self, rec: &Record) {
+        self.records += 1;
+        self.total_length += rec.sequence.len() as u64;
+        let mut any = false;
+        for (start, end) in rec.gaps(self.min_len) {
+            any = true;
+            self.gaps += 1;
+            self.gap_bases += (end - start) as u64;
+            if !matches!(&self.largest, Some(g) if g.len() >= end - start) {
+                self.largest = Some(LargestGap {
+                    id: rec.id.clone(),
+                    start,
+                    end,
+                });
+            }
+        }
+        if any {
+            self.records_with_gaps += 1;
+        }
+    }
+
+    /// Fraction of all bases that fall inside gaps
+    pub fn gap_fraction(&self) -> f64 {
+        if self.total_length == 0 {
+            0.0
+        } else {
+            self.gap_bases as f64 / self.total_length as f64
+        }
+    }
+
+    /// Write the report as tab-separated `key\tvalue` lines. The largest
+    /// gap is written as `id:start-end` with a 1-based inclusive range, or
+    /// `-` when there are no gaps.
+    pub fn write_report<W: Write>(&self, w: &mut W, fmt: &NumberFormat) -> io::Result<()> {
+        writeln!(w, "schema\t{}", SCHEMA_VERSION)?;
+        writeln!(w, "min_gap_length\t{}", self.min_len)?;
+        writeln!(w, "records\t{}", self.records)?;
+        writeln!(w, "records_with_gaps\t{}", self.records_with_gaps)?;
+        writeln!(w, "gaps\t{}", self.gaps)?;
+        writeln!(w, "gap_bases\t{}", self.gap_bases)?;
+        writeln!(w, "gap_fraction\t{}", fmt.format(self.gap_fraction()))?;
+        match &self.largest {
+            Some(g) => {
+                writeln!(w, "largest_gap\t{}", g.len())?;
+                writeln!(w, "largest_gap_at\t{}:{}-{}", g.id, g.start + 1, g.end)
+            }
+            None => {
+                writeln!(w, "largest_gap\t0")?;
+                writeln!(w, "largest_gap_at\t-")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    fn rec(seq: &str) -> Record {
+        Record {
+            sequence: seq.to_owned(),
+            ..Record::new()
+        }
+    }
+
+    #[test]
+    fn runs() {
+        let r = rec("NNACGnnnTNAN");
+        assert_eq!(
+            r.gaps(1).collect::<Vec<_>>(),
+            [(0, 2), (5, 8), (9, 10), (11, 12)]
+        );
+        assert_eq!(r.gaps(2).collect::<Vec<_>>(), [(0, 2), (5, 8)]);
+        assert_eq!(rec("ACGT").gaps(1).count(), 0);
+    }
+
+    #[test]
+    fn report() {
+        let input = ">a\nACNNNNGT\n>b\nACGT\n>c\nNNTTNNNN\n";
+        let report = GapReport::from_records(FastaBuffer::from(input.as_bytes()), 2).unwrap();
+        let mut out = Vec::new();
+        report
+            .write_report(&mut out, &NumberFormat::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "schema\tfasta-report/1\nmin_gap_length\t2\nrecords\t3\nrecords_with_gaps\t2\n\
+             gaps\t3\ngap_bases\t10\ngap_fraction\t0.5000\nlargest_gap\t4\nlargest_gap_at\ta:3-6\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Sliding-window GC content
+
+use crate::window::{Partial, Windows};
+use crate::Record;
+
+/// How `GcWindows` treats bases that are neither GC nor AT, such as N,
+/// gaps and most ambiguity codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ambiguous {
+    /// Leave them out of the denominator, as `Record::gc_content` does
+    #[default]
+    Exclude,
+    /// Count them as non-GC, so the fraction is GC bases / window length
+    Include,
+}
+
+/// Iterator over `(start, gc_fraction)` for windows of a sequence.
+/// Created by `Record::gc_windows`.
+#[derive(Debug, Clone)]
+pub struct GcWindows<'a> {
+    seq: &'a [u8],
+    windows: Windows<'a>,
+    ambiguous: Ambiguous,
+    // Counts for seq[counted.0..counted.1]
+    counted: (usize, usize),
+    gc: usize,
+    at: usize,
+}
+
+impl<'a> GcWindows<'a> {
+    /// Also yield a final window shorter than `window` covering the end
+    /// of the sequence if the full windows stop short of it. Off by
+    /// default.
+    pub fn partial(mut self, yes: bool) -> GcWindows<'a> {
+        let partial = if yes { Partial::Last } else { Partial::Skip };
+        self.windows = self.windows.partial(partial);
+        self
+    }
+
+    /// Set how ambiguous bases are counted
+    pub fn ambiguous(mut self, ambiguous: Ambiguous) -> GcWindows<'a> {
+        self.ambiguous = ambiguous;
+        self
+    }
+
+    fn count(&mut self, range: (usize, usize), sign: isize) {
+        for &b in &self.seq[range.0..range.1] {
+            let counter = match b.to_ascii_uppercase() {
+                b'G' | b'C' | b'S' => &mut self.gc,
+                b'A' | b'T' | b'U' | b'W' => &mut self.at,
+                _ => continue,
+            };
+            *counter = counter.wrapping_add_signed(sign);
+        }
+    }
+
+    /// Bring the running counts to cover `start..end`
+    fn move_to(&mut self, start: usize, end: usize) {
+        let (s, e) = self.counted;
+        if start >= e {
+            self.gc = 0;
+            self.at = 0;
+            self.count((start, end), 1);
+        } else {
+            self.count((s, start), -1);
+            self.count((e, end), 1);
+        }
+        self.counted = (start, end);
+    }
+}
+
+impl<'a> Iterator for GcWindows<'a> {
+    type Item = (usize, f64);
+
+    fn next(&mut self) -> Option<(usize, f64)> {
+        let (start, end) = self.windows.next_range()?;
+        self.move_to(start, end);
+        let total = match self.ambiguous {
+            Ambiguous::Exclude => self.gc + self.at,
+            Ambiguous::Include => end - start,
+        };
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            self.gc as f64 / total as f64
+        };
+        Some((start, fraction))
+    }
+}
+
+impl Record {
+    /// GC content of windows of `window` bases starting every `step`
+    /// bases, as `(start, fraction)` pairs with 0-based starts. Windows
+    /// with no counted bases report 0.0. Panics if `window` or `step`
+    /// is 0.
+    pub fn gc_windows(&self, window: usize, step: usize) -> GcWindows<'_> {
+        GcWindows {
+            seq: self.sequence.as_bytes(),
+            windows: Windows::new(&self.sequence, window, step),
+            ambiguous: Ambiguous::default(),
+            counted: (0, 0),
+            gc: 0,
+            at: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let rec = Record {
+            sequence: "GGAANNCCGT".to_owned(),
+            ..Record::new()
+        };
+        let full: Vec<(usize, f64)> = rec.gc_windows(4, 2).collect();
+        assert_eq!(full, [(0, 0.5), (2, 0.0), (4, 1.0), (6, 0.75)]);
+
+        let partial: Vec<(usize, f64)> = rec
+            .gc_windows(4, 4)
+            .partial(true)
+            .ambiguous(Ambiguous::Include)
+            .collect();
+        assert_eq!(partial, [(0, 0.5), (4, 0.5), (8, 0.5)]);
+        // Full windows already reach the end, so no partial one is added
+        assert_eq!(rec.gc_windows(4, 3).partial(true).count(), 3);
+    }
+}
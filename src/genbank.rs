@@ -0,0 +1,131 @@
+//! GenBank flatfile reader
+//!
+//! Each entry becomes a `Record` whose ID is the LOCUS name, whose
+//! description is the DEFINITION (continuation lines joined), and whose
+//! sequence is the ORIGIN section with numbering and spacing removed.
+//! Features and other sections are skipped.
+
+use std::io;
+use std::io::{BufRead, Lines};
+
+use crate::errors::FastaError;
+use crate::Record;
+
+/// An iterator over the entries of a GenBank flatfile
+pub struct GenbankReader<B: BufRead> {
+    lines: Lines<B>,
+    line: usize,
+}
+
+fn format_error(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::Format {
+            line,
+            message: message.to_owned(),
+        },
+    )
+}
+
+impl<B: BufRead> GenbankReader<B> {
+    pub fn new(b: B) -> GenbankReader<B> {
+        GenbankReader {
+            lines: b.lines(),
+            line: 0,
+        }
+    }
+}
+
+enum Section {
+    Definition,
+    Origin,
+    Other,
+}
+
+impl<B: BufRead> Iterator for GenbankReader<B> {
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut rec: Option<Record> = None;
+        let mut section = Section::Other;
+        for line in self.lines.by_ref() {
+            self.line += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            let rec = match rec.as_mut() {
+                Some(rec) => rec,
+                None if line.starts_with("LOCUS") => {
+                    let name = line.split_whitespace().nth(1).unwrap_or("");
+                    rec = Some(Record {
+                        id: name.to_owned(),
+                        ..Record::new()
+                    });
+                    continue;
+                }
+                None => return Some(Err(format_error(self.line, "expected LOCUS"))),
+            };
+            if line == "//" {
+                break;
+            }
+            let continuation = line.starts_with(' ');
+            if !continuation {
+                section = Section::Other;
+                if let Some(def) = line.strip_prefix("DEFINITION") {
+                    rec.description = def.trim().to_owned();
+                    section = Section::Definition;
+                } else if line.starts_with("ORIGIN") {
+                    section = Section::Origin;
+                }
+                continue;
+            }
+            match section {
+                Section::Definition => {
+                    rec.description.push(' ');
+                    rec.description.push_str(line.trim());
+                }
+                Section::Origin => rec.sequence.extend(
+                    line.chars()
+                        .filter(|c| !c.is_ascii_digit() && !c.is_whitespace()),
+                ),
+                Section::Other => (),
+            }
+        }
+        rec.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        let input = "LOCUS       SCU49845     12 bp    DNA             PLN       21-JUN-1999\n\
+            DEFINITION  Saccharomyces cerevisiae TCP1-beta gene, partial cds, and Axl2p\n            (AXL2) genes.\n\
+            ACCESSION   U49845\n\
+            FEATURES             Location/Qualifiers\n     source          1..12\n\
+            ORIGIN      \n        1 gatcctccat atacaacggt\n       21 at\n//\n\
+            LOCUS       X2  2 bp\nORIGIN\n        1 ac\n//\n";
+        let records: Vec<Record> = GenbankReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "SCU49845");
+        assert_eq!(
+            records[0].description,
+            "Saccharomyces cerevisiae TCP1-beta gene, partial cds, and Axl2p (AXL2) genes."
+        );
+        assert_eq!(records[0].sequence, "gatcctccatatacaacggtat");
+        assert_eq!(records[1].sequence, "ac");
+        assert!(GenbankReader::new("junk\n".as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+    }
+}
@@ -0,0 +1,347 @@
+//! GFF3 annotations and feature sequence extraction
+//!
+//! `read_gff` parses the feature lines of a GFF3 file and
+//! `extract_features` cuts the annotated sequences out of a reference,
+//! as `gffread -w`/`-x`/`-y` do: the segments of a feature (the lines
+//! of a multi-line CDS, or the exons of a transcript) are stitched in
+//! transcript order and reverse complemented on the minus strand.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::collection::Fasta;
+use crate::errors::FastaError;
+use crate::motif::Strand;
+use crate::seq::reverse_complement;
+use crate::translate::{translate, GeneticCode};
+use crate::Record;
+
+/// One feature line of a GFF3 file
+#[derive(Debug, Clone, PartialEq)]
+pub struct GffFeature {
+    pub seqid: String,
+    pub source: String,
+    pub feature_type: String,
+    /// 1-based, inclusive start
+    pub start: usize,
+    /// 1-based, inclusive end
+    pub end: usize,
+    pub score: Option<f64>,
+    /// None for unstranded (`.`) or unknown (`?`) features
+    pub strand: Option<Strand>,
+    /// CDS phase: bases to skip before the first complete codon
+    pub phase: Option<u8>,
+    /// Attributes in file order, with percent escapes decoded
+    pub attributes: Vec<(String, String)>,
+}
+
+impl GffFeature {
+    /// The value of the first attribute named `key`
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The `ID` attribute
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("ID")
+    }
+
+    /// The `Parent` IDs, of which there may be several
+    pub fn parents(&self) -> Vec<&str> {
+        self.attribute("Parent")
+            .map_or_else(Vec::new, |p| p.split(',').collect())
+    }
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_line(line: &str, n: usize) -> Result<GffFeature, FastaError> {
+    let err = |message: &str| FastaError::Format {
+        line: n,
+        message: message.to_owned(),
+    };
+    let f: Vec<&str> = line.split('\t').collect();
+    if f.len() != 9 {
+        return Err(err("expected 9 tab-separated columns"));
+    }
+    let start: usize = f[3].parse().map_err(|_| err("invalid start"))?;
+    let end: usize = f[4].parse().map_err(|_| err("invalid end"))?;
+    if start == 0 || end < start {
+        return Err(err("start must be at least 1 and not after end"));
+    }
+    let strand = match f[6] {
+        "+" => Some(Strand::Forward),
+        "-" => Some(Strand::Reverse),
+        "." | "?" => None,
+        _ => return Err(err("invalid strand")),
+    };
+    let phase = match f[7] {
+        "." => None,
+        p => Some(
+            p.parse()
+                .ok()
+                .filter(|&p| p < 3)
+                .ok_or_else(|| err("invalid phase"))?,
+        ),
+    };
+    let attributes = f[8]
+        .split(';')
+        .filter(|a| !a.trim().is_empty())
+        .map(|a| match a.split_once('=') {
+            Some((k, v)) => (decode(k.trim()), decode(v)),
+            None => (decode(a.trim()), String::new()),
+        })
+        .collect();
+    Ok(GffFeature {
+        seqid: decode(f[0]),
+        source: f[1].to_owned(),
+        feature_type: f[2].to_owned(),
+        start,
+        end,
+        score: f[5].parse().ok(),
+        strand,
+        phase,
+        attributes,
+    })
+}
+
+/// Read the features of a GFF3 file, stopping at an embedded `##FASTA`
+/// section
+pub fn read_gff<R: BufRead>(r: R) -> Result<Vec<GffFeature>, FastaError> {
+    let mut features = Vec::new();
+    for (n, line) in r.lines().enumerate() {
+        let line = line?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        features.push(parse_line(&line, n + 1)?);
+    }
+    Ok(features)
+}
+
+/// Types of the child features a transcript is stitched from, in order
+/// of preference
+const SEGMENT_TYPES: [&str; 2] = ["exon", "CDS"];
+
+/// Options for `extract_features`
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Feature type to extract, such as `CDS`, `exon`, `mRNA` or `gene`
+    pub feature_type: String,
+    /// Translate the extracted sequences with this code, starting at the
+    /// phase of the first segment
+    pub translate: Option<GeneticCode>,
+}
+
+/// Extract the sequences of all features of `opts.feature_type`.
+///
+/// Features are grouped by `ID`, one output record per ID, so the lines
+/// of a multi-line CDS are joined. Exon and CDS features without an `ID`
+/// are grouped by their `Parent` instead, one record per transcript,
+/// named after it; other features without an `ID` produce one record
+/// each, named `seqid:start-end`. A feature of any other type, such as
+/// an mRNA, is stitched from its exon children, or its CDS children if
+/// it has no exons, and is its own span if it has neither. Records are
+/// output in order of first appearance in `features`. Fails if a
+/// feature names an unknown sequence or lies outside it.
+pub fn extract_features(
+    reference: &Fasta,
+    features: &[GffFeature],
+    opts: &ExtractOptions,
+) -> Result<Vec<Record>, FastaError> {
+    let segment_type = SEGMENT_TYPES.contains(&opts.feature_type.as_str());
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<&GffFeature>> = HashMap::new();
+    for f in features
+        .iter()
+        .filter(|f| f.feature_type == opts.feature_type)
+    {
+        let parents = f.parents();
+        let names = match f.id() {
+            Some(id) => vec![id.to_owned()],
+            None if segment_type && !parents.is_empty() => {
+                parents.into_iter().map(str::to_owned).collect()
+            }
+            None => vec![format!("{}:{}-{}", f.seqid, f.start, f.end)],
+        };
+        for name in names {
+            match groups.get_mut(&name) {
+                Some(group) => group.push(f),
+                None => {
+                    order.push(name.clone());
+                    groups.insert(name, vec![f]);
+                }
+            }
+        }
+    }
+
+    // Exon and CDS children by parent ID, for stitching transcripts
+    let mut children: HashMap<&str, [Vec<&GffFeature>; 2]> = HashMap::new();
+    if !segment_type {
+        for f in features {
+            if let Some(t) = SEGMENT_TYPES.iter().position(|&t| t == f.feature_type) {
+                for parent in f.parents() {
+                    children.entry(parent).or_default()[t].push(f);
+                }
+            }
+        }
+    }
+
+    let mut records = Vec::with_capacity(order.len());
+    for name in order {
+        let mut segments = groups.remove(&name).unwrap_or_default();
+        if let Some([exons, cds]) = children.remove(name.as_str()) {
+            let parts = if exons.is_empty() { cds } else { exons };
+            if !parts.is_empty() {
+                segments = parts;
+            }
+        }
+        let reverse = segments[0].strand == Some(Strand::Reverse);
+        segments.sort_by_key(|f| f.start);
+        if reverse {
+            segments.reverse();
+        }
+        let mut sequence = String::new();
+        for f in &segments {
+            let rec = reference
+                .get(&f.seqid)
+                .ok_or_else(|| FastaError::UnknownId(f.seqid.clone()))?;
+            let part =
+                rec.sequence
+                    .get(f.start - 1..f.end)
+                    .ok_or_else(|| FastaError::OutOfBounds {
+                        id: f.seqid.clone(),
+                        start: f.start - 1,
+                        end: f.end,
+                        len: rec.sequence.len(),
+                    })?;
+            if reverse {
+                sequence.push_str(&reverse_complement(part));
+            } else {
+                sequence.push_str(part);
+            }
+        }
+        if let Some(code) = &opts.translate {
+            let phase = usize::from(segments[0].phase.unwrap_or(0));
+            sequence = translate(sequence.get(phase..).unwrap_or(""), code, 1);
+        }
+        records.push(Record {
+            id: name,
+            description: String::new(),
+            sequence,
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    const GFF: &str = "##gff-version 3\n\
+        chr1\tt\tgene\t2\t13\t.\t-\t.\tID=g1;Name=a%3Bb\n\
+        chr1\tt\tmRNA\t2\t13\t.\t-\t.\tID=m1;Parent=g1\n\
+        chr1\tt\tCDS\t2\t4\t.\t-\t0\tID=c1;Parent=m1\n\
+        chr1\tt\tCDS\t8\t13\t.\t-\t0\tID=c1;Parent=m1\n\
+        ##FASTA\n>chr1\nACGT\n";
+
+    #[test]
+    fn parse() {
+        let features = read_gff(GFF.as_bytes()).unwrap();
+        assert_eq!(features.len(), 4);
+        assert_eq!(features[0].attribute("Name"), Some("a;b"));
+        assert_eq!(features[2].parents(), ["m1"]);
+        assert_eq!(features[3].strand, Some(Strand::Reverse));
+        assert!(read_gff("chr1\tt\tgene\t5\t2\t.\t+\t.\t.\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn stitch_minus_strand_cds() {
+        let input = ">chr1\nATTATTTTTCCATGG\n";
+        let reference = Fasta::from(
+            FastaBuffer::from(input.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+        );
+        let features = read_gff(GFF.as_bytes()).unwrap();
+        let mut opts = ExtractOptions {
+            feature_type: "CDS".to_owned(),
+            translate: None,
+        };
+        let cds = extract_features(&reference, &features, &opts).unwrap();
+        assert_eq!(cds[0].id, "c1");
+        // revcomp of 8..13 (TTCCAT -> ATGGAA) then of 2..4 (TTA -> TAA)
+        assert_eq!(cds[0].sequence, "ATGGAATAA");
+        opts.translate = Some(GeneticCode::standard());
+        let protein = extract_features(&reference, &features, &opts).unwrap();
+        assert_eq!(protein[0].sequence, "ME*");
+    }
+
+    #[test]
+    fn isoforms_are_separate() {
+        let gff = "chr1\tt\tgene\t1\t16\t.\t+\t.\tID=g1\n\
+            chr1\tt\tmRNA\t1\t16\t.\t+\t.\tID=m1;Parent=g1\n\
+            chr1\tt\tmRNA\t1\t16\t.\t+\t.\tID=m2;Parent=g1\n\
+            chr1\tt\texon\t1\t4\t.\t+\t.\tParent=m1,m2\n\
+            chr1\tt\texon\t5\t8\t.\t+\t.\tParent=m1\n\
+            chr1\tt\texon\t13\t16\t.\t+\t.\tParent=m1,m2\n\
+            chr1\tt\tCDS\t2\t4\t.\t+\t0\tParent=m2\n\
+            chr1\tt\tCDS\t13\t15\t.\t+\t0\tParent=m2\n";
+        let input = ">chr1\nAAAACCCCTTTTGGGG\n";
+        let reference = Fasta::from(
+            FastaBuffer::from(input.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+        );
+        let features = read_gff(gff.as_bytes()).unwrap();
+        let extract = |feature_type: &str| -> Vec<(String, String)> {
+            let opts = ExtractOptions {
+                feature_type: feature_type.to_owned(),
+                translate: None,
+            };
+            extract_features(&reference, &features, &opts)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.id, r.sequence))
+                .collect()
+        };
+        let pairs = |v: &[(&str, &str)]| -> Vec<(String, String)> {
+            v.iter()
+                .map(|&(a, b)| (a.to_owned(), b.to_owned()))
+                .collect()
+        };
+        assert_eq!(
+            extract("mRNA"),
+            pairs(&[("m1", "AAAACCCCGGGG"), ("m2", "AAAAGGGG")])
+        );
+        assert_eq!(extract("CDS"), pairs(&[("m2", "AAAGGG")]));
+        assert_eq!(extract("gene"), pairs(&[("g1", "AAAACCCCTTTTGGGG")]));
+    }
+}
@@ -0,0 +1,251 @@
+//! Per-record size and time budgets for parsing untrusted input
+//!
+//! A `GuardedReader` parses like a `FastaBuffer` but fails a record as
+//! soon as it exceeds its size or time budget, so a single pathological
+//! record cannot exhaust memory or stall a service. Input lines are
+//! never buffered beyond the size budget, even when they contain no
+//! newline. The time budget is checked between lines, so it does not
+//! interrupt a read that blocks on the underlying input; use timeouts
+//! on the socket or file for that.
+
+use std::io;
+use std::io::{BufRead, Read};
+use std::time::{Duration, Instant};
+
+use crate::errors::FastaError;
+use crate::{FastaBuffer, FastaReaderBuilder, Record};
+
+/// Budgets enforced by a `GuardedReader`. `None` disables a check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum sequence bytes in a record, also the maximum length of
+    /// any input line
+    pub max_record_bytes: Option<usize>,
+    /// Maximum time spent parsing a single record
+    pub max_record_time: Option<Duration>,
+}
+
+impl Limits {
+    pub(crate) fn check_size(&self, id: &str, bytes: usize) -> io::Result<()> {
+        match self.max_record_bytes {
+            Some(limit) if bytes > limit => Err(too_large(id, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_time(&self, id: &str, started: Option<Instant>) -> io::Result<()> {
+        match (self.max_record_time, started) {
+            (Some(limit), Some(t)) if t.elapsed() > limit => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                FastaError::RecordTimeout {
+                    id: id.to_owned(),
+                    limit,
+                },
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A line budget error; the reader fills in the line's position
+fn too_long(limit: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::LineTooLong {
+            id: String::new(),
+            line: 0,
+            offset: 0,
+            limit,
+        },
+    )
+}
+
+fn too_large(id: &str, limit: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::RecordTooLarge {
+            id: id.to_owned(),
+            limit,
+        },
+    )
+}
+
+/// Returns true if `e` was raised by a guard budget
+pub fn is_limit_error(e: &io::Error) -> bool {
+    matches!(
+        e.get_ref().and_then(|e| e.downcast_ref::<FastaError>()),
+        Some(FastaError::RecordTooLarge { .. })
+            | Some(FastaError::LineTooLong { .. })
+            | Some(FastaError::RecordTimeout { .. })
+    )
+}
+
+/// Fill in the record ID of an error raised reading a line, before the
+/// parser saw it: a line budget or UTF-8 error
+pub(crate) fn set_record_id(e: &mut io::Error, record_id: &str) {
+    if let Some(FastaError::LineTooLong { id, .. }) | Some(FastaError::Validation { id, .. }) =
+        e.get_mut().and_then(|e| e.downcast_mut::<FastaError>())
+    {
+        if id.is_empty() {
+            *id = record_id.to_owned();
+        }
+    }
+}
+
+/// A BufRead adapter that fails once a line grows past `limit` bytes.
+/// Both `\n` and `\r` end a line.
+///
+/// `fill_buf` hands out bytes only up to the first byte past the limit,
+/// so every line is checked, however many fit in the inner buffer, and
+/// the error is raised while the over-long line is being read.
+struct LineLimit<B: BufRead> {
+    inner: B,
+    limit: Option<usize>,
+    run: usize,
+}
+
+impl<B: BufRead> Read for LineLimit<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let available = self.fill_buf()?;
+            let n = available.len().min(buf.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            n
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<B: BufRead> BufRead for LineLimit<B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return self.inner.fill_buf(),
+        };
+        if self.run > limit {
+            return Err(too_long(limit));
+        }
+        let buf = self.inner.fill_buf()?;
+        // Length of the line so far at the start of each line in `buf`
+        let mut carried = self.run;
+        let mut start = 0;
+        let ends = memchr::memchr2_iter(b'\n', b'\r', buf).chain(Some(buf.len()));
+        for end in ends {
+            if carried + (end - start) > limit {
+                return Ok(&buf[..start + limit + 1 - carried]);
+            }
+            carried = 0;
+            start = end + 1;
+        }
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.limit.is_some() {
+            // The inner buffer still holds the bytes being consumed
+            if let Ok(buf) = self.inner.fill_buf() {
+                let used = &buf[..amt.min(buf.len())];
+                match used.iter().rposition(|&b| b == b'\n' || b == b'\r') {
+                    Some(i) => self.run = used.len() - i - 1,
+                    None => self.run += used.len(),
+                }
+            }
+        }
+        self.inner.consume(amt);
+    }
+}
+
+/// A FASTA reader that enforces per-record `Limits`. Once a budget is
+/// exceeded the error is returned and iteration ends.
+pub struct GuardedReader<B: BufRead> {
+    records: FastaBuffer<LineLimit<B>>,
+}
+
+impl<B: BufRead> GuardedReader<B> {
+    /// Parse `b` with the default options
+    pub fn new(b: B, limits: Limits) -> GuardedReader<B> {
+        GuardedReader::with_options(&FastaReaderBuilder::new(), b, limits)
+    }
+
+    /// Parse `b` with the options of `builder`
+    pub fn with_options(builder: &FastaReaderBuilder, b: B, limits: Limits) -> GuardedReader<B> {
+        let inner = LineLimit {
+            inner: b,
+            limit: limits.max_record_bytes,
+            run: 0,
+        };
+        let mut records = builder.from_reader(inner);
+        records.limits = Some(limits);
+        GuardedReader { records }
+    }
+}
+
+impl<B: BufRead> Iterator for GuardedReader<B> {
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(bytes: usize) -> Limits {
+        Limits {
+            max_record_bytes: Some(bytes),
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn size_budget() {
+        let input = ">a\nACGT\nACGT\n>b\nACGTA\nCGTA\n>c\nA\n";
+        let mut reader = GuardedReader::new(input.as_bytes(), limits(8));
+        assert_eq!(reader.next().unwrap().unwrap().sequence, "ACGTACGT");
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(is_limit_error(&err));
+        assert_eq!(err.to_string(), "Record b exceeds the limit of 8 bytes");
+        assert!(reader.next().is_none());
+
+        // A single unterminated line is cut off at the budget
+        let long = format!(">x\n{}", "A".repeat(10_000));
+        let reader = io::BufReader::with_capacity(16, long.as_bytes());
+        let err = GuardedReader::new(reader, limits(64))
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 2 (offset 3) exceeds the limit of 64 bytes in record x"
+        );
+
+        // An over-long line ending well within the buffer
+        let long = format!(">{}\nACGT\n>b\nAC\n", "x".repeat(1003));
+        let err = GuardedReader::new(long.as_bytes(), limits(100))
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 1 (offset 0) exceeds the limit of 100 bytes"
+        );
+    }
+
+    #[test]
+    fn time_budget() {
+        let input = ">a\nACGT\nACGT\n";
+        let limits = Limits {
+            max_record_time: Some(Duration::from_secs(0)),
+            ..Limits::default()
+        };
+        let err = GuardedReader::new(input.as_bytes(), limits)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(err.to_string().starts_with("Record a took longer than"));
+    }
+}
@@ -0,0 +1,74 @@
+//! Detection of homopolymer runs
+
+use crate::Record;
+
+/// A run of a single repeated base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Homopolymer {
+    /// The repeated base, in upper case
+    pub base: u8,
+    /// 0-based start of the run
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Iterator over the homopolymer runs of a sequence. Created by
+/// `Record::homopolymers`.
+#[derive(Debug, Clone)]
+pub struct Homopolymers<'a> {
+    seq: &'a [u8],
+    pos: usize,
+    min_len: usize,
+}
+
+impl<'a> Iterator for Homopolymers<'a> {
+    type Item = Homopolymer;
+
+    fn next(&mut self) -> Option<Homopolymer> {
+        while self.pos < self.seq.len() {
+            let start = self.pos;
+            let base = self.seq[start].to_ascii_uppercase();
+            let len = self.seq[start..]
+                .iter()
+                .position(|b| b.to_ascii_uppercase() != base)
+                .unwrap_or(self.seq.len() - start);
+            self.pos += len;
+            if len >= self.min_len && base.is_ascii_alphabetic() && base != b'N' {
+                return Some(Homopolymer { base, start, len });
+            }
+        }
+        None
+    }
+}
+
+impl Record {
+    /// Returns the runs of a single base at least `min_len` bases long.
+    /// Case is ignored, so `AAaa` is one run. Runs of `N` and of
+    /// non-letters such as gaps are not reported; see `Record::gaps`.
+    pub fn homopolymers(&self, min_len: usize) -> Homopolymers<'_> {
+        Homopolymers {
+            seq: self.sequence.as_bytes(),
+            pos: 0,
+            min_len: min_len.max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let rec = Record {
+            sequence: "ACAAaAGTTTTNNNN----CC".to_owned(),
+            ..Record::new()
+        };
+        let runs: Vec<(u8, usize, usize)> = rec
+            .homopolymers(2)
+            .map(|h| (h.base, h.start, h.len))
+            .collect();
+        assert_eq!(runs, [(b'A', 2, 4), (b'T', 7, 4), (b'C', 19, 2)]);
+        assert_eq!(rec.homopolymers(4).count(), 2);
+    }
+}
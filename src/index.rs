@@ -0,0 +1,492 @@
+//! samtools-compatible `.fai` indexes and random access to indexed FASTA files
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bgzf::{gzi_path, BgzfReader, GziIndex};
+use crate::cancel::{self, CancellationToken};
+use crate::errors::FastaError;
+use crate::Record;
+
+/// One line of a `.fai` index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaiEntry {
+    pub name: String,
+    /// Number of bases in the sequence
+    pub length: u64,
+    /// Byte offset of the first base
+    pub offset: u64,
+    /// Bases per sequence line
+    pub line_bases: u64,
+    /// Bytes per sequence line, including the line terminator
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    /// Byte offset of the 0-based base position `pos`
+    pub fn position(&self, pos: u64) -> u64 {
+        if self.line_bases == 0 {
+            return self.offset;
+        }
+        self.offset + (pos / self.line_bases) * self.line_width + pos % self.line_bases
+    }
+}
+
+/// An in-memory `.fai` index
+#[derive(Debug, Clone, Default)]
+pub struct FastaIndex {
+    entries: Vec<FaiEntry>,
+    by_name: HashMap<String, usize>,
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(crate) fn unknown_id(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        FastaError::UnknownId(name.to_owned()),
+    )
+}
+
+impl FastaIndex {
+    fn from_entries(entries: Vec<FaiEntry>) -> FastaIndex {
+        let by_name = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.name.clone(), i))
+            .collect();
+        FastaIndex { entries, by_name }
+    }
+
+    /// Build an index by scanning a FASTA file.
+    ///
+    /// As with samtools, every sequence line of a record except the last
+    /// must have the same length.
+    pub fn build<R: BufRead>(r: R) -> io::Result<FastaIndex> {
+        FastaIndex::build_with_cancel(r, &CancellationToken::new())
+    }
+
+    /// Build an index as with `build`, stopping with a cancellation error
+    /// once `token` is cancelled
+    pub fn build_with_cancel<R: BufRead>(
+        mut r: R,
+        token: &CancellationToken,
+    ) -> io::Result<FastaIndex> {
+        let mut entries: Vec<FaiEntry> = Vec::new();
+        let mut line = Vec::new();
+        let mut offset: u64 = 0;
+        // Set once a record has a short (final) line; more sequence is an error
+        let mut ended = false;
+
+        loop {
+            if token.is_cancelled() {
+                return Err(cancel::cancelled());
+            }
+            line.clear();
+            let n = r.read_until(b'\n', &mut line)? as u64;
+            if n == 0 {
+                break;
+            }
+            let start = offset;
+            offset += n;
+            let content = trim_newline(&line);
+
+            if content.first() == Some(&b'>') {
+                let header = String::from_utf8_lossy(&content[1..]);
+                let name = header.split_whitespace().next().unwrap_or("").to_owned();
+                entries.push(FaiEntry {
+                    name,
+                    length: 0,
+                    offset,
+                    line_bases: 0,
+                    line_width: 0,
+                });
+                ended = false;
+                continue;
+            }
+            let entry = match entries.last_mut() {
+                Some(e) => e,
+                None if content.is_empty() => continue,
+                None => return Err(invalid_data("sequence data before first header".to_owned())),
+            };
+            if content.is_empty() {
+                ended = true;
+                continue;
+            }
+            let bases = content.len() as u64;
+            if entry.line_bases == 0 {
+                entry.offset = start;
+                entry.line_bases = bases;
+                entry.line_width = n;
+            } else if ended || bases > entry.line_bases {
+                return Err(invalid_data(format!(
+                    "inconsistent line length in sequence {}",
+                    entry.name
+                )));
+            } else if bases < entry.line_bases || n != entry.line_width {
+                ended = true;
+            }
+            entry.length += bases;
+        }
+
+        Ok(FastaIndex::from_entries(entries))
+    }
+
+    /// Read a `.fai` file
+    pub fn read<R: BufRead>(r: R) -> io::Result<FastaIndex> {
+        let mut entries = Vec::new();
+        for line in r.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 5 {
+                return Err(invalid_data(format!("malformed .fai line: {}", line)));
+            }
+            let num = |s: &str| {
+                s.parse::<u64>()
+                    .map_err(|_| invalid_data(format!("malformed .fai line: {}", line)))
+            };
+            let entry = FaiEntry {
+                name: fields[0].to_owned(),
+                length: num(fields[1])?,
+                offset: num(fields[2])?,
+                line_bases: num(fields[3])?,
+                line_width: num(fields[4])?,
+            };
+            // Offsets are computed from these fields, so reject entries
+            // that would make positions run backwards or overflow
+            let consistent = entry.line_width >= entry.line_bases
+                && (entry.line_bases > 0 || entry.length == 0)
+                && (entry.length / entry.line_bases.max(1))
+                    .checked_mul(entry.line_width)
+                    .and_then(|span| span.checked_add(entry.length))
+                    .and_then(|span| span.checked_add(entry.offset))
+                    .is_some();
+            if !consistent {
+                return Err(invalid_data(format!("inconsistent .fai line: {}", line)));
+            }
+            entries.push(entry);
+        }
+        Ok(FastaIndex::from_entries(entries))
+    }
+
+    /// Write the index in `.fai` format
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for e in &self.entries {
+            writeln!(
+                w,
+                "{}\t{}\t{}\t{}\t{}",
+                e.name, e.length, e.offset, e.line_bases, e.line_width
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Entries in file order
+    pub fn entries(&self) -> &[FaiEntry] {
+        &self.entries
+    }
+
+    /// Resolve a samtools-style region: `name`, `name:start` or
+    /// `name:start-end`, with 1-based inclusive coordinates that may
+    /// contain commas. A whole sequence name takes precedence, so names
+    /// containing `:` work, and an end past the sequence is clamped.
+    pub fn region(&self, spec: &str) -> io::Result<Region> {
+        if let Some(e) = self.get(spec) {
+            return Ok(Region {
+                name: e.name.clone(),
+                start: 0,
+                end: e.length,
+            });
+        }
+        let bad = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("malformed region {}", spec),
+            )
+        };
+        let (name, range) = spec.rsplit_once(':').ok_or_else(|| unknown_id(spec))?;
+        let e = self.get(name).ok_or_else(|| unknown_id(name))?;
+        let num = |s: &str| s.replace(',', "").parse::<u64>().map_err(|_| bad());
+        let (start, end) = match range.split_once('-') {
+            Some((start, "")) => (num(start)?, e.length),
+            Some((start, end)) => (num(start)?, num(end)?.min(e.length)),
+            None => (num(range)?, e.length),
+        };
+        if start == 0 || start > end + 1 {
+            return Err(bad());
+        }
+        Ok(Region {
+            name: e.name.clone(),
+            start: start - 1,
+            end,
+        })
+    }
+
+    /// Look up an entry by sequence name
+    pub fn get(&self, name: &str) -> Option<&FaiEntry> {
+        self.by_name.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Number of sequences in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the index has no sequences
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    while end > 0 && (line[end - 1] == b'\n' || line[end - 1] == b'\r') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Part of an indexed sequence, 0-based and half-open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// The conventional index path for a FASTA file, `<path>.fai`
+pub fn fai_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut p = path.as_ref().as_os_str().to_owned();
+    p.push(".fai");
+    PathBuf::from(p)
+}
+
+/// IndexedFasta provides random access to the sequences of a FASTA file
+pub struct IndexedFasta<R: Read + Seek> {
+    reader: R,
+    index: FastaIndex,
+}
+
+impl IndexedFasta<BufReader<File>> {
+    /// Open a FASTA file, reading `<path>.fai` if it exists and building
+    /// the index in memory otherwise
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IndexedFasta<BufReader<File>>> {
+        let fai = fai_path(&path);
+        let index = if fai.exists() {
+            FastaIndex::read(BufReader::new(File::open(fai)?))?
+        } else {
+            FastaIndex::build(BufReader::new(File::open(&path)?))?
+        };
+        Ok(IndexedFasta::new(BufReader::new(File::open(path)?), index))
+    }
+}
+
+impl IndexedFasta<BgzfReader<BufReader<File>>> {
+    /// Open a bgzipped FASTA file, reading `<path>.fai` and `<path>.gzi`
+    /// if they exist and building the indexes in memory otherwise
+    pub fn open_bgzf<P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<IndexedFasta<BgzfReader<BufReader<File>>>> {
+        let fai = fai_path(&path);
+        let index = if fai.exists() {
+            FastaIndex::read(BufReader::new(File::open(fai)?))?
+        } else {
+            let decoder = flate2::read::MultiGzDecoder::new(File::open(&path)?);
+            FastaIndex::build(BufReader::new(decoder))?
+        };
+        let gzi = gzi_path(&path);
+        let gzi = if gzi.exists() {
+            GziIndex::read(BufReader::new(File::open(gzi)?))?
+        } else {
+            GziIndex::build(BufReader::new(File::open(&path)?))?
+        };
+        let reader = BgzfReader::new(BufReader::new(File::open(path)?), gzi);
+        Ok(IndexedFasta::new(reader, index))
+    }
+}
+
+impl<R: Read + Seek> IndexedFasta<R> {
+    /// Create an IndexedFasta from a seekable reader and its index
+    pub fn new(reader: R, index: FastaIndex) -> IndexedFasta<R> {
+        IndexedFasta { reader, index }
+    }
+
+    /// The index describing the underlying file
+    pub fn index(&self) -> &FastaIndex {
+        &self.index
+    }
+
+    /// Fetch bases `start..end` (0-based, half-open) of sequence `name`
+    pub fn fetch_region(&mut self, name: &str, start: u64, end: u64) -> io::Result<String> {
+        let entry = self.index.get(name).ok_or_else(|| unknown_id(name))?;
+        if start > end || end > entry.length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "region {}:{}-{} is outside sequence of length {}",
+                    name, start, end, entry.length
+                ),
+            ));
+        }
+        if start == end {
+            return Ok(String::new());
+        }
+        let first = entry.position(start);
+        let last = entry.position(end - 1) + 1;
+        // Read through take() rather than into a pre-sized buffer so a
+        // corrupt index can't trigger an allocation larger than the file
+        let mut raw = Vec::new();
+        self.reader.seek(SeekFrom::Start(first))?;
+        (&mut self.reader)
+            .take(last - first)
+            .read_to_end(&mut raw)?;
+        if raw.len() as u64 != last - first {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("index points past the end of the file for {}", name),
+            ));
+        }
+        raw.retain(|&b| b != b'\n' && b != b'\r');
+        String::from_utf8(raw).map_err(|e| invalid_data(e.to_string()))
+    }
+
+    /// Fetch a whole sequence as a fasta::Record. The description is not
+    /// stored in the index and is left empty.
+    pub fn fetch(&mut self, name: &str) -> io::Result<Record> {
+        let length = self.index.get(name).ok_or_else(|| unknown_id(name))?.length;
+        Ok(Record {
+            id: name.to_owned(),
+            description: String::new(),
+            sequence: self.fetch_region(name, 0, length)?,
+        })
+    }
+}
+
+/// A summary of one record returned by `page`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageEntry {
+    pub id: String,
+    pub length: u64,
+    /// The first bases of the sequence, if a preview was requested
+    pub sequence: Option<String>,
+}
+
+/// One page of records from an indexed FASTA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    /// Total number of records in the file
+    pub total: usize,
+    pub entries: Vec<PageEntry>,
+}
+
+/// Return up to `limit` records starting at record number `offset`.
+///
+/// Names and lengths come from the index; when `preview` is given, up to
+/// that many leading bases of each sequence are read as well. Only the
+/// requested records are touched, so paging does not scan the file.
+pub fn page<R: Read + Seek>(
+    fasta: &mut IndexedFasta<R>,
+    offset: usize,
+    limit: usize,
+    preview: Option<u64>,
+) -> io::Result<Page> {
+    let entries: Vec<FaiEntry> = fasta
+        .index
+        .entries
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .cloned()
+        .collect();
+    let mut page = Page {
+        total: fasta.index.len(),
+        entries: Vec::with_capacity(entries.len()),
+    };
+    for e in entries {
+        let sequence = match preview {
+            Some(n) => Some(fasta.fetch_region(&e.name, 0, n.min(e.length))?),
+            None => None,
+        };
+        page.entries.push(PageEntry {
+            id: e.name,
+            length: e.length,
+            sequence,
+        });
+    }
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DATA: &str = ">chr1 first\nACGTA\nCGTAC\nGT\n>chr2\nTTTT\nGG\n>chr3\nC\n";
+
+    #[test]
+    fn build_and_fetch() {
+        let index = FastaIndex::build(DATA.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        index.write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t12\t12\t5\t6\nchr2\t6\t33\t4\t5\nchr3\t1\t47\t1\t2\n"
+        );
+
+        let mut fasta = IndexedFasta::new(Cursor::new(DATA), index);
+        assert_eq!(fasta.fetch_region("chr1", 3, 11).unwrap(), "TACGTACG");
+        assert_eq!(fasta.fetch("chr2").unwrap().sequence, "TTTTGG");
+        assert!(fasta.fetch_region("chr3", 0, 2).is_err());
+        assert!(fasta.fetch("chrX").is_err());
+    }
+
+    #[test]
+    fn regions() {
+        let index = FastaIndex::build(DATA.as_bytes()).unwrap();
+        let region = |spec| index.region(spec).map(|r| (r.name, r.start, r.end));
+        assert_eq!(region("chr1").unwrap(), ("chr1".to_owned(), 0, 12));
+        assert_eq!(region("chr1:4-11").unwrap(), ("chr1".to_owned(), 3, 11));
+        assert_eq!(region("chr1:1,0-100").unwrap(), ("chr1".to_owned(), 9, 12));
+        assert_eq!(region("chr2:3").unwrap(), ("chr2".to_owned(), 2, 6));
+        assert_eq!(region("chr2:3-").unwrap(), ("chr2".to_owned(), 2, 6));
+        assert_eq!(
+            index.region("chrX:1-2").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(region("chr1:0-5").is_err());
+        assert!(region("chr1:5-3").is_err());
+        assert!(region("chr1:a-b").is_err());
+    }
+
+    #[test]
+    fn inconsistent_lines_rejected() {
+        assert!(FastaIndex::build(">a\nACG\nACGT\n".as_bytes()).is_err());
+        assert!(FastaIndex::read("a\t10\t3\t5\t2\n".as_bytes()).is_err());
+        assert!(FastaIndex::read("a\t10\t3\t0\t0\n".as_bytes()).is_err());
+        assert!(FastaIndex::read("a\t2\t0\t1\t18446744073709551615\n".as_bytes()).is_err());
+
+        let index = FastaIndex::read("a\t100\t0\t10\t1000000\n".as_bytes()).unwrap();
+        let mut fasta = IndexedFasta::new(Cursor::new(">a\nACGT\n"), index);
+        assert!(fasta.fetch_region("a", 0, 100).is_err());
+    }
+
+    #[test]
+    fn paging() {
+        let index = FastaIndex::build(DATA.as_bytes()).unwrap();
+        let mut fasta = IndexedFasta::new(Cursor::new(DATA), index);
+        let p = page(&mut fasta, 1, 5, Some(3)).unwrap();
+        assert_eq!(p.total, 3);
+        assert_eq!(p.entries.len(), 2);
+        assert_eq!(p.entries[0].id, "chr2");
+        assert_eq!(p.entries[0].sequence.as_deref(), Some("TTT"));
+        assert_eq!(p.entries[1].sequence.as_deref(), Some("C"));
+    }
+}
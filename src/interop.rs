@@ -0,0 +1,91 @@
+//! Conversions to and from the record types of other FASTA crates
+//!
+//! With the `bio` feature, `Record` converts to and from
+//! `bio::io::fasta::Record`; with the `noodles` feature, to and from
+//! `noodles_fasta::Record`. An empty description maps to `None` and back.
+//! Both crates allow sequences that are not UTF-8, which are converted
+//! lossily, with invalid bytes replaced by U+FFFD.
+
+use crate::Record;
+
+fn description(desc: Option<&str>) -> String {
+    desc.unwrap_or("").to_owned()
+}
+
+#[cfg(feature = "bio")]
+impl From<bio::io::fasta::Record> for Record {
+    fn from(rec: bio::io::fasta::Record) -> Record {
+        Record {
+            id: rec.id().to_owned(),
+            description: description(rec.desc()),
+            sequence: String::from_utf8_lossy(rec.seq()).into_owned(),
+        }
+    }
+}
+
+#[cfg(feature = "bio")]
+impl From<Record> for bio::io::fasta::Record {
+    fn from(rec: Record) -> bio::io::fasta::Record {
+        let desc = Some(rec.description.as_str()).filter(|d| !d.is_empty());
+        bio::io::fasta::Record::with_attrs(&rec.id, desc, rec.sequence.as_bytes())
+    }
+}
+
+#[cfg(feature = "noodles")]
+impl From<noodles_fasta::Record> for Record {
+    fn from(rec: noodles_fasta::Record) -> Record {
+        let desc = rec.description().map(|d| d.to_string());
+        Record {
+            id: String::from_utf8_lossy(rec.name()).into_owned(),
+            description: description(desc.as_deref()),
+            sequence: String::from_utf8_lossy(rec.sequence().as_ref()).into_owned(),
+        }
+    }
+}
+
+#[cfg(feature = "noodles")]
+impl From<Record> for noodles_fasta::Record {
+    fn from(rec: Record) -> noodles_fasta::Record {
+        use noodles_fasta::record::{Definition, Sequence};
+
+        let desc = Some(rec.description).filter(|d| !d.is_empty());
+        let definition = Definition::new(rec.id, desc.map(Into::into));
+        noodles_fasta::Record::new(definition, Sequence::from(rec.sequence.into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(description: &str) -> Record {
+        Record {
+            id: "chr1".to_owned(),
+            description: description.to_owned(),
+            sequence: "ACGT".to_owned(),
+        }
+    }
+
+    #[cfg(feature = "bio")]
+    #[test]
+    fn bio_round_trip() {
+        let theirs = bio::io::fasta::Record::from(rec("one two"));
+        assert_eq!(theirs.desc(), Some("one two"));
+        assert_eq!(Record::from(theirs), rec("one two"));
+        let bare = bio::io::fasta::Record::from(rec(""));
+        assert_eq!(bare.desc(), None);
+        assert_eq!(Record::from(bare), rec(""));
+    }
+
+    #[cfg(feature = "noodles")]
+    #[test]
+    fn noodles_round_trip() {
+        let theirs = noodles_fasta::Record::from(rec("one two"));
+        assert_eq!(theirs.name(), b"chr1");
+        assert_eq!(theirs.sequence().as_ref(), b"ACGT");
+        assert_eq!(Record::from(theirs), rec("one two"));
+        let bare = noodles_fasta::Record::from(rec(""));
+        assert!(bare.description().is_none());
+        assert_eq!(Record::from(bare), rec(""));
+    }
+}
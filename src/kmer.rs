@@ -0,0 +1,407 @@
+//! K-mer counting, spectra and simple genome profile estimates
+//!
+//! `count_kmers` and `Record::count_kmers` count k-mers of any length as
+//! strings, optionally canonical. For k up to `MAX_K`,
+//! `count_packed_kmers` and `Record::count_packed_kmers` key the counts
+//! by a 2-bit encoding in a `u64` instead, which is much smaller and
+//! faster; `decode_kmer` turns a key back into bases.
+//!
+//! `kmer_spectrum` counts canonical k-mers (the smaller of a k-mer and
+//! its reverse complement) and summarizes how many distinct k-mers occur
+//! at each multiplicity. `KmerSpectrum::summary` reads a GenomeScope-like
+//! profile off that histogram: the error valley, the homozygous and
+//! heterozygous coverage peaks, a genome size and a heterozygosity
+//! estimate. These are simple peak-based estimates, not a model fit.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::report::{NumberFormat, SCHEMA_VERSION};
+use crate::Record;
+
+/// Largest k that `kmer_spectrum` and the packed counts support
+pub const MAX_K: usize = 32;
+
+fn encode(b: u8) -> Option<u64> {
+    match b.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' | b'U' => Some(3),
+        _ => None,
+    }
+}
+
+/// The 2-bit key of `kmer`, as used by `count_packed_kmers`, or None if
+/// it is longer than `MAX_K` or has anything other than ACGTU
+pub fn encode_kmer(kmer: &str) -> Option<u64> {
+    if kmer.len() > MAX_K {
+        return None;
+    }
+    kmer.bytes()
+        .try_fold(0u64, |key, b| Some((key << 2) | encode(b)?))
+}
+
+/// The k-mer of length `k` encoded by `key`, in upper case with T for
+/// both T and U
+pub fn decode_kmer(key: u64, k: usize) -> String {
+    (0..k)
+        .rev()
+        .map(|i| char::from(b"ACGT"[((key >> (2 * i)) & 3) as usize]))
+        .collect()
+}
+
+/// Call `f` with the 2-bit key of each k-mer of `seq`, or of its
+/// canonical form (the smaller of it and its reverse complement) if
+/// `canonical` is set. K-mers containing anything other than ACGTU are
+/// skipped.
+pub(crate) fn for_each_packed(seq: &[u8], k: usize, canonical: bool, mut f: impl FnMut(u64)) {
+    let mask = if k == 32 {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    };
+    let shift = 2 * (k - 1);
+    let (mut fwd, mut rev, mut valid) = (0u64, 0u64, 0usize);
+    for &b in seq {
+        match encode(b) {
+            Some(code) => {
+                fwd = ((fwd << 2) | code) & mask;
+                rev = (rev >> 2) | ((3 - code) << shift);
+                valid += 1;
+            }
+            None => valid = 0,
+        }
+        if valid >= k {
+            f(if canonical { fwd.min(rev) } else { fwd });
+        }
+    }
+}
+
+/// Add the k-mers of `seq` to `counts`, as `for_each_packed` finds them
+fn add_packed(seq: &[u8], k: usize, canonical: bool, counts: &mut HashMap<u64, u64>) {
+    for_each_packed(seq, k, canonical, |key| {
+        *counts.entry(key).or_insert(0) += 1
+    });
+}
+
+/// Add the k-mers of `seq` to `counts` as upper-case strings, skipping
+/// the same k-mers as `for_each_packed`
+fn add_strings(seq: &str, k: usize, canonical: bool, counts: &mut HashMap<String, u64>) {
+    let bytes = seq.as_bytes();
+    let mut valid = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if encode(b).is_none() {
+            valid = 0;
+            continue;
+        }
+        valid += 1;
+        if valid >= k {
+            let mut kmer = seq[i + 1 - k..=i].to_ascii_uppercase().replace('U', "T");
+            if canonical {
+                let rc = crate::seq::reverse_complement(&kmer);
+                if rc < kmer {
+                    kmer = rc;
+                }
+            }
+            *counts.entry(kmer).or_insert(0) += 1;
+        }
+    }
+}
+
+pub(crate) fn check_packed_k(k: usize) {
+    assert!(k > 0 && k <= MAX_K, "k must be between 1 and {}", MAX_K);
+}
+
+/// Count the k-mers of a record stream as strings, see `Record::count_kmers`.
+/// Panics if `k` is 0.
+pub fn count_kmers<I>(records: I, k: usize, canonical: bool) -> io::Result<HashMap<String, u64>>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    assert!(k > 0, "k must be non-zero");
+    let mut counts = HashMap::new();
+    for rec in records {
+        add_strings(&rec?.sequence, k, canonical, &mut counts);
+    }
+    Ok(counts)
+}
+
+/// Count the k-mers of a record stream by 2-bit key, see
+/// `Record::count_packed_kmers`. Panics unless `1 <= k <= MAX_K`.
+pub fn count_packed_kmers<I>(records: I, k: usize, canonical: bool) -> io::Result<HashMap<u64, u64>>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    check_packed_k(k);
+    let mut counts = HashMap::new();
+    for rec in records {
+        add_packed(rec?.sequence.as_bytes(), k, canonical, &mut counts);
+    }
+    Ok(counts)
+}
+
+impl Record {
+    /// Count the k-mers of the sequence, keyed by the k-mer in upper case
+    /// with U written as T. With `canonical`, a k-mer and its reverse
+    /// complement are counted together under the smaller of the two.
+    /// K-mers containing anything other than ACGTU are skipped. Panics if
+    /// `k` is 0.
+    pub fn count_kmers(&self, k: usize, canonical: bool) -> HashMap<String, u64> {
+        assert!(k > 0, "k must be non-zero");
+        let mut counts = HashMap::new();
+        add_strings(&self.sequence, k, canonical, &mut counts);
+        counts
+    }
+
+    /// Count k-mers as `count_kmers` does, keyed by their 2-bit encoding
+    /// (A, C, G, T as 0 to 3, first base highest); see `decode_kmer`.
+    /// Panics unless `1 <= k <= MAX_K`.
+    pub fn count_packed_kmers(&self, k: usize, canonical: bool) -> HashMap<u64, u64> {
+        check_packed_k(k);
+        let mut counts = HashMap::new();
+        add_packed(self.sequence.as_bytes(), k, canonical, &mut counts);
+        counts
+    }
+}
+
+/// Number of distinct k-mers seen at each multiplicity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmerSpectrum {
+    pub k: usize,
+    /// `(multiplicity, distinct k-mers)` pairs in increasing order of
+    /// multiplicity, omitting multiplicities with no k-mers
+    pub histogram: Vec<(u64, u64)>,
+}
+
+/// Estimates read off a `KmerSpectrum`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumSummary {
+    /// Multiplicity at the bottom of the valley after the error peak.
+    /// K-mers below it are treated as errors.
+    pub error_cutoff: u64,
+    /// Coverage of the homozygous peak
+    pub homozygous_peak: Option<u64>,
+    /// Coverage of the heterozygous peak, near half the homozygous one
+    pub heterozygous_peak: Option<u64>,
+    /// Total non-error k-mers divided by the homozygous coverage
+    pub genome_size: Option<u64>,
+    /// Fraction of heterozygous sites, from the relative size of the
+    /// heterozygous peak
+    pub heterozygosity: Option<f64>,
+}
+
+/// Count canonical k-mers across a record stream. Panics unless
+/// `1 <= k <= MAX_K`.
+pub fn kmer_spectrum<I>(records: I, k: usize) -> io::Result<KmerSpectrum>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let counts = count_packed_kmers(records, k, true)?;
+    let mut histogram: HashMap<u64, u64> = HashMap::new();
+    for &n in counts.values() {
+        *histogram.entry(n).or_insert(0) += 1;
+    }
+    Ok(KmerSpectrum::from_histogram(
+        k,
+        histogram.into_iter().collect(),
+    ))
+}
+
+impl KmerSpectrum {
+    /// Build a spectrum from `(multiplicity, distinct k-mers)` pairs in
+    /// any order, such as the output of `jellyfish histo`
+    pub fn from_histogram(k: usize, mut histogram: Vec<(u64, u64)>) -> KmerSpectrum {
+        histogram.retain(|&(_, n)| n > 0);
+        histogram.sort_unstable();
+        KmerSpectrum { k, histogram }
+    }
+
+    /// Number of distinct k-mers
+    pub fn distinct(&self) -> u64 {
+        self.histogram.iter().map(|&(_, n)| n).sum()
+    }
+
+    /// Total number of k-mers counted
+    pub fn total(&self) -> u64 {
+        self.histogram.iter().map(|&(c, n)| c * n).sum()
+    }
+
+    /// Distinct k-mers at multiplicity `c`
+    fn at(&self, c: u64) -> u64 {
+        match self.histogram.binary_search_by_key(&c, |&(m, _)| m) {
+            Ok(i) => self.histogram[i].1,
+            Err(_) => 0,
+        }
+    }
+
+    /// The multiplicity with the most k-mers in `lo..=hi`, if it is a
+    /// local maximum
+    fn peak_in(&self, lo: u64, hi: u64) -> Option<u64> {
+        let (c, n) = self
+            .histogram
+            .iter()
+            .filter(|&&(c, _)| c >= lo && c <= hi)
+            .max_by_key(|&&(c, n)| (n, std::cmp::Reverse(c)))
+            .copied()?;
+        if c > lo && c < hi && n >= self.at(c - 1) && n >= self.at(c + 1) {
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    /// Estimate the error cutoff, coverage peaks, genome size and
+    /// heterozygosity
+    pub fn summary(&self) -> SpectrumSummary {
+        let max = self.histogram.last().map_or(0, |&(c, _)| c);
+        let mut cutoff = 1;
+        while cutoff < max && self.at(cutoff + 1) <= self.at(cutoff) {
+            cutoff += 1;
+        }
+        let mut summary = SpectrumSummary {
+            error_cutoff: cutoff,
+            homozygous_peak: None,
+            heterozygous_peak: None,
+            genome_size: None,
+            heterozygosity: None,
+        };
+        let main = match self.peak_in(cutoff, max) {
+            Some(c) if cutoff < max => c,
+            _ => return summary,
+        };
+        // The tallest peak is heterozygous if there is another at twice
+        // its coverage, otherwise look for one at half
+        let double = self.peak_in(main * 2 - main * 2 / 5, main * 2 + main * 2 / 5);
+        let half = self.peak_in((main / 2 - main / 10).max(cutoff), main / 2 + main / 10);
+        let (hom, het) = match (double, half) {
+            (Some(d), _) if self.at(d) * 10 >= self.at(main) => (d, Some(main)),
+            (_, Some(h)) if h > cutoff && h < main => (main, Some(h)),
+            _ => (main, None),
+        };
+        let solid: u64 = self
+            .histogram
+            .iter()
+            .filter(|&&(c, _)| c >= cutoff)
+            .map(|&(c, n)| c * n)
+            .sum();
+        summary.homozygous_peak = Some(hom);
+        summary.heterozygous_peak = het;
+        summary.genome_size = Some((solid as f64 / hom as f64).round() as u64);
+        summary.heterozygosity = Some(match het {
+            Some(h) => {
+                // Distinct k-mers on either side of the midpoint between peaks
+                let mid = (h + hom) / 2;
+                let sum = |lo: u64, hi: u64| -> f64 {
+                    self.histogram
+                        .iter()
+                        .filter(|&&(c, _)| c >= lo && c < hi)
+                        .map(|&(_, n)| n as f64)
+                        .sum()
+                };
+                let n_het = sum(cutoff, mid);
+                let n_hom = sum(mid, u64::MAX);
+                n_het / (2.0 * self.k as f64 * (n_hom + n_het / 2.0))
+            }
+            None => 0.0,
+        });
+        summary
+    }
+
+    /// Write the histogram as tab-separated `multiplicity\tcount` lines
+    /// under a header, for plotting
+    pub fn write_tsv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "multiplicity\tcount")?;
+        for (c, n) in &self.histogram {
+            writeln!(w, "{}\t{}", c, n)?;
+        }
+        Ok(())
+    }
+
+    /// Write the summary estimates as `key\tvalue` lines. Estimates that
+    /// could not be made are written as `NA`.
+    pub fn write_summary<W: Write>(&self, w: &mut W, fmt: &NumberFormat) -> io::Result<()> {
+        let s = self.summary();
+        let na = |v: Option<u64>| v.map_or_else(|| "NA".to_owned(), |v| v.to_string());
+        writeln!(w, "schema\t{}", SCHEMA_VERSION)?;
+        writeln!(w, "k\t{}", self.k)?;
+        writeln!(w, "distinct_kmers\t{}", self.distinct())?;
+        writeln!(w, "total_kmers\t{}", self.total())?;
+        writeln!(w, "error_cutoff\t{}", s.error_cutoff)?;
+        writeln!(w, "homozygous_peak\t{}", na(s.homozygous_peak))?;
+        writeln!(w, "heterozygous_peak\t{}", na(s.heterozygous_peak))?;
+        writeln!(w, "genome_size\t{}", na(s.genome_size))?;
+        writeln!(
+            w,
+            "heterozygosity\t{}",
+            s.heterozygosity
+                .map_or_else(|| "NA".to_owned(), |h| fmt.format(h))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn canonical_counts() {
+        // ACG and its reverse complement CGT are the same canonical k-mer
+        let input = ">a\nACGT\n>b\nACGNACG\n";
+        let spectrum = kmer_spectrum(FastaBuffer::from(input.as_bytes()), 3).unwrap();
+        assert_eq!(spectrum.histogram, [(4, 1)]);
+        let mut out = Vec::new();
+        spectrum.write_tsv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "multiplicity\tcount\n4\t1\n"
+        );
+    }
+
+    #[test]
+    fn string_and_packed_backends() {
+        let rec = Record {
+            sequence: "ACGTnacgu".to_owned(),
+            ..Record::new()
+        };
+        let counts = rec.count_kmers(3, false);
+        assert_eq!(counts.len(), 2);
+        assert_eq!((counts["ACG"], counts["CGT"]), (2, 2));
+        let canonical = rec.count_kmers(3, true);
+        assert_eq!(canonical.len(), 1);
+        assert_eq!(canonical["ACG"], 4);
+
+        let packed = rec.count_packed_kmers(3, true);
+        let decoded: HashMap<String, u64> = packed
+            .iter()
+            .map(|(&key, &n)| (decode_kmer(key, 3), n))
+            .collect();
+        assert_eq!(decoded, canonical);
+        assert_eq!(encode_kmer("ACG"), Some(0b000110));
+        assert_eq!(encode_kmer("ANG"), None);
+
+        let input = ">a\nAAAA\n>b\nAA\n";
+        let stream = count_kmers(FastaBuffer::from(input.as_bytes()), 2, false).unwrap();
+        assert_eq!(stream["AA"], 4);
+    }
+
+    #[test]
+    fn peaks() {
+        // Errors at 1-2, a heterozygous peak at 15 and a homozygous one at 30
+        let mut hist = vec![(1, 5000), (2, 800), (3, 100), (4, 50)];
+        for c in 5..60u64 {
+            let het = 400.0 * (-((c as f64 - 15.0) / 4.0).powi(2)).exp();
+            let hom = 1000.0 * (-((c as f64 - 30.0) / 6.0).powi(2)).exp();
+            hist.push((c, (het + hom).round() as u64 + 1));
+        }
+        let s = KmerSpectrum::from_histogram(21, hist).summary();
+        assert_eq!(s.error_cutoff, 5);
+        assert_eq!(s.homozygous_peak, Some(30));
+        assert_eq!(s.heterozygous_peak, Some(15));
+        let size = s.genome_size.unwrap();
+        assert!(size > 11_000 && size < 13_500, "{}", size);
+        assert!(s.heterozygosity.unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,157 @@
+//! Records whose sequences are loaded on demand
+//!
+//! `LazyReader` parses headers and measures sequences without keeping
+//! them, recording where each record lies in the input. A `LazyRecord`
+//! reads its sequence back from the source only when `sequence` is
+//! called, so scanning the IDs and lengths of a large file costs
+//! little memory.
+
+use std::cell::RefCell;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+use crate::{FastaBuffer, FastaReaderBuilder, Record, RecordSpan};
+
+/// A reader sharing a seekable source, keeping its own position
+struct Shared<R: Read + Seek> {
+    source: Rc<RefCell<R>>,
+    pos: u64,
+}
+
+impl<R: Read + Seek> Read for Shared<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut source = self.source.borrow_mut();
+        source.seek(SeekFrom::Start(self.pos))?;
+        let n = source.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// A record whose sequence has not been read yet
+pub struct LazyRecord<R: Read + Seek> {
+    pub id: String,
+    pub description: String,
+    /// Length of the sequence
+    pub length: usize,
+    /// Position of the record in the source
+    pub span: RecordSpan,
+    source: Rc<RefCell<R>>,
+    opts: FastaReaderBuilder,
+}
+
+impl<R: Read + Seek> LazyRecord<R> {
+    /// Read the sequence from the source
+    pub fn sequence(&self) -> io::Result<String> {
+        Ok(self.load()?.sequence)
+    }
+
+    /// Read the sequence and return the complete record
+    pub fn into_record(self) -> io::Result<Record> {
+        self.load()
+    }
+
+    fn load(&self) -> io::Result<Record> {
+        let len = self.span.end_offset - self.span.header_offset;
+        let mut raw = Vec::with_capacity(len as usize);
+        {
+            let mut source = self.source.borrow_mut();
+            source.seek(SeekFrom::Start(self.span.header_offset))?;
+            source.by_ref().take(len).read_to_end(&mut raw)?;
+        }
+        if raw.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "source changed since it was parsed",
+            ));
+        }
+        // Parse the record again with the same options, so the sequence
+        // matches what the eager parser would have produced
+        self.opts
+            .from_reader(raw.as_slice())
+            .next()
+            .unwrap_or_else(|| Err(io::Error::other("record not found in source")))
+    }
+}
+
+/// An iterator over the records of a seekable source, deferring
+/// sequence loading. Offsets in the spans are absolute positions in the
+/// source, starting from its position when the reader was created.
+pub struct LazyReader<R: Read + Seek> {
+    buffer: FastaBuffer<BufReader<Shared<R>>>,
+    source: Rc<RefCell<R>>,
+    base: u64,
+    opts: FastaReaderBuilder,
+}
+
+impl<R: Read + Seek> LazyReader<R> {
+    /// Parse `source` with the default options
+    pub fn new(source: R) -> io::Result<LazyReader<R>> {
+        LazyReader::with_options(&FastaReaderBuilder::new(), source)
+    }
+
+    /// Parse `source` with the options of `builder`
+    pub fn with_options(builder: &FastaReaderBuilder, mut source: R) -> io::Result<LazyReader<R>> {
+        let base = source.stream_position()?;
+        let source = Rc::new(RefCell::new(source));
+        let shared = Shared {
+            source: Rc::clone(&source),
+            pos: base,
+        };
+        Ok(LazyReader {
+            buffer: builder.from_reader(BufReader::new(shared)),
+            source,
+            base,
+            opts: builder.clone(),
+        })
+    }
+}
+
+impl<R: Read + Seek> Iterator for LazyReader<R> {
+    type Item = Result<LazyRecord<R>, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rec = match self.buffer.read_record(false)? {
+            Ok(rec) => rec,
+            Err(e) => return Some(Err(e)),
+        };
+        let span = self.buffer.span;
+        Some(Ok(LazyRecord {
+            id: rec.id,
+            description: rec.description,
+            length: self.buffer.seq_len,
+            span: RecordSpan {
+                header_offset: span.header_offset + self.base,
+                seq_offset: span.seq_offset + self.base,
+                end_offset: span.end_offset + self.base,
+                header_line: span.header_line,
+            },
+            source: Rc::clone(&self.source),
+            opts: self.opts.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn deferred_sequences() {
+        let data = b"ignored>a one\nACGT\nAC\n>b\nTTTT\n".to_vec();
+        let mut cursor = Cursor::new(data);
+        cursor.set_position(7);
+        let records: Vec<LazyRecord<_>> = LazyReader::new(cursor)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].id.as_str(), records[0].length), ("a", 6));
+        assert_eq!(records[1].span.header_offset, 22);
+        // Out of order loading works because every read seeks first
+        assert_eq!(records[1].sequence().unwrap(), "TTTT");
+        assert_eq!(records[0].sequence().unwrap(), "ACGTAC");
+    }
+}
@@ -0,0 +1,177 @@
+//! Error-recovery parsing
+//!
+//! `FastaBuffer::lenient` keeps going past structurally broken input
+//! instead of failing or folding it into a neighbouring record:
+//!
+//! - non-header lines outside any record, such as text before the first
+//!   header, are skipped;
+//! - a record the parser options reject (e.g. a blank line inside it when
+//!   blank lines are disallowed) is skipped up to the next header;
+//! - a final record with a header but no sequence, as left by a truncated
+//!   download, is dropped.
+//!
+//! Each skip is reported as a `Warning`, either as an item of the
+//! `Lenient` iterator or through a callback with `Lenient::on_warning`.
+//! IO errors still end iteration as usual.
+
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+use crate::parser::ParseError;
+use crate::{FastaBuffer, Record};
+
+/// Input skipped in lenient mode. Offsets are of the first skipped byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Non-header lines outside any record
+    Junk { offset: u64, lines: usize },
+    /// A record rejected by the parser options, skipped with its lines
+    Malformed {
+        offset: u64,
+        id: String,
+        error: ParseError,
+    },
+    /// The input ended after the header of a record with no sequence
+    Truncated { offset: u64, id: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Junk { offset, lines } => {
+                write!(
+                    f,
+                    "skipped {} line(s) outside any record at byte {}",
+                    lines, offset
+                )
+            }
+            Warning::Malformed { offset, id, error } => {
+                write!(f, "skipped record {} at byte {}: {}", id, offset, error)
+            }
+            Warning::Truncated { offset, id } => write!(
+                f,
+                "skipped record {} at byte {}: input ends after its header",
+                id, offset
+            ),
+        }
+    }
+}
+
+/// An item of a `Lenient` iterator
+#[derive(Debug)]
+pub enum Parsed {
+    Record(Record),
+    Warning(Warning),
+}
+
+/// Iterator returned by `FastaBuffer::lenient`, yielding records and
+/// warnings in input order
+pub struct Lenient<B: BufRead> {
+    buffer: FastaBuffer<B>,
+    pending: Option<Record>,
+}
+
+impl<B: BufRead> Lenient<B> {
+    pub(crate) fn new(buffer: FastaBuffer<B>) -> Lenient<B> {
+        Lenient {
+            buffer,
+            pending: None,
+        }
+    }
+
+    /// Yield only records, passing each warning to `f`
+    pub fn on_warning<F: FnMut(Warning)>(self, f: F) -> OnWarning<B, F> {
+        OnWarning { inner: self, f }
+    }
+
+    fn next_warning(&mut self) -> Option<Warning> {
+        self.buffer.warnings.as_mut()?.pop_front()
+    }
+}
+
+impl<B: BufRead> Iterator for Lenient<B> {
+    type Item = Result<Parsed, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(warning) = self.next_warning() {
+            return Some(Ok(Parsed::Warning(warning)));
+        }
+        if let Some(rec) = self.pending.take() {
+            return Some(Ok(Parsed::Record(rec)));
+        }
+        let next = self.buffer.read_record(true);
+        // Warnings found while reading a record describe input before it
+        if let Some(warning) = self.next_warning() {
+            match next {
+                Some(Ok(rec)) => self.pending = Some(rec),
+                Some(Err(e)) => return Some(Err(e)),
+                None => (),
+            }
+            return Some(Ok(Parsed::Warning(warning)));
+        }
+        Some(next?.map(Parsed::Record))
+    }
+}
+
+/// Iterator returned by `Lenient::on_warning`
+pub struct OnWarning<B: BufRead, F> {
+    inner: Lenient<B>,
+    f: F,
+}
+
+impl<B: BufRead, F: FnMut(Warning)> Iterator for OnWarning<B, F> {
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(Parsed::Record(rec)) => return Some(Ok(rec)),
+                Ok(Parsed::Warning(w)) => (self.f)(w),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaReaderBuilder;
+
+    #[test]
+    fn skips_broken_input() {
+        let input = "junk\nmore junk\n>a\nAC\n\nGT\n>b\nGG\n>c\n";
+        let items: Vec<Parsed> = FastaReaderBuilder::new()
+            .allow_blank_lines(false)
+            .from_reader(input.as_bytes())
+            .lenient()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let summary: Vec<String> = items
+            .iter()
+            .map(|item| match item {
+                Parsed::Record(rec) => format!("record {} {}", rec.id, rec.sequence),
+                Parsed::Warning(w) => w.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                "skipped 2 line(s) outside any record at byte 0",
+                "skipped record a at byte 15: blank line inside FASTA record",
+                "record b GG",
+                "skipped record c at byte 31: input ends after its header",
+            ]
+        );
+
+        let mut warnings = 0;
+        let ids: Vec<String> = FastaBuffer::from(">x\nA\n>y\n".as_bytes())
+            .lenient()
+            .on_warning(|_| warnings += 1)
+            .map(|r| r.unwrap().id)
+            .collect();
+        assert_eq!(ids, ["x"]);
+        assert_eq!(warnings, 1);
+    }
+}
@@ -1,30 +1,182 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod align;
+#[cfg(feature = "std")]
+pub mod assembly;
+#[cfg(feature = "std")]
+pub mod attributes;
+#[cfg(feature = "std")]
+pub mod bed;
+#[cfg(feature = "std")]
+pub mod bgzf;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "std")]
+pub mod chunked;
+#[cfg(feature = "std")]
+pub mod codon;
+#[cfg(feature = "std")]
+pub mod collection;
+#[cfg(feature = "std")]
+pub mod compress;
+#[cfg(feature = "std")]
+pub mod consensus;
+#[cfg(feature = "std")]
+pub mod cursor;
+#[cfg(feature = "std")]
+pub mod dedup;
+#[cfg(feature = "std")]
+pub mod dict;
+#[cfg(feature = "std")]
+pub mod digest;
+#[cfg(feature = "std")]
+pub mod dust;
+#[cfg(feature = "std")]
 pub mod errors;
+#[cfg(feature = "std")]
+pub mod fastq;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fidelity;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod gaps;
+#[cfg(feature = "std")]
+pub mod gc;
+#[cfg(feature = "genbank")]
+pub mod genbank;
+#[cfg(feature = "std")]
+pub mod gff;
+#[cfg(feature = "std")]
+pub mod guard;
+mod hash;
+#[cfg(feature = "std")]
+pub mod homopolymer;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(any(feature = "bio", feature = "noodles"))]
+pub mod interop;
+#[cfg(feature = "std")]
+pub mod kmer;
+#[cfg(feature = "std")]
+pub mod lazy;
+#[cfg(feature = "std")]
+pub mod lenient;
+#[cfg(feature = "std")]
+pub mod mask;
+#[cfg(feature = "std")]
+pub mod motif;
+#[cfg(feature = "std")]
+pub mod msa;
+#[cfg(feature = "std")]
+pub mod ncbi;
+#[cfg(feature = "std")]
+pub mod paired;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod qc;
+#[cfg(feature = "std")]
+pub mod rename;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+mod rng;
+#[cfg(feature = "std")]
+pub mod sample;
+pub mod seq;
+#[cfg(feature = "std")]
+pub mod shard;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod sketch;
+#[cfg(feature = "std")]
+pub mod sort;
+#[cfg(feature = "std")]
+pub mod split;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod tabular;
+#[cfg(feature = "std")]
+pub mod translate;
+#[cfg(feature = "std")]
+pub mod trim;
+#[cfg(feature = "std")]
+pub mod umi;
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod window;
 
-use std::fmt;
+#[cfg(feature = "std")]
+pub use collection::{DuplicatePolicy, Extras, Fasta};
+pub use parser::Alphabet;
+
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::io;
+#[cfg(feature = "std")]
 use std::io::BufRead;
-use std::io::Lines;
+#[cfg(feature = "std")]
 use std::io::Write;
-use std::iter::Peekable;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+#[cfg(feature = "std")]
+use errors::FastaError;
 
 /*----------------------------------------------------------------------------*/
 
 fn wrap_string(s: &str, w: usize) -> String {
-    let mut result = String::new();
+    if w == 0 || s.len() <= w {
+        return s.to_owned();
+    }
+    let mut result = String::with_capacity(s.len() + s.len() / w);
 
-    let mut ctr = 0;
-    for i in (0..(s.len() - w)).step_by(w) {
-        result.push_str(&s[i..(i + w)]);
-        result.push('\n');
-        ctr = i;
+    if s.is_ascii() {
+        for (i, start) in (0..s.len()).step_by(w).enumerate() {
+            if i > 0 {
+                result.push('\n');
+            }
+            result.push_str(&s[start..s.len().min(start + w)]);
+        }
+    } else {
+        for (i, c) in s.chars().enumerate() {
+            if i > 0 && i % w == 0 {
+                result.push('\n');
+            }
+            result.push(c);
+        }
     }
-    result.push_str(&s[(ctr + w)..]);
 
     result
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 /// fasta::Record represents a single FASTA record
+///
+/// Records compare and hash by ID, description and sequence, exactly as
+/// stored: case and line wrapping in the input are not normalized. Use
+/// `same_sequence` to compare sequences alone.
 pub struct Record {
     pub id: String,
     pub description: String,
@@ -39,27 +191,140 @@ impl Record {
         }
     }
 
+    /// Whether the two records have the same sequence, whatever their
+    /// IDs and descriptions
+    pub fn same_sequence(&self, other: &Record) -> bool {
+        self.sequence == other.sequence
+    }
+
     fn set_header(&mut self, s: &str) {
-        let mut parts = if s.starts_with('>') {
-            s[1..].splitn(2, char::is_whitespace)
-        } else {
-            s.splitn(2, char::is_whitespace)
-        };
-        self.id = parts.next().unwrap_or("").to_owned();
-        self.description = parts.next().unwrap_or("").to_owned();
+        let mut parts = s
+            .strip_prefix('>')
+            .unwrap_or(s)
+            .splitn(2, char::is_whitespace);
+        // Reuse the existing buffers, see FastaBuffer::read_next_into
+        self.id.clear();
+        self.id.push_str(parts.next().unwrap_or(""));
+        self.description.clear();
+        self.description.push_str(parts.next().unwrap_or(""));
     }
 
     /// Generate a String representation of a fasta::Record
     pub fn as_string(&self) -> String {
-        let wrappedseq = wrap_string(&self.sequence, 80);
-        let result = format!(">{} {}\n{}\n", self.id, self.description, wrappedseq);
-        result
+        self.as_string_with_width(80)
+    }
+
+    /// Generate a String representation with sequence lines of at most
+    /// `width` characters; a width of 0 writes the sequence on one line
+    pub fn as_string_with_width(&self, width: usize) -> String {
+        let wrappedseq = wrap_string(&self.sequence, width);
+        if self.description.is_empty() {
+            format!(">{}\n{}\n", self.id, wrappedseq)
+        } else {
+            format!(">{} {}\n{}\n", self.id, self.description, wrappedseq)
+        }
+    }
+
+    /// Fraction of G and C among the unambiguous bases of the sequence.
+    ///
+    /// G, C and S count as GC; A, T, U and W as AT; other characters
+    /// (N, gaps, other ambiguity codes) are ignored. Returns 0.0 when the
+    /// sequence has no informative bases.
+    pub fn gc_content(&self) -> f64 {
+        let (mut gc, mut at) = (0usize, 0usize);
+        for b in self.sequence.bytes() {
+            match b.to_ascii_uppercase() {
+                b'G' | b'C' | b'S' => gc += 1,
+                b'A' | b'T' | b'U' | b'W' => at += 1,
+                _ => (),
+            }
+        }
+        if gc + at == 0 {
+            0.0
+        } else {
+            gc as f64 / (gc + at) as f64
+        }
+    }
+
+    /// A new record holding the reverse complement of this record's sequence
+    pub fn reverse_complement(&self) -> Record {
+        Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            sequence: seq::reverse_complement(&self.sequence),
+        }
     }
 
+    #[cfg(feature = "std")]
+    /// MD5 digest of the normalized sequence as 32 lowercase hex digits.
+    ///
+    /// The sequence is uppercased and gaps (`-`, `.`), whitespace and
+    /// non-printing characters are removed first, matching the `M5`
+    /// field written by samtools and GATK.
+    pub fn md5(&self) -> String {
+        let mut ctx = md5::Context::new();
+        ctx.consume(dict::normalized_sequence(&self.sequence));
+        format!("{:x}", ctx.compute())
+    }
+
+    #[cfg(feature = "std")]
+    /// Transcribe a DNA record to RNA, replacing T with U and preserving case.
+    ///
+    /// Fails if the sequence contains U or any character that is not an
+    /// IUPAC nucleotide code or gap.
+    pub fn transcribe(&self) -> Result<Record, FastaError> {
+        self.swap_base('T', 'U')
+    }
+
+    #[cfg(feature = "std")]
+    /// Back-transcribe an RNA record to DNA, replacing U with T and
+    /// preserving case.
+    ///
+    /// Fails if the sequence contains T or any character that is not an
+    /// IUPAC nucleotide code or gap.
+    pub fn back_transcribe(&self) -> Result<Record, FastaError> {
+        self.swap_base('U', 'T')
+    }
+
+    #[cfg(feature = "std")]
+    fn swap_base(&self, from: char, to: char) -> Result<Record, FastaError> {
+        let sequence = seq::swap_base(&self.sequence, from, to).map_err(|(position, found)| {
+            FastaError::InvalidSequence {
+                id: self.id.clone(),
+                position,
+                found,
+            }
+        })?;
+        Ok(Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            sequence,
+        })
+    }
+
+    /// A short hash of the ID and sequence, suitable as an HTTP ETag.
+    ///
+    /// The value is 16 hex digits and is stable across platforms and
+    /// releases; the description does not contribute to it.
+    pub fn etag(&self) -> String {
+        let mut h = hash::Fnv1a::new();
+        h.write(&(self.id.len() as u64).to_le_bytes());
+        h.write(self.id.as_bytes());
+        h.write(self.sequence.as_bytes());
+        format!("{:016x}", h.finish())
+    }
+
+    #[cfg(feature = "std")]
     /// Write a fasta::Record to an object implementing Write
-    pub fn write(&mut self, w: &mut impl Write) -> std::io::Result<()> {
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
         w.write_all(self.as_string().as_bytes())
     }
+
+    #[cfg(feature = "std")]
+    /// Write a fasta::Record wrapping the sequence at `width` characters
+    pub fn write_with_width(&self, w: &mut impl Write, width: usize) -> std::io::Result<()> {
+        w.write_all(self.as_string_with_width(width).as_bytes())
+    }
 }
 
 impl fmt::Display for Record {
@@ -74,87 +339,769 @@ impl fmt::Display for Record {
     }
 }
 
-/// PeekableLines is an iterator like object over the lines of any type
-/// implementing the BufRead trait.
+#[cfg(feature = "std")]
+/// Bytes `ChunkLines` asks its input for at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "std")]
+/// The lines of a BufRead, read in fixed-size chunks. `\n`, `\r\n` and
+/// lone `\r` end a line and are not part of it.
 ///
-/// PeekableLines implements two public functions
-/// 1. peekline -- returns the next line w/out advancing the iterator
-/// 2. advanceline -- advances the iterator
+/// Lines are returned as slices of the chunk buffer, so reading them
+/// neither copies nor allocates. A line running past the end of the
+/// buffer is moved to its front before the next chunk is read, and the
+/// buffer only grows for a line longer than a chunk. The one piece of
+/// state carried across chunk boundaries is whether the last line ended
+/// in `\r`, in which case a `\n` starting the next chunk is skipped.
+struct ChunkLines<B: BufRead> {
+    inner: B,
+    chunk: Vec<u8>,
+    // Start of the unread bytes of `chunk`, and their end
+    pos: usize,
+    filled: usize,
+    // Offset of `chunk[0]` in the input
+    chunk_offset: u64,
+    // The last line ended in `\r`; skip a `\n` that follows it
+    after_cr: bool,
+    // Where the line last returned starts in `chunk`, for `unread`
+    last_start: Option<usize>,
+    // Lines returned
+    lines: u64,
+    eof: bool,
+}
+
+#[cfg(feature = "std")]
+/// A line returned by `ChunkLines`
+struct Line<'a> {
+    text: &'a str,
+    // Offset of its first byte in the input
+    offset: u64,
+    // Line number, counting from 1
+    number: u64,
+}
+
+#[cfg(feature = "std")]
+impl<B: BufRead> ChunkLines<B> {
+    fn new(inner: B) -> ChunkLines<B> {
+        ChunkLines {
+            inner,
+            chunk: Vec::new(),
+            pos: 0,
+            filled: 0,
+            chunk_offset: 0,
+            after_cr: false,
+            last_start: None,
+            lines: 0,
+            eof: false,
+        }
+    }
+
+    /// Read another chunk after the unread bytes, first moving them to
+    /// the front of the buffer. Returns false at the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+        if self.pos > 0 {
+            self.chunk.copy_within(self.pos..self.filled, 0);
+            self.chunk_offset += self.pos as u64;
+            self.filled -= self.pos;
+            self.pos = 0;
+        }
+        if self.chunk.len() < self.filled + CHUNK_SIZE {
+            self.chunk.resize(self.filled + CHUNK_SIZE, 0);
+        }
+        loop {
+            match self.inner.read(&mut self.chunk[self.filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Ok(false);
+                }
+                Ok(n) => {
+                    self.filled += n;
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Offset of the end of the input once it is exhausted
+    fn end_offset(&self) -> u64 {
+        self.chunk_offset + self.pos as u64
+    }
+
+    /// The next line to be read
+    fn position(&self) -> Position {
+        let mut offset = self.end_offset();
+        if self.after_cr && self.chunk[self.pos..self.filled].first() == Some(&b'\n') {
+            offset += 1;
+        }
+        Position {
+            line: self.lines + 1,
+            offset,
+        }
+    }
+
+    /// Error `e` from reading the next line, which starts at `offset`,
+    /// tagged with the line's position: a line budget error gets it
+    /// filled in, and any other IO error becomes a FastaError::Read
+    fn line_error(&self, mut e: io::Error, offset: u64) -> io::Error {
+        let number = self.lines + 1;
+        if let Some(FastaError::LineTooLong {
+            line, offset: at, ..
+        }) = e.get_mut().and_then(|e| e.downcast_mut::<FastaError>())
+        {
+            *line = number;
+            *at = offset;
+            return e;
+        }
+        if guard::is_limit_error(&e) {
+            return e;
+        }
+        io::Error::new(
+            e.kind(),
+            FastaError::Read {
+                line: number,
+                offset,
+                error: Arc::new(e),
+            },
+        )
+    }
+
+    /// The next line, or None at the end of the input
+    fn next_line(&mut self) -> Option<io::Result<Line<'_>>> {
+        self.last_start = None;
+        if self.after_cr {
+            if self.pos == self.filled {
+                if let Err(e) = self.fill() {
+                    return Some(Err(self.line_error(e, self.end_offset())));
+                }
+            }
+            if self.pos < self.filled {
+                self.after_cr = false;
+                if self.chunk[self.pos] == b'\n' {
+                    self.pos += 1;
+                }
+            }
+        }
+        // Taken before reading on, so errors report the line they hit
+        let offset = self.end_offset();
+        // Bytes of the line already searched for an end
+        let mut searched = 0;
+        let end = loop {
+            let unread = &self.chunk[self.pos + searched..self.filled];
+            if let Some(i) = memchr::memchr2(b'\n', b'\r', unread) {
+                break self.pos + searched + i;
+            }
+            searched = self.filled - self.pos;
+            match self.fill() {
+                Ok(true) => (),
+                Ok(false) if searched == 0 => return None,
+                Ok(false) => break self.filled,
+                Err(e) => return Some(Err(self.line_error(e, offset))),
+            }
+        };
+        let start = self.pos;
+        self.pos = (end + 1).min(self.filled);
+        self.after_cr = self.chunk.get(end) == Some(&b'\r');
+        self.last_start = Some(start);
+        self.lines += 1;
+        let bytes = &self.chunk[start..end];
+        let line = match std::str::from_utf8(bytes) {
+            Ok(text) => Line {
+                text,
+                offset,
+                number: self.lines,
+            },
+            Err(e) => return Some(Err(utf8_error(self.lines, offset, bytes, e))),
+        };
+        Some(Ok(line))
+    }
+
+    /// Push the line last returned back, so `next_line` returns it again
+    fn unread(&mut self) {
+        if let Some(start) = self.last_start.take() {
+            self.pos = start;
+            self.after_cr = false;
+            self.lines -= 1;
+        }
+    }
+}
+
+/// Case normalization applied to sequence lines while parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Case {
+    /// Keep sequence characters as they appear in the input
+    #[default]
+    Preserve,
+    /// Convert sequence characters to upper case
+    Upper,
+    /// Convert sequence characters to lower case
+    Lower,
+}
+
+/// Which lines FastaBuffer skips as comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Comments {
+    /// No comment lines; every line is header or sequence
+    None,
+    /// Lines starting with `;`, as in the original FASTA format
+    #[default]
+    Semicolon,
+    /// Lines starting with `#`
+    Hash,
+}
+
+#[cfg(feature = "std")]
+/// FastaReaderBuilder configures how a FastaBuffer parses its input.
 ///
-struct PeekableLines<B: BufRead> {
-    iter: Peekable<Lines<B>>,
+/// The defaults reproduce the behavior of `FastaBuffer::from`:
+/// whitespace is trimmed, `;` lines are comments, blank lines and
+/// leading junk before the first header are ignored, and sequence case
+/// is preserved.
+#[derive(Debug, Clone, Default)]
+pub struct FastaReaderBuilder {
+    parse: parser::ParseOptions,
+    cancel: Option<cancel::CancellationToken>,
+    context_width: usize,
 }
 
-impl<B: BufRead> PeekableLines<B> {
-    /// Peek at the next line in the buffer, w/out advancing the iterator
-    pub fn peekline(&mut self) -> Option<&'_ Result<String, io::Error>> {
-        self.iter.peek()
+#[cfg(feature = "std")]
+impl FastaReaderBuilder {
+    /// Returns a builder with the default parsing options
+    pub fn new() -> FastaReaderBuilder {
+        FastaReaderBuilder::default()
+    }
+
+    /// Strip leading and trailing whitespace from every line
+    pub fn trim_whitespace(mut self, yes: bool) -> FastaReaderBuilder {
+        self.parse.trim_whitespace = yes;
+        self
+    }
+
+    /// Returns a builder that rejects anything but headers and sequence:
+    /// no comment lines, no blank lines inside records and nothing
+    /// before the first header. Whitespace is still trimmed.
+    pub fn strict() -> FastaReaderBuilder {
+        FastaReaderBuilder {
+            parse: parser::ParseOptions::strict(),
+            ..FastaReaderBuilder::default()
+        }
+    }
+
+    /// Treat lines starting with `;` as comments, or disable comments
+    pub fn comments(self, yes: bool) -> FastaReaderBuilder {
+        self.comment_style(if yes {
+            Comments::Semicolon
+        } else {
+            Comments::None
+        })
+    }
+
+    /// Choose which lines are skipped as comments
+    pub fn comment_style(mut self, comments: Comments) -> FastaReaderBuilder {
+        self.parse.comments = comments;
+        self
+    }
+
+    /// Allow blank lines between the sequence lines of a record.
+    ///
+    /// When disallowed, a blank line followed by more sequence for the
+    /// same record is reported as an error. Blank lines before a header
+    /// or at the end of the input are always accepted.
+    pub fn allow_blank_lines(mut self, yes: bool) -> FastaReaderBuilder {
+        self.parse.allow_blank_lines = yes;
+        self
+    }
+
+    /// Allow (and skip) non-header lines before the first `>`
+    pub fn allow_leading_junk(mut self, yes: bool) -> FastaReaderBuilder {
+        self.parse.allow_leading_junk = yes;
+        self
+    }
+
+    /// Normalize the case of sequence characters
+    pub fn case(mut self, case: Case) -> FastaReaderBuilder {
+        self.parse.case = case;
+        self
+    }
+
+    /// Reject sequence lines with characters outside `alphabet`
+    pub fn alphabet(mut self, alphabet: Alphabet) -> FastaReaderBuilder {
+        self.parse.alphabet = alphabet;
+        self
+    }
+
+    /// Include up to `width` characters either side of the offending
+    /// byte in validation errors; 0, the default, includes none
+    pub fn error_context(mut self, width: usize) -> FastaReaderBuilder {
+        self.context_width = width;
+        self
+    }
+
+    /// Stop parsing with a cancellation error once `token` is cancelled
+    pub fn cancellation(mut self, token: cancel::CancellationToken) -> FastaReaderBuilder {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Read records with the layout needed to write them back unchanged,
+    /// see `fidelity`
+    pub fn from_reader_faithful<B: BufRead>(&self, b: B) -> fidelity::Faithful<B> {
+        fidelity::Faithful::new(b, self)
+    }
+
+    /// Open a FASTA file, decompressing it if needed, see `compress`
+    pub fn open<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<FastaBuffer<Box<dyn BufRead + Send>>> {
+        let file = std::fs::File::open(path)?;
+        let reader = compress::decompress(std::io::BufReader::new(file))?;
+        Ok(self.from_reader(reader))
     }
 
-    /// Return the next line in the buffer, advancing the iterator
-    pub fn advanceline(&mut self) -> Option<Result<String, io::Error>> {
-        self.iter.next()
+    /// Create a FastaBuffer over an instance that implements BufRead
+    pub fn from_reader<B: BufRead>(&self, b: B) -> FastaBuffer<B> {
+        FastaBuffer {
+            lines: ChunkLines::new(b),
+            opts: self.clone(),
+            limits: None,
+            span: RecordSpan::default(),
+            seq_len: 0,
+            warnings: None,
+            finished: false,
+        }
     }
 }
 
-impl<B: BufRead> From<B> for PeekableLines<B> {
-    /// Convert an object implement BufRead to a PeekableLines
-    fn from(buf: B) -> PeekableLines<B> {
-        PeekableLines {
-            iter: buf.lines().peekable(),
+#[cfg(feature = "std")]
+/// Open a FASTA file with the default options, decompressing it if
+/// needed; see `FastaReaderBuilder::open`
+pub fn open<P: AsRef<std::path::Path>>(
+    path: P,
+) -> io::Result<FastaBuffer<Box<dyn BufRead + Send>>> {
+    FastaReaderBuilder::new().open(path)
+}
+
+#[cfg(feature = "std")]
+/// A FastaError::Validation for parse error `e` on `line`, which starts
+/// at byte `offset`
+fn validation_error(
+    id: &str,
+    line_number: u64,
+    offset: u64,
+    line: &str,
+    e: parser::ParseError,
+    context_width: usize,
+) -> io::Error {
+    let (column, message) = match e {
+        parser::ParseError::InvalidByte { column, .. } => {
+            (column, "unexpected character in sequence".to_owned())
         }
+        e => (line.len() - line.trim_start().len() + 1, e.to_string()),
+    };
+    let at = (column - 1).min(line.len());
+    let mut start = at.saturating_sub(context_width);
+    let mut end = if context_width == 0 {
+        at
+    } else {
+        line.len().min(at + 1 + context_width)
+    };
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !line.is_char_boundary(end) {
+        end += 1;
     }
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::Validation {
+            id: id.to_owned(),
+            line: line_number,
+            column,
+            offset: offset + at as u64,
+            byte: line.as_bytes().get(at).copied().unwrap_or(0),
+            message,
+            context: line[start..end].to_owned(),
+        },
+    )
 }
 
+#[cfg(feature = "std")]
+/// A FastaError::Validation for `line`, which starts at byte `offset` and
+/// is not valid UTF-8. The record ID is left for the caller to fill in.
+fn utf8_error(line_number: u64, offset: u64, line: &[u8], e: std::str::Utf8Error) -> io::Error {
+    let at = e.valid_up_to();
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::Validation {
+            id: String::new(),
+            line: line_number,
+            column: at + 1,
+            offset: offset + at as u64,
+            byte: line[at],
+            message: "stream did not contain valid UTF-8".to_owned(),
+            context: String::new(),
+        },
+    )
+}
+
+#[cfg(feature = "std")]
 /// FastaBuffer is the public interface for working
 /// with FASTA records in an iterator like manner
-pub struct FastaBuffer<B: BufRead>(PeekableLines<B>);
+pub struct FastaBuffer<B: BufRead> {
+    lines: ChunkLines<B>,
+    opts: FastaReaderBuilder,
+    limits: Option<guard::Limits>,
+    span: RecordSpan,
+    // Sequence length of the last record read, counted even when its
+    // sequence is not kept
+    seq_len: usize,
+    // Problems skipped in lenient mode, not yet handed out
+    warnings: Option<std::collections::VecDeque<lenient::Warning>>,
+    finished: bool,
+}
+
+/// Byte offsets of a record in its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordSpan {
+    /// Offset of the `>` starting the header line
+    pub header_offset: u64,
+    /// Offset of the first byte after the header line
+    pub seq_offset: u64,
+    /// Offset just past the record: the next header, or the end of the
+    /// input
+    pub end_offset: u64,
+    /// Line number of the header line, counting from 1
+    pub header_line: u64,
+}
+
+/// Where a FastaBuffer is in its input, see `FastaBuffer::position`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    /// Number of the next line to be read, counting from 1
+    pub line: u64,
+    /// Offset of the start of that line, counting from 0
+    pub offset: u64,
+}
+
+#[cfg(feature = "std")]
+/// An iterator over records and their byte offsets, created by
+/// `FastaBuffer::with_offsets`
+pub struct WithOffsets<B: BufRead> {
+    buffer: FastaBuffer<B>,
+}
+
+#[cfg(feature = "std")]
+impl<B: BufRead> Iterator for WithOffsets<B> {
+    type Item = Result<(Record, RecordSpan), io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rec = self.buffer.read_record(true)?;
+        Some(rec.map(|rec| (rec, self.buffer.span)))
+    }
+}
+
+#[cfg(feature = "std")]
+/// An iterator over records without their sequences, paired with the
+/// sequence lengths, created by `FastaBuffer::lengths`
+pub struct Lengths<B: BufRead> {
+    buffer: FastaBuffer<B>,
+}
+
+#[cfg(feature = "std")]
+impl<B: BufRead> Iterator for Lengths<B> {
+    type Item = Result<(Record, usize), io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rec = self.buffer.read_record(false)?;
+        Some(rec.map(|rec| (rec, self.buffer.seq_len)))
+    }
+}
+
+#[cfg(feature = "std")]
+/// An iterator over batches of up to `n` records, created by
+/// `FastaBuffer::batches`
+pub struct Batches<B: BufRead> {
+    buffer: FastaBuffer<B>,
+    n: usize,
+    // An error that ended a batch early, returned after the batch
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<B: BufRead> Iterator for Batches<B> {
+    type Item = Result<Vec<Record>, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        // The batch size comes from the caller, so only preallocate a
+        // bounded amount
+        let mut batch = Vec::with_capacity(self.n.min(1024));
+        while batch.len() < self.n {
+            match self.buffer.next() {
+                Some(Ok(rec)) => batch.push(rec),
+                Some(Err(e)) if batch.is_empty() => return Some(Err(e)),
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    break;
+                }
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            None
+        } else {
+            Some(Ok(batch))
+        }
+    }
+}
 
+#[cfg(feature = "std")]
 impl<B: BufRead> FastaBuffer<B> {
     /// Create a FastaBuffer from instance that implements BufRead
     pub fn from(b: B) -> FastaBuffer<B> {
-        FastaBuffer(PeekableLines::from(b))
+        FastaReaderBuilder::new().from_reader(b)
     }
-}
 
-/// An iterator that returns FASTA records from a FastaBuffer
-impl<B: BufRead> Iterator for FastaBuffer<B> {
-    type Item = Result<Record, io::Error>;
+    /// The line number and byte offset of the next line to be read, so
+    /// after a record, the start of the next one. After an error it is
+    /// just past the line that failed, where reading resumes.
+    pub fn position(&self) -> Position {
+        self.lines.position()
+    }
 
-    /// Return the next FASTA record
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut active_record = false;
-        let mut rec = Record::new();
+    /// Yield each record with its byte offsets in the input. Offsets count
+    /// from where the buffer started reading, so `seq_offset..end_offset`
+    /// holds the raw sequence lines, including any comment or blank lines
+    /// within the record.
+    pub fn with_offsets(self) -> WithOffsets<B> {
+        WithOffsets { buffer: self }
+    }
 
-        while let Some(nextline) = self.0.peekline() {
-            let nextline = match nextline {
-                Ok(line) => line.trim(),
-                Err(e) => {
-                    return Some(Err(io::Error::new(
-                        e.kind(),
-                        "IO error while parsing Fasta records.",
-                    )));
+    /// Yield each record with an empty sequence and the length the
+    /// sequence had. Sequences are measured as they are parsed but never
+    /// accumulated, so this is much cheaper than iterating.
+    pub fn lengths(self) -> Lengths<B> {
+        Lengths { buffer: self }
+    }
+
+    /// Yield each record's header, then its sequence in chunks of
+    /// `chunk_size` bytes, so a sequence is held whole only if it is on
+    /// a single line; see the `chunked` module. Panics if `chunk_size`
+    /// is 0.
+    pub fn chunked(self, chunk_size: usize) -> chunked::Chunked<B> {
+        chunked::Chunked::new(self, chunk_size)
+    }
+
+    /// Yield records with reference-counted sequences, which clone
+    /// without copying the sequence; see the `shared` module
+    pub fn shared(self) -> shared::Shared<B> {
+        shared::Shared::new(self)
+    }
+
+    /// Yield the records in batches of `n`, the last batch holding the
+    /// rest. An error ends a batch early and is returned after it.
+    /// Panics if `n` is 0.
+    pub fn batches(self, n: usize) -> Batches<B> {
+        assert!(n > 0, "batch size must be non-zero");
+        Batches {
+            buffer: self,
+            n,
+            error: None,
+        }
+    }
+
+    /// Keep every n-th record, starting with the first.
+    ///
+    /// Skipped records are parsed for structure only, so their sequences
+    /// are never accumulated. Panics if `n` is 0.
+    pub fn every_nth(self, n: usize) -> sample::EveryNth<B> {
+        sample::EveryNth::new(self, n)
+    }
+
+    /// Skip malformed input instead of failing or merging it into
+    /// records, reporting each skip; see the `lenient` module
+    pub fn lenient(mut self) -> lenient::Lenient<B> {
+        self.warnings = Some(std::collections::VecDeque::new());
+        lenient::Lenient::new(self)
+    }
+
+    /// Read the next record into `rec`, reusing its buffers. Returns
+    /// false, leaving `rec` empty, at the end of the input.
+    ///
+    /// Unlike iterating, a loop over this allocates only when a record is
+    /// longer than any before it.
+    pub fn read_next_into(&mut self, rec: &mut Record) -> Result<bool, io::Error> {
+        let spare = std::mem::take(rec);
+        match self.read_reusing(true, spare) {
+            Some(Ok(next)) => {
+                *rec = next;
+                Ok(true)
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(false),
+        }
+    }
+
+    /// Read the next record, accumulating its sequence only if `keep` is set
+    pub(crate) fn read_record(&mut self, keep: bool) -> Option<Result<Record, io::Error>> {
+        self.read_reusing(keep, Record::new())
+    }
+
+    fn read_reusing(&mut self, keep: bool, spare: Record) -> Option<Result<Record, io::Error>> {
+        if self.finished {
+            return None;
+        }
+        let mut parser = parser::Parser::new(self.opts.parse);
+        parser.recycle(spare);
+        let lenient = self.warnings.is_some();
+        let mut started = None;
+        let mut span = RecordSpan::default();
+        let mut after_header = false;
+        // The current record's ID, kept for errors after the parser drops
+        // the record
+        let mut header_id = String::new();
+        // Lenient mode: whether the record has any sequence, the pending
+        // run of junk lines, and whether the lines of a rejected record
+        // are being skipped
+        let mut has_sequence = false;
+        let mut junk: Option<(u64, usize)> = None;
+        let mut skipping = false;
+
+        while let Some(line) = self.lines.next_line() {
+            if let Some(token) = &self.opts.cancel {
+                if token.is_cancelled() {
+                    self.finished = true;
+                    return Some(Err(cancel::cancelled()));
+                }
+            }
+            let line = match line {
+                Ok(line) => line,
+                Err(mut err) => {
+                    guard::set_record_id(&mut err, parser.id());
+                    if guard::is_limit_error(&err) {
+                        self.finished = true;
+                    }
+                    return Some(Err(err));
                 }
             };
-            match nextline.chars().next() {
-                None | Some(';') => (),
-                Some('>') if active_record => {
+            let offset = line.offset;
+            if after_header {
+                span.seq_offset = offset;
+                after_header = false;
+            }
+            if let Some(limits) = &self.limits {
+                if let Err(e) = limits.check_time(parser.id(), started) {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+            match parser.push_line(line.text, keep) {
+                Ok(parser::Step::Done(rec)) => {
+                    // The header starts the next record
+                    self.lines.unread();
+                    span.end_offset = offset;
+                    self.span = span;
+                    self.seq_len = parser.seq_len();
                     return Some(Ok(rec));
                 }
-                Some('>') => {
-                    active_record = true;
-                    rec.set_header(&nextline);
+                Ok(parser::Step::Header) => {
+                    span.header_offset = offset;
+                    span.header_line = line.number;
+                    after_header = true;
+                    if self.limits.is_some() {
+                        started = Some(std::time::Instant::now());
+                    }
+                    header_id.clear();
+                    header_id.push_str(parser.id());
+                    if lenient {
+                        self.flush_junk(&mut junk);
+                        skipping = false;
+                        has_sequence = false;
+                    }
+                }
+                Ok(parser::Step::Sequence) => {
+                    has_sequence = true;
+                    if let Some(limits) = &self.limits {
+                        if let Err(e) = limits.check_size(parser.id(), parser.seq_len()) {
+                            self.finished = true;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                Ok(parser::Step::Skipped) => (),
+                Ok(parser::Step::Junk) | Err(parser::ParseError::LeadingJunk) if lenient => {
+                    if !skipping {
+                        junk.get_or_insert((offset, 0)).1 += 1;
+                    }
+                }
+                Ok(parser::Step::Junk) => (),
+                Err(error) if lenient => {
+                    self.push_warning(lenient::Warning::Malformed {
+                        offset: span.header_offset,
+                        id: header_id.clone(),
+                        error,
+                    });
+                    skipping = true;
+                }
+                Err(e) => {
+                    let err = validation_error(
+                        &header_id,
+                        line.number,
+                        offset,
+                        line.text,
+                        e,
+                        self.opts.context_width,
+                    );
+                    return Some(Err(err));
                 }
-                Some(_) if active_record => rec.sequence.push_str(nextline),
-                _ => (),
             }
-            self.0.advanceline();
         }
-        if active_record {
-            Some(Ok(rec))
-        } else {
-            None
+        self.flush_junk(&mut junk);
+        let rec = parser.finish()?;
+        if lenient && !has_sequence {
+            self.push_warning(lenient::Warning::Truncated {
+                offset: span.header_offset,
+                id: rec.id,
+            });
+            return None;
         }
+        span.end_offset = self.lines.end_offset();
+        if after_header {
+            span.seq_offset = span.end_offset;
+        }
+        self.span = span;
+        self.seq_len = parser.seq_len();
+        Some(Ok(rec))
+    }
+
+    fn push_warning(&mut self, warning: lenient::Warning) {
+        if let Some(warnings) = &mut self.warnings {
+            warnings.push_back(warning);
+        }
+    }
+
+    fn flush_junk(&mut self, junk: &mut Option<(u64, usize)>) {
+        if let Some((offset, lines)) = junk.take() {
+            self.push_warning(lenient::Warning::Junk { offset, lines });
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// An iterator that returns FASTA records from a FastaBuffer
+impl<B: BufRead> Iterator for FastaBuffer<B> {
+    type Item = Result<Record, io::Error>;
+
+    /// Return the next FASTA record
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record(true)
     }
 }
 
@@ -164,7 +1111,74 @@ mod tests {
     #[test]
     fn wrap_str_test() {
         let s = "hello world how are you today?";
-        let ws = super::wrap_string(&s, 14);
-        println!("{}", ws);
+        let ws = super::wrap_string(s, 14);
+        assert_eq!(ws, "hello world ho\nw are you toda\ny?");
+        assert_eq!(super::wrap_string("ACGT", 80), "ACGT");
+        assert_eq!(super::wrap_string("ACGTAC", 3), "ACG\nTAC");
+    }
+
+    #[test]
+    fn record_equality() {
+        let a = super::Record {
+            id: "a".to_owned(),
+            description: "x".to_owned(),
+            sequence: "ACGT".to_owned(),
+        };
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        b.id = "b".to_owned();
+        assert_ne!(a, b);
+        assert!(a.same_sequence(&b));
+        b.sequence.make_ascii_lowercase();
+        assert!(!a.same_sequence(&b));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn split_all_line_endings() {
+        // One byte per read splits every `\r\n` across chunks
+        struct OneByte<'a>(&'a [u8]);
+        impl std::io::Read for OneByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        impl std::io::BufRead for OneByte<'_> {
+            fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+                Ok(&self.0[..self.0.len().min(1)])
+            }
+            fn consume(&mut self, amt: usize) {
+                self.0 = &self.0[amt..];
+            }
+        }
+
+        let data = "a\r\nb\rc\n\r\rd";
+        let mut lines = super::ChunkLines::new(OneByte(data.as_bytes()));
+        let mut got = Vec::new();
+        while let Some(line) = lines.next_line() {
+            got.push(line.unwrap().text.to_owned());
+        }
+        assert_eq!(got, ["a", "b", "c", "", "", "d"]);
+        assert_eq!(lines.end_offset(), data.len() as u64);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn lines_longer_than_a_chunk() {
+        let long = "A".repeat(super::CHUNK_SIZE * 2 + 5);
+        let data = format!(">x\r\n{}\r\n>y\n", long);
+        let mut lines = super::ChunkLines::new(data.as_bytes());
+        assert_eq!(lines.next_line().unwrap().unwrap().text, ">x");
+        assert_eq!(lines.next_line().unwrap().unwrap().text, long);
+        let y = lines.next_line().unwrap().unwrap();
+        assert_eq!((y.text, y.offset), (">y", long.len() as u64 + 6));
+        lines.unread();
+        let y = lines.next_line().unwrap().unwrap();
+        assert_eq!((y.text, y.number), (">y", 3));
+        assert!(lines.next_line().is_none());
+        assert_eq!(lines.end_offset(), data.len() as u64);
     }
 }
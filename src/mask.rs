@@ -0,0 +1,102 @@
+//! Hard- and soft-masking of sequence intervals
+
+use std::ops::Range;
+
+use crate::errors::FastaError;
+use crate::Record;
+
+/// How masked bases are represented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskMode {
+    /// Replace masked bases with `N`
+    Hard,
+    /// Convert masked bases to lower case
+    Soft,
+}
+
+/// Sort intervals and merge those that overlap or touch. Empty
+/// intervals are dropped.
+pub fn merge_intervals(intervals: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut sorted: Vec<Range<usize>> = intervals
+        .iter()
+        .filter(|r| r.start < r.end)
+        .cloned()
+        .collect();
+    sorted.sort_by_key(|r| (r.start, r.end));
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(sorted.len());
+    for r in sorted {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+impl Record {
+    /// Mask the given 0-based, half-open intervals of the sequence.
+    ///
+    /// Overlapping intervals are merged first. Every interval must lie
+    /// within the sequence and the sequence must be ASCII; otherwise an
+    /// error is returned and the record is left unchanged.
+    pub fn mask(&mut self, intervals: &[Range<usize>], mode: MaskMode) -> Result<(), FastaError> {
+        let len = self.sequence.len();
+        if let Some(r) = intervals.iter().find(|r| r.start > r.end || r.end > len) {
+            return Err(FastaError::OutOfBounds {
+                id: self.id.clone(),
+                start: r.start,
+                end: r.end,
+                len,
+            });
+        }
+        if let Some((position, found)) = self
+            .sequence
+            .chars()
+            .enumerate()
+            .find(|(_, c)| !c.is_ascii())
+        {
+            return Err(FastaError::InvalidSequence {
+                id: self.id.clone(),
+                position,
+                found,
+            });
+        }
+
+        for r in merge_intervals(intervals) {
+            match mode {
+                MaskMode::Soft => self.sequence[r].make_ascii_lowercase(),
+                MaskMode::Hard => {
+                    let n = "N".repeat(r.len());
+                    self.sequence.replace_range(r, &n);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        assert_eq!(
+            merge_intervals(&[5..8, 0..2, 1..3, 8..9, 4..4]),
+            vec![0..3, 5..9]
+        );
+    }
+
+    #[test]
+    fn hard_and_soft() {
+        let mut rec = Record::new();
+        rec.id = "chr1".to_owned();
+        rec.sequence = "ACGTACGTAC".to_owned();
+        rec.mask(&[1..3, 2..4], MaskMode::Soft).unwrap();
+        assert_eq!(rec.sequence, "AcgtACGTAC");
+        rec.mask(&[8..10, 9..10], MaskMode::Hard).unwrap();
+        assert_eq!(rec.sequence, "AcgtACGTNN");
+        assert!(rec.mask(&[0..1, 9..11], MaskMode::Hard).is_err());
+        assert_eq!(rec.sequence, "AcgtACGTNN");
+    }
+}
@@ -0,0 +1,92 @@
+//! IUPAC degenerate motif search
+
+use crate::errors::FastaError;
+use crate::seq::{base_mask, reverse_complement};
+use crate::Record;
+
+/// The strand a motif was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+/// A motif occurrence. Coordinates are 0-based, half-open and always
+/// on the forward strand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotifMatch {
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+}
+
+impl Record {
+    /// Find every occurrence of an IUPAC `pattern` on both strands,
+    /// including overlapping ones, ordered by start.
+    ///
+    /// Ambiguity codes are expanded on both sides: a position matches
+    /// when the pattern and sequence codes share at least one base, so
+    /// `R` matches `A`, `G` and `N`. Case is ignored and U is read as T.
+    /// Gaps never match. A pattern equal to its own reverse complement
+    /// is reported on the forward strand only.
+    pub fn find_motif(&self, pattern: &str) -> Result<Vec<MotifMatch>, FastaError> {
+        let fwd: Vec<u8> = pattern.bytes().map(base_mask).collect();
+        if fwd.is_empty() || fwd.contains(&0) {
+            return Err(FastaError::InvalidPattern(pattern.to_owned()));
+        }
+        let rc = reverse_complement(&pattern.to_ascii_uppercase());
+        let rev: Vec<u8> = rc.bytes().map(base_mask).collect();
+        let seq: Vec<u8> = self.sequence.bytes().map(base_mask).collect();
+
+        let mut matches = Vec::new();
+        if seq.len() < fwd.len() {
+            return Ok(matches);
+        }
+        let hit = |pat: &[u8], at: usize| pat.iter().zip(&seq[at..]).all(|(p, s)| p & s != 0);
+        for start in 0..=seq.len() - fwd.len() {
+            let end = start + fwd.len();
+            if hit(&fwd, start) {
+                matches.push(MotifMatch {
+                    start,
+                    end,
+                    strand: Strand::Forward,
+                });
+            }
+            if rev != fwd && hit(&rev, start) {
+                matches.push(MotifMatch {
+                    start,
+                    end,
+                    strand: Strand::Reverse,
+                });
+            }
+        }
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_strands() {
+        let rec = Record {
+            sequence: "AGGTCATTATGACCT".to_owned(),
+            ..Record::new()
+        };
+        let found: Vec<(usize, Strand)> = rec
+            .find_motif("rggncay")
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.start, m.strand))
+            .collect();
+        assert_eq!(found, [(0, Strand::Forward), (8, Strand::Reverse)]);
+        // Ambiguity in the subject matches too
+        let n = Record {
+            sequence: "NNNN".to_owned(),
+            ..Record::new()
+        };
+        assert_eq!(n.find_motif("GATC").unwrap().len(), 1);
+        assert!(rec.find_motif("GA-C").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Multiple sequence alignments stored as aligned FASTA
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Range;
+
+use crate::errors::FastaError;
+use crate::seq::{base_mask, iupac_code};
+use crate::Record;
+
+/// Residue counts for one alignment column, keyed by the byte as it
+/// appears in the sequences (case and gap characters included)
+pub type ColumnCounts = BTreeMap<u8, usize>;
+
+/// How `Alignment::consensus_with` treats gap characters (`-` and `.`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapHandling {
+    /// Leave gaps out of the counts; all-gap columns are dropped
+    #[default]
+    Ignore,
+    /// Count gaps like residues and drop columns whose consensus is a gap
+    Count,
+    /// Count gaps like residues and keep `-` where the consensus is a
+    /// gap, so the consensus stays aligned to the rows
+    Keep,
+}
+
+/// Options for `Alignment::consensus_with`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsensusOptions {
+    /// Fraction of counted rows a residue needs to be called
+    pub threshold: f64,
+    /// Call IUPAC codes for the smallest set of nucleotides reaching
+    /// the threshold, instead of `N`
+    pub ambiguity_codes: bool,
+    pub gaps: GapHandling,
+    /// ID of the consensus record
+    pub id: String,
+}
+
+impl Default for ConsensusOptions {
+    /// Simple majority (threshold 0.5) with IUPAC codes for ties, gaps
+    /// ignored, ID `consensus`
+    fn default() -> ConsensusOptions {
+        ConsensusOptions {
+            threshold: 0.5,
+            ambiguity_codes: true,
+            gaps: GapHandling::Ignore,
+            id: "consensus".to_owned(),
+        }
+    }
+}
+
+fn is_gap(b: u8) -> bool {
+    b == b'-' || b == b'.'
+}
+
+/// A set of records of equal length, treated as rows of an alignment.
+/// Columns are indexed by byte position, so sequences are expected to be
+/// ASCII.
+#[derive(Debug)]
+pub struct Alignment {
+    rows: Vec<Record>,
+    width: usize,
+}
+
+impl Alignment {
+    /// Build an alignment, failing with `FastaError::LengthMismatch` on the
+    /// first record whose length differs from the first record's
+    pub fn new(rows: Vec<Record>) -> Result<Alignment, FastaError> {
+        let width = rows.first().map_or(0, |r| r.sequence.len());
+        if let Some(r) = rows.iter().find(|r| r.sequence.len() != width) {
+            return Err(FastaError::LengthMismatch {
+                id: r.id.clone(),
+                expected: width,
+                found: r.sequence.len(),
+            });
+        }
+        Ok(Alignment { rows, width })
+    }
+
+    /// Read an alignment from a record stream
+    pub fn from_records<I>(records: I) -> Result<Alignment, FastaError>
+    where
+        I: IntoIterator<Item = Result<Record, io::Error>>,
+    {
+        let rows = records.into_iter().collect::<Result<Vec<_>, _>>()?;
+        Alignment::new(rows)
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Number of columns
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The rows, in order
+    pub fn records(&self) -> &[Record] {
+        &self.rows
+    }
+
+    /// Consume the alignment, returning its rows
+    pub fn into_records(self) -> Vec<Record> {
+        self.rows
+    }
+
+    /// The residues of column `i`, top to bottom
+    pub fn column(&self, i: usize) -> Option<Vec<u8>> {
+        if i >= self.width {
+            return None;
+        }
+        Some(self.rows.iter().map(|r| r.sequence.as_bytes()[i]).collect())
+    }
+
+    /// Iterate over the columns from left to right
+    pub fn columns(&self) -> Columns<'_> {
+        Columns { aln: self, next: 0 }
+    }
+
+    /// How often each residue occurs in column `i`
+    pub fn column_counts(&self, i: usize) -> Option<ColumnCounts> {
+        let mut counts = ColumnCounts::new();
+        for b in self.column(i)? {
+            *counts.entry(b).or_insert(0) += 1;
+        }
+        Some(counts)
+    }
+
+    /// Consensus sequence using `threshold` and the default options
+    pub fn consensus(&self, threshold: f64) -> Record {
+        self.consensus_with(&ConsensusOptions {
+            threshold,
+            ..ConsensusOptions::default()
+        })
+    }
+
+    /// Consensus sequence of the alignment.
+    ///
+    /// Residues are compared ignoring case, with U read as T. A column
+    /// is called as its most frequent residue if that residue alone
+    /// reaches the threshold and is not tied. Otherwise, with
+    /// `ambiguity_codes`, residues are added in order of frequency (tied
+    /// residues together) until the threshold is met and the column is
+    /// called as the IUPAC code for the set; sets that include a gap or
+    /// a non-nucleotide, and every column without `ambiguity_codes`,
+    /// are called `N`.
+    pub fn consensus_with(&self, opts: &ConsensusOptions) -> Record {
+        let mut sequence = String::with_capacity(self.width);
+        for column in self.columns() {
+            let mut counts = ColumnCounts::new();
+            for b in column {
+                let b = match b.to_ascii_uppercase() {
+                    b'U' => b'T',
+                    b'.' => b'-',
+                    b => b,
+                };
+                if opts.gaps == GapHandling::Ignore && is_gap(b) {
+                    continue;
+                }
+                *counts.entry(b).or_insert(0) += 1;
+            }
+            let total: usize = counts.values().sum();
+            if total == 0 {
+                continue;
+            }
+            let mut ranked: Vec<(u8, usize)> = counts.into_iter().collect();
+            ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            let needed = opts.threshold * total as f64;
+
+            let (top, top_count) = ranked[0];
+            let tied = ranked.len() > 1 && ranked[1].1 == top_count;
+            let call = if !tied && top_count as f64 >= needed {
+                top
+            } else if opts.ambiguity_codes {
+                let mut mask = 0;
+                let mut covered = 0;
+                let mut valid = true;
+                for (i, &(b, n)) in ranked.iter().enumerate() {
+                    // Ties at the boundary are all included
+                    if i > 0 && covered as f64 >= needed && n < ranked[i - 1].1 {
+                        break;
+                    }
+                    let m = base_mask(b);
+                    valid &= m != 0 && b != b'N';
+                    mask |= m;
+                    covered += n;
+                }
+                if valid {
+                    iupac_code(mask)
+                } else {
+                    b'N'
+                }
+            } else {
+                b'N'
+            };
+            match call {
+                b'-' if opts.gaps == GapHandling::Keep => sequence.push('-'),
+                b'-' => (),
+                b => sequence.push(b as char),
+            }
+        }
+        Record {
+            id: opts.id.clone(),
+            description: String::new(),
+            sequence,
+        }
+    }
+
+    /// A new alignment holding columns `range` of every row
+    pub fn slice(&self, range: Range<usize>) -> Result<Alignment, FastaError> {
+        if range.start > range.end || range.end > self.width {
+            return Err(FastaError::OutOfBounds {
+                id: self.rows.first().map_or_else(String::new, |r| r.id.clone()),
+                start: range.start,
+                end: range.end,
+                len: self.width,
+            });
+        }
+        let rows = self
+            .rows
+            .iter()
+            .map(|r| Record {
+                id: r.id.clone(),
+                description: r.description.clone(),
+                sequence: r.sequence[range.clone()].to_owned(),
+            })
+            .collect();
+        Ok(Alignment {
+            rows,
+            width: range.end - range.start,
+        })
+    }
+}
+
+/// Iterator over the columns of an `Alignment`
+#[derive(Debug)]
+pub struct Columns<'a> {
+    aln: &'a Alignment,
+    next: usize,
+}
+
+impl<'a> Iterator for Columns<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let column = self.aln.column(self.next)?;
+        self.next += 1;
+        Some(column)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.aln.width - self.next.min(self.aln.width);
+        (left, Some(left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn columns_and_slices() {
+        let input = ">a\nAC-GT\n>b\nACTGT\n>c\nAG-GA\n";
+        let aln = Alignment::from_records(FastaBuffer::from(input.as_bytes())).unwrap();
+        assert_eq!((aln.len(), aln.width()), (3, 5));
+        assert_eq!(aln.column(2).unwrap(), b"-T-");
+        assert_eq!(aln.columns().count(), 5);
+        let counts = aln.column_counts(1).unwrap();
+        assert_eq!(counts.get(&b'C'), Some(&2));
+        assert_eq!(counts.get(&b'G'), Some(&1));
+
+        let sliced = aln.slice(1..3).unwrap();
+        assert_eq!(sliced.records()[1].sequence, "CT");
+        assert!(aln.slice(3..6).is_err());
+    }
+
+    #[test]
+    fn consensus_calls() {
+        let input = ">a\nACGT-A\n>b\nACGTTA\n>c\nAGCT-C\n>d\nATCT-C\n";
+        let aln = Alignment::from_records(FastaBuffer::from(input.as_bytes())).unwrap();
+        // Column 2 ties G/C (S), column 5 ties A/C (M)
+        assert_eq!(aln.consensus(0.5).sequence, "ACSTTM");
+        assert_eq!(aln.consensus(0.75).sequence, "ABSTTM");
+        let opts = ConsensusOptions {
+            ambiguity_codes: false,
+            gaps: GapHandling::Keep,
+            ..ConsensusOptions::default()
+        };
+        assert_eq!(aln.consensus_with(&opts).sequence, "ACNT-N");
+        let opts = ConsensusOptions {
+            gaps: GapHandling::Count,
+            ..ConsensusOptions::default()
+        };
+        assert_eq!(aln.consensus_with(&opts).sequence, "ACSTM");
+        // A threshold of 0 still calls tied columns by their top residues
+        assert_eq!(aln.consensus(0.0).sequence, "ACSTTM");
+    }
+
+    #[test]
+    fn unequal_lengths_rejected() {
+        let input = ">a\nACGT\n>b\nACG\n";
+        match Alignment::from_records(FastaBuffer::from(input.as_bytes())) {
+            Err(FastaError::LengthMismatch {
+                id,
+                expected,
+                found,
+            }) => {
+                assert_eq!((id.as_str(), expected, found), ("b", 4, 3))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Classic NCBI pipe-delimited identifiers
+//!
+//! IDs such as `gi|12345|ref|NM_000000.1|` are a chain of database tags,
+//! each followed by a fixed number of fields. `Record::ncbi_ids` splits
+//! them into structured form and `Record::set_canonical_id` replaces the
+//! ID by one of the components.
+
+use crate::Record;
+
+/// Number of fields following each known database tag
+const TAGS: &[(&str, usize)] = &[
+    ("bbs", 1),
+    ("dbj", 2),
+    ("emb", 2),
+    ("gb", 2),
+    ("gi", 1),
+    ("gnl", 2),
+    ("gpp", 2),
+    ("lcl", 1),
+    ("nat", 2),
+    ("pat", 3),
+    ("pdb", 2),
+    ("pir", 2),
+    ("prf", 2),
+    ("ref", 2),
+    ("sp", 2),
+    ("tpd", 2),
+    ("tpe", 2),
+    ("tpg", 2),
+    ("tr", 2),
+];
+
+/// Tags whose first field is a sequence accession, by preference
+const ACCESSION_TAGS: &[&str] = &[
+    "ref", "gb", "emb", "dbj", "sp", "tr", "tpg", "tpe", "tpd", "pir", "prf", "pdb",
+];
+
+fn field_count(tag: &str) -> Option<usize> {
+    TAGS.iter().find(|(t, _)| *t == tag).map(|&(_, n)| n)
+}
+
+/// One database tag and its fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NcbiId {
+    pub tag: String,
+    /// Fields after the tag; empty fields are kept as empty strings
+    pub fields: Vec<String>,
+}
+
+/// A parsed pipe-delimited identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NcbiIds {
+    pub ids: Vec<NcbiId>,
+}
+
+impl NcbiIds {
+    /// Parse an identifier made of known NCBI tags, e.g.
+    /// `gi|12345|ref|NM_000000.1|`. Returns None if the identifier has no
+    /// `|` or does not start with a known tag. Fields of an unknown tag
+    /// run up to the next known one.
+    pub fn parse(id: &str) -> Option<NcbiIds> {
+        if !id.contains('|') {
+            return None;
+        }
+        let parts: Vec<&str> = id.split('|').collect();
+        field_count(parts[0])?;
+        let mut ids = Vec::new();
+        let mut i = 0;
+        while i < parts.len() {
+            let tag = parts[i];
+            if tag.is_empty() && i == parts.len() - 1 {
+                break;
+            }
+            i += 1;
+            let n = match field_count(tag) {
+                Some(n) => n.min(parts.len() - i),
+                None => parts[i..]
+                    .iter()
+                    .position(|p| field_count(p).is_some())
+                    .unwrap_or(parts.len() - i),
+            };
+            let fields = parts[i..i + n].iter().map(|f| (*f).to_owned()).collect();
+            ids.push(NcbiId {
+                tag: tag.to_owned(),
+                fields,
+            });
+            i += n;
+        }
+        Some(NcbiIds { ids })
+    }
+
+    /// The first component with the given tag
+    pub fn get(&self, tag: &str) -> Option<&NcbiId> {
+        self.ids.iter().find(|id| id.tag == tag)
+    }
+
+    /// The GenInfo number, from `gi|...`
+    pub fn gi(&self) -> Option<&str> {
+        self.field(|id| id.tag == "gi")
+    }
+
+    /// The sequence accession, preferring RefSeq, then GenBank/ENA/DDBJ,
+    /// then UniProt and the other sequence databases
+    pub fn accession(&self) -> Option<&str> {
+        ACCESSION_TAGS
+            .iter()
+            .find_map(|tag| self.field(|id| id.tag == *tag))
+    }
+
+    /// First non-empty field of the first component matching `pred`
+    fn field<P: Fn(&NcbiId) -> bool>(&self, pred: P) -> Option<&str> {
+        self.ids
+            .iter()
+            .filter(|id| pred(id))
+            .find_map(|id| id.fields.first().filter(|f| !f.is_empty()))
+            .map(String::as_str)
+    }
+}
+
+/// Which component `Record::set_canonical_id` keeps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalId {
+    /// The GenInfo number
+    Gi,
+    /// The sequence accession (see `NcbiIds::accession`)
+    Accession,
+    /// The first field of the given tag, e.g. `lcl` or `gnl`; for `gnl`
+    /// the second field (the ID within the database) is used
+    Tag(String),
+}
+
+impl Record {
+    /// Parse the ID as an NCBI pipe-delimited identifier
+    pub fn ncbi_ids(&self) -> Option<NcbiIds> {
+        NcbiIds::parse(&self.id)
+    }
+
+    /// Replace a pipe-delimited ID by the chosen component. Returns false,
+    /// leaving the record unchanged, if the ID is not of that form or has
+    /// no such component.
+    pub fn set_canonical_id(&mut self, choice: &CanonicalId) -> bool {
+        let ids = match self.ncbi_ids() {
+            Some(ids) => ids,
+            None => return false,
+        };
+        let new = match choice {
+            CanonicalId::Gi => ids.gi(),
+            CanonicalId::Accession => ids.accession(),
+            CanonicalId::Tag(tag) if tag == "gnl" => ids
+                .get("gnl")
+                .and_then(|id| id.fields.get(1))
+                .map(String::as_str),
+            CanonicalId::Tag(tag) => ids.field(|id| &id.tag == tag),
+        };
+        match new.map(str::to_owned) {
+            Some(new) => {
+                self.id = new;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chains() {
+        let ids = NcbiIds::parse("gi|12345|ref|NM_000000.1|").unwrap();
+        assert_eq!(ids.ids.len(), 2);
+        assert_eq!(ids.gi(), Some("12345"));
+        assert_eq!(ids.accession(), Some("NM_000000.1"));
+        assert_eq!(ids.get("ref").unwrap().fields, ["NM_000000.1", ""]);
+
+        let sp = NcbiIds::parse("sp|P69905|HBA_HUMAN").unwrap();
+        assert_eq!(sp.get("sp").unwrap().fields, ["P69905", "HBA_HUMAN"]);
+        assert!(NcbiIds::parse("chr1").is_none());
+        assert!(NcbiIds::parse("foo|bar").is_none());
+    }
+
+    #[test]
+    fn canonical() {
+        let mut rec = Record {
+            id: "gi|7|gnl|taxon|9606".to_owned(),
+            ..Record::new()
+        };
+        assert!(!rec.set_canonical_id(&CanonicalId::Accession));
+        assert!(rec.set_canonical_id(&CanonicalId::Tag("gnl".to_owned())));
+        assert_eq!(rec.id, "9606");
+        assert!(!rec.set_canonical_id(&CanonicalId::Gi));
+    }
+}
@@ -0,0 +1,188 @@
+//! Interleaving and de-interleaving paired-end reads
+//!
+//! Mates are matched by read name: the ID with a trailing `/1` or `/2`
+//! removed. Casava 1.8 style IDs, which carry the read number in the
+//! description, match as they are.
+
+use std::io;
+use std::io::Write;
+
+use crate::errors::FastaError;
+use crate::fastq::FastqRecord;
+
+/// The read name shared by both mates of a pair
+pub fn read_name(id: &str) -> &str {
+    id.strip_suffix("/1")
+        .or_else(|| id.strip_suffix("/2"))
+        .unwrap_or(id)
+}
+
+fn unpaired(id: &str, mate: Option<&str>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::UnpairedRead {
+            id: id.to_owned(),
+            mate: mate.map(str::to_owned),
+        },
+    )
+}
+
+/// Iterator returned by `interleave`
+pub struct Interleave<I, J> {
+    r1: I,
+    r2: J,
+    mate: Option<FastqRecord>,
+    failed: bool,
+}
+
+impl<I, J> Iterator for Interleave<I, J>
+where
+    I: Iterator<Item = Result<FastqRecord, io::Error>>,
+    J: Iterator<Item = Result<FastqRecord, io::Error>>,
+{
+    type Item = Result<FastqRecord, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(mate) = self.mate.take() {
+            return Some(Ok(mate));
+        }
+        if self.failed {
+            return None;
+        }
+        let pair = match (self.r1.next(), self.r2.next()) {
+            (None, None) => return None,
+            (Some(Err(e)), _) | (_, Some(Err(e))) => Err(e),
+            (Some(Ok(a)), None) => Err(unpaired(&a.id, None)),
+            (None, Some(Ok(b))) => Err(unpaired(&b.id, None)),
+            (Some(Ok(a)), Some(Ok(b))) if read_name(&a.id) != read_name(&b.id) => {
+                Err(unpaired(&a.id, Some(&b.id)))
+            }
+            (Some(Ok(a)), Some(Ok(b))) => Ok((a, b)),
+        };
+        match pair {
+            Ok((a, b)) => {
+                self.mate = Some(b);
+                Some(Ok(a))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Merge R1 and R2 streams into one stream alternating R1 and R2 reads.
+///
+/// Yields an `UnpairedRead` error, and then stops, at the first pair whose
+/// names differ or when one stream ends before the other.
+pub fn interleave<I, J>(r1: I, r2: J) -> Interleave<I::IntoIter, J::IntoIter>
+where
+    I: IntoIterator<Item = Result<FastqRecord, io::Error>>,
+    J: IntoIterator<Item = Result<FastqRecord, io::Error>>,
+{
+    Interleave {
+        r1: r1.into_iter(),
+        r2: r2.into_iter(),
+        mate: None,
+        failed: false,
+    }
+}
+
+/// Counts returned by `deinterleave`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeinterleaveReport {
+    /// Pairs written, one read to each writer
+    pub pairs: usize,
+    /// IDs of reads without an adjacent mate, in input order; these are
+    /// not written
+    pub orphans: Vec<String>,
+}
+
+/// Split an interleaved stream, writing first mates to `w1` and second
+/// mates to `w2`.
+///
+/// A read not followed by a read of the same name is an orphan: it is
+/// left out and its ID reported, and pairing resumes with the next read.
+pub fn deinterleave<I, W1, W2>(
+    records: I,
+    w1: &mut W1,
+    w2: &mut W2,
+) -> io::Result<DeinterleaveReport>
+where
+    I: IntoIterator<Item = Result<FastqRecord, io::Error>>,
+    W1: Write,
+    W2: Write,
+{
+    let mut report = DeinterleaveReport::default();
+    let mut pending: Option<FastqRecord> = None;
+    for rec in records {
+        let rec = rec?;
+        match pending.take() {
+            Some(first) if read_name(&first.id) == read_name(&rec.id) => {
+                first.write(w1)?;
+                rec.write(w2)?;
+                report.pairs += 1;
+            }
+            Some(first) => {
+                report.orphans.push(first.id);
+                pending = Some(rec);
+            }
+            None => pending = Some(rec),
+        }
+    }
+    if let Some(last) = pending {
+        report.orphans.push(last.id);
+    }
+    w1.flush()?;
+    w2.flush()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fastq::FastqReader;
+
+    fn reads(ids: &[&str]) -> Vec<Result<FastqRecord, io::Error>> {
+        ids.iter()
+            .map(|id| {
+                Ok(FastqRecord {
+                    id: (*id).to_owned(),
+                    sequence: "A".to_owned(),
+                    quality: "I".to_owned(),
+                    ..FastqRecord::new()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleave_pairs() {
+        let ids: Vec<String> = interleave(reads(&["a/1", "b/1"]), reads(&["a/2", "b/2"]))
+            .map(|r| r.unwrap().id)
+            .collect();
+        assert_eq!(ids, ["a/1", "a/2", "b/1", "b/2"]);
+
+        let mut mixed = interleave(reads(&["a/1", "b/1"]), reads(&["a/2", "c/2"]));
+        assert!(mixed.nth(2).unwrap().is_err());
+        assert!(mixed.next().is_none());
+        assert!(interleave(reads(&["a"]), reads(&[]))
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn deinterleave_orphans() {
+        let input = "@a/1\nA\n+\nI\n@a/2\nC\n+\nI\n@b/1\nG\n+\nI\n@c/1\nT\n+\nI\n@c/2\nA\n+\nI\n";
+        let (mut w1, mut w2) = (Vec::new(), Vec::new());
+        let report = deinterleave(FastqReader::new(input.as_bytes()), &mut w1, &mut w2).unwrap();
+        assert_eq!(report.pairs, 2);
+        assert_eq!(report.orphans, ["b/1"]);
+        assert_eq!(
+            String::from_utf8(w2).unwrap(),
+            "@a/2\nC\n+\nI\n@c/2\nA\n+\nI\n"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! The FASTA parsing state machine
+//!
+//! `Parser` is fed one line at a time and needs only `core` and `alloc`,
+//! so it also builds without the `std` feature, for embedded and WASM
+//! targets. `FastaBuffer` drives it from a `BufRead`; `parse_slice`
+//! drives it over bytes already in memory.
+
+use alloc::string::String;
+use core::fmt;
+
+use crate::{Case, Comments, Record};
+
+/// Characters accepted in sequence lines. All alphabets ignore case and
+/// accept the gap characters `-` and `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alphabet {
+    /// No check
+    #[default]
+    Any,
+    /// IUPAC nucleotide codes with T
+    Dna,
+    /// IUPAC nucleotide codes with U
+    Rna,
+    /// IUPAC amino acid codes, including B, J, O, U, X, Z and `*`
+    Protein,
+}
+
+impl Alphabet {
+    /// Whether `b` may appear in a sequence line
+    pub fn allows(self, b: u8) -> bool {
+        let b = b.to_ascii_uppercase();
+        match self {
+            Alphabet::Any => true,
+            _ if b == b'-' || b == b'.' => true,
+            Alphabet::Dna => b"ACGTRYKMSWBDHVN".contains(&b),
+            Alphabet::Rna => b"ACGURYKMSWBDHVN".contains(&b),
+            Alphabet::Protein => b.is_ascii_uppercase() || b == b'*',
+        }
+    }
+}
+
+/// The parsing options of a `FastaReaderBuilder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub trim_whitespace: bool,
+    pub comments: Comments,
+    pub allow_blank_lines: bool,
+    pub allow_leading_junk: bool,
+    pub case: Case,
+    pub alphabet: Alphabet,
+}
+
+impl Default for ParseOptions {
+    /// The defaults of `FastaReaderBuilder::new`
+    fn default() -> ParseOptions {
+        ParseOptions {
+            trim_whitespace: true,
+            comments: Comments::Semicolon,
+            allow_blank_lines: true,
+            allow_leading_junk: true,
+            case: Case::Preserve,
+            alphabet: Alphabet::Any,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The options of `FastaReaderBuilder::strict`: no comment lines, no
+    /// blank lines inside records and nothing before the first header
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            comments: Comments::None,
+            allow_blank_lines: false,
+            allow_leading_junk: false,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+/// Input rejected by a `Parser`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// A blank line followed by more sequence, with blank lines disallowed
+    BlankLine,
+    /// A non-header line before the first header, with junk disallowed
+    LeadingJunk,
+    /// A line of `parse_slice` input is not valid UTF-8
+    InvalidUtf8,
+    /// A sequence line has a byte outside the alphabet, at the given
+    /// 1-based byte column of the line as pushed
+    InvalidByte { column: usize, byte: u8 },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::BlankLine => f.write_str("blank line inside FASTA record"),
+            ParseError::LeadingJunk => f.write_str("unexpected data before first FASTA header"),
+            ParseError::InvalidUtf8 => f.write_str("stream did not contain valid UTF-8"),
+            ParseError::InvalidByte { column, byte } => write!(
+                f,
+                "unexpected byte 0x{:02x} in sequence at column {}",
+                byte, column
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// How `Parser::push_line` handled a line
+#[derive(Debug)]
+pub enum Step {
+    /// The line was the header of a new record
+    Header,
+    /// The line was added to the current record's sequence
+    Sequence,
+    /// The line was a comment or a blank line, or belonged to a record
+    /// discarded after an error
+    Skipped,
+    /// The line was leading junk before the first header, allowed by
+    /// `allow_leading_junk`
+    Junk,
+    /// The line is a header ending the current record, which is returned.
+    /// The line was not consumed: push it again to start the next record.
+    Done(Record),
+}
+
+/// A line-at-a-time FASTA parser
+#[derive(Debug)]
+pub struct Parser {
+    opts: ParseOptions,
+    rec: Option<Record>,
+    // Cleared record whose buffers the next header reuses
+    spare: Option<Record>,
+    pending_blank: bool,
+    // A record was discarded after an error; drop lines until the next
+    // header
+    skipping: bool,
+    seq_len: usize,
+}
+
+impl Parser {
+    pub fn new(opts: ParseOptions) -> Parser {
+        Parser {
+            opts,
+            rec: None,
+            spare: None,
+            pending_blank: false,
+            skipping: false,
+            seq_len: 0,
+        }
+    }
+
+    /// Hand back a record whose allocations the next record should reuse
+    pub fn recycle(&mut self, mut rec: Record) {
+        rec.id.clear();
+        rec.description.clear();
+        rec.sequence.clear();
+        self.spare = Some(rec);
+    }
+
+    /// ID of the record being read, empty before the first header
+    pub fn id(&self) -> &str {
+        self.rec.as_ref().map_or("", |rec| rec.id.as_str())
+    }
+
+    /// The record being read, holding the sequence kept so far
+    pub fn record(&self) -> Option<&Record> {
+        self.rec.as_ref()
+    }
+
+    /// Move the sequence kept so far onto the end of `into`
+    pub fn take_sequence(&mut self, into: &mut String) {
+        if let Some(rec) = &mut self.rec {
+            into.push_str(&rec.sequence);
+            rec.sequence.clear();
+        }
+    }
+
+    /// Sequence length of the record being read, or of the record last
+    /// returned, counted even when the sequence is not kept
+    pub fn seq_len(&self) -> usize {
+        self.seq_len
+    }
+
+    /// Feed one line, without its line ending. Sequence is accumulated
+    /// only if `keep` is set. An error discards the record being read.
+    pub fn push_line(&mut self, line: &str, keep: bool) -> Result<Step, ParseError> {
+        let (line, lead) = if self.opts.trim_whitespace {
+            let trimmed = line.trim_start();
+            (trimmed.trim_end(), line.len() - trimmed.len())
+        } else {
+            (line, 0)
+        };
+        let step = match (line.chars().next(), self.rec.as_mut()) {
+            (Some(';'), _) if self.opts.comments == Comments::Semicolon => Step::Skipped,
+            (Some('#'), _) if self.opts.comments == Comments::Hash => Step::Skipped,
+            (None, rec) => {
+                self.pending_blank = rec.is_some();
+                Step::Skipped
+            }
+            (Some('>'), Some(_)) => {
+                self.pending_blank = false;
+                return Ok(Step::Done(self.rec.take().unwrap_or_default()));
+            }
+            (Some('>'), None) => {
+                self.skipping = false;
+                let mut rec = self.spare.take().unwrap_or_default();
+                rec.set_header(line);
+                self.rec = Some(rec);
+                self.seq_len = 0;
+                Step::Header
+            }
+            (Some(_), Some(rec)) => {
+                if self.pending_blank && !self.opts.allow_blank_lines {
+                    self.reset();
+                    return Err(ParseError::BlankLine);
+                }
+                if self.opts.alphabet != Alphabet::Any {
+                    let alphabet = self.opts.alphabet;
+                    if let Some(i) = line.bytes().position(|b| !alphabet.allows(b)) {
+                        let byte = line.as_bytes()[i];
+                        self.reset();
+                        return Err(ParseError::InvalidByte {
+                            column: lead + i + 1,
+                            byte,
+                        });
+                    }
+                }
+                self.seq_len += line.len();
+                if keep {
+                    push_sequence(&mut rec.sequence, line, self.opts.case);
+                }
+                Step::Sequence
+            }
+            (Some(_), None) if self.skipping => Step::Skipped,
+            (Some(_), None) if !self.opts.allow_leading_junk => {
+                return Err(ParseError::LeadingJunk);
+            }
+            (Some(_), None) => Step::Junk,
+        };
+        Ok(step)
+    }
+
+    /// Signal the end of the input, returning the last record
+    pub fn finish(&mut self) -> Option<Record> {
+        self.pending_blank = false;
+        self.skipping = false;
+        self.rec.take()
+    }
+
+    /// Discard the record being read and the rest of its lines
+    fn reset(&mut self) {
+        self.rec = None;
+        self.pending_blank = false;
+        self.skipping = true;
+    }
+}
+
+fn push_sequence(sequence: &mut String, line: &str, case: Case) {
+    match case {
+        Case::Preserve => sequence.push_str(line),
+        Case::Upper => sequence.push_str(&line.to_uppercase()),
+        Case::Lower => sequence.push_str(&line.to_lowercase()),
+    }
+}
+
+/// An iterator over the records in a byte slice, created by `parse_slice`
+pub struct SliceRecords<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    parser: Parser,
+}
+
+impl<'a> SliceRecords<'a> {
+    /// The next line and the position after its line ending
+    fn next_line(&self) -> Option<(&'a [u8], usize)> {
+        let rest: &'a [u8] = self.bytes.get(self.pos..).filter(|r| !r.is_empty())?;
+        Some(match memchr::memchr2(b'\n', b'\r', rest) {
+            Some(i) if rest[i] == b'\r' && rest.get(i + 1) == Some(&b'\n') => {
+                (&rest[..i], self.pos + i + 2)
+            }
+            Some(i) => (&rest[..i], self.pos + i + 1),
+            None => (rest, self.bytes.len()),
+        })
+    }
+}
+
+impl<'a> Iterator for SliceRecords<'a> {
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((line, next)) = self.next_line() {
+            let line = match core::str::from_utf8(line) {
+                Ok(line) => line,
+                Err(_) => {
+                    self.pos = next;
+                    self.parser.reset();
+                    return Some(Err(ParseError::InvalidUtf8));
+                }
+            };
+            match self.parser.push_line(line, true) {
+                Ok(Step::Done(rec)) => return Some(Ok(rec)),
+                Ok(_) => self.pos = next,
+                Err(e) => {
+                    self.pos = next;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.parser.finish().map(Ok)
+    }
+}
+
+/// Parse the FASTA records in `bytes`, which may use `\n`, `\r\n` or
+/// `\r` line endings
+pub fn parse_slice(bytes: &[u8], opts: ParseOptions) -> SliceRecords<'_> {
+    SliceRecords {
+        bytes,
+        pos: 0,
+        parser: Parser::new(opts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_records() {
+        let input = b";comment\r\n>a one\r\nAC\r\nGT\r\n\r\n>b\rNN";
+        let records: Vec<Record> = parse_slice(input, ParseOptions::default())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].description, "one");
+        assert_eq!(records[0].sequence, "ACGT");
+        assert_eq!(records[1].sequence, "NN");
+
+        let strict = ParseOptions {
+            allow_blank_lines: false,
+            ..ParseOptions::default()
+        };
+        let mut records = parse_slice(b">a\nAC\n\nGT\n>b\nA\n", strict);
+        assert!(matches!(records.next(), Some(Err(ParseError::BlankLine))));
+        assert_eq!(records.next().unwrap().unwrap().id, "b");
+
+        // The rest of a rejected record is dropped without further errors
+        let input = b">a\nAC\n\nGT\nGG\n>b\nAXC\nTT\n>c\nA\n";
+        let strict = ParseOptions {
+            alphabet: Alphabet::Dna,
+            ..ParseOptions::strict()
+        };
+        let mut records = parse_slice(input, strict);
+        assert!(matches!(records.next(), Some(Err(ParseError::BlankLine))));
+        assert!(matches!(
+            records.next(),
+            Some(Err(ParseError::InvalidByte { column: 2, .. }))
+        ));
+        assert_eq!(records.next().unwrap().unwrap().id, "c");
+        assert!(records.next().is_none());
+    }
+}
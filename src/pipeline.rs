@@ -0,0 +1,97 @@
+//! Streaming read-transform-write over FASTA records
+
+use std::io;
+use std::io::Write;
+
+use crate::Record;
+
+/// What to do with a record passed to `process`
+#[derive(Debug)]
+pub enum Action {
+    /// Write the record unchanged
+    Keep,
+    /// Write the given record in place of the original
+    Modify(Record),
+    /// Do not write the record
+    Drop,
+}
+
+/// Counts of the actions taken by `process`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessSummary {
+    pub kept: usize,
+    pub modified: usize,
+    pub dropped: usize,
+}
+
+/// Apply `f` to each record and write the results to `writer`.
+///
+/// Records are handled one at a time, so memory use is bounded by the
+/// largest single record. The first error from the input or the writer
+/// stops processing and is returned; on an input error (including
+/// cancellation) the records already written are flushed first.
+pub fn process<I, W, F>(records: I, writer: &mut W, mut f: F) -> io::Result<ProcessSummary>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(&Record) -> Action,
+{
+    let mut summary = ProcessSummary::default();
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                writer.flush()?;
+                return Err(e);
+            }
+        };
+        match f(&rec) {
+            Action::Keep => {
+                rec.write(writer)?;
+                summary.kept += 1;
+            }
+            Action::Modify(new) => {
+                new.write(writer)?;
+                summary.modified += 1;
+            }
+            Action::Drop => summary.dropped += 1,
+        }
+    }
+    writer.flush()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn keep_modify_drop() {
+        let input = ">a\nACGT\n>b\nAC\n>c\nacgt\n";
+        let mut out = Vec::new();
+        let summary = process(
+            FastaBuffer::from(input.as_bytes()),
+            &mut out,
+            |rec| match rec.id.as_str() {
+                "a" => Action::Keep,
+                "b" => Action::Drop,
+                _ => Action::Modify(Record {
+                    id: rec.id.clone(),
+                    description: String::new(),
+                    sequence: rec.sequence.to_uppercase(),
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            ProcessSummary {
+                kept: 1,
+                modified: 1,
+                dropped: 1
+            }
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), ">a\nACGT\n>c\nACGT\n");
+    }
+}
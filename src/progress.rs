@@ -0,0 +1,157 @@
+//! Progress reporting for long parses
+//!
+//! `ProgressReader` parses like a `FastaBuffer` and calls a callback
+//! with the bytes consumed and records emitted so far each time an
+//! interval passes, and once more when the input is exhausted.
+
+use std::io;
+use std::io::{BufRead, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::{FastaBuffer, FastaReaderBuilder, Record};
+
+/// Bytes consumed and records emitted so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub bytes: u64,
+    pub records: u64,
+}
+
+/// How often `ProgressReader` reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    /// After every `n` records
+    Records(u64),
+    /// Whenever another `n` bytes have been consumed
+    Bytes(u64),
+}
+
+/// A BufRead adapter counting the bytes consumed from it
+struct Counting<B: BufRead> {
+    inner: B,
+    bytes: Arc<AtomicU64>,
+}
+
+impl<B: BufRead> Read for Counting<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl<B: BufRead> BufRead for Counting<B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.bytes.fetch_add(amt as u64, Ordering::Relaxed);
+        self.inner.consume(amt);
+    }
+}
+
+/// A FASTA reader that reports its progress to a callback
+pub struct ProgressReader<B: BufRead, F: FnMut(Progress)> {
+    records: FastaBuffer<Counting<B>>,
+    bytes: Arc<AtomicU64>,
+    count: u64,
+    interval: Interval,
+    // Progress at which the next report is due
+    next_report: u64,
+    callback: F,
+    done: bool,
+}
+
+impl<B: BufRead, F: FnMut(Progress)> ProgressReader<B, F> {
+    /// Parse `b` with the options of `builder`, reporting every
+    /// `interval`. Panics if the interval is 0.
+    pub fn new(builder: &FastaReaderBuilder, b: B, interval: Interval, callback: F) -> Self {
+        let step = match interval {
+            Interval::Records(n) | Interval::Bytes(n) => n,
+        };
+        assert!(step > 0, "progress interval must be non-zero");
+        let bytes = Arc::new(AtomicU64::new(0));
+        let inner = Counting {
+            inner: b,
+            bytes: Arc::clone(&bytes),
+        };
+        ProgressReader {
+            records: builder.from_reader(inner),
+            bytes,
+            count: 0,
+            interval,
+            next_report: step,
+            callback,
+            done: false,
+        }
+    }
+
+    /// Progress so far
+    pub fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.bytes.load(Ordering::Relaxed),
+            records: self.count,
+        }
+    }
+}
+
+impl<B: BufRead, F: FnMut(Progress)> Iterator for ProgressReader<B, F> {
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.records.next();
+        if next.as_ref().is_some_and(Result::is_ok) {
+            self.count += 1;
+        }
+        let progress = self.progress();
+        if next.is_none() {
+            self.done = true;
+            (self.callback)(progress);
+            return None;
+        }
+        let (value, step) = match self.interval {
+            Interval::Records(n) => (progress.records, n),
+            Interval::Bytes(n) => (progress.bytes, n),
+        };
+        if value >= self.next_report {
+            (self.callback)(progress);
+            self.next_report = (value / step + 1) * step;
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_at_intervals() {
+        let data: String = (0..5).map(|i| format!(">r{}\nACGT\n", i)).collect();
+        let mut seen = Vec::new();
+        let reader = ProgressReader::new(
+            &FastaReaderBuilder::new(),
+            data.as_bytes(),
+            Interval::Records(2),
+            |p| seen.push(p.records),
+        );
+        assert_eq!(reader.count(), 5);
+        assert_eq!(seen, [2, 4, 5]);
+
+        let mut bytes = Vec::new();
+        let reader = ProgressReader::new(
+            &FastaReaderBuilder::new(),
+            data.as_bytes(),
+            Interval::Bytes(20),
+            |p| bytes.push(p.bytes),
+        );
+        reader.for_each(drop);
+        assert_eq!(*bytes.last().unwrap(), data.len() as u64);
+        assert!(bytes.len() >= 2);
+    }
+}
@@ -0,0 +1,228 @@
+//! Python bindings
+//!
+//! Built with the `python` feature as an extension module named `fasta`,
+//! for example with `maturin develop` (see `pyproject.toml`):
+//!
+//! ```python
+//! import fasta
+//! for rec in fasta.Reader("reads.fa"):
+//!     print(rec.id, len(rec), rec.gc_content())
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::stats::Stats;
+use crate::FastaBuffer;
+
+/// A FASTA record with `id`, `description` and `sequence` attributes
+#[pyclass(name = "Record", module = "fasta")]
+pub struct PyRecord {
+    inner: crate::Record,
+}
+
+#[pymethods]
+impl PyRecord {
+    #[new]
+    #[pyo3(signature = (id, sequence, description = String::new()))]
+    fn new(id: String, sequence: String, description: String) -> PyRecord {
+        PyRecord {
+            inner: crate::Record {
+                id,
+                description,
+                sequence,
+            },
+        }
+    }
+
+    #[getter]
+    fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: String) {
+        self.inner.id = id;
+    }
+
+    #[getter]
+    fn description(&self) -> &str {
+        &self.inner.description
+    }
+
+    #[setter]
+    fn set_description(&mut self, description: String) {
+        self.inner.description = description;
+    }
+
+    #[getter]
+    fn sequence(&self) -> &str {
+        &self.inner.sequence
+    }
+
+    #[setter]
+    fn set_sequence(&mut self, sequence: String) {
+        self.inner.sequence = sequence;
+    }
+
+    fn gc_content(&self) -> f64 {
+        self.inner.gc_content()
+    }
+
+    fn reverse_complement(&self) -> PyRecord {
+        PyRecord {
+            inner: self.inner.reverse_complement(),
+        }
+    }
+
+    /// The record as FASTA text, wrapped at `width` (0 for one line)
+    #[pyo3(signature = (width = 80))]
+    fn to_fasta(&self, width: usize) -> String {
+        self.inner.as_string_with_width(width)
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.sequence.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Record(id={:?}, length={})",
+            self.inner.id,
+            self.inner.sequence.len()
+        )
+    }
+}
+
+/// Iterates over the records of a FASTA file
+#[pyclass(name = "Reader", module = "fasta")]
+pub struct PyReader {
+    records: FastaBuffer<BufReader<File>>,
+}
+
+#[pymethods]
+impl PyReader {
+    #[new]
+    fn new(path: &str) -> PyResult<PyReader> {
+        let file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(PyReader {
+            records: FastaBuffer::from(BufReader::new(file)),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<PyRecord>> {
+        match self.records.next() {
+            Some(Ok(inner)) => Ok(Some(PyRecord { inner })),
+            Some(Err(e)) => Err(PyValueError::new_err(e.to_string())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Writes records to a FASTA file; usable as a context manager
+#[pyclass(name = "Writer", module = "fasta")]
+pub struct PyWriter {
+    out: Option<BufWriter<File>>,
+    width: usize,
+}
+
+impl PyWriter {
+    fn out(&mut self) -> PyResult<&mut BufWriter<File>> {
+        self.out
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("writer is closed"))
+    }
+}
+
+#[pymethods]
+impl PyWriter {
+    #[new]
+    #[pyo3(signature = (path, width = 80))]
+    fn new(path: &str, width: usize) -> PyResult<PyWriter> {
+        let file = File::create(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(PyWriter {
+            out: Some(BufWriter::new(file)),
+            width,
+        })
+    }
+
+    fn write(&mut self, record: &PyRecord) -> PyResult<()> {
+        let width = self.width;
+        record
+            .inner
+            .write_with_width(self.out()?, width)
+            .map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    fn close(&mut self) -> PyResult<()> {
+        if let Some(mut out) = self.out.take() {
+            out.flush().map_err(|e| PyIOError::new_err(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, _args: &Bound<'_, pyo3::types::PyTuple>) -> PyResult<bool> {
+        self.close()?;
+        Ok(false)
+    }
+}
+
+/// Parse FASTA text into a list of records
+#[pyfunction]
+fn parse(text: &str) -> PyResult<Vec<PyRecord>> {
+    FastaBuffer::from(text.as_bytes())
+        .map(|rec| {
+            rec.map(|inner| PyRecord { inner })
+                .map_err(|e| PyValueError::new_err(e.to_string()))
+        })
+        .collect()
+}
+
+/// Summary statistics of a FASTA file, or of a list of records
+#[pyfunction]
+fn stats<'py>(py: Python<'py>, source: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyDict>> {
+    let stats = if let Ok(path) = source.extract::<String>() {
+        let file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Stats::from_records(FastaBuffer::from(BufReader::new(file)))
+            .map_err(|e| PyValueError::new_err(e.to_string()))?
+    } else {
+        let mut stats = Stats::new();
+        for rec in source.try_iter()? {
+            stats.add(&rec?.extract::<PyRef<'_, PyRecord>>()?.inner);
+        }
+        stats
+    };
+    let dict = PyDict::new(py);
+    dict.set_item("records", stats.records)?;
+    dict.set_item("total_length", stats.total_length)?;
+    dict.set_item("min_length", stats.min_length)?;
+    dict.set_item("max_length", stats.max_length)?;
+    dict.set_item("mean_length", stats.mean_length())?;
+    dict.set_item("n50", stats.n50())?;
+    dict.set_item("gc_content", stats.gc_content())?;
+    dict.set_item("n_fraction", stats.n_fraction())?;
+    Ok(dict)
+}
+
+#[pymodule]
+fn fasta(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRecord>()?;
+    m.add_class::<PyReader>()?;
+    m.add_class::<PyWriter>()?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(stats, m)?)?;
+    Ok(())
+}
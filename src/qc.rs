@@ -0,0 +1,210 @@
+//! Quality trimming and filtering of FASTQ reads
+//!
+//! `quality_filter` trims each read and then drops it if it is too short
+//! or its mean quality too low.
+
+use std::io;
+
+use crate::fastq::{FastqRecord, PhredEncoding};
+
+/// Trimming steps and filters applied by `quality_filter`.
+///
+/// Trimming runs in the order leading, sliding window, trailing; the
+/// filters are checked on the trimmed read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QcOptions {
+    /// Clip bases from the start while their quality is below this
+    pub leading: Option<u8>,
+    /// Clip bases from the end while their quality is below this
+    pub trailing: Option<u8>,
+    /// `(width, quality)`: scanning from the start, cut the read at the
+    /// first window of `width` bases whose mean quality is below
+    /// `quality`, keeping the bases before that window
+    pub window: Option<(usize, u8)>,
+    /// Drop reads shorter than this after trimming
+    pub min_length: usize,
+    /// Drop reads whose mean quality after trimming is below this
+    pub min_mean_quality: Option<f64>,
+    /// How the qualities are encoded
+    pub encoding: PhredEncoding,
+}
+
+impl Default for QcOptions {
+    /// No trimming and no filters except dropping reads trimmed to
+    /// nothing; Phred+33 qualities
+    fn default() -> QcOptions {
+        QcOptions {
+            leading: None,
+            trailing: None,
+            window: None,
+            min_length: 1,
+            min_mean_quality: None,
+            encoding: PhredEncoding::Phred33,
+        }
+    }
+}
+
+fn mean(scores: &[u8]) -> f64 {
+    if scores.is_empty() {
+        return 0.0;
+    }
+    scores.iter().map(|&q| f64::from(q)).sum::<f64>() / scores.len() as f64
+}
+
+/// Mean Phred+33 quality of a read, 0 for an empty read
+pub fn mean_quality(rec: &FastqRecord) -> f64 {
+    mean(&rec.quality_scores(PhredEncoding::Phred33))
+}
+
+/// The range of bases kept by the trimming steps of `opts`
+fn trimmed_range(q: &[u8], opts: &QcOptions) -> (usize, usize) {
+    let mut start = 0;
+    let mut end = q.len();
+    if let Some(min) = opts.leading {
+        while start < end && q[start] < min {
+            start += 1;
+        }
+    }
+    if let Some((width, min)) = opts.window {
+        assert!(width > 0, "quality window width must be positive");
+        let needed = u64::from(min) * width as u64;
+        if end - start >= width {
+            let mut sum: u64 = q[start..start + width].iter().map(|&v| u64::from(v)).sum();
+            let mut i = start;
+            loop {
+                if sum < needed {
+                    end = i;
+                    break;
+                }
+                if i + width == end {
+                    break;
+                }
+                sum = sum + u64::from(q[i + width]) - u64::from(q[i]);
+                i += 1;
+            }
+        }
+    }
+    if let Some(min) = opts.trailing {
+        while end > start && q[end - 1] < min {
+            end -= 1;
+        }
+    }
+    (start, end)
+}
+
+/// Apply the trimming steps of `opts` to a read in place
+pub fn trim(rec: &mut FastqRecord, opts: &QcOptions) {
+    let q = rec.quality_scores(opts.encoding);
+    let (start, end) = trimmed_range(&q, opts);
+    rec.truncate_to(start, end);
+}
+
+/// Iterator returned by `quality_filter`
+pub struct QualityFilter<I> {
+    records: I,
+    opts: QcOptions,
+    dropped: usize,
+}
+
+impl<I> QualityFilter<I> {
+    /// Number of reads dropped so far
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl<I> Iterator for QualityFilter<I>
+where
+    I: Iterator<Item = Result<FastqRecord, io::Error>>,
+{
+    type Item = Result<FastqRecord, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut rec = match self.records.next()? {
+                Ok(rec) => rec,
+                Err(e) => return Some(Err(e)),
+            };
+            trim(&mut rec, &self.opts);
+            let too_short = rec.sequence.len() < self.opts.min_length;
+            let too_poor = self
+                .opts
+                .min_mean_quality
+                .is_some_and(|min| mean(&rec.quality_scores(self.opts.encoding)) < min);
+            if too_short || too_poor {
+                self.dropped += 1;
+                continue;
+            }
+            return Some(Ok(rec));
+        }
+    }
+}
+
+/// Trim each read with `opts` and keep those passing its filters.
+///
+/// Streams one read at a time. Panics on a sliding window of width 0.
+pub fn quality_filter<I>(records: I, opts: QcOptions) -> QualityFilter<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<FastqRecord, io::Error>>,
+{
+    QualityFilter {
+        records: records.into_iter(),
+        opts,
+        dropped: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fastq::FastqReader;
+
+    fn read(quality: &str) -> FastqRecord {
+        FastqRecord {
+            sequence: "A".repeat(quality.len()),
+            quality: quality.to_owned(),
+            ..FastqRecord::new()
+        }
+    }
+
+    #[test]
+    fn trimming_steps() {
+        // '#' is Q2, '5' Q20, 'I' Q40
+        let opts = QcOptions {
+            leading: Some(10),
+            trailing: Some(10),
+            ..QcOptions::default()
+        };
+        let mut r = read("##II5I##");
+        trim(&mut r, &opts);
+        assert_eq!(r.quality, "II5I");
+
+        let opts = QcOptions {
+            window: Some((2, 20)),
+            ..QcOptions::default()
+        };
+        let mut r = read("III5#III");
+        trim(&mut r, &opts);
+        assert_eq!(r.quality, "III");
+        let mut r = read("IIII");
+        trim(&mut r, &opts);
+        assert_eq!(r.quality, "IIII");
+    }
+
+    #[test]
+    fn filters() {
+        let input =
+            "@good\nACGTACGT\n+\nIIIIIII#\n@short\nACGT\n+\n##II\n@poor\nACGTAC\n+\n555555\n";
+        let opts = QcOptions {
+            trailing: Some(10),
+            min_length: 3,
+            min_mean_quality: Some(30.0),
+            ..QcOptions::default()
+        };
+        let mut reads = quality_filter(FastqReader::new(input.as_bytes()), opts);
+        let kept: Vec<FastqRecord> = reads.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].sequence, "ACGTACG");
+        assert_eq!(reads.dropped(), 2);
+    }
+}
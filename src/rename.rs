@@ -0,0 +1,194 @@
+//! Renaming and sanitizing record IDs
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use crate::errors::FastaError;
+use crate::Record;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Prefix(String),
+    Suffix(String),
+    Map(HashMap<String, String>),
+    Sanitize(Option<usize>),
+    #[cfg(feature = "regex")]
+    Replace(Regex, String),
+}
+
+/// Renamer applies a chain of ID transformations to records.
+///
+/// Operations run in the order they were added. Renaming a set of
+/// records fails with `FastaError::DuplicateId` if two different IDs
+/// would end up with the same name.
+#[derive(Debug, Clone, Default)]
+pub struct Renamer {
+    ops: Vec<Op>,
+}
+
+/// Characters kept by `Renamer::sanitize`; everything else becomes `_`
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+}
+
+impl Renamer {
+    /// Returns a Renamer that leaves IDs unchanged
+    pub fn new() -> Renamer {
+        Renamer::default()
+    }
+
+    /// Prepend `prefix` to every ID
+    pub fn prefix(mut self, prefix: &str) -> Renamer {
+        self.ops.push(Op::Prefix(prefix.to_owned()));
+        self
+    }
+
+    /// Append `suffix` to every ID
+    pub fn suffix(mut self, suffix: &str) -> Renamer {
+        self.ops.push(Op::Suffix(suffix.to_owned()));
+        self
+    }
+
+    /// Replace IDs found in `table` with their mapped value; other IDs
+    /// are left unchanged
+    pub fn map(mut self, table: HashMap<String, String>) -> Renamer {
+        self.ops.push(Op::Map(table));
+        self
+    }
+
+    /// Replace characters other than ASCII letters, digits, `.`, `_` and
+    /// `-` with `_`, and truncate IDs longer than `max_len` characters
+    pub fn sanitize(mut self, max_len: Option<usize>) -> Renamer {
+        self.ops.push(Op::Sanitize(max_len));
+        self
+    }
+
+    /// Replace all matches of `re` using `template`, which may refer to
+    /// capture groups as in `Regex::replace_all`
+    #[cfg(feature = "regex")]
+    pub fn replace(mut self, re: Regex, template: &str) -> Renamer {
+        self.ops.push(Op::Replace(re, template.to_owned()));
+        self
+    }
+
+    /// The new name for a single ID
+    pub fn apply(&self, id: &str) -> String {
+        let mut id = id.to_owned();
+        for op in &self.ops {
+            id = match op {
+                Op::Prefix(p) => format!("{}{}", p, id),
+                Op::Suffix(s) => format!("{}{}", id, s),
+                Op::Map(table) => table.get(&id).cloned().unwrap_or(id),
+                Op::Sanitize(max_len) => {
+                    let clean = id.chars().map(|c| if is_safe(c) { c } else { '_' });
+                    match max_len {
+                        Some(n) => clean.take(*n).collect(),
+                        None => clean.collect(),
+                    }
+                }
+                #[cfg(feature = "regex")]
+                Op::Replace(re, template) => re.replace_all(&id, template.as_str()).into_owned(),
+            };
+        }
+        id
+    }
+
+    /// Compute `(old, new)` names for a set of IDs without modifying anything.
+    ///
+    /// Fails if two distinct IDs would be given the same name. IDs that
+    /// were already duplicated in the input are not treated as collisions.
+    pub fn plan<'a, I>(&self, ids: I) -> Result<Vec<(String, String)>, FastaError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut seen: HashMap<String, String> = HashMap::new();
+        let mut plan = Vec::new();
+        for old in ids {
+            let new = self.apply(old);
+            match seen.get(&new) {
+                Some(prev) if prev != old => return Err(FastaError::DuplicateId(new)),
+                Some(_) => (),
+                None => {
+                    seen.insert(new.clone(), old.to_owned());
+                }
+            }
+            plan.push((old.to_owned(), new));
+        }
+        Ok(plan)
+    }
+
+    /// Rename records in place, returning the number of IDs that changed.
+    /// On a collision no record is modified.
+    pub fn rename(&self, records: &mut [Record]) -> Result<usize, FastaError> {
+        let plan = self.plan(records.iter().map(|r| r.id.as_str()))?;
+        let mut changed = 0;
+        for (rec, (old, new)) in records.iter_mut().zip(plan) {
+            if old != new {
+                rec.id = new;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Read an old→new ID table from two whitespace-separated columns.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_map<R: BufRead>(r: R) -> io::Result<HashMap<String, String>> {
+    let mut table = HashMap::new();
+    for line in r.lines() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some(old), _) if old.starts_with('#') => (),
+            (Some(old), Some(new)) => {
+                table.insert(old.to_owned(), new.to_owned());
+            }
+            (None, _) => (),
+            (Some(_), None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected two columns in rename table: {}", line),
+                ))
+            }
+        }
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recs(ids: &[&str]) -> Vec<Record> {
+        ids.iter()
+            .map(|id| {
+                let mut r = Record::new();
+                r.id = id.to_string();
+                r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chained_operations() {
+        let table = read_map("old1\tnew1\n# comment\n".as_bytes()).unwrap();
+        let renamer = Renamer::new().map(table).sanitize(Some(8)).prefix("s_");
+        let mut records = recs(&["old1", "gi|123|ref", "x"]);
+        assert_eq!(renamer.rename(&mut records).unwrap(), 3);
+        let ids: Vec<&str> = records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["s_new1", "s_gi_123_r", "s_x"]);
+    }
+
+    #[test]
+    fn collisions_detected() {
+        let renamer = Renamer::new().sanitize(None);
+        let mut records = recs(&["a|b", "a:b"]);
+        assert!(renamer.rename(&mut records).is_err());
+        assert_eq!(records[0].id, "a|b");
+    }
+}
@@ -0,0 +1,131 @@
+//! Deterministic formatting for stats and QC reports
+//!
+//! Every report written by this crate starts with `SCHEMA_VERSION` and
+//! formats numbers with a `NumberFormat`. Formatting is done by the
+//! crate itself rather than the platform, so it never depends on the
+//! system locale or C library and the same values always print the
+//! same way on every machine.
+
+/// Identifies the layout of reports written by this crate. It changes
+/// whenever fields are added, removed or reinterpreted.
+pub const SCHEMA_VERSION: &str = "fasta-report/1";
+
+/// How a value exactly halfway between two representable results is rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round half to even (banker's rounding), the IEEE 754 default
+    HalfEven,
+    /// Round half away from zero
+    HalfAwayFromZero,
+    /// Discard extra digits, rounding toward zero
+    TowardZero,
+}
+
+/// A fixed number of decimal places and a rounding policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimals: usize,
+    pub rounding: Rounding,
+}
+
+impl Default for NumberFormat {
+    /// Four decimal places, rounding half to even
+    fn default() -> NumberFormat {
+        NumberFormat {
+            decimals: 4,
+            rounding: Rounding::HalfEven,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns a NumberFormat with the given precision and rounding policy
+    pub fn new(decimals: usize, rounding: Rounding) -> NumberFormat {
+        NumberFormat { decimals, rounding }
+    }
+
+    /// Format `x` with exactly `decimals` digits after the point.
+    ///
+    /// Rounding is applied to the exact binary value of `x`, so for
+    /// example 2.675 (stored as 2.67499999...) rounds to 2.67 under every
+    /// policy. Negative zero prints as zero; NaN and infinities print as
+    /// `NaN`, `inf` and `-inf`.
+    pub fn format(&self, x: f64) -> String {
+        if !x.is_finite() {
+            return format!("{}", x);
+        }
+        // An f64 has at most 1074 significant fractional digits, so this
+        // expansion is exact.
+        let exact = format!("{:.1100}", x.abs());
+        let (int_part, frac_part) = exact.split_once('.').unwrap_or((&exact, ""));
+        let mut digits: Vec<u8> = int_part.bytes().collect();
+        let point = digits.len();
+        digits.extend(frac_part.bytes().take(self.decimals));
+        let rest = frac_part.as_bytes().get(self.decimals..).unwrap_or(&[]);
+
+        let round_up = match (self.rounding, rest.first()) {
+            (Rounding::TowardZero, _) | (_, None) => false,
+            (_, Some(&d)) if d > b'5' => true,
+            (_, Some(&d)) if d < b'5' => false,
+            _ if rest[1..].iter().any(|&d| d != b'0') => true,
+            (Rounding::HalfAwayFromZero, _) => true,
+            _ => digits.last().is_some_and(|&d| (d - b'0') % 2 == 1),
+        };
+        if round_up {
+            increment(&mut digits);
+        }
+
+        let offset = digits.len() - (point + self.decimals);
+        let (int_digits, frac_digits) = digits.split_at(point + offset);
+        let mut out = String::with_capacity(digits.len() + 2);
+        if x.is_sign_negative() && digits.iter().any(|&d| d != b'0') {
+            out.push('-');
+        }
+        out.push_str(std::str::from_utf8(int_digits).unwrap_or("0"));
+        if self.decimals > 0 {
+            out.push('.');
+            out.push_str(std::str::from_utf8(frac_digits).unwrap_or(""));
+        }
+        out
+    }
+}
+
+/// Add one unit in the last place to a string of decimal digits
+fn increment(digits: &mut Vec<u8>) {
+    for d in digits.iter_mut().rev() {
+        if *d == b'9' {
+            *d = b'0';
+        } else {
+            *d += 1;
+            return;
+        }
+    }
+    digits.insert(0, b'1');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_policies() {
+        let even = NumberFormat::new(2, Rounding::HalfEven);
+        let away = NumberFormat::new(2, Rounding::HalfAwayFromZero);
+        let zero = NumberFormat::new(2, Rounding::TowardZero);
+
+        assert_eq!(even.format(0.125), "0.12");
+        assert_eq!(away.format(0.125), "0.13");
+        assert_eq!(zero.format(0.129), "0.12");
+        assert_eq!(even.format(0.375), "0.38");
+        assert_eq!(away.format(2.675), "2.67");
+        assert_eq!(even.format(9.999), "10.00");
+        assert_eq!(away.format(-0.125), "-0.13");
+        assert_eq!(even.format(-0.001), "0.00");
+        assert_eq!(NumberFormat::new(0, Rounding::HalfEven).format(2.5), "2");
+        assert_eq!(
+            NumberFormat::new(0, Rounding::HalfAwayFromZero).format(2.5),
+            "3"
+        );
+        assert_eq!(even.format(f64::NAN), "NaN");
+    }
+}
@@ -0,0 +1,154 @@
+//! Selecting subsets of records from a stream
+
+use std::io;
+use std::io::BufRead;
+
+use crate::rng::Rng;
+use crate::{FastaBuffer, Record};
+
+/// Draw a uniform random sample of up to `n` records in a single pass.
+///
+/// Uses reservoir sampling, so at most `n` records are held in memory.
+/// The same seed and input always give the same sample, and the sampled
+/// records are returned in their input order.
+pub fn sample_n<I>(records: I, n: usize, seed: u64) -> Result<Vec<Record>, io::Error>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let mut rng = Rng::new(seed);
+    // n comes from the caller, so only preallocate a bounded amount
+    let mut reservoir: Vec<(usize, Record)> = Vec::with_capacity(n.min(1024));
+    for (i, rec) in records.into_iter().enumerate() {
+        let rec = rec?;
+        if i < n {
+            reservoir.push((i, rec));
+        } else {
+            let j = rng.below(i as u64 + 1) as usize;
+            if j < n {
+                reservoir[j] = (i, rec);
+            }
+        }
+    }
+    reservoir.sort_by_key(|(i, _)| *i);
+    Ok(reservoir.into_iter().map(|(_, rec)| rec).collect())
+}
+
+/// Keep each record independently with probability `p`.
+///
+/// Streams with constant memory; the same seed and input always select
+/// the same records.
+pub fn sample_fraction<I>(records: I, p: f64, seed: u64) -> SampleFraction<I::IntoIter>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    SampleFraction {
+        records: records.into_iter(),
+        p,
+        rng: Rng::new(seed),
+    }
+}
+
+/// Iterator returned by `sample_fraction`
+pub struct SampleFraction<I> {
+    records: I,
+    p: f64,
+    rng: Rng,
+}
+
+impl<I> Iterator for SampleFraction<I>
+where
+    I: Iterator<Item = Result<Record, io::Error>>,
+{
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.records.next()? {
+                Ok(rec) => {
+                    if self.rng.next_f64() < self.p {
+                        return Some(Ok(rec));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Iterator returned by `FastaBuffer::every_nth`
+pub struct EveryNth<B: BufRead> {
+    inner: FastaBuffer<B>,
+    n: usize,
+    first: bool,
+}
+
+impl<B: BufRead> EveryNth<B> {
+    pub(crate) fn new(inner: FastaBuffer<B>, n: usize) -> EveryNth<B> {
+        assert!(n > 0, "every_nth requires n > 0");
+        EveryNth {
+            inner,
+            n,
+            first: true,
+        }
+    }
+}
+
+impl<B: BufRead> Iterator for EveryNth<B> {
+    type Item = Result<Record, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.first {
+            for _ in 1..self.n {
+                match self.inner.read_record(false)? {
+                    Ok(_) => (),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        self.first = false;
+        self.inner.read_record(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    fn input() -> String {
+        (0..1000).map(|i| format!(">r{}\nACGT\n", i)).collect()
+    }
+
+    #[test]
+    fn reservoir_is_deterministic() {
+        let data = input();
+        let a = sample_n(FastaBuffer::from(data.as_bytes()), 10, 42).unwrap();
+        let b = sample_n(FastaBuffer::from(data.as_bytes()), 10, 42).unwrap();
+        let c = sample_n(FastaBuffer::from(data.as_bytes()), 10, 7).unwrap();
+        let ids = |v: &[Record]| v.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+        assert_eq!(a.len(), 10);
+        assert_eq!(ids(&a), ids(&b));
+        assert_ne!(ids(&a), ids(&c));
+
+        let all = sample_n(FastaBuffer::from(data.as_bytes()), 5000, 1).unwrap();
+        assert_eq!(all.len(), 1000);
+        assert_eq!(all[999].id, "r999");
+    }
+
+    #[test]
+    fn fraction_is_roughly_p() {
+        let data = input();
+        let n = sample_fraction(FastaBuffer::from(data.as_bytes()), 0.1, 42).count();
+        assert!(n > 50 && n < 150, "sampled {}", n);
+    }
+
+    #[test]
+    fn every_third_record() {
+        let data: String = (0..10).map(|i| format!(">r{}\nACGT\n", i)).collect();
+        let ids: Vec<String> = FastaBuffer::from(data.as_bytes())
+            .every_nth(3)
+            .map(|r| r.unwrap().id)
+            .collect();
+        assert_eq!(ids, vec!["r0", "r3", "r6", "r9"]);
+    }
+}
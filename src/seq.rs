@@ -0,0 +1,137 @@
+//! Sequence-level utilities shared by record operations
+
+use alloc::string::String;
+
+/// Complement of a single IUPAC nucleotide code, preserving case.
+/// Gaps and unrecognized characters are returned unchanged.
+pub fn complement(b: u8) -> u8 {
+    match b {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        b'a' => b't',
+        b'c' => b'g',
+        b'g' => b'c',
+        b't' | b'u' => b'a',
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        other => other,
+    }
+}
+
+/// Reverse complement of a nucleotide sequence. IUPAC ambiguity codes
+/// are complemented, case is preserved, and S, W, N and gaps map to
+/// themselves.
+pub fn reverse_complement(s: &str) -> String {
+    s.chars()
+        .rev()
+        .map(|c| {
+            if c.is_ascii() {
+                complement(c as u8) as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+/// Bit mask of the bases (T=1, C=2, A=4, G=8) an IUPAC code stands for
+pub(crate) fn base_mask(b: u8) -> u8 {
+    match b.to_ascii_uppercase() {
+        b'T' | b'U' => 1,
+        b'C' => 2,
+        b'A' => 4,
+        b'G' => 8,
+        b'Y' => 1 | 2,
+        b'W' => 1 | 4,
+        b'K' => 1 | 8,
+        b'M' => 2 | 4,
+        b'S' => 2 | 8,
+        b'R' => 4 | 8,
+        b'H' => 1 | 2 | 4,
+        b'B' => 1 | 2 | 8,
+        b'D' => 1 | 4 | 8,
+        b'V' => 2 | 4 | 8,
+        b'N' => 15,
+        _ => 0,
+    }
+}
+
+#[cfg(feature = "std")]
+/// The IUPAC code for a `base_mask` bit set, in upper case. An empty set
+/// maps to N.
+pub(crate) fn iupac_code(mask: u8) -> u8 {
+    b"NTCYAWMHGKSBRDVN"[usize::from(mask & 15)]
+}
+
+/// IUPAC nucleotide codes valid in both DNA and RNA, plus gaps
+#[cfg(feature = "std")]
+fn is_common_nucleotide(c: char) -> bool {
+    matches!(
+        c.to_ascii_uppercase(),
+        'A' | 'C'
+            | 'G'
+            | 'N'
+            | 'R'
+            | 'Y'
+            | 'S'
+            | 'W'
+            | 'K'
+            | 'M'
+            | 'B'
+            | 'D'
+            | 'H'
+            | 'V'
+            | '-'
+            | '.'
+            | '*'
+    )
+}
+
+#[cfg(feature = "std")]
+/// Replace `from` with `to` (matching case), failing with the position
+/// and character of the first symbol that is neither `from` nor a
+/// nucleotide code shared by DNA and RNA
+pub(crate) fn swap_base(s: &str, from: char, to: char) -> Result<String, (usize, char)> {
+    let (from_lower, to_lower) = (from.to_ascii_lowercase(), to.to_ascii_lowercase());
+    s.chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            c if c == from => Ok(to),
+            c if c == from_lower => Ok(to_lower),
+            c if is_common_nucleotide(c) => Ok(c),
+            c => Err((i, c)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn revcomp() {
+        assert_eq!(super::reverse_complement("ACGTNacgtn-RY"), "RY-nacgtNACGT");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn swap() {
+        assert_eq!(super::swap_base("ACGTtn", 'T', 'U').unwrap(), "ACGUun");
+        assert_eq!(super::swap_base("ACGU", 'T', 'U').unwrap_err(), (3, 'U'));
+    }
+}
@@ -0,0 +1,104 @@
+//! Records whose sequences are shared instead of copied
+//!
+//! A `SharedRecord` holds its sequence in an `Arc<str>`, so cloning one,
+//! say to file it in an index by ID and another by length, copies only
+//! the ID and description. `FastaBuffer::shared` reads records in this
+//! form directly.
+
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use crate::{wrap_string, FastaBuffer, Record};
+
+/// A record with a reference-counted sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedRecord {
+    pub id: String,
+    pub description: String,
+    pub sequence: Arc<str>,
+}
+
+impl SharedRecord {
+    /// Whether `self` and `other` hold the same sequence allocation
+    pub fn shares_sequence(&self, other: &SharedRecord) -> bool {
+        Arc::ptr_eq(&self.sequence, &other.sequence)
+    }
+
+    /// A plain record with a copy of the sequence
+    pub fn to_record(&self) -> Record {
+        Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            sequence: self.sequence.to_string(),
+        }
+    }
+
+    /// Write the record, wrapping the sequence at `width` characters; a
+    /// width of 0 writes the sequence on one line
+    pub fn write_with_width(&self, w: &mut impl Write, width: usize) -> io::Result<()> {
+        if self.description.is_empty() {
+            writeln!(w, ">{}", self.id)?;
+        } else {
+            writeln!(w, ">{} {}", self.id, self.description)?;
+        }
+        writeln!(w, "{}", wrap_string(&self.sequence, width))
+    }
+}
+
+impl From<Record> for SharedRecord {
+    /// Move the sequence into a shared allocation
+    fn from(rec: Record) -> SharedRecord {
+        SharedRecord {
+            id: rec.id,
+            description: rec.description,
+            sequence: Arc::from(rec.sequence),
+        }
+    }
+}
+
+/// An iterator over the records of a FastaBuffer as `SharedRecord`s,
+/// created by `FastaBuffer::shared`
+pub struct Shared<B: BufRead> {
+    buffer: FastaBuffer<B>,
+}
+
+impl<B: BufRead> Shared<B> {
+    pub(crate) fn new(buffer: FastaBuffer<B>) -> Shared<B> {
+        Shared { buffer }
+    }
+}
+
+impl<B: BufRead> Iterator for Shared<B> {
+    type Item = io::Result<SharedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.buffer.next()?.map(SharedRecord::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn clones_share_sequences() {
+        let input = ">a x\nACGTACGT\nAC\n>b\nA\n";
+        let records: Vec<SharedRecord> = FastaBuffer::from(input.as_bytes())
+            .shared()
+            .map(Result::unwrap)
+            .collect();
+        let by_id: HashMap<String, SharedRecord> = records
+            .iter()
+            .map(|rec| (rec.id.clone(), rec.clone()))
+            .collect();
+        assert!(by_id["a"].shares_sequence(&records[0]));
+        assert_eq!(&*by_id["a"].sequence, "ACGTACGTAC");
+        assert_eq!(by_id["b"].to_record().sequence, "A");
+
+        let mut out = Vec::new();
+        records[0].write_with_width(&mut out, 4).unwrap();
+        assert_eq!(out, b">a x\nACGT\nACGT\nAC\n");
+    }
+}
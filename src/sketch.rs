@@ -0,0 +1,201 @@
+//! MinHash sketches and Mash distances
+//!
+//! A `Sketch` keeps the `size` smallest hashes of the canonical k-mers
+//! of a record or a whole file. Comparing two sketches estimates the
+//! Jaccard index of their k-mer sets, and from it the Mash distance, an
+//! estimate of the per-base mutation rate between the sequences (Ondov
+//! et al. 2016). Sketches of a few thousand hashes are enough to screen
+//! assemblies against each other in milliseconds.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use crate::kmer::{check_packed_k, for_each_packed};
+use crate::Record;
+
+/// Mix the bits of a packed k-mer, the 64-bit finalizer of MurmurHash3
+fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+/// The bottom-`size` MinHash sketch of a set of canonical k-mers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sketch {
+    k: usize,
+    size: usize,
+    hashes: BTreeSet<u64>,
+}
+
+impl Sketch {
+    /// An empty sketch of k-mers of length `k`, keeping `size` hashes.
+    /// Panics unless `1 <= k <= kmer::MAX_K` and `size` is non-zero.
+    pub fn new(k: usize, size: usize) -> Sketch {
+        check_packed_k(k);
+        assert!(size > 0, "sketch size must be non-zero");
+        Sketch {
+            k,
+            size,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    /// Length of the k-mers sketched
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Number of hashes kept, at most the sketch size
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether no k-mers have been added
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// The hashes kept, in increasing order
+    pub fn hashes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.hashes.iter().copied()
+    }
+
+    /// Add the canonical k-mers of `seq`. K-mers containing anything
+    /// other than ACGTU are skipped.
+    pub fn add_sequence(&mut self, seq: &str) {
+        let (hashes, size) = (&mut self.hashes, self.size);
+        for_each_packed(seq.as_bytes(), self.k, true, |key| {
+            let h = mix(key);
+            if hashes.len() < size {
+                hashes.insert(h);
+            } else if hashes.last().is_some_and(|&max| h < max) && hashes.insert(h) {
+                hashes.pop_last();
+            }
+        });
+    }
+
+    /// Estimate the Jaccard index of the two k-mer sets: the fraction of
+    /// the smallest hashes of their union found in both. Sketches of
+    /// different sizes are compared at the smaller size. Panics if the
+    /// sketches use different k.
+    pub fn jaccard(&self, other: &Sketch) -> f64 {
+        assert_eq!(self.k, other.k, "sketches use different k");
+        let size = self.size.min(other.size);
+        let (mut a, mut b) = (
+            self.hashes.iter().peekable(),
+            other.hashes.iter().peekable(),
+        );
+        let (mut union, mut shared) = (0, 0);
+        while union < size {
+            match (a.peek(), b.peek()) {
+                (None, None) => break,
+                (Some(x), Some(y)) if x == y => {
+                    shared += 1;
+                    a.next();
+                    b.next();
+                }
+                (Some(x), Some(y)) if x > y => {
+                    b.next();
+                }
+                (None, Some(_)) => {
+                    b.next();
+                }
+                _ => {
+                    a.next();
+                }
+            }
+            union += 1;
+        }
+        if union == 0 {
+            0.0
+        } else {
+            shared as f64 / union as f64
+        }
+    }
+
+    /// The Mash distance `-ln(2j / (1 + j)) / k` for Jaccard estimate
+    /// `j`: 0 for identical sets and 1 when no hashes are shared
+    pub fn mash_distance(&self, other: &Sketch) -> f64 {
+        let j = self.jaccard(other);
+        if j == 0.0 {
+            1.0
+        } else if j == 1.0 {
+            0.0
+        } else {
+            (-(2.0 * j / (1.0 + j)).ln() / self.k as f64).min(1.0)
+        }
+    }
+}
+
+impl Record {
+    /// A MinHash sketch of the sequence's canonical k-mers, see
+    /// `Sketch::new`
+    pub fn sketch(&self, k: usize, size: usize) -> Sketch {
+        let mut sketch = Sketch::new(k, size);
+        sketch.add_sequence(&self.sequence);
+        sketch
+    }
+}
+
+/// One sketch of all the records of a stream, such as the contigs of an
+/// assembly; see `Sketch::new`
+pub fn sketch_records<I>(records: I, k: usize, size: usize) -> io::Result<Sketch>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let mut sketch = Sketch::new(k, size);
+    for rec in records {
+        sketch.add_sequence(&rec?.sequence);
+    }
+    Ok(sketch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn random_sequence(rng: &mut Rng, len: usize) -> String {
+        (0..len)
+            .map(|_| char::from(b"ACGT"[rng.below(4) as usize]))
+            .collect()
+    }
+
+    #[test]
+    fn distances() {
+        let mut rng = Rng::new(7);
+        let a = random_sequence(&mut rng, 20_000);
+        // One substitution every 100 bases
+        let b: String = a
+            .char_indices()
+            .map(|(i, c)| match (i % 100, c) {
+                (50, 'A') => 'C',
+                (50, _) => 'A',
+                (_, c) => c,
+            })
+            .collect();
+        let sketch = |seq: &str| {
+            let rec = Record {
+                sequence: seq.to_owned(),
+                ..Record::new()
+            };
+            rec.sketch(21, 1000)
+        };
+        let (sa, sb) = (sketch(&a), sketch(&b));
+        assert_eq!(sa.len(), 1000);
+        assert_eq!(sa.jaccard(&sa), 1.0);
+        assert_eq!(sa.mash_distance(&sa), 0.0);
+        let d = sa.mash_distance(&sb);
+        assert!(d > 0.005 && d < 0.02, "{}", d);
+
+        let other = sketch(&random_sequence(&mut rng, 20_000));
+        assert_eq!(sa.mash_distance(&other), 1.0);
+
+        // The reverse complement has the same canonical k-mers
+        let rc = sketch(&crate::seq::reverse_complement(&a));
+        assert_eq!(sa, rc);
+    }
+}
@@ -0,0 +1,365 @@
+//! Sorting record streams larger than memory
+//!
+//! `sort_records` buffers records until a memory budget is reached, then
+//! sorts them and spills the run to a temporary file. Once the input is
+//! exhausted the runs are merged, so only one record per run is held in
+//! memory. At most `MERGE_WIDTH` runs are open at once: beyond that,
+//! groups of runs are first merged into longer runs, in as many passes
+//! as needed. Input that fits in a single run is sorted without touching
+//! the disk.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use crate::collection::natural_cmp;
+use crate::{FastaBuffer, Record};
+
+/// Distinguishes the runs of sorts running concurrently in one process
+static SORT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs merged at once, bounding the number of open files
+const MERGE_WIDTH: usize = 64;
+
+/// The order produced by `sort_records`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Longest sequence first, as `Fasta::sort_by_length`
+    Length,
+    /// Natural ID order, as `Fasta::sort_by_id`
+    Id,
+}
+
+impl SortKey {
+    /// Compare two records in this order
+    pub fn compare(self, a: &Record, b: &Record) -> Ordering {
+        match self {
+            SortKey::Length => b.sequence.len().cmp(&a.sequence.len()),
+            SortKey::Id => natural_cmp(&a.id, &b.id),
+        }
+    }
+}
+
+/// Options for `sort_records`
+#[derive(Debug, Clone)]
+pub struct SortOptions {
+    pub key: SortKey,
+    /// Bytes of records buffered before a run is spilled to disk,
+    /// counting their IDs, descriptions and sequences and the size of
+    /// each `Record`
+    pub run_bytes: usize,
+    /// Directory for the temporary run files
+    pub temp_dir: PathBuf,
+}
+
+impl Default for SortOptions {
+    /// Sort by ID in runs of 1 GiB under the system temporary directory
+    fn default() -> SortOptions {
+        SortOptions {
+            key: SortKey::Id,
+            run_bytes: 1 << 30,
+            temp_dir: env::temp_dir(),
+        }
+    }
+}
+
+/// Temporary run files of one sort, in run order, removed when dropped
+struct RunFiles {
+    sort_id: usize,
+    // Files created so far, naming the next one
+    created: usize,
+    paths: Vec<PathBuf>,
+}
+
+impl RunFiles {
+    /// Create a new run file after the others
+    fn create(&mut self, dir: &Path) -> io::Result<BufWriter<File>> {
+        let name = format!(
+            "fasta-sort-{}-{}-{}.fa",
+            process::id(),
+            self.sort_id,
+            self.created
+        );
+        let path = dir.join(name);
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        self.created += 1;
+        self.paths.push(path);
+        Ok(BufWriter::new(file))
+    }
+
+    /// Remove the first `n` run files
+    fn remove_front(&mut self, n: usize) {
+        for path in self.paths.drain(..n) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for RunFiles {
+    fn drop(&mut self) {
+        self.remove_front(self.paths.len());
+    }
+}
+
+/// The next record of a run, ordered so that `BinaryHeap` pops the
+/// smallest first and, among equal records, the one from the earlier
+/// run, which keeps the merge stable
+struct Head {
+    key: SortKey,
+    record: Record,
+    run: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Head) -> Ordering {
+        self.key
+            .compare(&self.record, &other.record)
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Head) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Head) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// A merge of sorted runs
+struct Merge {
+    key: SortKey,
+    runs: Vec<FastaBuffer<BufReader<File>>>,
+    heads: BinaryHeap<Head>,
+}
+
+impl Merge {
+    fn open(paths: &[PathBuf], key: SortKey) -> io::Result<Merge> {
+        let mut runs = Vec::with_capacity(paths.len());
+        let mut heads = BinaryHeap::with_capacity(paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            let mut run = FastaBuffer::from(BufReader::new(File::open(path)?));
+            if let Some(record) = run.next().transpose()? {
+                heads.push(Head {
+                    key,
+                    record,
+                    run: i,
+                });
+            }
+            runs.push(run);
+        }
+        Ok(Merge { key, runs, heads })
+    }
+}
+
+impl Iterator for Merge {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let head = self.heads.pop()?;
+        match self.runs[head.run].next() {
+            Some(Ok(record)) => self.heads.push(Head {
+                key: self.key,
+                record,
+                run: head.run,
+            }),
+            Some(Err(e)) => return Some(Err(e)),
+            None => (),
+        }
+        Some(Ok(head.record))
+    }
+}
+
+/// Records in sorted order, returned by `sort_records`
+pub struct Sorted {
+    memory: std::vec::IntoIter<Record>,
+    merge: Option<Merge>,
+    spilled: usize,
+    _files: RunFiles,
+}
+
+impl Sorted {
+    /// Number of runs spilled to disk, 0 when the input fit in memory
+    pub fn spilled_runs(&self) -> usize {
+        self.spilled
+    }
+}
+
+impl Iterator for Sorted {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.merge {
+            Some(merge) => merge.next(),
+            None => self.memory.next().map(Ok),
+        }
+    }
+}
+
+fn spill(buffer: &mut Vec<Record>, opts: &SortOptions, files: &mut RunFiles) -> io::Result<()> {
+    buffer.sort_by(|a, b| opts.key.compare(a, b));
+    let mut w = files.create(&opts.temp_dir)?;
+    for rec in buffer.drain(..) {
+        rec.write_with_width(&mut w, 0)?;
+    }
+    w.flush()
+}
+
+/// Merge the runs in groups of `MERGE_WIDTH` until no more than that
+/// remain. Merged runs replace their group in order, keeping the sort
+/// stable.
+fn merge_passes(opts: &SortOptions, files: &mut RunFiles) -> io::Result<()> {
+    while files.paths.len() > MERGE_WIDTH {
+        let mut unmerged = files.paths.len();
+        while unmerged > 0 {
+            let width = unmerged.min(MERGE_WIDTH);
+            let merge = Merge::open(&files.paths[..width], opts.key)?;
+            let mut w = files.create(&opts.temp_dir)?;
+            for rec in merge {
+                rec?.write_with_width(&mut w, 0)?;
+            }
+            w.flush()?;
+            files.remove_front(width);
+            unmerged -= width;
+        }
+    }
+    Ok(())
+}
+
+/// Sort a record stream by `opts.key`, spilling sorted runs of
+/// `opts.run_bytes` to `opts.temp_dir` and merging them. The sort is
+/// stable. Temporary files are removed when the returned iterator is
+/// dropped, or on error.
+pub fn sort_records<I>(records: I, opts: &SortOptions) -> io::Result<Sorted>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+{
+    let mut files = RunFiles {
+        sort_id: SORT_ID.fetch_add(1, AtomicOrdering::Relaxed),
+        created: 0,
+        paths: Vec::new(),
+    };
+    let mut buffer = Vec::new();
+    let mut bytes = 0;
+    for rec in records {
+        let rec = rec?;
+        bytes +=
+            mem::size_of::<Record>() + rec.id.len() + rec.description.len() + rec.sequence.len();
+        buffer.push(rec);
+        if bytes >= opts.run_bytes {
+            spill(&mut buffer, opts, &mut files)?;
+            bytes = 0;
+        }
+    }
+
+    if files.paths.is_empty() {
+        buffer.sort_by(|a, b| opts.key.compare(a, b));
+        return Ok(Sorted {
+            memory: buffer.into_iter(),
+            merge: None,
+            spilled: 0,
+            _files: files,
+        });
+    }
+    if !buffer.is_empty() {
+        spill(&mut buffer, opts, &mut files)?;
+    }
+    let spilled = files.paths.len();
+    merge_passes(opts, &mut files)?;
+    Ok(Sorted {
+        memory: Vec::new().into_iter(),
+        merge: Some(Merge::open(&files.paths, opts.key)?),
+        spilled,
+        _files: files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(sorted: Sorted) -> Vec<String> {
+        sorted.map(|r| r.unwrap().id).collect()
+    }
+
+    #[test]
+    fn spilled_runs_merge() {
+        let input = ">chr10\nAC\n>chr2 b\nACGTACGT\n>chr1\nA\n>chr3\nACGT\n>chr2 a\nACG\n";
+        let opts = SortOptions {
+            run_bytes: 8,
+            ..SortOptions::default()
+        };
+        let sorted = sort_records(FastaBuffer::from(input.as_bytes()), &opts).unwrap();
+        assert!(sorted.spilled_runs() > 1);
+        let recs: Vec<Record> = sorted.map(Result::unwrap).collect();
+        let got: Vec<(&str, &str)> = recs
+            .iter()
+            .map(|r| (r.id.as_str(), r.description.as_str()))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("chr1", ""),
+                ("chr2", "b"),
+                ("chr2", "a"),
+                ("chr3", ""),
+                ("chr10", "")
+            ]
+        );
+
+        let opts = SortOptions {
+            key: SortKey::Length,
+            ..opts
+        };
+        let sorted = sort_records(FastaBuffer::from(input.as_bytes()), &opts).unwrap();
+        assert_eq!(ids(sorted), ["chr2", "chr3", "chr2", "chr10", "chr1"]);
+    }
+
+    #[test]
+    fn merge_in_passes() {
+        // Every record spills a run of its own
+        let n = 3 * MERGE_WIDTH + 5;
+        let input: String = (0..n)
+            .map(|i| format!(">r{} {}\nA\n", (n - i) % 10, i))
+            .collect();
+        let opts = SortOptions {
+            run_bytes: 1,
+            ..SortOptions::default()
+        };
+        let sorted = sort_records(FastaBuffer::from(input.as_bytes()), &opts).unwrap();
+        assert_eq!(sorted.spilled_runs(), n);
+        let recs: Vec<Record> = sorted.map(Result::unwrap).collect();
+        assert_eq!(recs.len(), n);
+        for pair in recs.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let position = |r: &Record| r.description.parse::<usize>().unwrap();
+            assert!(a.id < b.id || (a.id == b.id && position(a) < position(b)));
+        }
+    }
+
+    #[test]
+    fn in_memory() {
+        let input = ">b\nAC\n>a\nACG\n";
+        let sorted = sort_records(FastaBuffer::from(input.as_bytes()), &SortOptions::default());
+        let sorted = sorted.unwrap();
+        assert_eq!(sorted.spilled_runs(), 0);
+        assert_eq!(ids(sorted), ["a", "b"]);
+    }
+}
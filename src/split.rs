@@ -0,0 +1,272 @@
+//! Partitioning a record stream into several outputs
+
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+use crate::Record;
+
+/// Expand an output name template for a chunk.
+///
+/// `{}` is replaced by the chunk number (starting at 0) and `{id}` by
+/// the ID of the chunk's first record, with path separators replaced by
+/// underscores.
+pub fn fill_template(template: &str, chunk: usize, first: &Record) -> String {
+    let id = first.id.replace(['/', '\\'], "_");
+    template
+        .replace("{id}", &id)
+        .replace("{}", &chunk.to_string())
+}
+
+/// Check that `template` names each chunk differently, failing with
+/// `InvalidInput` unless it contains `{}` or `{id}`
+pub fn check_template(template: &str) -> io::Result<()> {
+    if template.contains("{}") || template.contains("{id}") {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "split file template must contain {} or {id}",
+        ))
+    }
+}
+
+/// A writer factory creating buffered files named from `template`,
+/// for use with the split functions. Fails with `InvalidInput` if the
+/// template has no `{}` or `{id}`, since every chunk would then
+/// overwrite the same file.
+pub fn file_writer(
+    template: &str,
+) -> io::Result<impl FnMut(usize, &Record) -> io::Result<BufWriter<File>>> {
+    check_template(template)?;
+    let template = template.to_owned();
+    Ok(move |chunk, first: &Record| {
+        File::create(fill_template(&template, chunk, first)).map(BufWriter::new)
+    })
+}
+
+/// How much of the current chunk has been written
+struct Filled {
+    records: usize,
+    bytes: u64,
+    bases: u64,
+}
+
+/// Drive a split, starting a new chunk whenever `new_chunk` returns true.
+/// `new_chunk` receives what the current chunk already holds and the
+/// formatted size and sequence length of the next record.
+fn split_with<I, W, F, P>(records: I, mut make_writer: F, mut new_chunk: P) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+    P: FnMut(&Filled, u64, u64) -> bool,
+{
+    let mut current: Option<W> = None;
+    let mut chunks = 0;
+    let mut filled = Filled {
+        records: 0,
+        bytes: 0,
+        bases: 0,
+    };
+
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                if let Some(mut w) = current {
+                    w.flush()?;
+                }
+                return Err(e);
+            }
+        };
+        let s = rec.as_string();
+        let size = s.len() as u64;
+        let bases = rec.sequence.len() as u64;
+        if current.is_none() || new_chunk(&filled, size, bases) {
+            if let Some(mut w) = current.take() {
+                w.flush()?;
+            }
+            current = Some(make_writer(chunks, &rec)?);
+            chunks += 1;
+            filled = Filled {
+                records: 0,
+                bytes: 0,
+                bases: 0,
+            };
+        }
+        if let Some(w) = current.as_mut() {
+            w.write_all(s.as_bytes())?;
+        }
+        filled.records += 1;
+        filled.bytes += size;
+        filled.bases += bases;
+    }
+    if let Some(mut w) = current {
+        w.flush()?;
+    }
+    Ok(chunks)
+}
+
+/// Split records into chunks of at most `n` records each, returning
+/// the number of chunks created. Panics if `n` is 0.
+pub fn split_by_count<I, W, F>(records: I, n: usize, make_writer: F) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+{
+    assert!(n > 0, "split_by_count requires n > 0");
+    split_with(records, make_writer, |filled, _, _| filled.records >= n)
+}
+
+/// Split records into chunks of at most `bytes` of formatted FASTA,
+/// returning the number of chunks created. A record larger than the
+/// limit is written to a chunk of its own.
+pub fn split_by_size<I, W, F>(records: I, bytes: u64, make_writer: F) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+{
+    split_with(records, make_writer, |filled, size, _| {
+        filled.records > 0 && filled.bytes + size > bytes
+    })
+}
+
+/// Split records into chunks holding at most `bases` of sequence,
+/// returning the number of chunks created. A record longer than the
+/// limit is written to a chunk of its own.
+pub fn split_by_length<I, W, F>(records: I, bases: u64, make_writer: F) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+{
+    split_with(records, make_writer, |filled, _, len| {
+        filled.records > 0 && filled.bases + len > bases
+    })
+}
+
+/// Distribute records over `n` outputs in turn, so parts differ in size
+/// by at most one record, returning the number of outputs created. Each
+/// output is created when its first record arrives; all are kept open
+/// until the input ends. Panics if `n` is 0.
+pub fn split_into_parts<I, W, F>(records: I, n: usize, mut make_writer: F) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+{
+    assert!(n > 0, "split_into_parts requires n > 0");
+    let mut parts: Vec<W> = Vec::new();
+    let mut result = Ok(());
+    for (i, rec) in records.into_iter().enumerate() {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+        let part = i % n;
+        if part == parts.len() {
+            parts.push(make_writer(part, &rec)?);
+        }
+        parts[part].write_all(rec.as_string().as_bytes())?;
+    }
+    for w in &mut parts {
+        w.flush()?;
+    }
+    result.map(|()| parts.len())
+}
+
+/// Write every record to its own output, returning the number of
+/// outputs created
+pub fn split_per_record<I, W, F>(records: I, make_writer: F) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+    F: FnMut(usize, &Record) -> io::Result<W>,
+{
+    split_with(records, make_writer, |_, _, _| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A writer appending into a shared list of chunks
+    struct Chunk(Rc<RefCell<Vec<Vec<u8>>>>, usize);
+
+    impl Write for Chunk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut()[self.1].extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn collect_chunks<S>(split: S) -> Vec<String>
+    where
+        S: FnOnce(&mut dyn FnMut(usize, &Record) -> io::Result<Chunk>) -> io::Result<usize>,
+    {
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let n = split(&mut |i, _| {
+            chunks.borrow_mut().push(Vec::new());
+            Ok(Chunk(chunks.clone(), i))
+        })
+        .unwrap();
+        let out: Vec<String> = chunks
+            .borrow()
+            .iter()
+            .map(|c| String::from_utf8(c.clone()).unwrap())
+            .collect();
+        assert_eq!(n, out.len());
+        out
+    }
+
+    const INPUT: &str = ">a\nAAAA\n>b\nCC\n>c\nGGGGGG\n";
+
+    #[test]
+    fn split_modes() {
+        let chunks = collect_chunks(|f| split_by_count(FastaBuffer::from(INPUT.as_bytes()), 2, f));
+        assert_eq!(chunks, vec![">a\nAAAA\n>b\nCC\n", ">c\nGGGGGG\n"]);
+
+        let chunks = collect_chunks(|f| split_by_size(FastaBuffer::from(INPUT.as_bytes()), 14, f));
+        assert_eq!(chunks, vec![">a\nAAAA\n>b\nCC\n", ">c\nGGGGGG\n"]);
+
+        let chunks = collect_chunks(|f| split_per_record(FastaBuffer::from(INPUT.as_bytes()), f));
+        assert_eq!(chunks.len(), 3);
+
+        let chunks = collect_chunks(|f| split_by_length(FastaBuffer::from(INPUT.as_bytes()), 6, f));
+        assert_eq!(chunks, vec![">a\nAAAA\n>b\nCC\n", ">c\nGGGGGG\n"]);
+
+        let chunks =
+            collect_chunks(|f| split_into_parts(FastaBuffer::from(INPUT.as_bytes()), 2, f));
+        assert_eq!(chunks, vec![">a\nAAAA\n>c\nGGGGGG\n", ">b\nCC\n"]);
+    }
+
+    #[test]
+    fn template_expansion() {
+        let mut rec = Record::new();
+        rec.id = "chr1/a".to_owned();
+        assert_eq!(
+            fill_template("part_{}.{id}.fa", 3, &rec),
+            "part_3.chr1_a.fa"
+        );
+    }
+
+    #[test]
+    fn template_without_placeholder() {
+        let err = file_writer("out.fa").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(check_template("out_{id}.fa").is_ok());
+        assert!(check_template("out_{}.fa").is_ok());
+    }
+}
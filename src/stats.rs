@@ -0,0 +1,143 @@
+//! Summary statistics over a set of records
+
+use std::io;
+use std::io::Write;
+
+use crate::report::{NumberFormat, SCHEMA_VERSION};
+use crate::Record;
+
+/// Accumulated sequence statistics for a file or collection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub records: u64,
+    pub total_length: u64,
+    pub min_length: u64,
+    pub max_length: u64,
+    /// G, C and S bases
+    pub gc_bases: u64,
+    /// A, T, U and W bases
+    pub at_bases: u64,
+    /// N bases, either case
+    pub n_bases: u64,
+    lengths: Vec<u64>,
+}
+
+impl Stats {
+    /// Returns empty statistics
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Compute statistics over a record stream
+    pub fn from_records<I>(records: I) -> io::Result<Stats>
+    where
+        I: IntoIterator<Item = Result<Record, io::Error>>,
+    {
+        let mut stats = Stats::new();
+        for rec in records {
+            stats.add(&rec?);
+        }
+        Ok(stats)
+    }
+
+    /// Add a record to the statistics
+    pub fn add(&mut self, rec: &Record) {
+        let len = rec.sequence.len() as u64;
+        if self.records == 0 || len < self.min_length {
+            self.min_length = len;
+        }
+        self.max_length = self.max_length.max(len);
+        self.records += 1;
+        self.total_length += len;
+        self.lengths.push(len);
+        for b in rec.sequence.bytes() {
+            match b.to_ascii_uppercase() {
+                b'G' | b'C' | b'S' => self.gc_bases += 1,
+                b'A' | b'T' | b'U' | b'W' => self.at_bases += 1,
+                b'N' => self.n_bases += 1,
+                _ => (),
+            }
+        }
+    }
+
+    /// Mean sequence length, 0 for no records
+    pub fn mean_length(&self) -> f64 {
+        if self.records == 0 {
+            0.0
+        } else {
+            self.total_length as f64 / self.records as f64
+        }
+    }
+
+    /// The length L such that sequences of length at least L contain half
+    /// of all bases, 0 for no records
+    pub fn n50(&self) -> u64 {
+        let mut lengths = self.lengths.clone();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        let mut acc = 0;
+        for len in lengths {
+            acc += len;
+            if 2 * acc >= self.total_length {
+                return len;
+            }
+        }
+        0
+    }
+
+    /// GC fraction among unambiguous bases, as in `Record::gc_content`
+    pub fn gc_content(&self) -> f64 {
+        let informative = self.gc_bases + self.at_bases;
+        if informative == 0 {
+            0.0
+        } else {
+            self.gc_bases as f64 / informative as f64
+        }
+    }
+
+    /// Fraction of all bases that are N
+    pub fn n_fraction(&self) -> f64 {
+        if self.total_length == 0 {
+            0.0
+        } else {
+            self.n_bases as f64 / self.total_length as f64
+        }
+    }
+
+    /// Write a `key<TAB>value` report, starting with the schema version.
+    /// Integers are written exactly and fractions with `fmt`.
+    pub fn write_report<W: Write>(&self, w: &mut W, fmt: &NumberFormat) -> io::Result<()> {
+        writeln!(w, "schema\t{}", SCHEMA_VERSION)?;
+        writeln!(w, "records\t{}", self.records)?;
+        writeln!(w, "total_length\t{}", self.total_length)?;
+        writeln!(w, "min_length\t{}", self.min_length)?;
+        writeln!(w, "max_length\t{}", self.max_length)?;
+        writeln!(w, "mean_length\t{}", fmt.format(self.mean_length()))?;
+        writeln!(w, "n50\t{}", self.n50())?;
+        writeln!(w, "gc_content\t{}", fmt.format(self.gc_content()))?;
+        writeln!(w, "n_bases\t{}", self.n_bases)?;
+        writeln!(w, "n_fraction\t{}", fmt.format(self.n_fraction()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn report_is_stable() {
+        let input = ">a\nACGTNN\n>b\nGGCC\n>c\nA\n";
+        let stats = Stats::from_records(FastaBuffer::from(input.as_bytes())).unwrap();
+        assert_eq!(stats.n50(), 6);
+        let mut out = Vec::new();
+        stats
+            .write_report(&mut out, &NumberFormat::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "schema\tfasta-report/1\nrecords\t3\ntotal_length\t11\nmin_length\t1\n\
+             max_length\t6\nmean_length\t3.6667\nn50\t6\ngc_content\t0.6667\n\
+             n_bases\t2\nn_fraction\t0.1818\n"
+        );
+    }
+}
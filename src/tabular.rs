@@ -0,0 +1,160 @@
+//! Tab-delimited records, one per line
+//!
+//! FASTA records become `id<TAB>description<TAB>sequence` and FASTQ
+//! records gain a fourth column of raw qualities, so files can be
+//! joined, sorted and edited with Unix tools and spreadsheets, then
+//! turned back into records with `TabReader`. Tabs and line breaks in
+//! descriptions are written as spaces.
+
+use std::io;
+use std::io::{BufRead, Lines, Write};
+
+use crate::errors::FastaError;
+use crate::fastq::FastqRecord;
+use crate::Record;
+
+/// A record read back from a tab-delimited line
+#[derive(Debug)]
+pub enum TabRecord {
+    /// A three-column line
+    Fasta(Record),
+    /// A four-column line
+    Fastq(FastqRecord),
+}
+
+fn clean(description: &str) -> String {
+    description.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Write a FASTA record as an `id`, `description`, `sequence` line
+pub fn write_fasta_tab<W: Write>(w: &mut W, rec: &Record) -> io::Result<()> {
+    let description = clean(&rec.description);
+    writeln!(w, "{}\t{}\t{}", rec.id, description, rec.sequence)
+}
+
+/// Write a FASTQ record as an `id`, `description`, `sequence`,
+/// `quality` line
+pub fn write_fastq_tab<W: Write>(w: &mut W, rec: &FastqRecord) -> io::Result<()> {
+    let description = clean(&rec.description);
+    writeln!(
+        w,
+        "{}\t{}\t{}\t{}",
+        rec.id, description, rec.sequence, rec.quality
+    )
+}
+
+fn format_error(line: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        FastaError::Format { line, message },
+    )
+}
+
+/// Parse one tab-delimited line; `line` numbers errors
+fn parse_line(text: &str, line: usize) -> io::Result<TabRecord> {
+    let fields: Vec<&str> = text.split('\t').collect();
+    match fields[..] {
+        ["", ..] => Err(format_error(line, "empty ID".to_owned())),
+        [id, description, sequence] => Ok(TabRecord::Fasta(Record {
+            id: id.to_owned(),
+            description: description.to_owned(),
+            sequence: sequence.to_owned(),
+        })),
+        [id, description, sequence, quality] => {
+            if quality.len() != sequence.len() {
+                let message = format!(
+                    "record {} has {} bases but {} qualities",
+                    id,
+                    sequence.len(),
+                    quality.len()
+                );
+                return Err(format_error(line, message));
+            }
+            Ok(TabRecord::Fastq(FastqRecord {
+                id: id.to_owned(),
+                description: description.to_owned(),
+                sequence: sequence.to_owned(),
+                quality: quality.to_owned(),
+            }))
+        }
+        _ => Err(format_error(
+            line,
+            format!(
+                "expected 3 or 4 tab-separated columns, found {}",
+                fields.len()
+            ),
+        )),
+    }
+}
+
+/// An iterator over the records of a tab-delimited file, skipping blank
+/// lines
+pub struct TabReader<B: BufRead> {
+    lines: Lines<B>,
+    line: usize,
+}
+
+impl<B: BufRead> TabReader<B> {
+    pub fn new(b: B) -> TabReader<B> {
+        TabReader {
+            lines: b.lines(),
+            line: 0,
+        }
+    }
+}
+
+impl<B: BufRead> Iterator for TabReader<B> {
+    type Item = io::Result<TabRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(e) => return Some(Err(e)),
+            };
+            self.line += 1;
+            let text = text.strip_suffix('\r').unwrap_or(&text);
+            if !text.is_empty() {
+                return Some(parse_line(text, self.line));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut out = Vec::new();
+        let rec = Record {
+            id: "a".to_owned(),
+            description: "x\ty".to_owned(),
+            sequence: "ACGT".to_owned(),
+        };
+        write_fasta_tab(&mut out, &rec).unwrap();
+        let read = FastqRecord {
+            id: "r1".to_owned(),
+            description: String::new(),
+            sequence: "AC".to_owned(),
+            quality: "II".to_owned(),
+        };
+        write_fastq_tab(&mut out, &read).unwrap();
+        assert_eq!(out, b"a\tx y\tACGT\nr1\t\tAC\tII\n");
+
+        let mut records = TabReader::new(&out[..]);
+        match records.next().unwrap().unwrap() {
+            TabRecord::Fasta(rec) => assert_eq!(rec.description, "x y"),
+            other => panic!("expected FASTA, got {:?}", other),
+        }
+        match records.next().unwrap().unwrap() {
+            TabRecord::Fastq(rec) => assert_eq!(rec.quality, "II"),
+            other => panic!("expected FASTQ, got {:?}", other),
+        }
+        assert!(records.next().is_none());
+
+        let bad = TabReader::new("\na\tACGT\n".as_bytes()).next().unwrap();
+        assert!(bad.unwrap_err().to_string().contains("line 2"));
+    }
+}
@@ -0,0 +1,422 @@
+//! Translation of nucleotide sequences to protein
+
+use std::io;
+use std::io::Write;
+
+use crate::seq::{base_mask, reverse_complement};
+use crate::Record;
+
+/// An NCBI genetic code (translation table)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    // Amino acids for the 64 codons in NCBI order (TCAG at each position)
+    amino_acids: &'static [u8; 64],
+}
+
+const CODES: &[GeneticCode] = &[
+    GeneticCode {
+        id: 1,
+        name: "Standard",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 2,
+        name: "Vertebrate Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 3,
+        name: "Yeast Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 4,
+        name: "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 5,
+        name: "Invertebrate Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 6,
+        name: "Ciliate, Dasycladacean and Hexamita Nuclear",
+        amino_acids: b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 9,
+        name: "Echinoderm and Flatworm Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 10,
+        name: "Euplotid Nuclear",
+        amino_acids: b"FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 11,
+        name: "Bacterial, Archaeal and Plant Plastid",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 12,
+        name: "Alternative Yeast Nuclear",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 13,
+        name: "Ascidian Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 14,
+        name: "Alternative Flatworm Mitochondrial",
+        amino_acids: b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 15,
+        name: "Blepharisma Macronuclear",
+        amino_acids: b"FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 16,
+        name: "Chlorophycean Mitochondrial",
+        amino_acids: b"FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 21,
+        name: "Trematode Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 22,
+        name: "Scenedesmus obliquus Mitochondrial",
+        amino_acids: b"FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 23,
+        name: "Thraustochytrium Mitochondrial",
+        amino_acids: b"FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 24,
+        name: "Rhabdopleuridae Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 25,
+        name: "Candidate Division SR1 and Gracilibacteria",
+        amino_acids: b"FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 26,
+        name: "Pachysolen tannophilus Nuclear",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 27,
+        name: "Karyorelict Nuclear",
+        amino_acids: b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 28,
+        name: "Condylostoma Nuclear",
+        amino_acids: b"FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 29,
+        name: "Mesodinium Nuclear",
+        amino_acids: b"FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 30,
+        name: "Peritrich Nuclear",
+        amino_acids: b"FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 31,
+        name: "Blastocrithidia Nuclear",
+        amino_acids: b"FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 32,
+        name: "Balanophoraceae Plastid",
+        amino_acids: b"FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+    },
+    GeneticCode {
+        id: 33,
+        name: "Cephalodiscidae Mitochondrial",
+        amino_acids: b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+    },
+];
+
+impl GeneticCode {
+    /// The standard genetic code (NCBI table 1)
+    pub fn standard() -> GeneticCode {
+        CODES[0]
+    }
+
+    /// Look up a genetic code by its NCBI translation table number
+    pub fn from_id(id: u8) -> Option<GeneticCode> {
+        CODES.iter().find(|c| c.id == id).copied()
+    }
+
+    /// All supported genetic codes
+    pub fn all() -> &'static [GeneticCode] {
+        CODES
+    }
+
+    /// Translate one codon.
+    ///
+    /// Case is ignored and U is read as T. Ambiguous codons translate to
+    /// an amino acid if every base they stand for agrees (e.g. `GCN` is
+    /// A) and to `X` otherwise; `---` becomes `-`.
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        if codon == b"---" {
+            return b'-';
+        }
+        if codon.len() != 3 {
+            return b'X';
+        }
+        let masks = [
+            base_mask(codon[0]),
+            base_mask(codon[1]),
+            base_mask(codon[2]),
+        ];
+        let mut aa = None;
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    if masks[0] & (1 << i) == 0
+                        || masks[1] & (1 << j) == 0
+                        || masks[2] & (1 << k) == 0
+                    {
+                        continue;
+                    }
+                    let this = self.amino_acids[i * 16 + j * 4 + k];
+                    match aa {
+                        None => aa = Some(this),
+                        Some(prev) if prev != this => return b'X',
+                        _ => (),
+                    }
+                }
+            }
+        }
+        aa.unwrap_or(b'X')
+    }
+}
+
+/// Translate `seq` in the given reading frame: 1, 2 or 3 on the forward
+/// strand, -1, -2 or -3 on the reverse complement. A trailing partial
+/// codon is ignored. Panics on any other frame.
+pub fn translate(seq: &str, code: &GeneticCode, frame: i8) -> String {
+    assert!(
+        matches!(frame, 1..=3 | -3..=-1),
+        "frame must be one of 1, 2, 3, -1, -2, -3"
+    );
+    let strand;
+    let bytes = if frame > 0 {
+        seq.as_bytes()
+    } else {
+        strand = reverse_complement(seq);
+        strand.as_bytes()
+    };
+    let start = (frame.unsigned_abs() - 1) as usize;
+    bytes
+        .get(start..)
+        .unwrap_or(&[])
+        .chunks_exact(3)
+        .map(|codon| code.translate_codon(codon) as char)
+        .collect()
+}
+
+impl Record {
+    /// Translate the record's sequence in `frame` (see `translate::translate`),
+    /// keeping its ID and description
+    pub fn translate(&self, code: &GeneticCode, frame: i8) -> Record {
+        Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            sequence: translate(&self.sequence, code, frame),
+        }
+    }
+
+    /// Translate the record in all six frames: 1, 2, 3, -1, -2, -3.
+    ///
+    /// Each protein's ID is the record's with `_f{frame}` appended, as from
+    /// `fasta translate --all-frames`, and its description starts with
+    /// `frame={frame} strand={+|-}`, followed by the record's description.
+    pub fn translate_six_frames(&self, code: &GeneticCode) -> Vec<Record> {
+        [1, 2, 3, -1, -2, -3]
+            .iter()
+            .map(|&frame| {
+                let strand = if frame > 0 { '+' } else { '-' };
+                let mut description = format!("frame={} strand={}", frame, strand);
+                if !self.description.is_empty() {
+                    description.push(' ');
+                    description.push_str(&self.description);
+                }
+                Record {
+                    id: format!("{}_f{}", self.id, frame),
+                    description,
+                    sequence: translate(&self.sequence, code, frame),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Options for `translate_stream`
+#[derive(Debug, Clone)]
+pub struct TranslateOptions {
+    pub code: GeneticCode,
+    /// Frames to translate, each producing one output record
+    pub frames: Vec<i8>,
+    /// Appended to each output ID; `{frame}` is replaced by the frame number
+    pub id_suffix: String,
+    /// Protein line width, 0 for unwrapped output
+    pub line_width: usize,
+    /// End each protein before its first stop codon
+    pub trim_at_stop: bool,
+}
+
+impl Default for TranslateOptions {
+    /// Standard code, frame 1, no suffix, 80 column lines, stops kept
+    fn default() -> TranslateOptions {
+        TranslateOptions {
+            code: GeneticCode::standard(),
+            frames: vec![1],
+            id_suffix: String::new(),
+            line_width: 80,
+            trim_at_stop: false,
+        }
+    }
+}
+
+/// Translate each record and write the protein records to `writer`,
+/// returning the number of protein records written.
+///
+/// Only the record being translated is held in memory.
+pub fn translate_stream<I, W>(
+    records: I,
+    writer: &mut W,
+    opts: &TranslateOptions,
+) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Record, io::Error>>,
+    W: Write,
+{
+    if let Some(f) = opts.frames.iter().find(|f| !matches!(f, 1..=3 | -3..=-1)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid reading frame {}", f),
+        ));
+    }
+    let mut written = 0;
+    for rec in records {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) => {
+                writer.flush()?;
+                return Err(e);
+            }
+        };
+        for &frame in &opts.frames {
+            let mut protein = rec.translate(&opts.code, frame);
+            if opts.trim_at_stop {
+                if let Some(stop) = protein.sequence.find('*') {
+                    protein.sequence.truncate(stop);
+                }
+            }
+            protein
+                .id
+                .push_str(&opts.id_suffix.replace("{frame}", &frame.to_string()));
+            protein.write_with_width(writer, opts.line_width)?;
+            written += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FastaBuffer;
+
+    #[test]
+    fn codons_and_frames() {
+        let code = GeneticCode::standard();
+        assert_eq!(code.translate_codon(b"ATG"), b'M');
+        assert_eq!(code.translate_codon(b"uaa"), b'*');
+        assert_eq!(code.translate_codon(b"GCN"), b'A');
+        assert_eq!(code.translate_codon(b"TAR"), b'*');
+        assert_eq!(code.translate_codon(b"NNN"), b'X');
+        assert_eq!(translate("ATGGCCTAAG", &code, 1), "MA*");
+        assert_eq!(translate("ATGGCCTAAG", &code, 2), "WPK");
+        assert_eq!(translate("ATGGCCTAAG", &code, -1), "LRP");
+
+        let mito = GeneticCode::from_id(2).unwrap();
+        assert_eq!(translate("TGAAGA", &mito, 1), "W*");
+
+        let ids: Vec<u8> = GeneticCode::all().iter().map(|c| c.id).collect();
+        let expected: Vec<u8> = (1..=6).chain(9..=16).chain(21..=33).collect();
+        assert_eq!(ids, expected);
+        let code = |id| GeneticCode::from_id(id).unwrap();
+        assert_eq!(translate("TAGTCA", &code(22), 1), "L*");
+        assert_eq!(translate("TTACTG", &code(23), 1), "*L");
+        assert_eq!(translate("CTGTGA", &code(26), 1), "A*");
+        assert_eq!(translate("TAATGAAGG", &code(33), 1), "YWK");
+    }
+
+    #[test]
+    fn six_frames() {
+        let rec = Record {
+            id: "g".to_owned(),
+            description: "gene".to_owned(),
+            sequence: "ATGGCCTAAG".to_owned(),
+        };
+        let proteins = rec.translate_six_frames(&GeneticCode::standard());
+        let ids: Vec<&str> = proteins.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["g_f1", "g_f2", "g_f3", "g_f-1", "g_f-2", "g_f-3"]);
+        assert_eq!(proteins[1].sequence, "WPK");
+        assert_eq!(proteins[3].sequence, "LRP");
+        assert_eq!(proteins[3].description, "frame=-1 strand=- gene");
+        let bare = Record {
+            description: String::new(),
+            ..rec
+        };
+        let proteins = bare.translate_six_frames(&GeneticCode::standard());
+        assert_eq!(proteins[0].description, "frame=1 strand=+");
+    }
+
+    #[test]
+    fn stream() {
+        let input = ">g1 gene\nATGGCCAAATTT\n>g2\nATGTAA\n";
+        let opts = TranslateOptions {
+            frames: vec![1, -1],
+            id_suffix: "_f{frame}".to_owned(),
+            line_width: 2,
+            ..TranslateOptions::default()
+        };
+        let mut out = Vec::new();
+        let n = translate_stream(FastaBuffer::from(input.as_bytes()), &mut out, &opts).unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            ">g1_f1 gene\nMA\nKF\n>g1_f-1 gene\nKF\nGH\n>g2_f1\nM*\n>g2_f-1\nLH\n"
+        );
+
+        let opts = TranslateOptions {
+            trim_at_stop: true,
+            ..TranslateOptions::default()
+        };
+        let mut out = Vec::new();
+        let input = ">g\nATGGCCTAAATG\n";
+        translate_stream(FastaBuffer::from(input.as_bytes()), &mut out, &opts).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), ">g\nMA\n");
+    }
+}
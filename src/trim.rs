@@ -0,0 +1,140 @@
+//! Trimming sequences to a range, by a number of bases, or of terminal Ns
+
+use std::ops::Range;
+
+use crate::errors::FastaError;
+use crate::Record;
+
+impl Record {
+    /// Keep only the 0-based, half-open `range` of the sequence.
+    ///
+    /// The range must lie within the sequence and fall on character
+    /// boundaries; otherwise an error is returned and the record is left
+    /// unchanged.
+    pub fn trim(&mut self, range: Range<usize>) -> Result<(), FastaError> {
+        self.check_range(&range)?;
+        self.sequence.truncate(range.end);
+        self.sequence.replace_range(..range.start, "");
+        Ok(())
+    }
+
+    /// A copy of the record with its sequence trimmed to `range`, see
+    /// `trim`
+    pub fn trimmed(&self, range: Range<usize>) -> Result<Record, FastaError> {
+        self.check_range(&range)?;
+        Ok(Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            sequence: self.sequence[range].to_owned(),
+        })
+    }
+
+    /// Remove the first `n` bases, failing if there are fewer
+    pub fn trim_start(&mut self, n: usize) -> Result<(), FastaError> {
+        let len = self.check_count(n)?;
+        self.trim(n..len)
+    }
+
+    /// Remove the last `n` bases, failing if there are fewer
+    pub fn trim_end(&mut self, n: usize) -> Result<(), FastaError> {
+        let len = self.check_count(n)?;
+        self.trim(0..len - n)
+    }
+
+    /// Remove runs of `N` or `n` from both ends of the sequence, returning
+    /// the range of the original sequence that was kept. A sequence of
+    /// only Ns becomes empty.
+    pub fn trim_terminal_ns(&mut self) -> Range<usize> {
+        let bytes = self.sequence.as_bytes();
+        let start = bytes
+            .iter()
+            .position(|b| !b.eq_ignore_ascii_case(&b'N'))
+            .unwrap_or(bytes.len());
+        let end = bytes
+            .iter()
+            .rposition(|b| !b.eq_ignore_ascii_case(&b'N'))
+            .map_or(start, |i| i + 1);
+        self.sequence.truncate(end);
+        self.sequence.replace_range(..start, "");
+        start..end
+    }
+
+    /// The sequence length, if it has at least `n` bases
+    fn check_count(&self, n: usize) -> Result<usize, FastaError> {
+        let len = self.sequence.len();
+        if n > len {
+            return Err(FastaError::OutOfBounds {
+                id: self.id.clone(),
+                start: 0,
+                end: n,
+                len,
+            });
+        }
+        Ok(len)
+    }
+
+    fn check_range(&self, range: &Range<usize>) -> Result<(), FastaError> {
+        let len = self.sequence.len();
+        if range.start > range.end || range.end > len {
+            return Err(FastaError::OutOfBounds {
+                id: self.id.clone(),
+                start: range.start,
+                end: range.end,
+                len,
+            });
+        }
+        for position in [range.start, range.end] {
+            if !self.sequence.is_char_boundary(position) {
+                let found = self.sequence[..position].chars().next_back().unwrap_or('?');
+                return Err(FastaError::InvalidSequence {
+                    id: self.id.clone(),
+                    position,
+                    found,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(sequence: &str) -> Record {
+        Record {
+            id: "r".to_owned(),
+            description: String::new(),
+            sequence: sequence.to_owned(),
+        }
+    }
+
+    #[test]
+    fn trim_by_range_and_count() {
+        let mut r = rec("ACGTACGT");
+        assert_eq!(r.trimmed(2..5).unwrap().sequence, "GTA");
+        r.trim_start(1).unwrap();
+        r.trim_end(2).unwrap();
+        assert_eq!(r.sequence, "CGTAC");
+        r.trim(1..3).unwrap();
+        assert_eq!(r.sequence, "GT");
+
+        assert!(matches!(
+            r.trim_end(3),
+            Err(FastaError::OutOfBounds { end: 3, len: 2, .. })
+        ));
+        assert!(r.trim_start(3).is_err());
+        assert!(r.trim(1..4).is_err());
+        assert_eq!(r.sequence, "GT");
+    }
+
+    #[test]
+    fn terminal_ns() {
+        let mut r = rec("nNACNGTNNN");
+        assert_eq!(r.trim_terminal_ns(), 2..7);
+        assert_eq!(r.sequence, "ACNGT");
+        let mut r = rec("NNN");
+        assert_eq!(r.trim_terminal_ns(), 3..3);
+        assert_eq!(r.sequence, "");
+    }
+}
@@ -0,0 +1,101 @@
+//! Extraction of unique molecular identifiers (UMIs) from read sequences
+
+use crate::errors::FastaError;
+use crate::Record;
+
+/// SAM tag used to record the extracted UMI in the record description
+pub const UMI_TAG: &str = "RX:Z:";
+
+/// Remove a UMI from the start of a record's sequence.
+///
+/// The pattern follows the umi_tools convention: each `N` marks a UMI
+/// base and each `X` marks a base that is kept in the read. The UMI
+/// bases are appended to the description as an `RX:Z:<umi>` tag and
+/// the returned record holds the remaining sequence.
+pub fn extract_umi(record: &Record, pattern: &str) -> Result<Record, FastaError> {
+    if pattern.is_empty() {
+        return Err(FastaError::InvalidPattern("empty UMI pattern".to_owned()));
+    }
+    if let Some(c) = pattern.chars().find(|c| !matches!(c, 'N' | 'X')) {
+        return Err(FastaError::InvalidPattern(format!(
+            "unexpected character '{}' in UMI pattern {}",
+            c, pattern
+        )));
+    }
+    if record.sequence.len() < pattern.len() {
+        return Err(FastaError::SequenceTooShort {
+            id: record.id.clone(),
+            len: record.sequence.len(),
+            needed: pattern.len(),
+        });
+    }
+    if let Some((position, found)) = record
+        .sequence
+        .chars()
+        .take(pattern.len())
+        .enumerate()
+        .find(|(_, c)| !c.is_ascii())
+    {
+        return Err(FastaError::InvalidSequence {
+            id: record.id.clone(),
+            position,
+            found,
+        });
+    }
+    let prefix = &record.sequence[..pattern.len()];
+
+    let mut umi = String::new();
+    let mut kept = String::new();
+    for (c, p) in prefix.chars().zip(pattern.chars()) {
+        match p {
+            'N' => umi.push(c),
+            _ => kept.push(c),
+        }
+    }
+    kept.push_str(&record.sequence[pattern.len()..]);
+
+    let description = if record.description.is_empty() {
+        format!("{}{}", UMI_TAG, umi)
+    } else {
+        format!("{} {}{}", record.description, UMI_TAG, umi)
+    };
+
+    Ok(Record {
+        id: record.id.clone(),
+        description,
+        sequence: kept,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_with_kept_bases() {
+        let rec = Record {
+            id: "r1".to_owned(),
+            description: "sample=A".to_owned(),
+            sequence: "AACCGGTT".to_owned(),
+        };
+        let out = extract_umi(&rec, "NNXN").unwrap();
+        assert_eq!(out.sequence, "CGGTT");
+        assert_eq!(out.description, "sample=A RX:Z:AAC");
+
+        assert!(extract_umi(&rec, "NNNNNNNNNN").is_err());
+        assert!(extract_umi(&rec, "NNQ").is_err());
+
+        let rec = Record {
+            sequence: "AéCGTACGT".to_owned(),
+            ..rec
+        };
+        match extract_umi(&rec, "NN") {
+            Err(FastaError::InvalidSequence {
+                position, found, ..
+            }) => {
+                assert_eq!((position, found), (1, 'é'))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Checking FASTA files for problems without parsing them into records
+//!
+//! `validate` feeds a file line by line to a `Parser` with the options
+//! of `FastaReaderBuilder::strict`, so comment lines are not accepted,
+//! and reports what it rejects (sequence before the first header,
+//! characters outside an alphabet) as errors, together with invalid
+//! UTF-8, headers without an ID and duplicate IDs. Lines end at `\n`,
+//! `\r\n` or a lone `\r`, as for `FastaBuffer`. Empty sequences, blank
+//! lines followed by more sequence, mixed line endings and a missing
+//! final newline are warnings. Only the first invalid line of a record
+//! is reported, as the parser discards the rest of the record.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+use crate::cancel::{self, CancellationToken};
+use crate::parser::{Alphabet, ParseError, ParseOptions, Parser, Step};
+
+/// How serious an `Issue` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// 1-based line number, 0 for problems of the whole file
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if self.line == 0 {
+            write!(f, "{}: {}", severity, self.message)
+        } else {
+            write!(f, "line {}: {}: {}", self.line, severity, self.message)
+        }
+    }
+}
+
+/// Options for `validate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidateOptions {
+    /// Characters allowed in sequence lines
+    pub alphabet: Alphabet,
+    /// Issues kept in the report; later ones are only counted
+    pub max_issues: usize,
+}
+
+impl Default for ValidateOptions {
+    /// Any alphabet, keeping the first 100 issues
+    fn default() -> ValidateOptions {
+        ValidateOptions {
+            alphabet: Alphabet::Any,
+            max_issues: 100,
+        }
+    }
+}
+
+/// The outcome of `validate`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub records: u64,
+    pub errors: u64,
+    pub warnings: u64,
+    /// The first `max_issues` issues, in input order except that
+    /// whole-file warnings come last
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    /// Whether the file passed: no errors, and with `strict` no warnings
+    pub fn is_valid(&self, strict: bool) -> bool {
+        self.errors == 0 && (!strict || self.warnings == 0)
+    }
+
+    fn add(&mut self, max: usize, severity: Severity, line: usize, message: String) {
+        let at = self.issues.len();
+        self.add_at(at, max, severity, line, message);
+    }
+
+    /// Add an issue found late, such as an empty sequence seen only at
+    /// the next header, at index `at` to keep the issues in input order
+    fn add_at(&mut self, at: usize, max: usize, severity: Severity, line: usize, message: String) {
+        match severity {
+            Severity::Warning => self.warnings += 1,
+            Severity::Error => self.errors += 1,
+        }
+        if at < max {
+            self.issues.insert(
+                at,
+                Issue {
+                    severity,
+                    line,
+                    message,
+                },
+            );
+            self.issues.truncate(max);
+        }
+    }
+}
+
+fn alphabet_name(alphabet: Alphabet) -> &'static str {
+    match alphabet {
+        Alphabet::Any => "any",
+        Alphabet::Dna => "DNA",
+        Alphabet::Rna => "RNA",
+        Alphabet::Protein => "protein",
+    }
+}
+
+/// The record being read
+struct Current {
+    id: String,
+    line: usize,
+    /// Index in `Report::issues` that the record's issues start at
+    first_issue: usize,
+    /// The first of the blank lines since its last sequence line
+    blank: Option<usize>,
+}
+
+/// How a line ended
+enum Ending {
+    Lf,
+    Crlf,
+    Cr,
+    /// The last line of a file without a final newline
+    Eof,
+}
+
+/// Read the next line into `line` without its ending, which is `\n`,
+/// `\r\n` or a lone `\r`. Returns None at the end of the input.
+fn read_line<R: BufRead>(r: &mut R, line: &mut Vec<u8>) -> io::Result<Option<Ending>> {
+    line.clear();
+    loop {
+        let buf = match r.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if buf.is_empty() {
+            return Ok(if line.is_empty() {
+                None
+            } else {
+                Some(Ending::Eof)
+            });
+        }
+        let i = match memchr::memchr2(b'\n', b'\r', buf) {
+            Some(i) => i,
+            None => {
+                let n = buf.len();
+                line.extend_from_slice(buf);
+                r.consume(n);
+                continue;
+            }
+        };
+        line.extend_from_slice(&buf[..i]);
+        let cr = buf[i] == b'\r';
+        r.consume(i + 1);
+        if !cr {
+            return Ok(Some(Ending::Lf));
+        }
+        let next = loop {
+            match r.fill_buf() {
+                Ok(buf) => break buf.first().copied(),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        };
+        if next == Some(b'\n') {
+            r.consume(1);
+            return Ok(Some(Ending::Crlf));
+        }
+        return Ok(Some(Ending::Cr));
+    }
+}
+
+/// Check a FASTA file, as described in the module documentation
+pub fn validate<R: BufRead>(r: R, opts: &ValidateOptions) -> io::Result<Report> {
+    validate_with_cancel(r, opts, &CancellationToken::new())
+}
+
+/// Check a file as with `validate`, stopping with a cancellation error
+/// once `token` is cancelled
+pub fn validate_with_cancel<R: BufRead>(
+    mut r: R,
+    opts: &ValidateOptions,
+    token: &CancellationToken,
+) -> io::Result<Report> {
+    let mut report = Report::default();
+    let max = opts.max_issues;
+    let mut parser = Parser::new(ParseOptions {
+        alphabet: opts.alphabet,
+        // Reported as warnings below rather than rejected
+        allow_blank_lines: true,
+        ..ParseOptions::strict()
+    });
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut current: Option<Current> = None;
+    let (mut lf, mut crlf, mut cr) = (0u64, 0u64, 0u64);
+    let mut missing_newline = false;
+    let mut line = Vec::new();
+    let mut n = 0;
+
+    let empty = |rec: &Current, report: &mut Report| {
+        let message = format!("record {} has an empty sequence", rec.id);
+        report.add_at(rec.first_issue, max, Severity::Warning, rec.line, message);
+    };
+
+    loop {
+        if token.is_cancelled() {
+            return Err(cancel::cancelled());
+        }
+        match read_line(&mut r, &mut line)? {
+            None => break,
+            Some(Ending::Lf) => lf += 1,
+            Some(Ending::Crlf) => crlf += 1,
+            Some(Ending::Cr) => cr += 1,
+            Some(Ending::Eof) => missing_newline = true,
+        }
+        n += 1;
+        // The line still goes to the parser, so that it counts as
+        // sequence or a header, but nothing more is reported for it
+        let bad_utf8 = match std::str::from_utf8(&line) {
+            Ok(_) => false,
+            Err(e) => {
+                let message = format!("invalid UTF-8 at column {}", e.valid_up_to() + 1);
+                report.add(max, Severity::Error, n, message);
+                true
+            }
+        };
+        let content = String::from_utf8_lossy(&line);
+
+        loop {
+            match parser.push_line(&content, false) {
+                Ok(Step::Done(_)) => {
+                    match &current {
+                        Some(rec) if parser.seq_len() == 0 => empty(rec, &mut report),
+                        _ => (),
+                    }
+                    continue;
+                }
+                Ok(Step::Header) => {
+                    report.records += 1;
+                    let id = parser.id().to_owned();
+                    if id.is_empty() {
+                        report.add(max, Severity::Error, n, "header without an ID".to_owned());
+                    } else if let Some(first) = seen.get(&id) {
+                        let message = format!("duplicate ID {}, first seen at line {}", id, first);
+                        report.add(max, Severity::Error, n, message);
+                    } else {
+                        seen.insert(id.clone(), n);
+                    }
+                    current = Some(Current {
+                        id,
+                        line: n,
+                        first_issue: report.issues.len(),
+                        blank: None,
+                    });
+                }
+                // Only a blank line followed by more sequence is
+                // rejected by the strict parser. The rest of a record
+                // discarded after an error is skipped too, but never
+                // followed by sequence.
+                Ok(Step::Skipped) => {
+                    if let Some(rec) = &mut current {
+                        rec.blank.get_or_insert(n);
+                    }
+                }
+                Ok(Step::Sequence) => {
+                    if let Some(rec) = &mut current {
+                        if let Some(blank) = rec.blank.take() {
+                            let message = format!("blank line in record {}", rec.id);
+                            report.add(max, Severity::Warning, blank, message);
+                        }
+                    }
+                }
+                Ok(Step::Junk) => (),
+                Err(_) if bad_utf8 => (),
+                Err(ParseError::LeadingJunk) => {
+                    let message = "sequence data before the first header".to_owned();
+                    report.add(max, Severity::Error, n, message);
+                }
+                Err(ParseError::InvalidByte { column, byte }) => {
+                    let id = current.as_ref().map_or("", |rec| rec.id.as_str());
+                    let message = format!(
+                        "invalid {} character {:?} at column {} in record {}",
+                        alphabet_name(opts.alphabet),
+                        char::from(byte),
+                        column,
+                        id
+                    );
+                    report.add(max, Severity::Error, n, message);
+                }
+                Err(e) => report.add(max, Severity::Error, n, e.to_string()),
+            }
+            break;
+        }
+    }
+    let unfinished = parser.finish().is_some();
+    match &current {
+        Some(rec) if unfinished && parser.seq_len() == 0 => empty(rec, &mut report),
+        _ => (),
+    }
+
+    let endings: Vec<String> = [(lf, "LF"), (crlf, "CRLF"), (cr, "CR")]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect();
+    if let [rest @ .., last] = &endings[..] {
+        if !rest.is_empty() {
+            let message = format!("mixed line endings: {} and {} lines", rest.join(", "), last);
+            report.add(max, Severity::Warning, 0, message);
+        }
+    }
+    if missing_newline {
+        let message = "no newline at the end of the file".to_owned();
+        report.add(max, Severity::Warning, 0, message);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_and_warnings() {
+        let input = "ACGT\n>a\nACXT\r\n>b\n\n>a dup\nAC\n\n\nGT\n> \nA";
+        let opts = ValidateOptions {
+            alphabet: Alphabet::Dna,
+            ..ValidateOptions::default()
+        };
+        let report = validate(input.as_bytes(), &opts).unwrap();
+        let lines: Vec<String> = report.issues.iter().map(Issue::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "line 1: error: sequence data before the first header",
+                "line 3: error: invalid DNA character 'X' at column 3 in record a",
+                "line 4: warning: record b has an empty sequence",
+                "line 6: error: duplicate ID a, first seen at line 2",
+                "line 8: warning: blank line in record a",
+                "line 11: error: header without an ID",
+                "warning: mixed line endings: 10 LF and 1 CRLF lines",
+                "warning: no newline at the end of the file",
+            ]
+        );
+        assert_eq!((report.records, report.errors, report.warnings), (4, 4, 4));
+        assert!(!report.is_valid(false));
+
+        let clean = validate(">a\nACGT\n".as_bytes(), &opts).unwrap();
+        assert!(clean.is_valid(true));
+    }
+
+    #[test]
+    fn strict_parsing() {
+        let input = ";comment\n>a\nAC;\nXX\n>b\n";
+        let opts = ValidateOptions {
+            alphabet: Alphabet::Dna,
+            max_issues: 2,
+        };
+        let report = validate(input.as_bytes(), &opts).unwrap();
+        let lines: Vec<String> = report.issues.iter().map(Issue::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "line 1: error: sequence data before the first header",
+                "line 3: error: invalid DNA character ';' at column 3 in record a",
+            ]
+        );
+        assert_eq!((report.errors, report.warnings), (2, 1));
+
+        let opts = ValidateOptions {
+            max_issues: 1,
+            ..ValidateOptions::default()
+        };
+        let report = validate(">a\n\n>b\n\nAC\n".as_bytes(), &opts).unwrap();
+        assert_eq!(
+            report.issues[0].to_string(),
+            "line 1: warning: record a has an empty sequence"
+        );
+        assert_eq!((report.issues.len(), report.warnings), (1, 2));
+    }
+
+    #[test]
+    fn line_endings_and_utf8() {
+        let opts = ValidateOptions::default();
+        let report = validate(">a\rACGT\r>b\rGG\r".as_bytes(), &opts).unwrap();
+        assert_eq!(report.records, 2);
+        assert!(report.is_valid(true));
+
+        let report = validate(">a\rAC\r\n>b\nGG\n".as_bytes(), &opts).unwrap();
+        assert_eq!(
+            report.issues[0].to_string(),
+            "warning: mixed line endings: 2 LF, 1 CRLF and 1 CR lines"
+        );
+
+        let report = validate(&b">a\nAC\xffGT\n"[..], &opts).unwrap();
+        assert_eq!(
+            report.issues[0].to_string(),
+            "line 2: error: invalid UTF-8 at column 3"
+        );
+        assert!(!report.is_valid(false));
+    }
+}
@@ -0,0 +1,246 @@
+//! Browser-side validation and summaries
+//!
+//! Built with the `wasm` feature, which needs neither `std` nor file I/O,
+//! for `wasm32-unknown-unknown`. `Summarizer`, `Summary` and `summarize`
+//! are exported with `#[wasm_bindgen]`, so the `wasm-bindgen` CLI makes
+//! a JavaScript module of a `cdylib` build; multi-word methods take
+//! camelCase names there. Input arrives in chunks of any size, as
+//! read from a `File` or `ReadableStream`, and sequences are never kept.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::parser::{ParseOptions, Parser, Step};
+
+/// What `Summarizer::finish` reports about an input
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    records: u64,
+    total_length: u64,
+    min_length: u64,
+    max_length: u64,
+    n50: u64,
+    gc_content: f64,
+    error: Option<String>,
+    error_line: Option<u64>,
+}
+
+#[wasm_bindgen]
+impl Summary {
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    #[wasm_bindgen(js_name = totalLength)]
+    pub fn total_length(&self) -> u64 {
+        self.total_length
+    }
+
+    #[wasm_bindgen(js_name = minLength)]
+    pub fn min_length(&self) -> u64 {
+        self.min_length
+    }
+
+    #[wasm_bindgen(js_name = maxLength)]
+    pub fn max_length(&self) -> u64 {
+        self.max_length
+    }
+
+    pub fn n50(&self) -> u64 {
+        self.n50
+    }
+
+    /// GC fraction among unambiguous bases, as in `Record::gc_content`
+    #[wasm_bindgen(js_name = gcContent)]
+    pub fn gc_content(&self) -> f64 {
+        self.gc_content
+    }
+
+    /// True if the input parsed without errors and held a record
+    #[wasm_bindgen(js_name = isValid)]
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Why the input is invalid
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    /// Line of the first error, numbered from 1
+    #[wasm_bindgen(js_name = errorLine)]
+    pub fn error_line(&self) -> Option<u64> {
+        self.error_line
+    }
+}
+
+/// Incremental validator and summarizer fed with chunks of input
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Summarizer {
+    parser: Parser,
+    partial: Vec<u8>,
+    after_cr: bool,
+    line: u64,
+    lengths: Vec<u64>,
+    gc: u64,
+    at: u64,
+    error: Option<(String, u64)>,
+}
+
+#[wasm_bindgen]
+impl Summarizer {
+    /// A summarizer using the default parsing options, or if `strict` is
+    /// set those of `FastaReaderBuilder::strict`
+    #[wasm_bindgen(constructor)]
+    pub fn new(strict: bool) -> Summarizer {
+        let opts = if strict {
+            ParseOptions::strict()
+        } else {
+            ParseOptions::default()
+        };
+        Summarizer {
+            parser: Parser::new(opts),
+            partial: Vec::new(),
+            after_cr: false,
+            line: 0,
+            lengths: Vec::new(),
+            gc: 0,
+            at: 0,
+            error: None,
+        }
+    }
+
+    /// Feed the next chunk. Lines may span chunks. Returns false once an
+    /// error has been found; later input is ignored.
+    pub fn push(&mut self, chunk: &[u8]) -> bool {
+        for &b in chunk {
+            if self.error.is_some() {
+                break;
+            }
+            match b {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\n' | b'\r' => {
+                    self.after_cr = b == b'\r';
+                    self.end_line();
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.partial.push(b);
+                }
+            }
+        }
+        self.error.is_none()
+    }
+
+    /// Summarize everything pushed so far
+    pub fn finish(mut self) -> Summary {
+        if !self.partial.is_empty() {
+            self.end_line();
+        }
+        if self.error.is_none() {
+            if self.parser.finish().is_some() {
+                self.lengths.push(self.parser.seq_len() as u64);
+            }
+            if self.lengths.is_empty() {
+                self.error = Some(("no FASTA records found".to_string(), self.line.max(1)));
+            }
+        }
+
+        let mut summary = Summary {
+            records: self.lengths.len() as u64,
+            total_length: self.lengths.iter().sum(),
+            min_length: self.lengths.iter().copied().min().unwrap_or(0),
+            max_length: self.lengths.iter().copied().max().unwrap_or(0),
+            ..Summary::default()
+        };
+        if self.gc + self.at > 0 {
+            summary.gc_content = self.gc as f64 / (self.gc + self.at) as f64;
+        }
+        self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        let mut acc = 0;
+        for &len in &self.lengths {
+            acc += len;
+            if 2 * acc >= summary.total_length {
+                summary.n50 = len;
+                break;
+            }
+        }
+        if let Some((message, line)) = self.error {
+            summary.error = Some(message);
+            summary.error_line = Some(line);
+        }
+        summary
+    }
+}
+
+impl Summarizer {
+    fn end_line(&mut self) {
+        self.line += 1;
+        let bytes = core::mem::take(&mut self.partial);
+        let line = match core::str::from_utf8(&bytes) {
+            Ok(line) => line,
+            Err(_) => {
+                self.error = Some(("line is not valid UTF-8".to_string(), self.line));
+                return;
+            }
+        };
+        loop {
+            match self.parser.push_line(line, false) {
+                Ok(Step::Done(_)) => self.lengths.push(self.parser.seq_len() as u64),
+                Ok(Step::Sequence) => {
+                    for b in line.bytes() {
+                        match b.to_ascii_uppercase() {
+                            b'G' | b'C' | b'S' => self.gc += 1,
+                            b'A' | b'T' | b'U' | b'W' => self.at += 1,
+                            _ => (),
+                        }
+                    }
+                    break;
+                }
+                Ok(_) => break,
+                Err(e) => {
+                    self.error = Some((e.to_string(), self.line));
+                    break;
+                }
+            }
+        }
+        self.partial = bytes;
+        self.partial.clear();
+    }
+}
+
+/// Validate and summarize a whole input held in memory
+#[wasm_bindgen]
+pub fn summarize(bytes: &[u8], strict: bool) -> Summary {
+    let mut summarizer = Summarizer::new(strict);
+    summarizer.push(bytes);
+    summarizer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_input() {
+        let input = b">a\r\nACGT\r\nGG\r\n>b\r\nTTN\r\n";
+        let mut summarizer = Summarizer::new(false);
+        for chunk in input.chunks(3) {
+            assert!(summarizer.push(chunk));
+        }
+        let summary = summarizer.finish();
+        assert!(summary.is_valid());
+        assert_eq!(summary.records(), 2);
+        assert_eq!(summary.total_length(), 9);
+        assert_eq!(summary.n50(), 6);
+        assert!((summary.gc_content() - 0.5).abs() < 1e-12);
+
+        let bad = summarize(b">a\nAC\n\nGT\n", true);
+        assert_eq!(bad.error_line(), Some(4));
+        assert!(!summarize(b"", false).is_valid());
+    }
+}
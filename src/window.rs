@@ -0,0 +1,144 @@
+//! Sliding windows over a sequence
+//!
+//! `Record::windows` yields `(start, subsequence)` pairs for windows of a
+//! fixed size started every `step` bases. `Partial` picks what happens
+//! at the end of the sequence, where a window would run past it.
+//! Analyses over windows, such as `Record::gc_windows`, follow the same
+//! schedule.
+
+use crate::Record;
+
+/// Which windows shorter than the window size `Windows` yields at the
+/// end of a sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Partial {
+    /// Only full windows
+    #[default]
+    Skip,
+    /// Also one final shorter window covering the end of the sequence,
+    /// if the full windows stop short of it
+    Last,
+    /// Every window whose start is within the sequence, cut short at its
+    /// end
+    All,
+}
+
+/// Iterator over `(start, subsequence)` for windows of a sequence, with
+/// 0-based starts. Created by `Record::windows`.
+#[derive(Debug, Clone)]
+pub struct Windows<'a> {
+    seq: &'a str,
+    size: usize,
+    step: usize,
+    partial: Partial,
+    start: usize,
+    done: bool,
+}
+
+impl<'a> Windows<'a> {
+    pub(crate) fn new(seq: &'a str, size: usize, step: usize) -> Windows<'a> {
+        assert!(size > 0 && step > 0, "window and step must be non-zero");
+        Windows {
+            seq,
+            size,
+            step,
+            partial: Partial::default(),
+            start: 0,
+            done: false,
+        }
+    }
+
+    /// Set which windows shorter than the window size are yielded
+    pub fn partial(mut self, partial: Partial) -> Windows<'a> {
+        self.partial = partial;
+        self
+    }
+
+    /// The byte range of the next window
+    pub(crate) fn next_range(&mut self) -> Option<(usize, usize)> {
+        let len = self.seq.len();
+        if self.done || self.start >= len {
+            return None;
+        }
+        let start = self.start;
+        let mut end = start.saturating_add(self.size);
+        if end > len {
+            let covered = start > 0 && (start - self.step).saturating_add(self.size) >= len;
+            match self.partial {
+                Partial::Skip => {
+                    self.done = true;
+                    return None;
+                }
+                // The previous full window already reached the end
+                Partial::Last if covered => {
+                    self.done = true;
+                    return None;
+                }
+                Partial::Last => self.done = true,
+                Partial::All => (),
+            }
+            end = len;
+        }
+        self.start = start.saturating_add(self.step);
+        Some((start, end))
+    }
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let (start, end) = self.next_range()?;
+        Some((start, &self.seq[start..end]))
+    }
+}
+
+impl Record {
+    /// Windows of `size` bases starting every `step` bases, as
+    /// `(start, subsequence)` pairs; only full windows unless set with
+    /// `Windows::partial`. Positions are byte offsets, so a window
+    /// splitting a non-ASCII character panics, as does a `size` or `step`
+    /// of 0.
+    pub fn windows(&self, size: usize, step: usize) -> Windows<'_> {
+        Windows::new(&self.sequence, size, step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_policies() {
+        let rec = Record {
+            sequence: "ACGTACG".to_owned(),
+            ..Record::new()
+        };
+        let full: Vec<(usize, &str)> = rec.windows(3, 2).collect();
+        assert_eq!(full, [(0, "ACG"), (2, "GTA"), (4, "ACG")]);
+
+        let last: Vec<(usize, &str)> = rec.windows(4, 2).partial(Partial::Last).collect();
+        assert_eq!(last, [(0, "ACGT"), (2, "GTAC"), (4, "ACG")]);
+        let last: Vec<(usize, &str)> = rec.windows(3, 2).partial(Partial::Last).collect();
+        assert_eq!(last, full);
+
+        let all: Vec<(usize, &str)> = rec.windows(4, 2).partial(Partial::All).collect();
+        assert_eq!(all, [(0, "ACGT"), (2, "GTAC"), (4, "ACG"), (6, "G")]);
+    }
+
+    #[test]
+    fn huge_size_and_step() {
+        let rec = Record {
+            sequence: "ACGT".to_owned(),
+            ..Record::new()
+        };
+        assert_eq!(rec.windows(usize::MAX, 1).count(), 0);
+        let all: Vec<(usize, &str)> = rec
+            .windows(usize::MAX, usize::MAX)
+            .partial(Partial::All)
+            .collect();
+        assert_eq!(all, [(0, "ACGT")]);
+        let last: Vec<(usize, &str)> = rec.windows(2, 3).partial(Partial::Last).collect();
+        assert_eq!(last, [(0, "AC"), (3, "T")]);
+    }
+}
@@ -1,8 +1,357 @@
-use fasta;
-
 #[test]
 fn new_record() {
     let r = fasta::Record::new();
     assert_eq!(r.id.as_str(), "");
     assert_eq!(r.description.as_str(), "");
 }
+
+#[test]
+fn builder_defaults_match_from() {
+    let data = "junk\n>seq1 first\nACGT\n\n;comment\nacgt\n>seq2\nTT\n";
+    let recs: Vec<fasta::Record> = fasta::FastaReaderBuilder::new()
+        .from_reader(data.as_bytes())
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(recs.len(), 2);
+    assert_eq!(recs[0].id, "seq1");
+    assert_eq!(recs[0].description, "first");
+    assert_eq!(recs[0].sequence, "ACGTacgt");
+    assert_eq!(recs[1].sequence, "TT");
+}
+
+#[test]
+fn builder_strict_options() {
+    let junk = "junk\n>seq1\nACGT\n";
+    let mut buf = fasta::FastaReaderBuilder::new()
+        .allow_leading_junk(false)
+        .from_reader(junk.as_bytes());
+    assert!(buf.next().unwrap().is_err());
+
+    let blank = ">seq1\nACGT\n\nACGT\n";
+    let mut buf = fasta::FastaReaderBuilder::new()
+        .allow_blank_lines(false)
+        .from_reader(blank.as_bytes());
+    assert!(buf.next().unwrap().is_err());
+
+    let comment = ">seq1\n;ACGT\nacgt\n";
+    let rec = fasta::FastaReaderBuilder::new()
+        .comments(false)
+        .case(fasta::Case::Upper)
+        .from_reader(comment.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(rec.sequence, ";ACGTACGT");
+
+    let hashed = "# generated\n>seq1\n#note\nACGT\n";
+    let rec = fasta::FastaReaderBuilder::new()
+        .allow_leading_junk(false)
+        .comment_style(fasta::Comments::Hash)
+        .from_reader(hashed.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(rec.sequence, "ACGT");
+
+    let mut buf = fasta::FastaReaderBuilder::strict().from_reader(";c\n>seq1\nACGT\n".as_bytes());
+    assert!(buf.next().unwrap().is_err());
+}
+
+#[test]
+fn mixed_line_endings() {
+    let data = ">seq1 first\r\nACGT\r\nacgt\r>seq2\rTT\r\r\n";
+    let recs: Vec<fasta::Record> = fasta::FastaReaderBuilder::new()
+        .trim_whitespace(false)
+        .from_reader(data.as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(recs.len(), 2);
+    assert_eq!(recs[0].description, "first");
+    assert_eq!(recs[0].sequence, "ACGTacgt");
+    assert_eq!(recs[1].id, "seq2");
+    assert_eq!(recs[1].sequence, "TT");
+}
+
+#[test]
+fn record_offsets() {
+    let data = "junk\n>a desc\r\nAC\r\nGT\r\n>b\n>c\nTT";
+    let spans: Vec<(String, fasta::RecordSpan)> = fasta::FastaBuffer::from(data.as_bytes())
+        .with_offsets()
+        .map(|r| r.map(|(rec, span)| (rec.id, span)).unwrap())
+        .collect();
+    let offsets: Vec<(u64, u64, u64)> = spans
+        .iter()
+        .map(|(_, s)| (s.header_offset, s.seq_offset, s.end_offset))
+        .collect();
+    assert_eq!(offsets, [(5, 14, 22), (22, 25, 25), (25, 28, 30)]);
+    let (_, a) = &spans[0];
+    assert_eq!(
+        &data[a.seq_offset as usize..a.end_offset as usize],
+        "AC\r\nGT\r\n"
+    );
+}
+
+#[test]
+fn record_lengths() {
+    let data = ">a desc\nACGT\nAC\n>b\n>c\nT\n";
+    let lengths: Vec<(String, String, usize)> = fasta::FastaBuffer::from(data.as_bytes())
+        .lengths()
+        .map(|r| r.map(|(rec, len)| (rec.id, rec.sequence, len)).unwrap())
+        .collect();
+    assert_eq!(
+        lengths,
+        [
+            ("a".into(), String::new(), 6),
+            ("b".into(), String::new(), 0),
+            ("c".into(), String::new(), 1)
+        ]
+    );
+}
+
+#[test]
+fn collect_fasta_from_buffer() {
+    let data = ">chr1\nACGT\n>chr2\nGGCC\n";
+    let fasta: Result<fasta::Fasta, _> = fasta::FastaBuffer::from(data.as_bytes()).collect();
+    let fasta = fasta.unwrap();
+    assert_eq!(fasta.len(), 2);
+    assert_eq!(fasta["chr2"].sequence, "GGCC");
+    for rec in &fasta {
+        assert_eq!(rec.sequence.len(), 4);
+    }
+}
+
+#[test]
+fn etag_is_stable() {
+    let mut a = fasta::Record::new();
+    a.id = "chr1".to_owned();
+    a.sequence = "ACGT".to_owned();
+    let mut b = fasta::Record::new();
+    b.id = "chr1A".to_owned();
+    b.sequence = "CGT".to_owned();
+    assert_eq!(a.etag().len(), 16);
+    assert_eq!(a.etag(), a.etag());
+    assert_ne!(a.etag(), b.etag());
+    // FNV-1a of the ID length, ID and sequence; descriptions don't count
+    assert_eq!(a.etag(), "a05e96b525911e4c");
+    a.description = "changed".to_owned();
+    assert_eq!(a.etag(), "a05e96b525911e4c");
+}
+
+#[test]
+fn cancelled_reader_stops() {
+    let token = fasta::cancel::CancellationToken::new();
+    let data = ">a\nACGT\n>b\nACGT\n>c\nACGT\n";
+    let mut buf = fasta::FastaReaderBuilder::new()
+        .cancellation(token.clone())
+        .from_reader(data.as_bytes());
+    assert!(buf.next().unwrap().is_ok());
+    token.cancel();
+    let err = buf.next().unwrap().unwrap_err();
+    assert!(fasta::cancel::is_cancelled(&err));
+    assert!(buf.next().is_none());
+}
+
+#[test]
+fn short_sequences_write() {
+    let mut rec = fasta::Record::new();
+    rec.id = "short".to_owned();
+    rec.sequence = "ACGT".to_owned();
+    assert_eq!(rec.as_string(), ">short\nACGT\n");
+    rec.sequence = "A".repeat(80);
+    assert_eq!(rec.as_string(), format!(">short\n{}\n", "A".repeat(80)));
+    rec.sequence = String::new();
+    assert_eq!(rec.as_string(), ">short\n\n");
+}
+
+#[test]
+fn transcription_round_trip() {
+    let mut rec = fasta::Record::new();
+    rec.id = "tx".to_owned();
+    rec.sequence = "ATGcatNN".to_owned();
+    let rna = rec.transcribe().unwrap();
+    assert_eq!(rna.sequence, "AUGcauNN");
+    assert_eq!(rna.back_transcribe().unwrap().sequence, rec.sequence);
+    assert!(rna.transcribe().is_err());
+    rec.sequence = "ATGX".to_owned();
+    assert!(rec.transcribe().is_err());
+}
+
+#[test]
+fn read_next_into_reuses_record() {
+    let data = format!(">long\n{}\n>short one\nAC\n", "A".repeat(1000));
+    let mut buf = fasta::FastaBuffer::from(data.as_bytes());
+    let mut rec = fasta::Record::new();
+    assert!(buf.read_next_into(&mut rec).unwrap());
+    assert_eq!(rec.sequence.len(), 1000);
+    let allocation = rec.sequence.as_ptr();
+    assert!(buf.read_next_into(&mut rec).unwrap());
+    assert_eq!(
+        (rec.id.as_str(), rec.description.as_str()),
+        ("short", "one")
+    );
+    assert_eq!(rec.sequence, "AC");
+    assert_eq!(rec.sequence.as_ptr(), allocation);
+    assert!(!buf.read_next_into(&mut rec).unwrap());
+}
+
+#[test]
+fn validation_error_position() {
+    let data = ">ok\nACGT\n>bad\nACGT\n  ACGTXACGTACGT\n";
+    let mut buf = fasta::FastaReaderBuilder::new()
+        .alphabet(fasta::Alphabet::Dna)
+        .error_context(3)
+        .from_reader(data.as_bytes());
+    assert!(buf.next().unwrap().is_ok());
+    let err = buf.next().unwrap().unwrap_err();
+    match err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<fasta::errors::FastaError>())
+    {
+        Some(fasta::errors::FastaError::Validation {
+            id,
+            line,
+            column,
+            offset,
+            byte,
+            context,
+            ..
+        }) => {
+            assert_eq!((id.as_str(), *line, *column, *byte), ("bad", 5, 7, b'X'));
+            assert_eq!(*offset, 25);
+            assert_eq!(context, "CGTXACG");
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+    assert_eq!(
+        err.to_string(),
+        "unexpected character in sequence at line 5, column 7 (byte 0x58) in record bad: \"CGTXACG\""
+    );
+}
+
+#[test]
+fn parser_position() {
+    let data = ">a\r\nAC\r\n>b\r\nG\r\n";
+    let mut buf = fasta::FastaBuffer::from(data.as_bytes()).with_offsets();
+    let (_, span) = buf.next().unwrap().unwrap();
+    assert_eq!((span.header_line, span.header_offset), (1, 0));
+    let (_, span) = buf.next().unwrap().unwrap();
+    assert_eq!((span.header_line, span.header_offset), (3, 8));
+
+    let mut buf = fasta::FastaBuffer::from(data.as_bytes());
+    buf.next().unwrap().unwrap();
+    let pos = buf.position();
+    assert_eq!((pos.line, pos.offset), (3, 8));
+
+    // A reader that fails once its bytes run out
+    struct Failing(&'static [u8]);
+    impl std::io::Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::other("disk on fire"));
+            }
+            let n = self.0.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+    let input = std::io::BufReader::new(Failing(b">a\nACGT\nAC"));
+    let err = fasta::FastaBuffer::from(input).next().unwrap().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "IO error while parsing Fasta records at line 3 (offset 8): disk on fire"
+    );
+}
+
+#[test]
+fn invalid_utf8_position() {
+    let data: &[u8] = b">a\nAC\xffGT\n>b\n";
+    let err = fasta::FastaBuffer::from(data).next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    match err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<fasta::errors::FastaError>())
+    {
+        Some(fasta::errors::FastaError::Validation {
+            id,
+            line,
+            column,
+            offset,
+            byte,
+            ..
+        }) => assert_eq!(
+            (id.as_str(), *line, *column, *offset, *byte),
+            ("a", 2, 3, 5, 0xff)
+        ),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn line_limit_position() {
+    let limits = fasta::guard::Limits {
+        max_record_bytes: Some(4),
+        ..Default::default()
+    };
+    let data = ">a\nACGT\n>b\nACGTACGTACGT\n";
+    let input = std::io::BufReader::with_capacity(4, data.as_bytes());
+    let mut reader = fasta::guard::GuardedReader::new(input, limits);
+    assert_eq!(reader.next().unwrap().unwrap().sequence, "ACGT");
+    let err = reader.next().unwrap().unwrap_err();
+    assert!(fasta::guard::is_limit_error(&err));
+    match err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<fasta::errors::FastaError>())
+    {
+        Some(fasta::errors::FastaError::LineTooLong {
+            id, line, offset, ..
+        }) => assert_eq!((id.as_str(), *line, *offset), ("b", 4, 11)),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn record_batches() {
+    let data = ">a\nA\n>b\nC\n>c\nG\n>d\nT\n>e\nN\n";
+    let batches: Vec<Vec<String>> = fasta::FastaBuffer::from(data.as_bytes())
+        .batches(2)
+        .map(|batch| batch.unwrap().into_iter().map(|r| r.id).collect())
+        .collect();
+    assert_eq!(batches, [vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+
+    let strict = fasta::FastaReaderBuilder::new().alphabet(fasta::Alphabet::Dna);
+    let mut batches = strict
+        .from_reader(">a\nA\n>b\nX\n>c\nG\n".as_bytes())
+        .batches(3);
+    assert_eq!(batches.next().unwrap().unwrap().len(), 1);
+    assert!(batches.next().unwrap().is_err());
+    assert_eq!(batches.next().unwrap().unwrap()[0].id, "c");
+    assert!(batches.next().is_none());
+}
+
+#[test]
+fn open_gzip_file() {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join("fasta_open_test.fa.gz");
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(b">a\nACGT\n").unwrap();
+    std::fs::write(&path, gz.finish().unwrap()).unwrap();
+    let recs: Vec<fasta::Record> = fasta::open(&path)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recs.len(), 1);
+    assert_eq!(recs[0].sequence, "ACGT");
+}
+
+#[test]
+fn io_errors_clone() {
+    use std::error::Error;
+
+    let err = fasta::errors::FastaError::from(std::io::Error::other("disk full"));
+    let copy = err.clone();
+    assert_eq!(copy.to_string(), "IO error: disk full");
+    assert!(copy.source().is_some());
+}